@@ -0,0 +1,68 @@
+//! Parsing of files with a single multi-megabyte line, as some generators
+//! write. Run with `cargo bench --bench long_lines`; every case should take
+//! time proportional to the length of the line.
+
+use std::{hint::black_box, time::Instant};
+
+use webvtt::{parse_file, TailParser};
+
+const LENGTH: usize = 8 << 20;
+
+fn cue_with_text(text: &str) -> String {
+    format!("WEBVTT\n\n00:00:00.000 --> 00:00:01.000\n{text}\n")
+}
+
+fn repeat(piece: &str) -> String {
+    piece.repeat(LENGTH / piece.len())
+}
+
+fn bench(name: &str, run: impl Fn()) {
+    run();
+
+    let start = Instant::now();
+    let runs = 5;
+
+    for _ in 0..runs {
+        run();
+    }
+
+    println!("{name:<24} {:>10.2?}", start.elapsed() / runs);
+}
+
+fn main() {
+    let words = cue_with_text(&repeat("<b>word</b> &amp; <00:00.500>more "));
+    let ampersands = cue_with_text(&repeat("&"));
+    let tags = cue_with_text(&repeat("<c.a.b"));
+
+    bench("words and markup", || {
+        let file = parse_file(black_box(&words)).unwrap();
+        file.cues()
+            .for_each(|cue| drop(black_box(cue.plain_text())));
+    });
+
+    bench("ampersands", || {
+        let file = parse_file(black_box(&ampersands)).unwrap();
+        file.cues()
+            .for_each(|cue| drop(black_box(cue.plain_text())));
+    });
+
+    bench("unclosed tags", || {
+        let file = parse_file(black_box(&tags)).unwrap();
+        file.cues().for_each(|cue| drop(black_box(cue.nodes())));
+    });
+
+    bench("tail parser, 4 KiB pieces", || {
+        let mut parser = TailParser::new();
+        let mut cues = 0;
+
+        for piece in words.as_bytes().chunks(4096) {
+            cues += parser
+                .push(std::str::from_utf8(piece).unwrap())
+                .unwrap()
+                .len();
+        }
+
+        cues += parser.finish().unwrap().len();
+        assert_eq!(black_box(cues), 1);
+    });
+}
@@ -0,0 +1,66 @@
+//! Parsing of a long file of ordinary cues, like the segments an HLS
+//! packager reads. Run with `cargo bench --bench parse`.
+
+use std::{fmt::Write, hint::black_box, time::Instant};
+
+use webvtt::{parse_file, parse_header, CueReader};
+
+fn file(cues: usize) -> String {
+    let mut input = String::from("WEBVTT\n\n");
+
+    for idx in 0..cues {
+        let start = idx * 2000;
+        let _ = write!(
+            input,
+            "{idx}\n{:02}:{:02}:{:02}.{:03} --> {:02}:{:02}:{:02}.{:03} line:90% align:center\n\
+             <v Speaker {}>Some words that were said -- and more &amp; more</v>\n\
+             A second line of the cue\n\n",
+            start / 3_600_000,
+            start / 60_000 % 60,
+            start / 1000 % 60,
+            start % 1000,
+            (start + 1500) / 3_600_000,
+            (start + 1500) / 60_000 % 60,
+            (start + 1500) / 1000 % 60,
+            (start + 1500) % 1000,
+            idx % 3,
+        );
+    }
+
+    input
+}
+
+fn bench(name: &str, run: impl Fn()) {
+    run();
+
+    let start = Instant::now();
+    let runs = 10;
+
+    for _ in 0..runs {
+        run();
+    }
+
+    println!("{name:<24} {:>10.2?}", start.elapsed() / runs);
+}
+
+fn main() {
+    let input = file(100_000);
+
+    bench("parse_file", || {
+        black_box(parse_file(black_box(&input)).unwrap());
+    });
+
+    bench("parse_header", || {
+        black_box(parse_header(black_box(&input)).unwrap());
+    });
+
+    bench("CueReader", || {
+        black_box(CueReader::new(black_box(&input)).unwrap().count());
+    });
+
+    let file = parse_file(&input).unwrap();
+
+    bench("cue text", || {
+        file.cues().for_each(|cue| drop(black_box(cue.nodes())));
+    });
+}
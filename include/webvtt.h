@@ -0,0 +1,102 @@
+#ifndef WEBVTT_H
+#define WEBVTT_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The last call succeeded.
+#define VTT_OK 0
+
+// The input was not valid UTF-8.
+#define VTT_ERROR_UTF8 1
+
+// The input was not a WebVTT file.
+#define VTT_ERROR_PARSE 2
+
+// The library panicked, which is a bug in it. The panic was caught so that
+// it does not unwind into the caller.
+#define VTT_ERROR_PANIC 3
+
+// A parsed WebVTT file.
+typedef struct VttFile VttFile;
+
+// Parses `len` bytes of UTF-8 at `input` as a WebVTT file. Returns NULL if
+// the input is not valid UTF-8 or not a WebVTT file, setting the error of
+// `vtt_last_error`. The handle must be freed with `vtt_file_free`.
+//
+// # Safety
+//
+// `input` must point to at least `len` readable bytes.
+VttFile *vtt_parse(const char *input, uintptr_t len);
+
+// Returns the error of the last call on this thread that returned NULL, or
+// `VTT_OK` if the last call succeeded.
+int vtt_last_error(void);
+
+// Frees a file returned by `vtt_parse`. Does nothing if `file` is NULL.
+//
+// # Safety
+//
+// `file` must be NULL or a handle from `vtt_parse` that has not been freed.
+void vtt_file_free(VttFile *file);
+
+// Returns the number of cues in the file.
+//
+// # Safety
+//
+// `file` must be a live handle from `vtt_parse`.
+uintptr_t vtt_cue_count(const VttFile *file);
+
+// Returns the start time of a cue in milliseconds, or 0 if `index` is out of
+// range.
+//
+// # Safety
+//
+// `file` must be a live handle from `vtt_parse`.
+uint64_t vtt_cue_start_ms(const VttFile *file, uintptr_t index);
+
+// Returns the end time of a cue in milliseconds, or 0 if `index` is out of
+// range.
+//
+// # Safety
+//
+// `file` must be a live handle from `vtt_parse`.
+uint64_t vtt_cue_end_ms(const VttFile *file, uintptr_t index);
+
+// Returns the id of a cue, which is empty if it has none, or NULL if `index`
+// is out of range.
+//
+// # Safety
+//
+// `file` must be a live handle from `vtt_parse`.
+const char *vtt_cue_id(const VttFile *file, uintptr_t index);
+
+// Returns the text of a cue, with its markup, or NULL if `index` is out of
+// range.
+//
+// # Safety
+//
+// `file` must be a live handle from `vtt_parse`.
+const char *vtt_cue_text(const VttFile *file, uintptr_t index);
+
+// Writes the file back into WebVTT. The string must be freed with
+// `vtt_string_free`. Returns NULL if writing failed, setting the error of
+// `vtt_last_error`.
+//
+// # Safety
+//
+// `file` must be a live handle from `vtt_parse`.
+char *vtt_write(const VttFile *file);
+
+// Frees a string returned by `vtt_write`. Does nothing if `string` is NULL.
+//
+// # Safety
+//
+// `string` must be NULL or a string from `vtt_write` that has not been freed.
+void vtt_string_free(char *string);
+
+#endif  /* WEBVTT_H */
@@ -0,0 +1,58 @@
+//! Retiming cues against their audio with a forced-alignment engine.
+
+use std::time::Duration;
+
+use crate::File;
+
+/// A forced-alignment engine, which finds where a piece of text is spoken.
+pub trait Aligner {
+    /// Returns the start and end of the speech of `text` in the audio between
+    /// the start and end of `window`, or `None` if it could not be found.
+    fn align(&mut self, text: &str, window: (Duration, Duration)) -> Option<(Duration, Duration)>;
+}
+
+impl File {
+    /// Tightens the timings of the cues to their speech with an [`Aligner`].
+    ///
+    /// The aligner is given the text of each cue without markup, and a window
+    /// of audio that runs from the end of the previous cue to the start of the
+    /// next one, widened to include the cue itself. Times it returns outside
+    /// the window are clamped to it, and results that are empty after that are
+    /// ignored. Returns the number of cues whose timings changed.
+    pub fn align_with(&mut self, aligner: &mut impl Aligner) -> usize {
+        let spans: Vec<_> = self.cues().map(|cue| (cue.start, cue.end)).collect();
+        let mut changed = 0;
+
+        for (idx, cue) in self.cues_mut().enumerate() {
+            let text = cue.plain_text();
+
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let previous_end = idx
+                .checked_sub(1)
+                .map_or(Duration::ZERO, |idx| spans[idx].1);
+            let next_start = spans.get(idx + 1).map_or(cue.end, |span| span.0);
+
+            let window = (previous_end.min(cue.start), next_start.max(cue.end));
+
+            let Some((start, end)) = aligner.align(&text, window) else {
+                continue;
+            };
+
+            let start = start.clamp(window.0, window.1);
+            let end = end.clamp(window.0, window.1);
+
+            if start >= end || (start, end) == (cue.start, cue.end) {
+                continue;
+            }
+
+            cue.start = start;
+            cue.end = end;
+            changed += 1;
+        }
+
+        changed
+    }
+}
@@ -0,0 +1,104 @@
+//! Anchoring cues to named markers in the media, like chapter starts, so that
+//! they can follow the markers when the media is re-edited.
+//!
+//! A cue is anchored with an `anchor` entry in its
+//! [extensions](crate::ExtensionMap), which is kept in the `NOTE extensions`
+//! block before it:
+//!
+//! ```text
+//! NOTE extensions
+//! anchor=chapter-2
+//!
+//! 00:05:01.000 --> 00:05:03.000
+//! Meanwhile, back at the lab
+//! ```
+
+use std::time::Duration;
+
+use crate::{Cue, File};
+
+/// The extension key holding the name of a cue's marker.
+const ANCHOR_KEY: &str = "anchor";
+
+/// A named point in the media.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Marker {
+    pub name: String,
+    pub time: Duration,
+}
+
+impl Marker {
+    pub fn new(name: impl Into<String>, time: Duration) -> Self {
+        Marker {
+            name: name.into(),
+            time,
+        }
+    }
+}
+
+impl Cue {
+    /// The name of the marker this cue is anchored to, if any.
+    pub fn anchor(&self) -> Option<&str> {
+        self.extensions.get(ANCHOR_KEY)
+    }
+
+    /// Anchors this cue to a marker, or removes its anchor.
+    pub fn set_anchor(&mut self, marker: Option<&str>) {
+        match marker {
+            Some(marker) => self.extensions.insert(ANCHOR_KEY, marker),
+            None => self.extensions.remove(ANCHOR_KEY),
+        };
+    }
+}
+
+fn find<'a>(markers: &'a [Marker], name: &str) -> Option<&'a Marker> {
+    markers.iter().find(|marker| marker.name == name)
+}
+
+impl File {
+    /// Moves cues along with the markers they belong to, after an edit of the
+    /// media moved the markers from `old_markers` to `new_markers`.
+    ///
+    /// A cue belongs to the marker it is anchored to, or if it has no anchor,
+    /// to the latest of `old_markers` at or before its start, taking the first
+    /// listed if several are at the same time. Each cue keeps its distance
+    /// from its marker; cues whose marker is not in both lists are left alone. Returns the number of cues that moved.
+    pub fn rebase_on_markers(&mut self, old_markers: &[Marker], new_markers: &[Marker]) -> usize {
+        let mut moved = 0;
+
+        for cue in self.cues_mut() {
+            let old = match cue.anchor() {
+                Some(name) => find(old_markers, name),
+                None => old_markers
+                    .iter()
+                    .filter(|marker| marker.time <= cue.start)
+                    .rev()
+                    .max_by_key(|marker| marker.time),
+            };
+
+            let Some(old) = old else {
+                continue;
+            };
+
+            let Some(new) = find(new_markers, &old.name) else {
+                continue;
+            };
+
+            if new.time == old.time {
+                continue;
+            }
+
+            let duration = cue.end.saturating_sub(cue.start);
+
+            cue.start = if cue.start >= old.time {
+                new.time + (cue.start - old.time)
+            } else {
+                new.time.saturating_sub(old.time - cue.start)
+            };
+            cue.end = cue.start + duration;
+            moved += 1;
+        }
+
+        moved
+    }
+}
@@ -0,0 +1,253 @@
+//! Records of the automated changes made to a file, kept in the file itself
+//! as `NOTE audit` blocks at its end:
+//!
+//! ```text
+//! NOTE audit
+//! cues.after=12
+//! cues.before=12
+//! cues.changed=12
+//! param.offset=+00:00:01.500
+//! tool=webvtt
+//! transform=shift
+//! version=0.2.0
+//! ```
+//!
+//! The blocks use the `key=value` lines of [extensions](crate::ExtensionMap),
+//! so values are escaped the same way.
+
+use std::{collections::HashMap, time::SystemTime};
+
+use crate::{
+    format_date_time, format_timestamp, Block, ExtensionMap, File, Finding, Profile, Shift,
+    ShiftError, SpeedFix,
+};
+
+/// The first line of the NOTE blocks that hold an [`AuditRecord`].
+const NOTE_HEADER: &str = "NOTE audit";
+
+/// Who made the changes recorded in a file, for the `_audited` versions of
+/// its transforms.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Audit {
+    pub tool: String,
+    pub version: String,
+    /// The time the changes are recorded as made at. It is left out unless
+    /// set, so that the same changes give the same file.
+    pub date: Option<SystemTime>,
+}
+
+impl Audit {
+    pub fn new(tool: impl Into<String>, version: impl Into<String>) -> Self {
+        Audit {
+            tool: tool.into(),
+            version: version.into(),
+            date: None,
+        }
+    }
+}
+
+impl Default for Audit {
+    /// Records this crate as the tool.
+    fn default() -> Self {
+        Audit::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    }
+}
+
+/// A transform applied to a file, as recorded in a `NOTE audit` block.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AuditRecord {
+    /// The name of the transform, like `shift`.
+    pub transform: String,
+    pub tool: String,
+    pub version: String,
+    /// The time of the change in ISO 8601, if it was recorded.
+    pub date: Option<String>,
+    /// The parameters the transform was called with.
+    pub parameters: ExtensionMap,
+    pub cues_before: usize,
+    pub cues_after: usize,
+    /// The number of cues of the result that were not in the file before,
+    /// with the same identifier, timings and text.
+    pub cues_changed: usize,
+}
+
+impl AuditRecord {
+    fn to_note(&self) -> String {
+        let mut map = ExtensionMap::new();
+        map.insert("transform", &self.transform);
+        map.insert("tool", &self.tool);
+        map.insert("version", &self.version);
+
+        if let Some(date) = &self.date {
+            map.insert("date", date);
+        }
+
+        for (key, value) in self.parameters.iter() {
+            map.insert(format!("param.{key}"), value);
+        }
+
+        map.insert("cues.before", self.cues_before);
+        map.insert("cues.after", self.cues_after);
+        map.insert("cues.changed", self.cues_changed);
+
+        map.to_note_with_header(NOTE_HEADER)
+    }
+
+    fn from_note(note: &str) -> Option<Self> {
+        let map = ExtensionMap::from_note_with_header(note, NOTE_HEADER)?;
+        let count = |key| map.get_parsed(key).unwrap_or(0);
+
+        Some(AuditRecord {
+            transform: map.get("transform")?.to_owned(),
+            tool: map.get("tool").unwrap_or_default().to_owned(),
+            version: map.get("version").unwrap_or_default().to_owned(),
+            date: map.get("date").map(str::to_owned),
+            parameters: map
+                .iter()
+                .filter_map(|(key, value)| Some((key.strip_prefix("param.")?, value)))
+                .collect(),
+            cues_before: count("cues.before"),
+            cues_after: count("cues.after"),
+            cues_changed: count("cues.changed"),
+        })
+    }
+}
+
+/// The identifier, timings and text of each cue, to count the cues a
+/// transform changed.
+type CueKey = (String, u128, u128, String);
+
+fn cue_keys(file: &File) -> Vec<CueKey> {
+    file.cues()
+        .map(|cue| {
+            (
+                cue.id.clone(),
+                cue.start.as_millis(),
+                cue.end.as_millis(),
+                cue.text.clone(),
+            )
+        })
+        .collect()
+}
+
+fn profile_name(profile: &Profile) -> &'static str {
+    match profile {
+        Profile::Youtube => "youtube",
+        Profile::Netflix => "netflix",
+        Profile::Hls => "hls",
+        Profile::Custom(_) => "custom",
+    }
+}
+
+impl File {
+    /// Returns the records of the `NOTE audit` blocks of this file, oldest
+    /// first, including blocks that were attached to a cue added after them.
+    pub fn audit_trail(&self) -> Vec<AuditRecord> {
+        self.blocks
+            .iter()
+            .flat_map(|block| match block {
+                Block::Note(note) => vec![note],
+                Block::Cue(cue) => cue.notes.iter().collect(),
+                _ => vec![],
+            })
+            .filter_map(|note| AuditRecord::from_note(note))
+            .collect()
+    }
+
+    /// Runs a transform, then appends a `NOTE audit` block recording it.
+    fn audited<T>(
+        &mut self,
+        audit: &Audit,
+        transform: &str,
+        parameters: ExtensionMap,
+        run: impl FnOnce(&mut File) -> T,
+    ) -> T {
+        let before = cue_keys(self);
+        let result = run(self);
+        let after = cue_keys(self);
+
+        let mut counts: HashMap<&CueKey, usize> = HashMap::new();
+
+        for key in &before {
+            *counts.entry(key).or_default() += 1;
+        }
+
+        let cues_changed = after
+            .iter()
+            .filter(|key| match counts.get_mut(key) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .count();
+
+        let record = AuditRecord {
+            transform: transform.to_owned(),
+            tool: audit.tool.clone(),
+            version: audit.version.clone(),
+            date: audit.date.map(format_date_time),
+            parameters,
+            cues_before: before.len(),
+            cues_after: after.len(),
+            cues_changed,
+        };
+
+        self.blocks.push(Block::Note(record.to_note()));
+        result
+    }
+
+    /// Shifts every cue as in [`File::shift`], recording the shift in a
+    /// `NOTE audit` block if it succeeds.
+    pub fn shift_audited(&mut self, shift: Shift, audit: &Audit) -> Result<(), ShiftError> {
+        let offset = match shift {
+            Shift::Later(by) => format!("+{}", format_timestamp(by)),
+            Shift::Earlier(by) => format!("-{}", format_timestamp(by)),
+        };
+
+        let mut shifted = self.clone();
+        let parameters = [("offset", offset)].into_iter().collect();
+
+        shifted.audited(audit, "shift", parameters, |file| file.shift(shift))?;
+        *self = shifted;
+        Ok(())
+    }
+
+    /// Fixes this file as in [`File::conform`], recording the profile in a
+    /// `NOTE audit` block.
+    pub fn conform_audited(&mut self, profile: &Profile, audit: &Audit) -> Vec<Finding> {
+        let parameters = [("profile", profile_name(profile))].into_iter().collect();
+
+        self.audited(audit, "conform", parameters, |file| file.conform(profile))
+    }
+
+    /// Slows down cues as in [`File::fix_reading_speed`], recording the
+    /// largest speed and the fixes in a `NOTE audit` block.
+    pub fn fix_reading_speed_audited(
+        &mut self,
+        max_cps: f64,
+        fixes: &[SpeedFix],
+        audit: &Audit,
+    ) -> Vec<usize> {
+        let fix_names: Vec<_> = fixes
+            .iter()
+            .map(|fix| match fix {
+                SpeedFix::Extend => "extend",
+                SpeedFix::Merge => "merge",
+                SpeedFix::Truncate => "truncate",
+            })
+            .collect();
+
+        let parameters = [
+            ("max_cps", max_cps.to_string()),
+            ("fixes", fix_names.join(",")),
+        ]
+        .into_iter()
+        .collect();
+
+        self.audited(audit, "fix_reading_speed", parameters, |file| {
+            file.fix_reading_speed(max_cps, fixes)
+        })
+    }
+}
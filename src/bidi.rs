@@ -0,0 +1,178 @@
+//! Helpers for right-to-left and mixed-direction cue text.
+//!
+//! The base direction of a line of cue text is that of its first strongly
+//! directional character, as in rules P2 and P3 of the Unicode bidirectional
+//! algorithm. Lines that start or end with neutral characters, like
+//! punctuation, can be displayed out of order when a renderer guesses a
+//! different base direction, so marks can be added to pin it down.
+
+use crate::{text, Cue, File};
+
+const LRM: char = '\u{200e}';
+const RLM: char = '\u{200f}';
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/// Returns the direction of a character if it is strongly directional. This
+/// uses the blocks of the right-to-left scripts rather than the full Unicode
+/// bidirectional class data.
+fn strong_direction(char: char) -> Option<Direction> {
+    match char {
+        LRM => Some(Direction::Ltr),
+        RLM => Some(Direction::Rtl),
+        // arabic-indic digits are weak
+        '\u{660}'..='\u{669}' | '\u{6f0}'..='\u{6f9}' => None,
+        '\u{590}'..='\u{8ff}'
+        | '\u{fb1d}'..='\u{fdff}'
+        | '\u{fe70}'..='\u{feff}'
+        | '\u{10800}'..='\u{10fff}'
+        | '\u{1e800}'..='\u{1efff}'
+            if char.is_alphabetic() =>
+        {
+            Some(Direction::Rtl)
+        }
+        _ if char.is_alphabetic() => Some(Direction::Ltr),
+        _ => None,
+    }
+}
+
+/// Returns the base direction of a line of plain text, or `None` if it has
+/// no strongly directional characters.
+pub fn base_direction(line: &str) -> Option<Direction> {
+    line.chars().find_map(strong_direction)
+}
+
+impl Cue {
+    /// Returns whether the first line of this cue with strongly directional
+    /// text is right-to-left.
+    pub fn is_rtl(&self) -> bool {
+        self.plain_text()
+            .lines()
+            .find_map(base_direction)
+            .is_some_and(|direction| direction == Direction::Rtl)
+    }
+
+    /// Returns the base direction of each line of this cue. Lines without
+    /// strongly directional text are left-to-right.
+    pub fn line_directions(&self) -> Vec<Direction> {
+        self.plain_text()
+            .split('\n')
+            .map(|line| base_direction(line).unwrap_or_default())
+            .collect()
+    }
+
+    /// Adds `&lrm;` or `&rlm;` marks to the start and end of each line that
+    /// starts or ends with neutral characters, matching the line's base
+    /// direction, so that renderers do not reorder its punctuation. Metadata
+    /// cues are left as they are.
+    pub fn add_direction_marks(&mut self) {
+        if self.is_metadata() {
+            return;
+        }
+
+        let lines: Vec<_> = self
+            .text
+            .split('\n')
+            .map(|line| {
+                let plain = text::plain(&text::parse(line));
+
+                let Some(direction) = base_direction(&plain) else {
+                    return line.to_owned();
+                };
+
+                let mark = match direction {
+                    Direction::Ltr => "&lrm;",
+                    Direction::Rtl => "&rlm;",
+                };
+
+                let first = plain.chars().next().and_then(strong_direction);
+                let last = plain.chars().next_back().and_then(strong_direction);
+
+                let mut line = line.to_owned();
+
+                if first.is_none() {
+                    line.insert_str(0, mark);
+                }
+
+                if last.is_none() {
+                    line.push_str(mark);
+                }
+
+                line
+            })
+            .collect();
+
+        self.text = lines.join("\n");
+    }
+
+    /// Removes all `&lrm;` and `&rlm;` marks from this cue, whether they are
+    /// written as character references or as the characters themselves.
+    /// Metadata cues are left as they are.
+    pub fn strip_direction_marks(&mut self) {
+        if self.is_metadata() {
+            return;
+        }
+
+        let mut text = String::with_capacity(self.text.len());
+        let mut rest = self.text.as_str();
+
+        while let Some(char) = rest.chars().next() {
+            let len = match char {
+                '&' => mark_reference_len(rest).unwrap_or(0),
+                LRM | RLM => char.len_utf8(),
+                _ => 0,
+            };
+
+            if len > 0 {
+                rest = &rest[len..];
+            } else {
+                text.push(char);
+                rest = &rest[char.len_utf8()..];
+            }
+        }
+
+        self.text = text;
+    }
+}
+
+/// Returns the length of the `&lrm;` or `&rlm;` reference at the start of
+/// `text`, if there is one.
+fn mark_reference_len(text: &str) -> Option<usize> {
+    [
+        "&lrm;", "&rlm;", "&#8206;", "&#8207;", "&#x200e;", "&#x200f;",
+    ]
+    .into_iter()
+    .find(|reference| {
+        text.get(..reference.len()).is_some_and(|prefix| {
+            // the x and the hex digits can be in either case
+            if reference.starts_with("&#x") {
+                prefix.eq_ignore_ascii_case(reference)
+            } else {
+                prefix == *reference
+            }
+        })
+    })
+    .map(str::len)
+}
+
+impl File {
+    /// Adds direction marks to every cue, as in [`Cue::add_direction_marks`].
+    pub fn add_direction_marks(&mut self) {
+        for cue in self.cues_mut() {
+            cue.add_direction_marks();
+        }
+    }
+
+    /// Removes direction marks from every cue, as in
+    /// [`Cue::strip_direction_marks`].
+    pub fn strip_direction_marks(&mut self) {
+        for cue in self.cues_mut() {
+            cue.strip_direction_marks();
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Building cues from the timed words of a speech recognizer, and merging
+//! fragments of sentences into fuller cues.
+
+use std::time::Duration;
+
+use crate::{text, Block, Cue, File, LengthMetric, SentenceSegmenter};
+
+/// A word of a transcript and when it is spoken.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimedWord {
+    pub text: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl TimedWord {
+    pub fn new(text: impl Into<String>, start: Duration, end: Duration) -> Self {
+        TimedWord {
+            text: text.into(),
+            start,
+            end,
+        }
+    }
+}
+
+/// Returns whether `segmenter` finds a sentence starting at `second`, when it
+/// follows `first`.
+fn starts_sentence(segmenter: &impl SentenceSegmenter, first: &str, second: &str) -> bool {
+    let first = first.trim_end();
+    let text = format!("{first} {}", second.trim_start());
+
+    segmenter
+        .sentence_starts(&text)
+        .contains(&(first.len() + 1))
+}
+
+/// The length of some plain text, not counting line breaks.
+fn length(text: &str) -> usize {
+    text.split('\n')
+        .map(|line| LengthMetric::Graphemes.measure(line))
+        .sum()
+}
+
+impl File {
+    /// Builds cues from timed words, such as the output of a speech
+    /// recognizer. Each cue is one line of words of at most `max_chars`
+    /// characters, and a new cue starts with each sentence found by
+    /// `segmenter`. A single word longer than `max_chars` gets a cue of its
+    /// own.
+    pub fn from_words(
+        words: &[TimedWord],
+        max_chars: usize,
+        segmenter: &impl SentenceSegmenter,
+    ) -> File {
+        let mut transcript = String::new();
+        let mut offsets = vec![];
+
+        for word in words {
+            if !transcript.is_empty() {
+                transcript.push(' ');
+            }
+
+            offsets.push(transcript.len());
+            transcript.push_str(word.text.trim());
+        }
+
+        let sentence_starts = segmenter.sentence_starts(&transcript);
+
+        let mut blocks = vec![];
+        let mut line: Vec<&TimedWord> = vec![];
+        let mut length = 0;
+
+        let mut flush = |line: &mut Vec<&TimedWord>| {
+            if let (Some(first), Some(last)) = (line.first(), line.last()) {
+                let text = line
+                    .iter()
+                    .map(|word| word.text.trim())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                blocks.push(Block::Cue(Cue {
+                    start: first.start,
+                    end: last.end.max(first.start),
+                    text: text::escape(&text),
+                    ..Default::default()
+                }));
+            }
+
+            line.clear();
+        };
+
+        for (word, offset) in words.iter().zip(offsets) {
+            let word_length = LengthMetric::Graphemes.measure(word.text.trim());
+
+            if !line.is_empty()
+                && (sentence_starts.contains(&offset) || length + 1 + word_length > max_chars)
+            {
+                flush(&mut line);
+            }
+
+            length = if line.is_empty() {
+                word_length
+            } else {
+                length + 1 + word_length
+            };
+            line.push(word);
+        }
+
+        flush(&mut line);
+
+        File {
+            description: None,
+            header_lines: vec![],
+            blocks,
+            format: Default::default(),
+        }
+    }
+
+    /// Merges each cue into the cue before it if it continues the same
+    /// sentence, follows it by at most `max_gap`, and the text of both fits in
+    /// `max_chars` characters, not counting markup. The text of merged cues is
+    /// kept on separate lines. Only cues in consecutive blocks are merged, and
+    /// metadata cues are never merged. Returns the number of cues merged away.
+    pub fn merge_adjacent(
+        &mut self,
+        max_gap: Duration,
+        max_chars: usize,
+        segmenter: &impl SentenceSegmenter,
+    ) -> usize {
+        let mut merged = 0;
+        let mut idx = 0;
+
+        while idx + 1 < self.blocks.len() {
+            let (Block::Cue(first), Block::Cue(second)) =
+                (&self.blocks[idx], &self.blocks[idx + 1])
+            else {
+                idx += 1;
+                continue;
+            };
+
+            let first_text = first.plain_text();
+            let second_text = second.plain_text();
+
+            let mergeable = !first.is_metadata()
+                && !second.is_metadata()
+                && second.start >= first.start
+                && second.start.saturating_sub(first.end) <= max_gap
+                && length(&first_text) + 1 + length(&second_text) <= max_chars
+                && !starts_sentence(segmenter, &first_text, &second_text);
+
+            if !mergeable {
+                idx += 1;
+                continue;
+            }
+
+            let Block::Cue(second) = self.blocks.remove(idx + 1) else {
+                unreachable!();
+            };
+
+            let first = self.blocks[idx].as_cue_mut().unwrap();
+            first.end = first.end.max(second.end);
+            first.text = format!("{}\n{}", first.text, second.text);
+            first.notes.extend(second.notes);
+            merged += 1;
+        }
+
+        merged
+    }
+}
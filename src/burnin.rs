@@ -0,0 +1,257 @@
+//! Export of the artifacts needed to hardcode subtitles into video with
+//! ffmpeg: a filter script of `drawtext` filters, or an ASS file for the `ass`
+//! filter.
+
+use std::{fmt::Write, time::Duration};
+
+use crate::{
+    layout,
+    text::{self, Node, RubyPart, SpanKind},
+    Align, Cue, CueSettings, Direction, File, WritingDirection,
+};
+
+/// The resolution of the ASS files written by [`File::to_ass_for_burnin`];
+/// libass scales it to the video.
+const ASS_WIDTH: f64 = 1920.0;
+const ASS_HEIGHT: f64 = 1080.0;
+
+/// The height of a line of text in the ASS files written by
+/// [`File::to_ass_for_burnin`], as a percentage of the video's height.
+const ASS_LINE_SIZE: f64 = 5.0;
+
+#[derive(Clone, Debug)]
+pub struct DrawtextOptions {
+    /// Path to the font file to draw with. ffmpeg picks a default font through
+    /// fontconfig when this is not given.
+    pub font_file: Option<String>,
+    pub font_size: u32,
+    /// Any color ffmpeg understands, such as `white` or `#ffcc00`.
+    pub font_color: String,
+    /// Color of a box drawn behind the text, or `None` for no box.
+    pub box_color: Option<String>,
+}
+
+impl Default for DrawtextOptions {
+    fn default() -> Self {
+        DrawtextOptions {
+            font_file: None,
+            font_size: 48,
+            font_color: "white".to_string(),
+            box_color: Some("black@0.5".to_string()),
+        }
+    }
+}
+
+impl File {
+    /// Formats the cues of this file as an ffmpeg filter script of `drawtext`
+    /// filters, one per cue, centered at the bottom of the video. Use it with
+    /// `ffmpeg -i in.mp4 -filter_script:v script.txt out.mp4`.
+    ///
+    /// Markup and cue settings are dropped; see [`File::to_ass_for_burnin`] to
+    /// keep them.
+    pub fn to_ffmpeg_drawtext(&self, options: &DrawtextOptions) -> String {
+        let mut filters = vec![];
+
+        for cue in self.cues() {
+            let mut args = vec![];
+
+            if let Some(font_file) = &options.font_file {
+                args.push(format!("fontfile={}", escape_option(font_file)));
+            }
+
+            args.push(format!("text={}", escape_option(&cue.plain_text())));
+            args.push("expansion=none".to_string());
+            args.push(format!("fontsize={}", options.font_size));
+            args.push(format!("fontcolor={}", escape_option(&options.font_color)));
+
+            if let Some(box_color) = &options.box_color {
+                args.push("box=1".to_string());
+                args.push(format!("boxcolor={}", escape_option(box_color)));
+                args.push(format!("boxborderw={}", options.font_size / 6));
+            }
+
+            args.push("x=(w-text_w)/2".to_string());
+            args.push("y=h-text_h-h/12".to_string());
+            args.push(format!(
+                "enable=between(t\\,{}\\,{})",
+                seconds(cue.start),
+                seconds(cue.end)
+            ));
+
+            filters.push(format!("drawtext={}", escape_filter(&args.join(":"))));
+        }
+
+        filters.join(",\n") + "\n"
+    }
+
+    /// Formats this file as an ASS subtitle file for ffmpeg's `ass` filter,
+    /// keeping italics, bold, underline, speakers and the position of
+    /// horizontal cues. Use it with `ffmpeg -i in.mp4 -vf ass=subs.ass
+    /// out.mp4`.
+    pub fn to_ass_for_burnin(&self) -> String {
+        let mut out = String::new();
+
+        let _ = write!(
+            out,
+            "[Script Info]\n\
+             ScriptType: v4.00+\n\
+             PlayResX: {ASS_WIDTH}\n\
+             PlayResY: {ASS_HEIGHT}\n\
+             ScaledBorderAndShadow: yes\n\
+             WrapStyle: 0\n\
+             \n\
+             [V4+ Styles]\n\
+             Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+             Style: Default,Arial,{},&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,2,1,2,60,60,50,1\n\
+             \n\
+             [Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+            ASS_HEIGHT * ASS_LINE_SIZE / 100.0,
+        );
+
+        for cue in self.cues() {
+            let speaker = cue.speaker().unwrap_or_default().replace(',', ";");
+
+            let _ = writeln!(
+                out,
+                "Dialogue: 0,{},{},Default,{speaker},0,0,0,,{}{}",
+                ass_timestamp(cue.start),
+                ass_timestamp(cue.end),
+                ass_position(cue),
+                ass_text(&text::strip_ruby(cue.nodes(), RubyPart::Base)),
+            );
+        }
+
+        out
+    }
+}
+
+/// Escapes a value for the option level of a filter description, where `:`
+/// separates options.
+fn escape_option(value: &str) -> String {
+    escape_chars(value, &['\\', '\'', ':'])
+}
+
+/// Escapes a filter's arguments for the filter graph level of a filter
+/// description, where `,` and `;` separate filters.
+fn escape_filter(args: &str) -> String {
+    escape_chars(args, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+fn escape_chars(value: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if special.contains(&c) {
+            out.push('\\');
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+fn seconds(time: Duration) -> String {
+    format!("{}.{:03}", time.as_secs(), time.subsec_millis())
+}
+
+/// Formats a time as `h:mm:ss.cc`.
+fn ass_timestamp(time: Duration) -> String {
+    let centis = time.as_millis() / 10;
+
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// Returns an override tag placing a horizontal cue with non-default settings
+/// where its cue box is.
+fn ass_position(cue: &Cue) -> String {
+    let settings = &cue.settings;
+
+    if *settings == CueSettings::default()
+        || settings.writing_direction != WritingDirection::Horizontal
+    {
+        return String::new();
+    }
+
+    let plain = cue.plain_text();
+    let direction = plain
+        .lines()
+        .find_map(crate::base_direction)
+        .unwrap_or(Direction::Ltr);
+
+    let cue_box = layout::cue_box(
+        settings,
+        direction,
+        ASS_LINE_SIZE,
+        plain.lines().count().max(1),
+    );
+
+    let (alignment, x) = match (settings.align, direction) {
+        (Align::Left, _) | (Align::Start, Direction::Ltr) | (Align::End, Direction::Rtl) => {
+            (7, cue_box.x)
+        }
+        (Align::Right, _) | (Align::End, Direction::Ltr) | (Align::Start, Direction::Rtl) => {
+            (9, cue_box.x + cue_box.width)
+        }
+        (Align::Center, _) => (8, cue_box.x + cue_box.width / 2.0),
+    };
+
+    format!(
+        "{{\\an{alignment}\\pos({:.0},{:.0})}}",
+        x * ASS_WIDTH / 100.0,
+        cue_box.y * ASS_HEIGHT / 100.0
+    )
+}
+
+fn ass_text(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    ass_text_into(&mut out, nodes);
+    out
+}
+
+fn ass_text_into(out: &mut String, nodes: &[Node]) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                for c in text.chars() {
+                    match c {
+                        '\n' => out.push_str("\\N"),
+                        '{' | '}' => {
+                            out.push('\\');
+                            out.push(c);
+                        }
+                        // keep a backslash from starting an escape like `\N`
+                        '\\' => out.push_str("\\\u{2060}"),
+                        c => out.push(c),
+                    }
+                }
+            }
+            Node::Timestamp(_) => {}
+            Node::Span(span) => {
+                let tag = match span.kind {
+                    SpanKind::Italic => Some('i'),
+                    SpanKind::Bold => Some('b'),
+                    SpanKind::Underline => Some('u'),
+                    _ => None,
+                };
+
+                if let Some(tag) = tag {
+                    let _ = write!(out, "{{\\{tag}1}}");
+                }
+
+                ass_text_into(out, &span.children);
+
+                if let Some(tag) = tag {
+                    let _ = write!(out, "{{\\{tag}0}}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Parsing files that are not known to be valid UTF-8.
+
+use crate::{parse_file, File, ParseError};
+
+/// What [`parse_bytes`] does with bytes that are not valid UTF-8.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Utf8Policy {
+    /// Fail with [`ParseError::InvalidUtf8`].
+    #[default]
+    Strict,
+    /// Replace each invalid sequence with U+FFFD REPLACEMENT CHARACTER.
+    Lossy,
+    /// Drop each block, such as a cue, that contains invalid bytes. Invalid
+    /// bytes in the header still fail the parse.
+    SkipInvalidCues,
+    /// Decode the whole input as Windows-1252 if it is not valid UTF-8, as
+    /// many old files converted from SRT are. Since every byte is a
+    /// Windows-1252 character, this never fails. Its printable characters are
+    /// a superset of Latin-1's, so Latin-1 files are decoded correctly too.
+    Windows1252,
+}
+
+/// Parses bytes as a WebVTT file, handling invalid UTF-8 as `policy` says.
+/// When cues are skipped, the byte offsets of the cues after them are
+/// offsets into the input without them.
+pub fn parse_bytes(input: &[u8], policy: Utf8Policy) -> Result<File, ParseError> {
+    match policy {
+        Utf8Policy::Strict => match std::str::from_utf8(input) {
+            Ok(input) => parse_file(input),
+            Err(err) => Err(ParseError::InvalidUtf8 {
+                offset: err.valid_up_to(),
+            }),
+        },
+        Utf8Policy::Lossy => parse_file(&String::from_utf8_lossy(input)),
+        Utf8Policy::SkipInvalidCues => parse_file(&skip_invalid_blocks(input)?),
+        Utf8Policy::Windows1252 => match std::str::from_utf8(input) {
+            Ok(input) => parse_file(input),
+            Err(_) => parse_file(&decode_windows_1252(input)),
+        },
+    }
+}
+
+/// The characters of the Windows-1252 bytes from 0x80 to 0x9F. The five
+/// bytes it leaves undefined are the C1 controls with the same value, as in
+/// the WHATWG Encoding Standard.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+fn decode_windows_1252(input: &[u8]) -> String {
+    input
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9f => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+            byte => char::from(byte),
+        })
+        .collect()
+}
+
+/// Returns the input without the blocks of lines that are not valid UTF-8.
+fn skip_invalid_blocks(input: &[u8]) -> Result<String, ParseError> {
+    let mut out = String::with_capacity(input.len());
+    let mut offset = 0;
+    let mut header = true;
+
+    while offset < input.len() {
+        // a block runs to the end of the next blank line
+        let mut end = offset;
+        let mut in_block = false;
+
+        while end < input.len() {
+            let line_end = input[end..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(input.len(), |idx| end + idx + 1);
+            let blank = input[end..line_end]
+                .iter()
+                .all(|&b| b == b'\n' || b == b'\r');
+
+            end = line_end;
+
+            if blank && in_block {
+                break;
+            }
+
+            in_block |= !blank;
+        }
+
+        match std::str::from_utf8(&input[offset..end]) {
+            Ok(block) => out.push_str(block),
+            Err(err) if header => {
+                return Err(ParseError::InvalidUtf8 {
+                    offset: offset + err.valid_up_to(),
+                })
+            }
+            Err(_) => {}
+        }
+
+        header = false;
+        offset = end;
+    }
+
+    Ok(out)
+}
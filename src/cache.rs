@@ -0,0 +1,374 @@
+//! A compact binary encoding of parsed files, so that they can be cached and
+//! loaded again without parsing.
+
+use std::time::Duration;
+
+use crate::{
+    Align, Block, Cue, CuePayload, CueSettings, ExtensionMap, File, HeaderSeparator, Line,
+    LineAlign, LineEnding, ParseError, Percentage, PositionAlign, SourceFormat, TimestampStyle,
+    WritingDirection,
+};
+
+const MAGIC: &[u8; 4] = b"VTTC";
+const VERSION: u8 = 3;
+
+const LINE_ENDINGS: [LineEnding; 2] = [LineEnding::Lf, LineEnding::CrLf];
+const TIMESTAMP_STYLES: [TimestampStyle; 2] = [TimestampStyle::Full, TimestampStyle::OmitZeroHours];
+const HEADER_SEPARATORS: [HeaderSeparator; 2] = [HeaderSeparator::Space, HeaderSeparator::Tab];
+const PAYLOADS: [CuePayload; 2] = [CuePayload::Text, CuePayload::Metadata];
+const WRITING_DIRECTIONS: [WritingDirection; 3] = [
+    WritingDirection::Horizontal,
+    WritingDirection::VerticalLeft,
+    WritingDirection::VerticalRight,
+];
+const LINE_ALIGNS: [LineAlign; 3] = [LineAlign::Start, LineAlign::Center, LineAlign::End];
+const POSITION_ALIGNS: [PositionAlign; 4] = [
+    PositionAlign::Auto,
+    PositionAlign::LineLeft,
+    PositionAlign::Center,
+    PositionAlign::LineRight,
+];
+const ALIGNS: [Align; 5] = [
+    Align::Start,
+    Align::Center,
+    Align::End,
+    Align::Left,
+    Align::Right,
+];
+
+impl File {
+    /// Encodes the file into bytes that [`File::from_cache_bytes`] decodes
+    /// into an identical file. The encoding is only meant to be read by the
+    /// same version of this crate.
+    pub fn to_cache_bytes(&self) -> Vec<u8> {
+        let mut out = Writer(MAGIC.to_vec());
+        out.u8(VERSION);
+
+        out.opt_str(self.description.as_deref());
+        out.strs(&self.header_lines);
+        out.variant(&LINE_ENDINGS, self.format.line_ending);
+        out.u8(self.format.bom as u8);
+        out.variant(&TIMESTAMP_STYLES, self.format.timestamps);
+        out.variant(&HEADER_SEPARATORS, self.format.header_separator);
+
+        out.u64(self.blocks.len() as u64);
+
+        for block in &self.blocks {
+            match block {
+                Block::Cue(cue) => {
+                    out.u8(0);
+                    out.cue(cue);
+                }
+                Block::Note(note) => {
+                    out.u8(1);
+                    out.str(note);
+                }
+                Block::Raw { lines } => {
+                    out.u8(2);
+                    out.strs(lines);
+                }
+            }
+        }
+
+        out.0
+    }
+
+    /// Decodes a file encoded by [`File::to_cache_bytes`].
+    pub fn from_cache_bytes(bytes: &[u8]) -> Result<File, ParseError> {
+        let bytes = bytes.strip_prefix(MAGIC).ok_or(ParseError::BadCache {
+            reason: "missing magic",
+        })?;
+
+        let mut input = Reader(bytes);
+
+        if input.u8()? != VERSION {
+            return Err(ParseError::BadCache {
+                reason: "unsupported version",
+            });
+        }
+
+        let description = input.opt_str()?;
+        let header_lines = input.strs()?;
+        let format = SourceFormat {
+            line_ending: input.variant(&LINE_ENDINGS)?,
+            bom: input.u8()? != 0,
+            timestamps: input.variant(&TIMESTAMP_STYLES)?,
+            header_separator: input.variant(&HEADER_SEPARATORS)?,
+        };
+
+        let count = input.len()?;
+        let mut blocks = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            blocks.push(match input.u8()? {
+                0 => Block::Cue(input.cue()?),
+                1 => Block::Note(input.str()?),
+                2 => Block::Raw {
+                    lines: input.strs()?,
+                },
+                _ => return Err(input.bad()),
+            });
+        }
+
+        if !input.0.is_empty() {
+            return Err(ParseError::BadCache {
+                reason: "trailing bytes",
+            });
+        }
+
+        Ok(File {
+            description,
+            header_lines,
+            blocks,
+            format,
+        })
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn duration(&mut self, value: Duration) {
+        self.u64(value.as_secs());
+        self.0
+            .extend_from_slice(&value.subsec_nanos().to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u64(value.len() as u64);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn opt_str(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.str(value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn strs(&mut self, values: &[String]) {
+        self.u64(values.len() as u64);
+
+        for value in values {
+            self.str(value);
+        }
+    }
+
+    fn variant<T: PartialEq>(&mut self, variants: &[T], value: T) {
+        let idx = variants.iter().position(|variant| *variant == value);
+        self.u8(idx.unwrap() as u8);
+    }
+
+    fn percentage(&mut self, value: Percentage) {
+        self.u64(value.get().to_bits());
+    }
+
+    fn cue(&mut self, cue: &Cue) {
+        self.duration(cue.start);
+        self.duration(cue.end);
+        self.str(&cue.id);
+        self.str(&cue.text);
+        self.settings(&cue.settings);
+        self.opt_str(cue.raw_settings.as_deref());
+        self.u64(cue.offset as u64);
+        self.u64(cue.source_order as u64);
+
+        self.u64(cue.extensions.len() as u64);
+
+        for (key, value) in cue.extensions.iter() {
+            self.str(key);
+            self.str(value);
+        }
+
+        self.strs(&cue.notes);
+        self.variant(&PAYLOADS, cue.payload);
+    }
+
+    fn settings(&mut self, settings: &CueSettings) {
+        self.opt_str(settings.region.as_deref());
+        self.variant(&WRITING_DIRECTIONS, settings.writing_direction);
+
+        match settings.line {
+            Line::Auto => self.u8(0),
+            Line::Number(number) => {
+                self.u8(1);
+                self.u64(number as u64);
+            }
+            Line::Percentage(percentage) => {
+                self.u8(2);
+                self.percentage(percentage);
+            }
+        }
+
+        self.variant(&LINE_ALIGNS, settings.line_align);
+
+        match settings.position {
+            Some(position) => {
+                self.u8(1);
+                self.percentage(position);
+            }
+            None => self.u8(0),
+        }
+
+        self.variant(&POSITION_ALIGNS, settings.position_align);
+        self.percentage(settings.size);
+        self.variant(&ALIGNS, settings.align);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bad(&self) -> ParseError {
+        ParseError::BadCache {
+            reason: "invalid data",
+        }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ParseError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(ParseError::BadCache {
+                reason: "truncated",
+            })?;
+
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, ParseError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    /// Reads a length, which cannot be more than the bytes left.
+    fn len(&mut self) -> Result<usize, ParseError> {
+        let len = self.u64()?;
+
+        if len > self.0.len() as u64 {
+            return Err(ParseError::BadCache {
+                reason: "truncated",
+            });
+        }
+
+        Ok(len as usize)
+    }
+
+    fn duration(&mut self) -> Result<Duration, ParseError> {
+        let secs = self.u64()?;
+        let nanos = u32::from_le_bytes(self.take()?);
+
+        if nanos >= 1_000_000_000 {
+            return Err(self.bad());
+        }
+
+        Ok(Duration::new(secs, nanos))
+    }
+
+    fn str(&mut self) -> Result<String, ParseError> {
+        let len = self.len()?;
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.bad())
+    }
+
+    fn opt_str(&mut self) -> Result<Option<String>, ParseError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => self.str().map(Some),
+            _ => Err(self.bad()),
+        }
+    }
+
+    fn strs(&mut self) -> Result<Vec<String>, ParseError> {
+        (0..self.len()?).map(|_| self.str()).collect()
+    }
+
+    fn variant<T: Copy>(&mut self, variants: &[T]) -> Result<T, ParseError> {
+        let idx = self.u8()? as usize;
+        variants.get(idx).copied().ok_or_else(|| self.bad())
+    }
+
+    fn percentage(&mut self) -> Result<Percentage, ParseError> {
+        Percentage::new(f64::from_bits(self.u64()?)).ok_or_else(|| self.bad())
+    }
+
+    fn cue(&mut self) -> Result<Cue, ParseError> {
+        let start = self.duration()?;
+        let end = self.duration()?;
+        let id = self.str()?;
+        let text = self.str()?;
+        let settings = self.settings()?;
+        let raw_settings = self.opt_str()?;
+        let offset = self.u64()? as usize;
+        let source_order = self.u64()? as usize;
+
+        let mut extensions = ExtensionMap::new();
+
+        for _ in 0..self.len()? {
+            let key = self.str()?;
+            extensions.insert(key, self.str()?);
+        }
+
+        Ok(Cue {
+            start,
+            end,
+            id,
+            text,
+            settings,
+            raw_settings,
+            offset,
+            source_order,
+            extensions,
+            notes: self.strs()?,
+            payload: self.variant(&PAYLOADS)?,
+        })
+    }
+
+    fn settings(&mut self) -> Result<CueSettings, ParseError> {
+        let region = self.opt_str()?;
+        let writing_direction = self.variant(&WRITING_DIRECTIONS)?;
+
+        let line = match self.u8()? {
+            0 => Line::Auto,
+            1 => Line::Number(self.u64()? as i64),
+            2 => Line::Percentage(self.percentage()?),
+            _ => return Err(self.bad()),
+        };
+
+        let line_align = self.variant(&LINE_ALIGNS)?;
+
+        let position = match self.u8()? {
+            0 => None,
+            1 => Some(self.percentage()?),
+            _ => return Err(self.bad()),
+        };
+
+        Ok(CueSettings {
+            region,
+            writing_direction,
+            line,
+            line_align,
+            position,
+            position_align: self.variant(&POSITION_ALIGNS)?,
+            size: self.percentage()?,
+            align: self.variant(&ALIGNS)?,
+        })
+    }
+}
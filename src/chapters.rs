@@ -0,0 +1,149 @@
+//! Conversion between chapter tracks and the chapters JSON of the Podcasting
+//! 2.0 namespace.
+//!
+//! The `img`, `url` and `toc` of a chapter are kept in the
+//! [extensions](crate::ExtensionMap) of its cue, so they survive a round trip
+//! through WebVTT.
+
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+use crate::{text, Block, ConvertError, Cue, ExtensionMap, File};
+
+const VERSION: &str = "1.2.0";
+
+fn seconds(time: Duration) -> Value {
+    json!(time.as_millis() as f64 / 1000.0)
+}
+
+fn bad(message: impl Into<String>) -> ConvertError {
+    ConvertError::BadChapters(message.into())
+}
+
+fn time(
+    chapter: &Map<String, Value>,
+    key: &str,
+    idx: usize,
+) -> Result<Option<Duration>, ConvertError> {
+    let Some(value) = chapter.get(key) else {
+        return Ok(None);
+    };
+
+    value
+        .as_f64()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .map(Some)
+        .ok_or_else(|| bad(format!("chapter {idx} has a bad {key}")))
+}
+
+impl File {
+    /// Exports the cues as Podcasting 2.0 chapters JSON, with the text of each
+    /// cue as the title of a chapter and the description of the file as the
+    /// title of the document.
+    pub fn to_podcast_chapters(&self) -> String {
+        let chapters: Vec<_> = self
+            .cues()
+            .map(|cue| {
+                let mut chapter = Map::new();
+                chapter.insert("startTime".into(), seconds(cue.start));
+                chapter.insert("endTime".into(), seconds(cue.end));
+                chapter.insert("title".into(), cue.plain_text().into());
+
+                for key in ["img", "url"] {
+                    if let Some(value) = cue.extensions.get(key) {
+                        chapter.insert(key.into(), value.into());
+                    }
+                }
+
+                if let Some(toc) = cue.extensions.get_parsed::<bool>("toc") {
+                    chapter.insert("toc".into(), toc.into());
+                }
+
+                Value::Object(chapter)
+            })
+            .collect();
+
+        let mut root = Map::new();
+        root.insert("version".into(), VERSION.into());
+
+        if let Some(description) = &self.description {
+            root.insert("title".into(), description.as_str().into());
+        }
+
+        root.insert("chapters".into(), chapters.into());
+
+        serde_json::to_string_pretty(&Value::Object(root)).unwrap()
+    }
+
+    /// Imports Podcasting 2.0 chapters JSON as a chapters track. A chapter
+    /// without an `endTime` ends where the next one starts, or for the last
+    /// chapter, at `media_duration`. Chapters are sorted by start time, as the
+    /// format does not require them to be.
+    pub fn from_podcast_chapters(
+        json: &str,
+        media_duration: Option<Duration>,
+    ) -> Result<File, ConvertError> {
+        let root: Value = serde_json::from_str(json).map_err(|err| bad(err.to_string()))?;
+
+        let chapters = root
+            .get("chapters")
+            .and_then(Value::as_array)
+            .ok_or_else(|| bad("missing chapters array"))?;
+
+        let mut parsed = vec![];
+
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let chapter = chapter
+                .as_object()
+                .ok_or_else(|| bad(format!("chapter {idx} is not an object")))?;
+
+            let start = time(chapter, "startTime", idx)?
+                .ok_or_else(|| bad(format!("chapter {idx} has no startTime")))?;
+            let end = time(chapter, "endTime", idx)?;
+
+            let mut extensions = ExtensionMap::new();
+
+            for key in ["img", "url"] {
+                if let Some(value) = chapter.get(key).and_then(Value::as_str) {
+                    extensions.insert(key, value);
+                }
+            }
+
+            if let Some(toc) = chapter.get("toc").and_then(Value::as_bool) {
+                extensions.insert("toc", toc);
+            }
+
+            let title = chapter.get("title").and_then(Value::as_str).unwrap_or("");
+
+            parsed.push((start, end, title, extensions));
+        }
+
+        parsed.sort_by_key(|&(start, ..)| start);
+
+        let starts: Vec<_> = parsed.iter().map(|&(start, ..)| start).collect();
+        let mut blocks = vec![];
+
+        for (idx, (start, end, title, extensions)) in parsed.into_iter().enumerate() {
+            let end = end
+                .or(starts.get(idx + 1).copied())
+                .or(media_duration)
+                .ok_or_else(|| bad("the last chapter has no endTime"))?;
+
+            blocks.push(Block::Cue(Cue {
+                start,
+                end,
+                text: text::escape(title),
+                extensions,
+                ..Default::default()
+            }));
+        }
+
+        Ok(File {
+            description: root.get("title").and_then(Value::as_str).map(String::from),
+            header_lines: vec![],
+            blocks,
+            format: Default::default(),
+        })
+    }
+}
@@ -0,0 +1,190 @@
+//! Conversion between the conventional color classes of captions converted
+//! from broadcast formats and explicit style rules.
+
+use std::collections::HashMap;
+
+use crate::{text::Node, Block, File};
+
+/// The colors of the spec's default classes, which follow those of CEA-608
+/// captions. Each has a class for the text color, like `.yellow`, and one for
+/// the background color, like `.bg_yellow`.
+const COLORS: [(&str, &str); 8] = [
+    ("white", "#ffffff"),
+    ("lime", "#00ff00"),
+    ("cyan", "#00ffff"),
+    ("red", "#ff0000"),
+    ("yellow", "#ffff00"),
+    ("magenta", "#ff00ff"),
+    ("blue", "#0000ff"),
+    ("black", "#000000"),
+];
+
+impl File {
+    /// Adds rules for the color classes used in the cues to a `STYLE` block,
+    /// so that players without built-in rules for them still show the colors.
+    /// The rules go into the first `STYLE` block of the file, leaving out any
+    /// it already has, or else into a new one at the start of the file.
+    /// Returns the number of rules added; no block is added if there are none.
+    pub fn add_color_styles(&mut self) -> usize {
+        let style = self.blocks.iter().position(|block| {
+            matches!(block, Block::Raw { lines }
+                if lines.first().is_some_and(|line| line.trim_end() == "STYLE"))
+        });
+
+        let existing = match style.map(|idx| &self.blocks[idx]) {
+            Some(Block::Raw { lines }) => lines.clone(),
+            _ => vec![],
+        };
+
+        let mut rules = vec![];
+
+        for usage in self.classes() {
+            let (property, name) = match usage.name.strip_prefix("bg_") {
+                Some(name) => ("background-color", name),
+                None => ("color", usage.name.as_str()),
+            };
+
+            let rule = format!("::cue(.{}) {{ {property}: {name}; }}", usage.name);
+
+            if COLORS.iter().any(|&(color, _)| color == name) && !existing.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+
+        let added = rules.len();
+
+        match style.map(|idx| &mut self.blocks[idx]) {
+            Some(Block::Raw { lines }) => lines.extend(rules),
+            _ if added > 0 => {
+                let lines = std::iter::once("STYLE".to_owned()).chain(rules).collect();
+                self.blocks.insert(0, Block::Raw { lines });
+            }
+            _ => {}
+        }
+
+        added
+    }
+
+    /// Replaces classes that `STYLE` blocks give one of the conventional colors
+    /// with the color classes themselves, so that the colors survive when the
+    /// styles are dropped. Only rules of the form `::cue(.name) { ... }` with
+    /// `color` and `background-color` declarations are understood. Returns the
+    /// number of cues changed.
+    pub fn apply_color_styles(&mut self) -> usize {
+        let mut classes: HashMap<String, Vec<String>> = HashMap::new();
+
+        for block in &self.blocks {
+            if let Block::Raw { lines } = block {
+                if lines.first().is_some_and(|line| line.trim_end() == "STYLE") {
+                    parse_color_rules(&lines[1..].join("\n"), &mut classes);
+                }
+            }
+        }
+
+        if classes.is_empty() {
+            return 0;
+        }
+
+        let mut changed = 0;
+
+        for cue in self.cues_mut() {
+            if !cue.text.contains('.') {
+                continue;
+            }
+
+            let mut nodes = cue.nodes();
+
+            if rename_classes(&mut nodes, &classes) {
+                cue.text = cue.format_nodes(&nodes);
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+}
+
+/// Finds the color classes that each class given a color in the style sheet
+/// stands for.
+fn parse_color_rules(css: &str, classes: &mut HashMap<String, Vec<String>>) {
+    for rule in css.split('}') {
+        let Some((selector, declarations)) = rule.split_once('{') else {
+            continue;
+        };
+
+        let Some(class) = selector
+            .trim()
+            .strip_prefix("::cue(.")
+            .and_then(|rest| rest.strip_suffix(')'))
+        else {
+            continue;
+        };
+
+        for declaration in declarations.split(';') {
+            let Some((property, value)) = declaration.split_once(':') else {
+                continue;
+            };
+
+            let prefix = match property.trim() {
+                "color" => "",
+                "background-color" => "bg_",
+                _ => continue,
+            };
+
+            if let Some(color) = color_name(value) {
+                classes
+                    .entry(class.to_owned())
+                    .or_default()
+                    .push(format!("{prefix}{color}"));
+            }
+        }
+    }
+}
+
+/// Returns the conventional color a CSS color value stands for, if any.
+pub(crate) fn color_name(value: &str) -> Option<&'static str> {
+    let value = value.trim().to_ascii_lowercase();
+
+    // expand short hex colors like `#ff0` to `#ffff00`
+    let value = match value.strip_prefix('#') {
+        Some(digits) if digits.len() == 3 => {
+            digits.chars().fold("#".to_owned(), |mut out, digit| {
+                out.push(digit);
+                out.push(digit);
+                out
+            })
+        }
+        _ => value,
+    };
+
+    COLORS
+        .iter()
+        .find(|&&(name, hex)| value == name || value == hex)
+        .map(|&(name, _)| name)
+}
+
+fn rename_classes(nodes: &mut [Node], classes: &HashMap<String, Vec<String>>) -> bool {
+    let mut changed = false;
+
+    for node in nodes {
+        if let Node::Span(span) = node {
+            let mut renamed: Vec<String> = vec![];
+
+            for class in &span.classes {
+                match classes.get(class) {
+                    Some(colors) => {
+                        renamed.extend(colors.iter().cloned());
+                        changed = true;
+                    }
+                    None => renamed.push(class.clone()),
+                }
+            }
+
+            renamed.dedup();
+            span.classes = renamed;
+            changed |= rename_classes(&mut span.children, classes);
+        }
+    }
+
+    changed
+}
@@ -0,0 +1,175 @@
+//! Triage of machine transcripts by the confidence of the recognizer, kept in
+//! cue [extensions](crate::ExtensionMap).
+//!
+//! The `confidence` extension holds the confidence of a whole cue, and the
+//! optional `word_confidence` extension holds one value per word of its text,
+//! separated by spaces:
+//!
+//! ```text
+//! NOTE extensions
+//! confidence=0.71
+//! word_confidence=0.98 0.95 0.22
+//!
+//! 00:01.000 --> 00:02.000
+//! Turn left here
+//! ```
+
+use crate::{
+    text::{Node, Span, SpanKind},
+    Cue, File,
+};
+
+const CONFIDENCE_KEY: &str = "confidence";
+const WORD_CONFIDENCE_KEY: &str = "word_confidence";
+
+impl Cue {
+    /// The confidence of the recognizer in this cue, from 0 to 1.
+    pub fn confidence(&self) -> Option<f64> {
+        self.extensions.get_parsed(CONFIDENCE_KEY)
+    }
+
+    /// The confidence of the recognizer in each word of this cue, if every
+    /// value parses.
+    pub fn word_confidence(&self) -> Option<Vec<f64>> {
+        self.extensions
+            .get(WORD_CONFIDENCE_KEY)?
+            .split_whitespace()
+            .map(|value| value.parse().ok())
+            .collect()
+    }
+
+    /// Wraps the parts of this cue that have a confidence below `min` in a
+    /// `<c>` span with the given class, returning whether anything was
+    /// wrapped. If the cue has a confidence for each of its words, only the
+    /// words below `min` are wrapped; otherwise the whole text is, if the
+    /// cue's confidence is below `min`.
+    pub fn mark_low_confidence(&mut self, min: f64, class: &str) -> bool {
+        let nodes = self.nodes();
+
+        if let Some(confidence) = self.word_confidence() {
+            let mut words = 0;
+            walk_words(&nodes, &mut |_| words += 1);
+
+            if words == confidence.len() {
+                let mut scores = confidence.into_iter();
+                let mut marked = false;
+
+                let nodes = mark_words(nodes, &mut scores, min, class, &mut marked);
+
+                if marked {
+                    self.set_nodes(&nodes);
+                }
+
+                return marked;
+            }
+        }
+
+        if !self.confidence().is_some_and(|confidence| confidence < min) {
+            return false;
+        }
+
+        self.set_nodes(&[span(class, nodes)]);
+        true
+    }
+}
+
+impl File {
+    /// Removes the cues with a confidence below `min`, returning how many were
+    /// removed. Cues without a confidence are kept.
+    pub fn filter_by_confidence(&mut self, min: f64) -> usize {
+        let before = self.blocks.len();
+
+        self.blocks.retain(|block| {
+            block
+                .as_cue()
+                .and_then(Cue::confidence)
+                .is_none_or(|confidence| confidence >= min)
+        });
+
+        before - self.blocks.len()
+    }
+
+    /// Marks the low-confidence parts of every cue, as in
+    /// [`Cue::mark_low_confidence`]. Returns the number of cues changed.
+    pub fn mark_low_confidence(&mut self, min: f64, class: &str) -> usize {
+        self.cues_mut()
+            .map(|cue| cue.mark_low_confidence(min, class))
+            .filter(|&marked| marked)
+            .count()
+    }
+}
+
+fn span(class: &str, children: Vec<Node>) -> Node {
+    Node::Span(Span {
+        kind: SpanKind::Class,
+        classes: vec![class.to_owned()],
+        children,
+    })
+}
+
+fn walk_words(nodes: &[Node], f: &mut impl FnMut(&str)) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => text.split_whitespace().for_each(&mut *f),
+            Node::Span(span) => walk_words(&span.children, f),
+            Node::Timestamp(_) => {}
+        }
+    }
+}
+
+fn mark_words(
+    nodes: Vec<Node>,
+    scores: &mut impl Iterator<Item = f64>,
+    min: f64,
+    class: &str,
+    marked: &mut bool,
+) -> Vec<Node> {
+    let mut out = vec![];
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                let mut buffer = String::new();
+                let mut rest = text.as_str();
+
+                while !rest.is_empty() {
+                    let start = rest
+                        .find(|c: char| !c.is_whitespace())
+                        .unwrap_or(rest.len());
+                    buffer.push_str(&rest[..start]);
+                    rest = &rest[start..];
+
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let word = &rest[..end];
+                    rest = &rest[end..];
+
+                    if word.is_empty() {
+                        continue;
+                    }
+
+                    if scores.next().is_some_and(|score| score < min) {
+                        if !buffer.is_empty() {
+                            out.push(Node::Text(std::mem::take(&mut buffer)));
+                        }
+
+                        out.push(span(class, vec![Node::Text(word.to_owned())]));
+                        *marked = true;
+                    } else {
+                        buffer.push_str(word);
+                    }
+                }
+
+                if !buffer.is_empty() {
+                    out.push(Node::Text(buffer));
+                }
+            }
+            Node::Span(span) => out.push(Node::Span(Span {
+                children: mark_words(span.children, scores, min, class, marked),
+                ..span
+            })),
+            node => out.push(node),
+        }
+    }
+
+    out
+}
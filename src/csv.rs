@@ -0,0 +1,192 @@
+//! Export and import of cues as comma- or tab-separated values, for review in
+//! spreadsheets.
+
+use std::collections::HashMap;
+
+use crate::{
+    format_timestamp, parse_timestamp,
+    settings::parse_settings,
+    text::{self, SpanKind},
+    Block, ConvertError, Cue, File,
+};
+
+const COLUMNS: [&str; 6] = ["id", "start", "end", "speaker", "text", "settings"];
+
+impl File {
+    /// Exports the cues as CSV, with a header row followed by one row per cue
+    /// with the columns `id`, `start`, `end`, `speaker`, `text` and
+    /// `settings`. The speaker is the name of the cue's voice, which is
+    /// removed from the text column.
+    pub fn to_csv(&self) -> String {
+        self.to_delimited(',')
+    }
+
+    /// Exports the cues as tab-separated values, like [`File::to_csv`].
+    pub fn to_tsv(&self) -> String {
+        self.to_delimited('\t')
+    }
+
+    /// Imports cues from CSV as produced by [`File::to_csv`]. Columns are
+    /// matched by the names in the header row, so they may be reordered, and
+    /// only `start` and `end` are required.
+    pub fn from_csv(input: &str) -> Result<File, ConvertError> {
+        Self::from_delimited(input, ',')
+    }
+
+    /// Imports cues from tab-separated values, like [`File::from_csv`].
+    pub fn from_tsv(input: &str) -> Result<File, ConvertError> {
+        Self::from_delimited(input, '\t')
+    }
+
+    fn to_delimited(&self, delimiter: char) -> String {
+        let mut out = String::new();
+        write_record(&mut out, delimiter, COLUMNS.map(String::from));
+
+        for cue in self.cues() {
+            let speaker = cue.speaker();
+
+            let nodes = text::unwrap_spans(
+                cue.nodes(),
+                &mut |span| matches!(&span.kind, SpanKind::Voice { name } if Some(name) == speaker.as_ref()),
+            );
+
+            write_record(
+                &mut out,
+                delimiter,
+                [
+                    cue.id.clone(),
+                    format_timestamp(cue.start),
+                    format_timestamp(cue.end),
+                    speaker.unwrap_or_default(),
+                    cue.format_nodes(&nodes),
+                    cue.settings.to_string(),
+                ],
+            );
+        }
+
+        out
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(input), fields(bytes = input.len()), err)
+    )]
+    fn from_delimited(input: &str, delimiter: char) -> Result<File, ConvertError> {
+        let mut records = read_records(input, delimiter).into_iter();
+
+        let header = records.next().unwrap_or_default();
+        let columns: HashMap<_, _> = header
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.trim().to_ascii_lowercase(), idx))
+            .collect();
+
+        let mut blocks = vec![];
+
+        for (idx, record) in records.enumerate() {
+            let row = idx + 2;
+
+            if record.iter().all(|field| field.is_empty()) {
+                continue;
+            }
+
+            let field = |name: &str| {
+                columns
+                    .get(name)
+                    .and_then(|&idx| record.get(idx))
+                    .map_or("", String::as_str)
+            };
+
+            let timestamp = |name: &'static str| match parse_timestamp(field(name).trim()) {
+                Some((time, "")) => Ok(time),
+                _ => Err(ConvertError::BadCsv { row, column: name }),
+            };
+
+            let speaker = field("speaker");
+            let text = if speaker.is_empty() {
+                field("text").to_owned()
+            } else {
+                format!("<v {}>{}", text::escape(speaker), field("text"))
+            };
+
+            blocks.push(Block::Cue(Cue {
+                id: field("id").to_owned(),
+                start: timestamp("start")?,
+                end: timestamp("end")?,
+                text,
+                settings: parse_settings(field("settings")),
+                ..Default::default()
+            }));
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(cues = blocks.len(), "imported cues");
+
+        Ok(File {
+            description: None,
+            header_lines: vec![],
+            blocks,
+            format: Default::default(),
+        })
+    }
+}
+
+fn write_record(out: &mut String, delimiter: char, fields: [String; 6]) {
+    for (idx, field) in fields.iter().enumerate() {
+        if idx > 0 {
+            out.push(delimiter);
+        }
+
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+
+    out.push('\n');
+}
+
+/// Splits the input into records of fields. Fields may be quoted with `"`, in
+/// which case they may contain delimiters, newlines and doubled quotes.
+fn read_records(input: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        if quoted {
+            match char {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(char),
+            }
+        } else if char == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else {
+            match char {
+                '"' if field.is_empty() => quoted = true,
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => field.push(char),
+            }
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
@@ -0,0 +1,205 @@
+//! Mapping media times to wall-clock times, for the archives of live streams,
+//! whose HLS playlists tie segments to a date with `#EXT-X-PROGRAM-DATE-TIME`.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::Cue;
+
+const TAG: &str = "#EXT-X-PROGRAM-DATE-TIME:";
+
+/// A wall-clock time and the media time it corresponds to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProgramDateTime {
+    pub wall_clock: SystemTime,
+    pub media_time: Duration,
+}
+
+impl ProgramDateTime {
+    pub fn new(wall_clock: SystemTime, media_time: Duration) -> Self {
+        ProgramDateTime {
+            wall_clock,
+            media_time,
+        }
+    }
+
+    /// Parses an ISO 8601 date and time, like `2024-03-01T12:00:00.250Z` or
+    /// `2024-03-01T14:00:00+02:00`, optionally as a whole
+    /// `#EXT-X-PROGRAM-DATE-TIME` tag, anchored at `media_time`.
+    pub fn parse(input: &str, media_time: Duration) -> Option<Self> {
+        let input = input.trim();
+        let input = input.strip_prefix(TAG).unwrap_or(input);
+
+        Some(ProgramDateTime {
+            wall_clock: parse_date_time(input)?,
+            media_time,
+        })
+    }
+
+    /// Returns the wall-clock time at a media time.
+    pub fn wall_clock_at(&self, media_time: Duration) -> SystemTime {
+        if media_time >= self.media_time {
+            self.wall_clock + (media_time - self.media_time)
+        } else {
+            self.wall_clock - (self.media_time - media_time)
+        }
+    }
+
+    /// Returns the media time at a wall-clock time, or `None` if it would be
+    /// before the start of the media.
+    pub fn media_time_at(&self, wall_clock: SystemTime) -> Option<Duration> {
+        match wall_clock.duration_since(self.wall_clock) {
+            Ok(after) => Some(self.media_time + after),
+            Err(err) => self.media_time.checked_sub(err.duration()),
+        }
+    }
+}
+
+/// Formats as an `#EXT-X-PROGRAM-DATE-TIME` tag, in UTC with milliseconds.
+impl fmt::Display for ProgramDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{TAG}{}", format_date_time(self.wall_clock))
+    }
+}
+
+impl Cue {
+    /// Returns the wall-clock times at which this cue starts and ends.
+    pub fn absolute_time(&self, anchor: &ProgramDateTime) -> (SystemTime, SystemTime) {
+        (
+            anchor.wall_clock_at(self.start),
+            anchor.wall_clock_at(self.end),
+        )
+    }
+}
+
+/// Returns the number of days since 1970-01-01 of a date in the proleptic
+/// Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// The inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+fn number(input: &str, digits: usize) -> Option<(u32, &str)> {
+    let (number, rest) = input.split_at_checked(digits)?;
+
+    if !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((number.parse().ok()?, rest))
+}
+
+fn parse_date_time(input: &str) -> Option<SystemTime> {
+    let (year, rest) = number(input, 4)?;
+    let (month, rest) = number(rest.strip_prefix('-')?, 2)?;
+    let (day, rest) = number(rest.strip_prefix('-')?, 2)?;
+    let rest = rest.strip_prefix(['T', 't'])?;
+    let (hour, rest) = number(rest, 2)?;
+    let (minute, rest) = number(rest.strip_prefix(':')?, 2)?;
+    let (second, mut rest) = number(rest.strip_prefix(':')?, 2)?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    // leap seconds are folded into the second before them
+    let second = second.min(59);
+    let mut nanos = 0;
+
+    if let Some(fraction) = rest.strip_prefix(['.', ',']) {
+        let digits = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+
+        if digits == 0 {
+            return None;
+        }
+
+        for (idx, digit) in fraction[..digits.min(9)].bytes().enumerate() {
+            nanos += u32::from(digit - b'0') * 10u32.pow(8 - idx as u32);
+        }
+
+        rest = &fraction[digits..];
+    }
+
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.chars().next()? {
+                '+' => 1,
+                '-' => -1,
+                _ => return None,
+            };
+            let (hours, rest) = number(&rest[1..], 2)?;
+            let rest = rest.strip_prefix(':').unwrap_or(rest);
+            let (minutes, rest) = number(rest, 2)?;
+
+            if !rest.is_empty() {
+                return None;
+            }
+
+            sign * i64::from(hours * 3600 + minutes * 60)
+        }
+    };
+
+    let seconds = days_from_civil(i64::from(year), month, day) * 86400
+        + i64::from(hour * 3600 + minute * 60 + second)
+        - offset;
+
+    let time = if seconds >= 0 {
+        UNIX_EPOCH + Duration::new(seconds as u64, nanos)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+            + Duration::from_nanos(nanos.into())
+    };
+
+    Some(time)
+}
+
+/// Formats a time in ISO 8601, in UTC with milliseconds, like
+/// `2024-03-01T12:00:00.250Z`.
+pub fn format_date_time(time: SystemTime) -> String {
+    let (seconds, millis) = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as i64, since.subsec_millis()),
+        Err(err) => {
+            let before = err.duration();
+            let millis = (1000 - before.subsec_millis()) % 1000;
+            let seconds = -(before.as_secs() as i64) - i64::from(millis > 0);
+            (seconds, millis)
+        }
+    };
+
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let time_of_day = seconds.rem_euclid(86400);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
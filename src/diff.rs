@@ -0,0 +1,118 @@
+//! The changes between two versions of a file, cue by cue, as events that
+//! collaborative editors can broadcast.
+
+use crate::{matching::match_cues, Cue, File};
+
+/// A change to one cue. Cues are matched across versions as in
+/// [`merge_edits`](crate::merge_edits): by id where each version uses it
+/// once, and otherwise by aligning them on their timings and text. Times are
+/// in milliseconds.
+///
+/// With the `serde` feature, events serialize with a `type` field naming the
+/// variant, like `{"type": "retimed", "cue_index": 3, ...}`.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum CueEvent {
+    Added {
+        /// Index of the cue among the cues of the new version.
+        cue_index: usize,
+        id: String,
+        start_ms: u64,
+        end_ms: u64,
+        text: String,
+    },
+    Removed {
+        /// Index of the cue among the cues of the old version.
+        cue_index: usize,
+        id: String,
+    },
+    Retimed {
+        /// Index of the cue among the cues of the new version.
+        cue_index: usize,
+        id: String,
+        old_start_ms: u64,
+        old_end_ms: u64,
+        start_ms: u64,
+        end_ms: u64,
+    },
+    TextChanged {
+        /// Index of the cue among the cues of the new version.
+        cue_index: usize,
+        id: String,
+        old_text: String,
+        text: String,
+    },
+}
+
+fn millis(cue: &Cue) -> (u64, u64) {
+    (cue.start.as_millis() as u64, cue.end.as_millis() as u64)
+}
+
+impl File {
+    /// Returns the changes that turn this file into `new`: the removed cues
+    /// in their old order, then the added and changed cues in their new
+    /// order. A cue that was retimed and had its text changed has an event
+    /// for each.
+    pub fn diff(&self, new: &File) -> Vec<CueEvent> {
+        let old_cues: Vec<_> = self.cues().collect();
+        let new_cues: Vec<_> = new.cues().collect();
+        let matches = match_cues(&old_cues, &new_cues);
+
+        let mut kept = vec![false; old_cues.len()];
+
+        for &old_idx in matches.iter().flatten() {
+            kept[old_idx] = true;
+        }
+
+        let mut events: Vec<_> = old_cues
+            .iter()
+            .enumerate()
+            .filter(|&(cue_index, _)| !kept[cue_index])
+            .map(|(cue_index, cue)| CueEvent::Removed {
+                cue_index,
+                id: cue.id.clone(),
+            })
+            .collect();
+
+        for (cue_index, (cue, old_idx)) in new_cues.iter().zip(matches).enumerate() {
+            let (start_ms, end_ms) = millis(cue);
+
+            let Some(old) = old_idx.map(|old_idx| old_cues[old_idx]) else {
+                events.push(CueEvent::Added {
+                    cue_index,
+                    id: cue.id.clone(),
+                    start_ms,
+                    end_ms,
+                    text: cue.text.clone(),
+                });
+                continue;
+            };
+
+            let (old_start_ms, old_end_ms) = millis(old);
+
+            if (old_start_ms, old_end_ms) != (start_ms, end_ms) {
+                events.push(CueEvent::Retimed {
+                    cue_index,
+                    id: cue.id.clone(),
+                    old_start_ms,
+                    old_end_ms,
+                    start_ms,
+                    end_ms,
+                });
+            }
+
+            if old.text != cue.text {
+                events.push(CueEvent::TextChanged {
+                    cue_index,
+                    id: cue.id.clone(),
+                    old_text: old.text.clone(),
+                    text: cue.text.clone(),
+                });
+            }
+        }
+
+        events
+    }
+}
@@ -0,0 +1,192 @@
+//! Editing a file with undo and redo, the state a caption editor keeps.
+
+use std::sync::Arc;
+
+use crate::{CueEvent, File};
+
+/// What happened to the file of an [`EditSession`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChangeKind {
+    Edit,
+    Undo,
+    Redo,
+}
+
+/// A change made to the file of an [`EditSession`], passed to its listeners.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    /// The label of the edit that was made, undone or redone.
+    pub label: String,
+    /// What the change did to each cue, as in [`File::diff`].
+    pub events: Vec<CueEvent>,
+}
+
+/// A state of the file, as it was before or after an edit.
+#[derive(Clone)]
+struct Revision {
+    file: Arc<File>,
+    label: String,
+}
+
+type Listener = Box<dyn FnMut(&Change) + Send>;
+
+/// Wraps a [`File`] to record each edit so that it can be undone and redone.
+///
+/// Revisions share the file through an [`Arc`], so an edit only copies the
+/// file when an earlier revision still refers to it.
+pub struct EditSession {
+    file: Arc<File>,
+    undo: Vec<Revision>,
+    redo: Vec<Revision>,
+    /// The label of the open group, and the file as it was when it opened.
+    group: Option<(String, Arc<File>)>,
+    listeners: Vec<Listener>,
+}
+
+impl EditSession {
+    pub fn new(file: File) -> Self {
+        EditSession {
+            file: Arc::new(file),
+            undo: vec![],
+            redo: vec![],
+            group: None,
+            listeners: vec![],
+        }
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn into_file(self) -> File {
+        Arc::unwrap_or_clone(self.file)
+    }
+
+    /// Registers a function to call after every edit, undo and redo.
+    pub fn subscribe(&mut self, listener: impl FnMut(&Change) + Send + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Applies an edit to the file as one undoable step, unless a group is
+    /// open, in which case it becomes part of the group's step.
+    pub fn edit<R>(&mut self, label: &str, edit: impl FnOnce(&mut File) -> R) -> R {
+        if self.group.is_none() {
+            self.undo.push(Revision {
+                file: self.file.clone(),
+                label: label.to_owned(),
+            });
+            self.redo.clear();
+        }
+
+        // the file as it was is only needed to tell listeners what changed
+        let before = (!self.listeners.is_empty()).then(|| self.file.clone());
+        let result = edit(Arc::make_mut(&mut self.file));
+
+        if let Some(before) = before {
+            self.notify(ChangeKind::Edit, label, &before);
+        }
+
+        result
+    }
+
+    /// Opens a group, so that the edits made until [`EditSession::end_group`]
+    /// are undone and redone together. Groups do not nest: opening a group
+    /// while one is open closes the first.
+    pub fn begin_group(&mut self, label: &str) {
+        self.end_group();
+        self.group = Some((label.to_owned(), self.file.clone()));
+    }
+
+    /// Closes the open group, if any, recording it as a single step if it
+    /// made any edits.
+    pub fn end_group(&mut self) {
+        let Some((label, before)) = self.group.take() else {
+            return;
+        };
+
+        if !Arc::ptr_eq(&before, &self.file) {
+            self.undo.push(Revision {
+                file: before,
+                label,
+            });
+            self.redo.clear();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The label of the step that [`EditSession::undo`] would undo.
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.last().map(|revision| revision.label.as_str())
+    }
+
+    /// The label of the step that [`EditSession::redo`] would redo.
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|revision| revision.label.as_str())
+    }
+
+    /// Undoes the last step, closing the open group first. Returns whether
+    /// there was a step to undo.
+    pub fn undo(&mut self) -> bool {
+        self.end_group();
+
+        let Some(revision) = self.undo.pop() else {
+            return false;
+        };
+
+        let current = std::mem::replace(&mut self.file, revision.file);
+        self.notify(ChangeKind::Undo, &revision.label, &current);
+
+        self.redo.push(Revision {
+            file: current,
+            label: revision.label,
+        });
+
+        true
+    }
+
+    /// Redoes the last undone step. Returns whether there was a step to redo.
+    pub fn redo(&mut self) -> bool {
+        self.end_group();
+
+        let Some(revision) = self.redo.pop() else {
+            return false;
+        };
+
+        let current = std::mem::replace(&mut self.file, revision.file);
+        self.notify(ChangeKind::Redo, &revision.label, &current);
+
+        self.undo.push(Revision {
+            file: current,
+            label: revision.label,
+        });
+
+        true
+    }
+
+    /// Tells the listeners about a change from the file `before` to the
+    /// current one.
+    fn notify(&mut self, kind: ChangeKind, label: &str, before: &File) {
+        if self.listeners.is_empty() {
+            return;
+        }
+
+        let change = Change {
+            kind,
+            label: label.to_owned(),
+            events: before.diff(&self.file),
+        };
+
+        for listener in &mut self.listeners {
+            listener(&change);
+        }
+    }
+}
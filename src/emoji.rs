@@ -0,0 +1,142 @@
+//! Finding, stripping and replacing emoji in cue text, for pipelines that
+//! only accept text in a legacy character set.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    text::{Node, Span},
+    Cue, File,
+};
+
+/// What to do with emoji in cue text.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EmojiPolicy {
+    /// Leave them, only reporting them.
+    #[default]
+    Keep,
+    /// Remove them, along with a space next to them so that no double spaces
+    /// are left.
+    Strip,
+    /// Replace each of them with some text, like `[emoji]`.
+    Replace(String),
+}
+
+/// The emoji found in a cue.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EmojiReport {
+    pub cue_index: usize,
+    /// Each emoji, in order, as written in the cue. A sequence like a flag or
+    /// a family joined with zero-width joiners is one emoji.
+    pub emoji: Vec<String>,
+}
+
+/// Returns whether a character is a pictograph shown as an emoji by default.
+fn is_pictograph(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1F000}'..='\u{1FAFF}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{231A}'
+            | '\u{231B}'
+            | '\u{23E9}'..='\u{23F3}'
+            | '\u{23F8}'..='\u{23FA}'
+            | '\u{2B1B}'
+            | '\u{2B1C}'
+            | '\u{2B50}'
+            | '\u{2B55}'
+    )
+}
+
+/// Returns whether a grapheme cluster is an emoji: a pictograph, or any
+/// character made into an emoji by a variation selector or a keycap, like
+/// `©️` and `1️⃣`.
+fn is_emoji(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .any(|c| is_pictograph(c) || c == '\u{FE0F}' || c == '\u{20E3}')
+}
+
+fn apply_to_text(text: &str, policy: &EmojiPolicy, found: &mut Vec<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut graphemes = text.graphemes(true).peekable();
+
+    while let Some(grapheme) = graphemes.next() {
+        if !is_emoji(grapheme) {
+            out.push_str(grapheme);
+            continue;
+        }
+
+        found.push(grapheme.to_owned());
+
+        match policy {
+            EmojiPolicy::Keep => out.push_str(grapheme),
+            EmojiPolicy::Replace(replacement) => out.push_str(replacement),
+            EmojiPolicy::Strip => {
+                let after_space = out.is_empty() || out.ends_with([' ', '\n']);
+
+                match graphemes.peek() {
+                    Some(&" ") if after_space => {
+                        graphemes.next();
+                    }
+                    None | Some(&"\n") if out.ends_with(' ') => {
+                        out.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn apply_to_nodes(nodes: Vec<Node>, policy: &EmojiPolicy, found: &mut Vec<String>) -> Vec<Node> {
+    nodes
+        .into_iter()
+        .map(|node| match node {
+            Node::Text(text) => Node::Text(apply_to_text(&text, policy, found)),
+            Node::Span(span) => Node::Span(Span {
+                children: apply_to_nodes(span.children, policy, found),
+                ..span
+            }),
+            node => node,
+        })
+        .collect()
+}
+
+impl Cue {
+    /// Applies an emoji policy to the text of this cue, keeping its markup.
+    /// Returns the emoji found, as in [`EmojiReport::emoji`]. Metadata cues
+    /// are left as they are.
+    pub fn apply_emoji_policy(&mut self, policy: &EmojiPolicy) -> Vec<String> {
+        if self.is_metadata() {
+            return vec![];
+        }
+
+        let mut found = vec![];
+        let nodes = apply_to_nodes(self.nodes(), policy, &mut found);
+
+        if !found.is_empty() && *policy != EmojiPolicy::Keep {
+            self.set_nodes(&nodes);
+        }
+
+        found
+    }
+}
+
+impl File {
+    /// Applies an emoji policy to every cue, as in
+    /// [`Cue::apply_emoji_policy`]. Returns a report for each cue that has
+    /// emoji; with [`EmojiPolicy::Keep`], this only finds them.
+    pub fn apply_emoji_policy(&mut self, policy: &EmojiPolicy) -> Vec<EmojiReport> {
+        self.cues_mut()
+            .enumerate()
+            .filter_map(|(cue_index, cue)| {
+                let emoji = cue.apply_emoji_policy(policy);
+
+                (!emoji.is_empty()).then_some(EmojiReport { cue_index, emoji })
+            })
+            .collect()
+    }
+}
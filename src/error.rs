@@ -0,0 +1,106 @@
+//! The errors returned by the crate, grouped by what failed.
+
+use thiserror::Error;
+
+use crate::{PatchError, TemplateError};
+
+/// An error from reading WebVTT, or data derived from it like a
+/// [`CueIndex`](crate::CueIndex).
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseError {
+    #[error("missing file magic")]
+    NoMagic,
+
+    #[error("bad file header {line:?}: expected whitespace after WEBVTT")]
+    BadHeader { line: String },
+
+    #[error("unexpected end-of-file")]
+    UnexpectedEof,
+
+    #[error("another WEBVTT header at byte {offset}, as if several files were concatenated")]
+    RepeatedHeader { offset: usize },
+
+    #[error("bad cue index: {reason}")]
+    BadIndex { reason: &'static str },
+
+    #[error("bad cached file: {reason}")]
+    BadCache { reason: &'static str },
+
+    #[error("invalid UTF-8 at byte {offset}")]
+    InvalidUtf8 { offset: usize },
+}
+
+/// An error that keeps a file from being checked.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    #[cfg(feature = "json-schema")]
+    #[error("bad JSON schema: {0}")]
+    BadSchema(String),
+}
+
+/// An error from converting another format into WebVTT.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConvertError {
+    #[error("bad {column} in CSV row {row}")]
+    BadCsv { row: usize, column: &'static str },
+
+    #[error("bad timed element {element} in HTML: {reason}")]
+    BadHtml {
+        element: usize,
+        reason: &'static str,
+    },
+
+    #[cfg(feature = "json")]
+    #[error("bad podcast chapters: {0}")]
+    BadChapters(String),
+
+    #[cfg(feature = "srv3")]
+    #[error("bad srv3 document: {0}")]
+    BadSrv3(String),
+
+    #[cfg(feature = "xliff")]
+    #[error("bad XLIFF document: {0}")]
+    BadXliff(String),
+}
+
+/// Any error returned by the crate, for callers that do not need to tell them
+/// apart.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+
+    #[error(transparent)]
+    Convert(#[from] ConvertError),
+
+    #[error(transparent)]
+    Patch(#[from] PatchError),
+
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+}
+
+/// Runs `run`, turning a panic into its message, so that the bindings can
+/// report a bug as an error instead of unwinding into the host.
+#[cfg(any(
+    feature = "ffi",
+    feature = "napi",
+    feature = "python",
+    feature = "uniffi"
+))]
+pub(crate) fn catch_panic<T>(run: impl FnOnce() -> T) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(run)).map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_owned())
+    })
+}
@@ -0,0 +1,174 @@
+//! User data attached to cues, such as confidence scores or ASR metadata.
+//!
+//! Extensions are written as a `NOTE extensions` block directly before their
+//! cue, with one `key=value` line per entry, and attached back to the cue when
+//! the file is parsed again:
+//!
+//! ```text
+//! NOTE extensions
+//! confidence=0.93
+//! asr.model=large-v3
+//!
+//! 00:01.000 --> 00:02.000
+//! Hello
+//! ```
+
+use std::{collections::BTreeMap, str::FromStr};
+
+/// The first line of the NOTE blocks that hold a cue's extensions.
+pub(crate) const NOTE_HEADER: &str = "NOTE extensions";
+
+/// A map of string keys to string values, kept in key order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ExtensionMap(BTreeMap<String, String>);
+
+impl ExtensionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Sets the value for a key, returning the previous value.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl ToString) -> Option<String> {
+        self.0.insert(key.into(), value.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Returns the value for a key parsed as a `T`, or `None` if it is missing
+    /// or does not parse.
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.parse().ok()
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.0.remove(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Formats the map as the text of a NOTE block, including the
+    /// `NOTE extensions` line.
+    pub(crate) fn to_note(&self) -> String {
+        self.to_note_with_header(NOTE_HEADER)
+    }
+
+    /// Formats the map as the text of a NOTE block with the given first line.
+    pub(crate) fn to_note_with_header(&self, header: &str) -> String {
+        let mut out = header.to_string();
+
+        for (key, value) in self.iter() {
+            out.push('\n');
+            out.push_str(&escape(key));
+            out.push('=');
+            out.push_str(&escape(value));
+        }
+
+        out
+    }
+
+    /// Parses the text of a NOTE block written by [`ExtensionMap::to_note`],
+    /// returning `None` if it is some other comment.
+    pub(crate) fn from_note(note: &str) -> Option<Self> {
+        Self::from_note_with_header(note, NOTE_HEADER)
+    }
+
+    /// Parses the text of a NOTE block written by
+    /// [`ExtensionMap::to_note_with_header`] with the given first line.
+    pub(crate) fn from_note_with_header(note: &str, header: &str) -> Option<Self> {
+        let mut lines = note.lines();
+
+        if lines.next()? != header {
+            return None;
+        }
+
+        let mut map = ExtensionMap::new();
+
+        for line in lines {
+            let (key, value) = split_unescaped(line);
+            map.0.insert(unescape(key), unescape(value));
+        }
+
+        Some(map)
+    }
+}
+
+impl<K: Into<String>, V: ToString> FromIterator<(K, V)> for ExtensionMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = ExtensionMap::new();
+
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+
+        map
+    }
+}
+
+/// Escapes a key or value so that it stays on one line, does not contain an
+/// unescaped `=`, and cannot form the `-->` that would end a NOTE block.
+fn escape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\\' | '=' | '>' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn unescape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(escaped) => out.push(escaped),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+/// Splits a line at its first unescaped `=`.
+fn split_unescaped(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+
+    for (idx, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' => return (&line[..idx], &line[idx + 1..]),
+            _ => {}
+        }
+    }
+
+    (line, "")
+}
@@ -0,0 +1,223 @@
+//! A C interface to the parser, for players written in C or C++. The
+//! declarations are in `include/webvtt.h`, generated with cbindgen. Build a
+//! library to link against with
+//! `cargo rustc --release --features ffi --crate-type staticlib` (or `cdylib`).
+//!
+//! Files are passed around as opaque [`VttFile`] handles. Strings returned by
+//! a handle live as long as the handle does. When a function returns NULL,
+//! `vtt_last_error` tells why.
+
+use std::{
+    cell::Cell,
+    ffi::{c_char, c_int, CString},
+    ptr, slice,
+};
+
+use crate::{error::catch_panic, parse_file, File};
+
+/// The last call succeeded.
+pub const VTT_OK: c_int = 0;
+/// The input was not valid UTF-8.
+pub const VTT_ERROR_UTF8: c_int = 1;
+/// The input was not a WebVTT file.
+pub const VTT_ERROR_PARSE: c_int = 2;
+/// The library panicked, which is a bug in it. The panic was caught so that
+/// it does not unwind into the caller.
+pub const VTT_ERROR_PANIC: c_int = 3;
+
+thread_local! {
+    static LAST_ERROR: Cell<c_int> = const { Cell::new(VTT_OK) };
+}
+
+fn set_last_error(code: c_int) {
+    LAST_ERROR.with(|last| last.set(code));
+}
+
+/// A parsed WebVTT file.
+pub struct VttFile {
+    file: File,
+    cues: Vec<VttCue>,
+}
+
+struct VttCue {
+    start_ms: u64,
+    end_ms: u64,
+    id: CString,
+    text: CString,
+}
+
+/// Copies a string into a C string, replacing NULs, which C strings cannot
+/// hold, with U+FFFD as the spec does when parsing.
+fn c_string(value: &str) -> CString {
+    CString::new(value.replace('\0', "\u{fffd}")).unwrap()
+}
+
+impl VttFile {
+    fn new(file: File) -> Self {
+        let cues = file
+            .cues()
+            .map(|cue| VttCue {
+                start_ms: cue.start.as_millis() as u64,
+                end_ms: cue.end.as_millis() as u64,
+                id: c_string(&cue.id),
+                text: c_string(&cue.text),
+            })
+            .collect();
+
+        VttFile { file, cues }
+    }
+}
+
+/// Parses `len` bytes of UTF-8 at `input` as a WebVTT file. Returns NULL if
+/// the input is not valid UTF-8 or not a WebVTT file, setting the error of
+/// `vtt_last_error`. The handle must be freed with `vtt_file_free`.
+///
+/// # Safety
+///
+/// `input` must point to at least `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_parse(input: *const c_char, len: usize) -> *mut VttFile {
+    if input.is_null() {
+        set_last_error(VTT_ERROR_PARSE);
+        return ptr::null_mut();
+    }
+
+    let bytes = slice::from_raw_parts(input.cast::<u8>(), len);
+
+    let Ok(input) = std::str::from_utf8(bytes) else {
+        set_last_error(VTT_ERROR_UTF8);
+        return ptr::null_mut();
+    };
+
+    match catch_panic(|| parse_file(input).map(VttFile::new)) {
+        Ok(Ok(file)) => {
+            set_last_error(VTT_OK);
+            Box::into_raw(Box::new(file))
+        }
+        Ok(Err(_)) => {
+            set_last_error(VTT_ERROR_PARSE);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error(VTT_ERROR_PANIC);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the error of the last call on this thread that returned NULL, or
+/// `VTT_OK` if the last call succeeded.
+#[no_mangle]
+pub extern "C" fn vtt_last_error() -> c_int {
+    LAST_ERROR.with(Cell::get)
+}
+
+/// Frees a file returned by `vtt_parse`. Does nothing if `file` is NULL.
+///
+/// # Safety
+///
+/// `file` must be NULL or a handle from `vtt_parse` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_file_free(file: *mut VttFile) {
+    if !file.is_null() {
+        drop(Box::from_raw(file));
+    }
+}
+
+/// Returns the number of cues in the file.
+///
+/// # Safety
+///
+/// `file` must be a live handle from `vtt_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_cue_count(file: *const VttFile) -> usize {
+    let file = &*file;
+    file.cues.len()
+}
+
+/// Returns the start time of a cue in milliseconds, or 0 if `index` is out of
+/// range.
+///
+/// # Safety
+///
+/// `file` must be a live handle from `vtt_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_cue_start_ms(file: *const VttFile, index: usize) -> u64 {
+    let file = &*file;
+    file.cues.get(index).map_or(0, |cue| cue.start_ms)
+}
+
+/// Returns the end time of a cue in milliseconds, or 0 if `index` is out of
+/// range.
+///
+/// # Safety
+///
+/// `file` must be a live handle from `vtt_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_cue_end_ms(file: *const VttFile, index: usize) -> u64 {
+    let file = &*file;
+    file.cues.get(index).map_or(0, |cue| cue.end_ms)
+}
+
+/// Returns the id of a cue, which is empty if it has none, or NULL if `index`
+/// is out of range.
+///
+/// # Safety
+///
+/// `file` must be a live handle from `vtt_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_cue_id(file: *const VttFile, index: usize) -> *const c_char {
+    let file = &*file;
+    file.cues
+        .get(index)
+        .map_or(ptr::null(), |cue| cue.id.as_ptr())
+}
+
+/// Returns the text of a cue, with its markup, or NULL if `index` is out of
+/// range.
+///
+/// # Safety
+///
+/// `file` must be a live handle from `vtt_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_cue_text(file: *const VttFile, index: usize) -> *const c_char {
+    let file = &*file;
+    file.cues
+        .get(index)
+        .map_or(ptr::null(), |cue| cue.text.as_ptr())
+}
+
+/// Writes the file back into WebVTT. The string must be freed with
+/// `vtt_string_free`. Returns NULL if writing failed, setting the error of
+/// `vtt_last_error`.
+///
+/// # Safety
+///
+/// `file` must be a live handle from `vtt_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_write(file: *const VttFile) -> *mut c_char {
+    let file = &*file;
+
+    match catch_panic(|| c_string(&file.file.to_string())) {
+        Ok(string) => {
+            set_last_error(VTT_OK);
+            string.into_raw()
+        }
+        Err(_) => {
+            set_last_error(VTT_ERROR_PANIC);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a string returned by `vtt_write`. Does nothing if `string` is NULL.
+///
+/// # Safety
+///
+/// `string` must be NULL or a string from `vtt_write` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtt_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
@@ -0,0 +1,257 @@
+//! A corpus of WebVTT samples and the results of parsing them, for testing
+//! other code that handles WebVTT, like players and renderers, against the
+//! same cases this crate is tested with.
+//!
+//! ```
+//! for fixture in webvtt::fixtures::FIXTURES {
+//!     fixture.check().unwrap();
+//! }
+//! ```
+
+use crate::parse_file;
+
+/// A sample file and what parsing it gives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub input: &'static str,
+    pub expected: Expected,
+}
+
+/// What parsing a [`Fixture`] gives.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Expected {
+    Valid {
+        description: Option<&'static str>,
+        cues: &'static [GoldenCue],
+    },
+    /// The file is rejected, with the message of the
+    /// [`ParseError`](crate::ParseError) this crate returns.
+    Invalid { error: &'static str },
+}
+
+/// A cue as it is parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GoldenCue {
+    pub id: &'static str,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// The settings in canonical form, leaving out defaults.
+    pub settings: &'static str,
+    /// The cue text, with markup.
+    pub text: &'static str,
+    /// The text without markup, with character references decoded.
+    pub plain_text: &'static str,
+}
+
+const fn cue(start_ms: u64, end_ms: u64, text: &'static str) -> GoldenCue {
+    GoldenCue {
+        id: "",
+        start_ms,
+        end_ms,
+        settings: "",
+        text,
+        plain_text: text,
+    }
+}
+
+pub const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "minimal",
+        input: include_str!("../test/fixtures/minimal.vtt"),
+        expected: Expected::Valid {
+            description: None,
+            cues: &[],
+        },
+    },
+    Fixture {
+        name: "header-and-notes",
+        input: include_str!("../test/fixtures/header-and-notes.vtt"),
+        expected: Expected::Valid {
+            description: Some("- Weather report"),
+            cues: &[
+                GoldenCue {
+                    id: "intro",
+                    ..cue(1000, 4000, "Good evening.")
+                },
+                cue(4500, 6000, "Rain is on the way."),
+            ],
+        },
+    },
+    Fixture {
+        name: "bom-crlf-short-timestamps",
+        input: include_str!("../test/fixtures/bom-crlf-short-timestamps.vtt"),
+        expected: Expected::Valid {
+            description: None,
+            cues: &[
+                cue(1000, 2500, "Short timestamps"),
+                cue(3723004, 3725000, "An hour in"),
+            ],
+        },
+    },
+    Fixture {
+        name: "settings",
+        input: include_str!("../test/fixtures/settings.vtt"),
+        expected: Expected::Valid {
+            description: None,
+            cues: &[
+                GoldenCue {
+                    settings: "vertical:rl line:0 position:10%,line-left size:50% align:start",
+                    ..cue(0, 1000, "Vertical from the right")
+                },
+                GoldenCue {
+                    settings: "vertical:lr line:-1",
+                    ..cue(1000, 2000, "Vertical from the left")
+                },
+                GoldenCue {
+                    settings: "line:50%,center position:90%,line-right align:end",
+                    ..cue(2000, 3000, "Centered line")
+                },
+                GoldenCue {
+                    settings: "size:80.5% align:left",
+                    ..cue(3000, 4000, "Left aligned")
+                },
+                GoldenCue {
+                    settings: "line:10%,end position:50%,center align:right",
+                    ..cue(4000, 5000, "Right aligned")
+                },
+            ],
+        },
+    },
+    Fixture {
+        name: "markup",
+        input: include_str!("../test/fixtures/markup.vtt"),
+        expected: Expected::Valid {
+            description: None,
+            cues: &[
+                GoldenCue {
+                    plain_text: "We are in the studio",
+                    ..cue(
+                        0,
+                        3000,
+                        "<v.loud Roger Bingham>We are <i>in</i> <b>the</b> <u>studio</u></v>",
+                    )
+                },
+                GoldenCue {
+                    plain_text: "Karaoke words here",
+                    ..cue(
+                        3000,
+                        6000,
+                        "<c.yellow.bg_blue>Karaoke</c> <00:04.000>words <00:05.000>here",
+                    )
+                },
+                GoldenCue {
+                    plain_text: "Bonjour & 漢かん <3 \u{a0}\u{200e}",
+                    ..cue(
+                        6000,
+                        8000,
+                        "<lang fr>Bonjour</lang> &amp; <ruby>漢<rt>かん</rt></ruby> &lt;3 &nbsp;&lrm;",
+                    )
+                },
+            ],
+        },
+    },
+    Fixture {
+        name: "unusual-timings",
+        input: include_str!("../test/fixtures/unusual-timings.vtt"),
+        expected: Expected::Valid {
+            description: None,
+            cues: &[
+                cue(2000, 1000, "Ends before it starts"),
+                cue(5000, 6000, "After"),
+                cue(3000, 4000, "Out of order"),
+            ],
+        },
+    },
+    Fixture {
+        name: "bad-timing-line",
+        input: include_str!("../test/fixtures/bad-timing-line.vtt"),
+        expected: Expected::Valid {
+            description: None,
+            cues: &[cue(3000, 4000, "Kept")],
+        },
+    },
+    Fixture {
+        name: "bad-header",
+        input: include_str!("../test/fixtures/bad-header.vtt"),
+        expected: Expected::Invalid {
+            error: "bad file header \"WEBVTTX\": expected whitespace after WEBVTT",
+        },
+    },
+    Fixture {
+        name: "no-magic",
+        input: include_str!("../test/fixtures/no-magic.vtt"),
+        expected: Expected::Invalid {
+            error: "missing file magic",
+        },
+    },
+];
+
+impl Fixture {
+    /// Parses the input with this crate and compares the result with the
+    /// expected one, describing the first difference.
+    pub fn check(&self) -> Result<(), String> {
+        let name = self.name;
+
+        let (file, expected_description, expected_cues) =
+            match (parse_file(self.input), self.expected) {
+                (Ok(file), Expected::Valid { description, cues }) => (file, description, cues),
+                (Err(err), Expected::Invalid { error }) if err.to_string() == error => {
+                    return Ok(())
+                }
+                (Err(err), _) => return Err(format!("{name}: unexpected error: {err}")),
+                (Ok(_), Expected::Invalid { error }) => {
+                    return Err(format!("{name}: parsed, but expected error: {error}"))
+                }
+            };
+
+        if file.description.as_deref() != expected_description {
+            return Err(format!(
+                "{name}: description {:?}, expected {expected_description:?}",
+                file.description
+            ));
+        }
+
+        let actual: Vec<_> = file
+            .cues()
+            .map(|cue| {
+                (
+                    cue.id.as_str(),
+                    cue.start.as_millis() as u64,
+                    cue.end.as_millis() as u64,
+                    cue.settings.to_string(),
+                    cue.text.as_str(),
+                    cue.plain_text(),
+                )
+            })
+            .collect();
+
+        if actual.len() != expected_cues.len() {
+            return Err(format!(
+                "{name}: {} cues, expected {}",
+                actual.len(),
+                expected_cues.len()
+            ));
+        }
+
+        for (idx, (actual, expected)) in actual.iter().zip(expected_cues).enumerate() {
+            let expected_tuple = (
+                expected.id,
+                expected.start_ms,
+                expected.end_ms,
+                expected.settings.to_owned(),
+                expected.text,
+                expected.plain_text.to_owned(),
+            );
+
+            if *actual != expected_tuple {
+                return Err(format!(
+                    "{name}: cue {idx} is {actual:?}, expected {expected:?}"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+//! Support for forced narratives, the subtitles that are shown even when
+//! subtitles are turned off, such as translations of on-screen text.
+//!
+//! A cue is forced when its text uses the `forced` class, as in
+//! `<c.forced>…</c>`. Since the marker is part of the cue text, it is kept by
+//! transforms that leave the text alone and written out as it was parsed.
+
+use crate::{
+    text::{self, Node},
+    File,
+};
+
+const FORCED_CLASS: &str = "forced";
+
+impl crate::Cue {
+    /// Returns whether this cue is part of a forced narrative.
+    pub fn is_forced(&self) -> bool {
+        let nodes = self.nodes();
+        let mut forced = false;
+
+        text::walk_spans(&nodes, &mut |span| {
+            forced |= span.classes.iter().any(|class| class == FORCED_CLASS);
+        });
+
+        forced
+    }
+
+    /// Marks this cue as forced by wrapping its text in a `forced` class span,
+    /// or unmarks it by removing the `forced` class from all of its spans.
+    /// Metadata cues are left as they are.
+    pub fn set_forced(&mut self, forced: bool) {
+        if forced == self.is_forced() || self.is_metadata() {
+            return;
+        }
+
+        if forced {
+            self.text = format!("<c.{FORCED_CLASS}>{}</c>", self.text);
+            return;
+        }
+
+        let mut nodes = self.nodes();
+        remove_class(&mut nodes);
+
+        // class spans with no other classes carry no meaning
+        let nodes = text::unwrap_spans(nodes, &mut |span| {
+            span.kind == text::SpanKind::Class && span.classes.is_empty()
+        });
+
+        self.set_nodes(&nodes);
+    }
+}
+
+fn remove_class(nodes: &mut [Node]) {
+    for node in nodes {
+        if let Node::Span(span) = node {
+            span.classes.retain(|class| class != FORCED_CLASS);
+            remove_class(&mut span.children);
+        }
+    }
+}
+
+impl File {
+    /// Returns a copy of this file with only the forced cues.
+    pub fn extract_forced(&self) -> File {
+        let mut file = self.clone();
+
+        file.blocks
+            .retain(|block| block.as_cue().is_none_or(|cue| cue.is_forced()));
+
+        file
+    }
+}
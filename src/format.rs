@@ -0,0 +1,91 @@
+//! Details of how a file was written that do not change its meaning, kept so
+//! that an unchanged file is written back as it was read.
+
+use std::time::Duration;
+
+use crate::format_timestamp;
+
+/// How a file was encoded, as detected by the parser and followed by the
+/// writer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SourceFormat {
+    pub line_ending: LineEnding,
+    /// Whether the file starts with a byte order mark.
+    pub bom: bool,
+    pub timestamps: TimestampStyle,
+    pub header_separator: HeaderSeparator,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// How the timestamps of cue timings are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TimestampStyle {
+    /// Always write the hours, as in `00:01:02.345`.
+    #[default]
+    Full,
+    /// Leave out the hours when they are zero, as in `01:02.345`.
+    OmitZeroHours,
+}
+
+/// The character between `WEBVTT` and the description on the first line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HeaderSeparator {
+    #[default]
+    Space,
+    Tab,
+}
+
+impl LineEnding {
+    /// Detects the line ending of the input from its first line.
+    pub(crate) fn detect(input: &str) -> LineEnding {
+        match input.find('\n') {
+            Some(idx) if input[..idx].ends_with('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+}
+
+impl HeaderSeparator {
+    pub fn as_char(self) -> char {
+        match self {
+            HeaderSeparator::Space => ' ',
+            HeaderSeparator::Tab => '\t',
+        }
+    }
+
+    /// Detects the separator from the first line of the input.
+    pub(crate) fn detect(input: &str) -> HeaderSeparator {
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+
+        if input.starts_with("WEBVTT\t") {
+            HeaderSeparator::Tab
+        } else {
+            HeaderSeparator::Space
+        }
+    }
+}
+
+impl TimestampStyle {
+    pub(crate) fn format(self, time: Duration) -> String {
+        let full = format_timestamp(time);
+
+        match self {
+            TimestampStyle::OmitZeroHours if time.as_secs() < 3600 => full[3..].to_owned(),
+            _ => full,
+        }
+    }
+}
+
+/// Returns whether the first timestamp of a timing line has hours.
+pub(crate) fn has_hours(line: &str) -> bool {
+    line.trim_start()
+        .split(|char: char| !char.is_ascii_digit() && char != ':')
+        .next()
+        .is_some_and(|timestamp| timestamp.matches(':').count() == 2)
+}
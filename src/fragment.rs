@@ -0,0 +1,51 @@
+//! Links to the moment of a cue in its media, as temporal Media Fragments
+//! URIs like `video.mp4#t=12.5,15`.
+
+use std::time::Duration;
+
+use crate::{Cue, File};
+
+/// A link to the time span of a cue.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeepLink {
+    /// Index of the cue among the cues of the file.
+    pub cue_index: usize,
+    pub url: String,
+    /// The text of the cue without markup, to show with the link.
+    pub text: String,
+}
+
+/// Formats a time in seconds, with as many decimals as it needs up to
+/// milliseconds, like `12.5`.
+fn seconds(time: Duration) -> String {
+    let millis = time.as_millis();
+    let mut out = format!("{}.{:03}", millis / 1000, millis % 1000);
+
+    out.truncate(out.trim_end_matches('0').trim_end_matches('.').len());
+    out
+}
+
+impl Cue {
+    /// Returns the temporal media fragment of this cue's time span, like
+    /// `#t=12.5,15`, with times in seconds.
+    pub fn media_fragment(&self) -> String {
+        format!("#t={},{}", seconds(self.start), seconds(self.end))
+    }
+}
+
+impl File {
+    /// Returns a link into `base_url` for every cue, with the cue's media
+    /// fragment in place of any fragment the URL has.
+    pub fn deep_links(&self, base_url: &str) -> Vec<DeepLink> {
+        let base = base_url.split('#').next().unwrap_or_default();
+
+        self.cues()
+            .enumerate()
+            .map(|(cue_index, cue)| DeepLink {
+                cue_index,
+                url: format!("{base}{}", cue.media_fragment()),
+                text: cue.plain_text(),
+            })
+            .collect()
+    }
+}
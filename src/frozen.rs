@@ -0,0 +1,126 @@
+//! An immutable form of a file that is cheap to share between threads, like
+//! the UI and decode threads of a player.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use crate::{Cue, CueSettings, File};
+
+/// A cue of a [`FrozenFile`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrozenCue {
+    pub start: Duration,
+    pub end: Duration,
+    pub id: Arc<str>,
+    pub text: Arc<str>,
+    pub settings: CueSettings,
+    /// The position of the cue in the file it was frozen from.
+    pub source_order: usize,
+}
+
+impl From<&Cue> for FrozenCue {
+    fn from(cue: &Cue) -> Self {
+        FrozenCue {
+            start: cue.start,
+            end: cue.end,
+            id: cue.id.as_str().into(),
+            text: cue.text.as_str().into(),
+            settings: cue.settings.clone(),
+            source_order: cue.source_order,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    description: Option<Arc<str>>,
+    /// Sorted by start time, then by source order.
+    cues: Vec<FrozenCue>,
+    /// The latest end time of `cues[..=i]`, for finding active cues without
+    /// scanning the whole file.
+    max_end: Vec<Duration>,
+    by_id: HashMap<Arc<str>, usize>,
+}
+
+/// A read-only snapshot of the cues of a file, with the indexes needed to
+/// look them up by time or id. Cloning it only bumps a reference count, and
+/// it is `Send` and `Sync`.
+#[derive(Clone, Debug)]
+pub struct FrozenFile(Arc<Inner>);
+
+impl FrozenFile {
+    pub fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    /// The cues, sorted by start time and then by their order in the file.
+    pub fn cues(&self) -> &[FrozenCue] {
+        &self.0.cues
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.cues.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.cues.is_empty()
+    }
+
+    /// Returns the first cue with the given id.
+    pub fn get(&self, id: &str) -> Option<&FrozenCue> {
+        self.0.by_id.get(id).map(|&idx| &self.0.cues[idx])
+    }
+
+    /// Returns the cues active at the given time, in the same order as
+    /// [`File::cues_at`].
+    pub fn cues_at(&self, time: Duration) -> Vec<&FrozenCue> {
+        let started = self.0.cues.partition_point(|cue| cue.start <= time);
+
+        let mut cues: Vec<_> = (0..started)
+            .rev()
+            .take_while(|&idx| self.0.max_end[idx] > time)
+            .map(|idx| &self.0.cues[idx])
+            .filter(|cue| time < cue.end)
+            .collect();
+
+        cues.sort_by_key(|cue| cue.source_order);
+        cues
+    }
+}
+
+impl File {
+    /// Takes an immutable snapshot of the cues of this file.
+    pub fn freeze(&self) -> FrozenFile {
+        let mut cues: Vec<_> = self.cues().map(FrozenCue::from).collect();
+        cues.sort_by_key(|cue| (cue.start, cue.source_order));
+
+        let max_end = cues
+            .iter()
+            .scan(Duration::ZERO, |max, cue| {
+                *max = cue.end.max(*max);
+                Some(*max)
+            })
+            .collect();
+
+        let mut by_id = HashMap::new();
+
+        for (idx, cue) in cues.iter().enumerate() {
+            if !cue.id.is_empty() {
+                by_id
+                    .entry(cue.id.clone())
+                    .and_modify(|existing: &mut usize| {
+                        if cues[*existing].source_order > cue.source_order {
+                            *existing = idx;
+                        }
+                    })
+                    .or_insert(idx);
+            }
+        }
+
+        FrozenFile(Arc::new(Inner {
+            description: self.description.as_deref().map(Arc::from),
+            cues,
+            max_end,
+            by_id,
+        }))
+    }
+}
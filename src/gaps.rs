@@ -0,0 +1,55 @@
+//! Filling silences between cues with placeholder cues.
+
+use std::time::Duration;
+
+use crate::{text::Node, Block, Cue, File};
+
+impl File {
+    /// Inserts a cue with the given text, such as `[no dialogue]`, into every
+    /// gap between cues that is longer than `min_gap`. Each placeholder spans
+    /// the whole gap and is placed just before the cue that ends it. The text
+    /// is escaped, so `&` and `<` show as they are. Returns the number of cues
+    /// inserted.
+    pub fn fill_gaps(&mut self, text: &str, min_gap: Duration) -> usize {
+        // (block index, start, end) of each cue, by start time
+        let mut cues: Vec<_> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, block)| block.as_cue().map(|cue| (idx, cue.start, cue.end)))
+            .collect();
+
+        cues.sort_by_key(|&(idx, start, _)| (start, idx));
+
+        let mut placeholders = vec![];
+        let mut covered_until: Option<Duration> = None;
+
+        for (idx, start, end) in cues {
+            if let Some(covered_until) = covered_until {
+                if start > covered_until && start - covered_until > min_gap {
+                    let mut cue = Cue {
+                        start: covered_until,
+                        end: start,
+                        ..Default::default()
+                    };
+                    cue.set_nodes(&[Node::Text(text.to_owned())]);
+
+                    placeholders.push((idx, cue));
+                }
+            }
+
+            covered_until = Some(covered_until.map_or(end, |until| until.max(end)));
+        }
+
+        let inserted = placeholders.len();
+
+        // insert from the back so the block indices stay valid
+        placeholders.sort_by_key(|&(idx, _)| idx);
+
+        for (idx, cue) in placeholders.into_iter().rev() {
+            self.blocks.insert(idx, Block::Cue(cue));
+        }
+
+        inserted
+    }
+}
@@ -0,0 +1,104 @@
+//! Building tracks from structured data, like the events of a sports match
+//! for an overlay, or from a clock, like a workout timer.
+
+use std::{collections::HashMap, time::Duration};
+
+use crate::{text, Block, Cue, CueTemplate, File, TemplateError};
+
+/// The timing and template values of a cue to generate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    pub start: Duration,
+    pub end: Duration,
+    /// The identifier of the cue, which can be empty.
+    pub id: String,
+    pub values: HashMap<String, String>,
+}
+
+impl File {
+    /// Builds a file with a cue for each event, whose text is `template`
+    /// filled in with the event's values. The cues are in the order of the
+    /// events, and have the payload of the template.
+    pub fn generate(
+        template: &CueTemplate,
+        events: impl IntoIterator<Item = Event>,
+    ) -> Result<File, TemplateError> {
+        let mut blocks = vec![];
+
+        for event in events {
+            blocks.push(Block::Cue(Cue {
+                start: event.start,
+                end: event.end,
+                id: event.id,
+                text: template.render(&event.values)?,
+                payload: template.payload,
+                ..Default::default()
+            }));
+        }
+
+        Ok(File {
+            description: None,
+            header_lines: vec![],
+            blocks,
+            format: Default::default(),
+        })
+    }
+}
+
+/// One of the intervals of [`intervals`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Interval {
+    /// The number of the interval, counting from zero.
+    pub index: usize,
+    pub start: Duration,
+    pub end: Duration,
+    /// The time from the start of the interval to the end of the track, as
+    /// shown by a countdown.
+    pub remaining: Duration,
+}
+
+/// Builds a track with a cue for each `step` of time up to `total`, whose text
+/// is what `formatter` returns for its interval, like the time left on a
+/// timer. The text is escaped, so it cannot contain markup. The last interval
+/// ends at `total`, so it is shorter than `step` if `step` does not divide
+/// `total`.
+///
+/// # Panics
+///
+/// Panics if `step` is zero.
+pub fn intervals(
+    total: Duration,
+    step: Duration,
+    mut formatter: impl FnMut(Interval) -> String,
+) -> File {
+    assert!(!step.is_zero(), "intervals need a step longer than zero");
+
+    let mut blocks = vec![];
+    let mut start = Duration::ZERO;
+
+    while start < total {
+        let end = (start + step).min(total);
+        let interval = Interval {
+            index: blocks.len(),
+            start,
+            end,
+            remaining: total - start,
+        };
+
+        blocks.push(Block::Cue(Cue {
+            start,
+            end,
+            text: text::escape(&formatter(interval)),
+            ..Default::default()
+        }));
+
+        start = end;
+    }
+
+    File {
+        description: None,
+        header_lines: vec![],
+        blocks,
+        format: Default::default(),
+    }
+}
@@ -0,0 +1,59 @@
+//! Hashing of the content of files, to tell whether captions changed.
+
+use crate::File;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hashers is
+/// stable across platforms and releases.
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Writes a field followed by a separator, so that moving text between
+    /// fields changes the hash.
+    fn write_field(&mut self, field: &str) {
+        self.write(field.as_bytes());
+        self.write(&[0x1f]);
+    }
+}
+
+impl File {
+    /// Returns a hash of the content of this file's cues: their times, ids,
+    /// settings and text, in document order. The hash ignores how the file was
+    /// written, such as the timestamp format, the order of settings, spacing
+    /// in tags, the form of character references and runs of whitespace, as
+    /// well as the header, comments and extensions.
+    ///
+    /// The hash is stable across platforms and versions of this crate, but it
+    /// is not cryptographic.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv(FNV_OFFSET_BASIS);
+
+        for cue in self.cues() {
+            let text = cue.format_nodes(&cue.nodes());
+
+            let text = text
+                .split('\n')
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            hasher.write_field(&cue.start.as_millis().to_string());
+            hasher.write_field(&cue.end.as_millis().to_string());
+            hasher.write_field(&cue.id);
+            hasher.write_field(&cue.settings.to_string());
+            hasher.write_field(&text);
+            hasher.write(&[0x1e]);
+        }
+
+        hasher.0
+    }
+}
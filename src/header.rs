@@ -0,0 +1,92 @@
+//! Fast paths for reading a little about many files without parsing them.
+
+use crate::{parse_cue_timings_settings, parse_magic, Lines, ParseError};
+
+/// The header of a WebVTT file: the `WEBVTT` line and the lines after it, up
+/// to the first blank line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    /// The text after `WEBVTT` on the first line.
+    pub description: Option<String>,
+    /// The `name: value` or `name=value` lines of the header, like the
+    /// `Kind: captions` and `Language: en` that some tools write, or the
+    /// `X-TIMESTAMP-MAP` of HLS segments.
+    pub metadata: Vec<(String, String)>,
+}
+
+impl Header {
+    /// Returns the value of the first metadata line with the given name,
+    /// ignoring ASCII case.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parses only the header of a WebVTT file, reading no further than the first
+/// blank line.
+pub fn parse_header(input: &str) -> Result<Header, ParseError> {
+    let mut lines = Lines::new(input, 0);
+    let description = parse_magic(&mut lines)?;
+    let mut metadata = vec![];
+
+    for (_, line) in lines {
+        if line.is_empty() || crate::scan::has_arrow(line) {
+            break;
+        }
+
+        let Some(idx) = line.find([':', '=']) else {
+            continue;
+        };
+
+        metadata.push((
+            line[..idx].trim().to_owned(),
+            line[idx + 1..].trim().to_owned(),
+        ));
+    }
+
+    Ok(Header {
+        description,
+        metadata,
+    })
+}
+
+/// Counts the cues of a WebVTT file, finding the same cues as
+/// [`crate::parse_file`] without building them.
+pub fn count_cues(input: &str) -> Result<usize, ParseError> {
+    let mut lines = Lines::new(input, 0);
+    parse_magic(&mut lines)?;
+
+    let mut count = 0;
+    let mut line_in_block = 0;
+    let mut seen_arrow = false;
+
+    for (_, line) in lines {
+        if line.is_empty() {
+            line_in_block = 0;
+            seen_arrow = false;
+            continue;
+        }
+
+        line_in_block += 1;
+
+        // the timings of a cue are on the first line of its block, or on the
+        // second if the first is its id; an arrow on any other line starts
+        // the next block
+        if crate::scan::has_arrow(line) {
+            if line_in_block > 2 || seen_arrow {
+                line_in_block = 1;
+            }
+
+            seen_arrow = true;
+
+            if parse_cue_timings_settings(line).is_some() {
+                count += 1;
+            }
+        }
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,263 @@
+//! Import of the timed transcripts that some sites publish as HTML, with the
+//! times of each paragraph in data attributes:
+//!
+//! ```html
+//! <p data-begin="12.5" data-end="15.0">Good evening.</p>
+//! <p data-begin="00:00:15.000">Our top story tonight:</p>
+//! ```
+
+use std::time::Duration;
+
+use crate::{parse_timestamp, text, Block, ConvertError, Cue, File};
+
+/// An element with a start time, and the text inside it.
+struct Timed {
+    begin: Duration,
+    end: Option<Duration>,
+    text: String,
+}
+
+/// Parses a time attribute, either a timestamp or a number of seconds.
+fn parse_time(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Some((time, "")) = parse_timestamp(value) {
+        return Some(time);
+    }
+
+    let seconds = value.strip_suffix('s').unwrap_or(value);
+    Duration::try_from_secs_f64(seconds.parse().ok()?).ok()
+}
+
+/// Reads the attributes of a start tag, given the text between its name and
+/// the closing `>`.
+fn attributes(mut rest: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+
+        if name_end == 0 {
+            break;
+        }
+
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            attributes.push((name, String::new()));
+            continue;
+        };
+
+        let after_eq = after_eq.trim_start();
+
+        let (value, after) = match after_eq.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let inner = &after_eq[1..];
+                let end = inner.find(quote).unwrap_or(inner.len());
+                (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                after_eq.split_at(end)
+            }
+        };
+
+        attributes.push((name, text::decode_entities(value)));
+        rest = after;
+    }
+
+    attributes
+}
+
+/// Elements that separate words even when there is no whitespace around them.
+const BLOCK_ELEMENTS: [&str; 12] = [
+    "p", "div", "li", "td", "th", "tr", "h1", "h2", "h3", "h4", "h5", "h6",
+];
+
+/// The plain text of some HTML, with tags removed, `<br>` as line breaks and
+/// whitespace collapsed.
+fn plain_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+
+    while let Some(idx) = rest.find('<') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        // a `<` that does not start a tag is text
+        if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') {
+            out.push('<');
+            rest = &rest[1..];
+            continue;
+        }
+
+        let end = rest.find('>').map_or(rest.len(), |idx| idx + 1);
+        let name = rest[1..end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        if name == "br" {
+            out.push('\n');
+        } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            out.push(' ');
+        }
+
+        rest = &rest[end..];
+    }
+
+    out.push_str(rest);
+
+    text::decode_entities(&out)
+        .split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Finds the outermost elements with a `data-begin` or `data-start`
+/// attribute.
+fn timed_elements(html: &str) -> Result<Vec<Timed>, ConvertError> {
+    let mut elements = vec![];
+    let mut rest = html;
+
+    while let Some(idx) = rest.find('<') {
+        rest = &rest[idx + 1..];
+
+        let tag_end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[..tag_end];
+        rest = rest.get(tag_end + 1..).unwrap_or("");
+
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let name = &tag[..name_end];
+
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            continue;
+        }
+
+        let attributes = attributes(&tag[name_end..]);
+        let attribute = |names: &[&str]| {
+            attributes
+                .iter()
+                .find(|(name, _)| names.contains(&name.as_str()))
+                .map(|(_, value)| value.as_str())
+        };
+
+        let Some(begin) = attribute(&["data-begin", "data-start"]) else {
+            continue;
+        };
+
+        let element = elements.len() + 1;
+        let bad = |reason| ConvertError::BadHtml { element, reason };
+
+        let begin = parse_time(begin).ok_or(bad("bad begin time"))?;
+        let end = match attribute(&["data-end"]) {
+            Some(end) => Some(parse_time(end).ok_or(bad("bad end time"))?),
+            None => None,
+        };
+
+        // the content runs to the matching end tag, counting nested elements
+        // with the same name
+        let mut depth = 1;
+        let mut content_end = rest.len();
+        let mut search = 0;
+
+        while let Some(idx) = rest[search..].find('<') {
+            let at = search + idx;
+            let after = &rest[at + 1..];
+            let (closing, after) = match after.strip_prefix('/') {
+                Some(after) => (true, after),
+                None => (false, after),
+            };
+
+            let matches = after
+                .get(..name.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+                && after[name.len()..]
+                    .starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/');
+
+            if matches {
+                if closing {
+                    depth -= 1;
+                } else {
+                    depth += 1;
+                }
+
+                if depth == 0 {
+                    content_end = at;
+                    break;
+                }
+            }
+
+            search = at + 1;
+        }
+
+        elements.push(Timed {
+            begin,
+            end,
+            text: plain_text(&rest[..content_end]),
+        });
+
+        rest = &rest[content_end..];
+    }
+
+    Ok(elements)
+}
+
+impl File {
+    /// Imports cues from the timed transcript markup of a web page: elements
+    /// like `<p data-begin="12.5" data-end="15">`, whose times are seconds or
+    /// WebVTT timestamps. The text of each element becomes a cue without its
+    /// markup, keeping `<br>` as line breaks. An element without a
+    /// `data-end` ends where the next one begins, so only the last one needs
+    /// it.
+    ///
+    /// This is a scraper rather than an HTML parser, so only the outermost
+    /// timed elements are read, and the text of scripts and comments inside
+    /// them is not skipped.
+    pub fn from_html_track_list(html: &str) -> Result<File, ConvertError> {
+        let elements = timed_elements(html)?;
+        let mut blocks = vec![];
+
+        for (idx, element) in elements.iter().enumerate() {
+            let end = match (element.end, elements.get(idx + 1)) {
+                (Some(end), _) => end,
+                (None, Some(next)) => next.begin,
+                (None, None) => {
+                    return Err(ConvertError::BadHtml {
+                        element: idx + 1,
+                        reason: "missing end time",
+                    })
+                }
+            };
+
+            if element.text.is_empty() {
+                continue;
+            }
+
+            blocks.push(Block::Cue(Cue {
+                start: element.begin,
+                end,
+                text: text::escape(&element.text),
+                ..Default::default()
+            }));
+        }
+
+        Ok(File {
+            description: None,
+            header_lines: vec![],
+            blocks,
+            format: Default::default(),
+        })
+    }
+}
@@ -0,0 +1,158 @@
+//! Hyphenation of words too long for a line when rewrapping, using the TeX
+//! hyphenation patterns of each language.
+
+use std::collections::HashMap;
+
+use hyphenation::{Hyphenator, Language, Load, Standard};
+
+use crate::{
+    text::{Node, SpanKind},
+    Cue, File, LengthMetric,
+};
+
+/// The languages whose words are hyphenated when rewrapping, and the
+/// language of text outside `<lang>` spans.
+#[derive(Default)]
+pub struct Hyphenation {
+    /// The patterns of each language added, by its tag in lowercase.
+    dictionaries: HashMap<String, Standard>,
+    default_language: Option<String>,
+}
+
+/// Returns the patterns for a language tag: the ones for the whole tag, like
+/// `en-gb`, or else the usual ones for its primary language.
+fn language(tag: &str) -> Option<Language> {
+    let primary = tag.split('-').next().unwrap_or_default();
+
+    Language::try_from_code(tag).or_else(|| {
+        Language::try_from_code(match primary {
+            "en" => "en-us",
+            "de" => "de-1996",
+            "el" => "el-monoton",
+            "mn" => "mn-cyrl",
+            "sr" => "sr-cyrl",
+            primary => primary,
+        })
+    })
+}
+
+impl Hyphenation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hyphenates text in the language with the BCP 47 tag `tag`, like `en-US`
+    /// or `de`, and in more specific languages, like `de-AT` for `de`.
+    /// Returns `false` if there are no patterns for the language.
+    pub fn add_language(&mut self, tag: &str) -> bool {
+        let tag = tag.to_ascii_lowercase();
+
+        let Some(dictionary) = language(&tag).and_then(|lang| Standard::from_embedded(lang).ok())
+        else {
+            return false;
+        };
+
+        self.dictionaries.insert(tag, dictionary);
+        true
+    }
+
+    /// Sets the language of text that is not in a `<lang>` span, usually the
+    /// language of the track. Text in it is only hyphenated if the language
+    /// was added with [`Hyphenation::add_language`].
+    pub fn set_default_language(&mut self, tag: &str) {
+        self.default_language = Some(tag.to_ascii_lowercase());
+    }
+
+    /// Returns the patterns for text in a language, looking up the tag and
+    /// then each shorter prefix of it, so that `en-GB` falls back to `en`.
+    fn dictionary(&self, tag: Option<&str>) -> Option<&Standard> {
+        let mut tag = match tag {
+            Some(tag) => tag.to_ascii_lowercase(),
+            None => self.default_language.clone()?,
+        };
+
+        loop {
+            if let Some(dictionary) = self.dictionaries.get(&tag) {
+                return Some(dictionary);
+            }
+
+            tag.truncate(tag.rfind('-')?);
+        }
+    }
+
+    /// Returns the character offsets in `word` where it can be hyphenated.
+    /// Punctuation around the word is left out of the lookup.
+    fn breaks(&self, word: &str, tag: Option<&str>) -> Vec<usize> {
+        let Some(dictionary) = self.dictionary(tag) else {
+            return vec![];
+        };
+
+        let trimmed = word.trim_start_matches(|c: char| !c.is_alphabetic());
+        let start = word.len() - trimmed.len();
+        let trimmed = trimmed.trim_end_matches(|c: char| !c.is_alphabetic());
+
+        dictionary
+            .hyphenate(trimmed)
+            .breaks
+            .into_iter()
+            .map(|idx| word[..start + idx].chars().count())
+            .collect()
+    }
+}
+
+/// Collects the language of each character of the plain text of some nodes,
+/// from the innermost `<lang>` span around it.
+fn char_languages<'a>(nodes: &'a [Node], lang: Option<&'a str>, out: &mut Vec<Option<&'a str>>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.extend(text.chars().map(|_| lang)),
+            Node::Span(span) => {
+                let lang = match &span.kind {
+                    SpanKind::Language { tag } => Some(tag.as_str()),
+                    _ => lang,
+                };
+
+                char_languages(&span.children, lang, out);
+            }
+            Node::Timestamp(_) => {}
+        }
+    }
+}
+
+impl Cue {
+    /// Rewraps the text of this cue as in [`Cue::rewrap_with`], hyphenating
+    /// words that do not fit on a line of their own in the languages added to
+    /// `hyphenation`. The language of each word is that of the `<lang>` span
+    /// around it, or the default language of `hyphenation`. The hyphen counts
+    /// towards the length of its line.
+    pub fn rewrap_hyphenated(
+        &mut self,
+        max_length: usize,
+        metric: LengthMetric,
+        hyphenation: &Hyphenation,
+    ) -> bool {
+        let nodes = self.nodes();
+        let mut languages = vec![];
+        char_languages(&nodes, None, &mut languages);
+
+        self.rewrap_words(max_length, metric, &|word, start| {
+            hyphenation.breaks(word, languages.get(start).copied().flatten())
+        })
+    }
+}
+
+impl File {
+    /// Rewraps every cue as in [`Cue::rewrap_hyphenated`]. Returns the number
+    /// of cues changed.
+    pub fn rewrap_hyphenated(
+        &mut self,
+        max_length: usize,
+        metric: LengthMetric,
+        hyphenation: &Hyphenation,
+    ) -> usize {
+        self.cues_mut()
+            .map(|cue| cue.rewrap_hyphenated(max_length, metric, hyphenation))
+            .filter(|&changed| changed)
+            .count()
+    }
+}
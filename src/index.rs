@@ -0,0 +1,122 @@
+//! A compact, serializable index from cue start times to byte offsets, used to
+//! seek into a file with [`CueReader::at`](crate::CueReader::at) without
+//! parsing everything before the playback position.
+
+use std::time::Duration;
+
+use crate::{File, ParseError};
+
+const MAGIC: &[u8; 4] = b"VTTI";
+const VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    start: Duration,
+    end: Duration,
+    offset: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct CueIndex {
+    /// Sorted by start time, then by offset.
+    entries: Vec<Entry>,
+}
+
+impl CueIndex {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the byte offset at which to start reading in order to see every
+    /// cue that is active at or after `position`, or `None` if no cue ends
+    /// after `position`.
+    pub fn seek(&self, position: Duration) -> Option<usize> {
+        let started = self.entries.partition_point(|e| e.start <= position);
+
+        let active = self.entries[..started]
+            .iter()
+            .filter(|e| e.end > position)
+            .map(|e| e.offset);
+
+        let upcoming = self.entries[started..].iter().map(|e| e.offset);
+
+        active.chain(upcoming).min()
+    }
+
+    /// Serializes the index. The format is a 4-byte magic, a version byte, a
+    /// little-endian `u32` entry count, and then for each entry the start and
+    /// end times in milliseconds and the byte offset as little-endian `u64`s.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9 + self.entries.len() * 24);
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+
+        for entry in &self.entries {
+            bytes.extend_from_slice(&(entry.start.as_millis() as u64).to_le_bytes());
+            bytes.extend_from_slice(&(entry.end.as_millis() as u64).to_le_bytes());
+            bytes.extend_from_slice(&(entry.offset as u64).to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Deserializes an index produced by [`CueIndex::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let bad = |reason| ParseError::BadIndex { reason };
+
+        let bytes = bytes.strip_prefix(MAGIC).ok_or(bad("missing magic"))?;
+        let (&version, bytes) = bytes.split_first().ok_or(bad("missing version"))?;
+
+        if version != VERSION {
+            return Err(bad("unsupported version"));
+        }
+
+        let (count, bytes) = bytes
+            .split_first_chunk::<4>()
+            .ok_or(bad("missing cue count"))?;
+        let count = u32::from_le_bytes(*count) as usize;
+
+        if bytes.len() != count * 24 {
+            return Err(bad("wrong length for the cue count"));
+        }
+
+        let read = |chunk: &[u8]| u64::from_le_bytes(chunk.try_into().unwrap());
+
+        let entries = bytes
+            .chunks_exact(24)
+            .map(|chunk| Entry {
+                start: Duration::from_millis(read(&chunk[0..8])),
+                end: Duration::from_millis(read(&chunk[8..16])),
+                offset: read(&chunk[16..24]) as usize,
+            })
+            .collect();
+
+        Ok(CueIndex { entries })
+    }
+}
+
+impl File {
+    /// Builds an index of this file's cues. The offsets in the index refer to
+    /// the input the file was parsed from, so the index is only meaningful for
+    /// files that came out of [`parse_file`](crate::parse_file) unmodified.
+    pub fn build_index(&self) -> CueIndex {
+        let mut entries: Vec<_> = self
+            .cues()
+            .map(|cue| Entry {
+                start: cue.start,
+                end: cue.end,
+                offset: cue.offset,
+            })
+            .collect();
+
+        entries.sort_by_key(|e| (e.start, e.offset));
+
+        CueIndex { entries }
+    }
+}
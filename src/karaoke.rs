@@ -0,0 +1,202 @@
+//! Export of word-timed cues as one cue per word, phrase or line, which social
+//! video tools animate as karaoke-style captions.
+
+use std::time::Duration;
+
+use crate::{text, text::Node, Block, Cue, File};
+
+/// How much text each cue of a karaoke export shows.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KaraokeUnit {
+    /// One word per cue.
+    Word,
+    /// The words up to a comma, full stop or other punctuation, or the end of
+    /// a line.
+    Phrase,
+    /// One line of the original cue per cue.
+    Line,
+}
+
+struct Word {
+    text: String,
+    /// The time of the inline timestamp before the word, if any.
+    time: Option<Duration>,
+    /// Whether a line break follows the word.
+    line_end: bool,
+}
+
+fn collect_words(
+    nodes: &[Node],
+    words: &mut Vec<Word>,
+    pending: &mut Option<Duration>,
+    in_word: &mut bool,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        *in_word = false;
+
+                        if c == '\n' {
+                            if let Some(last) = words.last_mut() {
+                                last.line_end = true;
+                            }
+                        }
+                    } else if *in_word {
+                        // a timestamp inside a word, like between syllables,
+                        // does not start the next one
+                        *pending = None;
+                        words.last_mut().unwrap().text.push(c);
+                    } else {
+                        *in_word = true;
+                        words.push(Word {
+                            text: c.to_string(),
+                            time: pending.take(),
+                            line_end: false,
+                        });
+                    }
+                }
+            }
+            Node::Timestamp(time) => *pending = Some(*time),
+            Node::Span(span) => collect_words(&span.children, words, pending, in_word),
+        }
+    }
+}
+
+/// Returns the start time of each word. Words without an inline timestamp
+/// share the time between their neighbours in proportion to their length.
+fn word_times(words: &[Word], start: Duration, end: Duration) -> Vec<Duration> {
+    let weight = |word: &Word| word.text.chars().count().max(1) as u128;
+    let mut times = Vec::with_capacity(words.len());
+    let mut idx = 0;
+
+    while idx < words.len() {
+        let from = match words[idx].time {
+            Some(time) => time.clamp(times.last().copied().unwrap_or(start), end),
+            None => times.last().copied().unwrap_or(start),
+        };
+
+        // the run of words up to the next one with a timestamp
+        let run_end = words[idx + 1..]
+            .iter()
+            .position(|word| word.time.is_some())
+            .map_or(words.len(), |offset| idx + 1 + offset);
+        let to = words
+            .get(run_end)
+            .and_then(|word| word.time)
+            .unwrap_or(end)
+            .clamp(from, end);
+
+        let total: u128 = words[idx..run_end].iter().map(weight).sum();
+        let span = (to - from).as_millis();
+        let mut elapsed = 0;
+
+        for word in &words[idx..run_end] {
+            times.push(from + Duration::from_millis((span * elapsed / total) as u64));
+            elapsed += weight(word);
+        }
+
+        idx = run_end;
+    }
+
+    times
+}
+
+fn ends_phrase(word: &Word) -> bool {
+    word.line_end
+        || word.text.ends_with([
+            ',', '.', ';', ':', '!', '?', '…', '—', '。', '、', '！', '？',
+        ])
+}
+
+impl Cue {
+    /// Splits this cue into consecutive cues of one word, phrase or line each,
+    /// timed by its inline timestamps. Words without a timestamp of their own
+    /// share the time up to the next one in proportion to their length, so
+    /// cues without any timestamps are still split. The parts are plain text,
+    /// without the cue's markup. Only the first part keeps the cue's id and
+    /// notes, and metadata cues are not split.
+    pub fn karaoke_parts(&self, unit: KaraokeUnit) -> Vec<Cue> {
+        if self.is_metadata() {
+            return vec![self.clone()];
+        }
+
+        let mut words = vec![];
+        collect_words(&self.nodes(), &mut words, &mut None, &mut false);
+
+        if words.is_empty() {
+            return vec![self.clone()];
+        }
+
+        let times = word_times(&words, self.start, self.end);
+
+        let mut groups: Vec<Vec<usize>> = vec![vec![]];
+
+        for (idx, word) in words.iter().enumerate() {
+            groups.last_mut().unwrap().push(idx);
+
+            let ends = match unit {
+                KaraokeUnit::Word => true,
+                KaraokeUnit::Phrase => ends_phrase(word),
+                KaraokeUnit::Line => word.line_end,
+            };
+
+            if ends && idx + 1 < words.len() {
+                groups.push(vec![]);
+            }
+        }
+
+        let starts: Vec<_> = groups.iter().map(|group| times[group[0]]).collect();
+
+        groups
+            .iter()
+            .enumerate()
+            .map(|(idx, group)| {
+                let text = group
+                    .iter()
+                    .map(|&word| words[word].text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                Cue {
+                    start: starts[idx],
+                    end: starts.get(idx + 1).copied().unwrap_or(self.end),
+                    id: if idx == 0 {
+                        self.id.clone()
+                    } else {
+                        String::new()
+                    },
+                    text: text::escape(&text),
+                    notes: if idx == 0 { self.notes.clone() } else { vec![] },
+                    ..self.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+impl File {
+    /// Returns a copy of this file with every cue split into one cue per word,
+    /// phrase or line, as with [`Cue::karaoke_parts`].
+    pub fn to_karaoke(&self, unit: KaraokeUnit) -> File {
+        let mut blocks = vec![];
+
+        for block in &self.blocks {
+            match block {
+                Block::Cue(cue) => {
+                    blocks.extend(cue.karaoke_parts(unit).into_iter().map(Block::Cue))
+                }
+                block => blocks.push(block.clone()),
+            }
+        }
+
+        File {
+            description: self.description.clone(),
+            header_lines: self.header_lines.clone(),
+            blocks,
+            format: self.format,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Computation of where a cue's box is displayed, following the spec's rules
+//! for obtaining the CSS boxes of a cue.
+//!
+//! All values are percentages of the video viewport: horizontal values are of
+//! its width and vertical values are of its height. Overlap with other cues is
+//! not taken into account.
+
+use crate::{Align, CueSettings, Direction, Line, LineAlign, PositionAlign, WritingDirection};
+
+/// The box a cue's text is displayed in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CueBox {
+    /// the offset of the box's left side from the viewport's left edge
+    pub x: f64,
+    /// the offset of the box's top side from the viewport's top edge
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// The thickness of a line of cue text as a percentage of the viewport's
+/// height, since the spec sets the font size of cues to 5vh.
+const LINE_HEIGHT: f64 = 5.0;
+
+/// Where a single-line cue is displayed, with all defaults and `auto` values
+/// resolved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResolvedPosition {
+    /// the offset of the box's left side from the viewport's left edge
+    pub x: f64,
+    /// the offset of the box's top side from the viewport's top edge
+    pub y: f64,
+    /// the size of the box along the writing direction, as a percentage of
+    /// the viewport's width for horizontal cues and of its height otherwise
+    pub size: f64,
+}
+
+impl CueSettings {
+    /// Resolves where a single line of left-to-right text with these settings
+    /// is displayed in a video with the given aspect ratio (width divided by
+    /// height), which sets how thick a line is across the width of the
+    /// viewport for vertical cues. Use [`cue_box`] for other text.
+    pub fn resolved_position(&self, video_aspect: f64) -> ResolvedPosition {
+        let line_size = match self.writing_direction {
+            WritingDirection::Horizontal => LINE_HEIGHT,
+            _ => LINE_HEIGHT / video_aspect,
+        };
+
+        let cue_box = cue_box(self, Direction::Ltr, line_size, 1);
+
+        ResolvedPosition {
+            x: cue_box.x,
+            y: cue_box.y,
+            size: match self.writing_direction {
+                WritingDirection::Horizontal => cue_box.width,
+                _ => cue_box.height,
+            },
+        }
+    }
+}
+
+/// Returns the position of the cue, resolving `auto` based on the text
+/// alignment.
+pub fn computed_position(settings: &CueSettings) -> f64 {
+    match (settings.position, settings.align) {
+        (Some(position), _) => position.get(),
+        (None, Align::Left) => 0.0,
+        (None, Align::Right) => 100.0,
+        (None, _) => 50.0,
+    }
+}
+
+/// Returns the position alignment of the cue, resolving `auto` based on the
+/// text alignment and the base direction of the cue's text. Never returns
+/// [`PositionAlign::Auto`].
+pub fn computed_position_align(settings: &CueSettings, base_direction: Direction) -> PositionAlign {
+    match (settings.position_align, settings.align, base_direction) {
+        (PositionAlign::Auto, Align::Left, _) => PositionAlign::LineLeft,
+        (PositionAlign::Auto, Align::Right, _) => PositionAlign::LineRight,
+        (PositionAlign::Auto, Align::Start, Direction::Ltr) => PositionAlign::LineLeft,
+        (PositionAlign::Auto, Align::Start, Direction::Rtl) => PositionAlign::LineRight,
+        (PositionAlign::Auto, Align::End, Direction::Ltr) => PositionAlign::LineRight,
+        (PositionAlign::Auto, Align::End, Direction::Rtl) => PositionAlign::LineLeft,
+        (PositionAlign::Auto, Align::Center, _) => PositionAlign::Center,
+        (align, _, _) => align,
+    }
+}
+
+/// Returns the size of the cue box along its writing direction, limited so
+/// that the box stays inside the viewport at its position.
+pub fn computed_size(settings: &CueSettings, base_direction: Direction) -> f64 {
+    let position = computed_position(settings);
+
+    let maximum = match computed_position_align(settings, base_direction) {
+        PositionAlign::LineLeft => 100.0 - position,
+        PositionAlign::LineRight => position,
+        _ if position <= 50.0 => position * 2.0,
+        _ => (100.0 - position) * 2.0,
+    };
+
+    settings.size.get().min(maximum)
+}
+
+/// Computes the box of a cue with the given settings and base direction,
+/// holding `line_count` lines of text that are each `line_size` thick (as a
+/// percentage of the viewport's height for horizontal cues and of its width
+/// for vertical cues).
+pub fn cue_box(
+    settings: &CueSettings,
+    base_direction: Direction,
+    line_size: f64,
+    line_count: usize,
+) -> CueBox {
+    let position = computed_position(settings);
+    let size = computed_size(settings, base_direction);
+    let extent = line_size * line_count as f64;
+
+    // the offset of the box along the writing direction
+    let indent = match computed_position_align(settings, base_direction) {
+        PositionAlign::LineLeft => position,
+        PositionAlign::LineRight => position - size,
+        _ => position - size / 2.0,
+    };
+
+    // the offset of the box across the writing direction
+    let offset = match settings.line {
+        Line::Percentage(line) => {
+            let line = line.get();
+
+            match settings.line_align {
+                LineAlign::Start => line,
+                LineAlign::Center => line - extent / 2.0,
+                LineAlign::End => line - extent,
+            }
+        }
+        Line::Auto => snapped_offset(settings.writing_direction, -1, line_size, extent),
+        Line::Number(line) => snapped_offset(settings.writing_direction, line, line_size, extent),
+    };
+
+    match settings.writing_direction {
+        WritingDirection::Horizontal => CueBox {
+            x: indent,
+            y: offset,
+            width: size,
+            height: extent,
+        },
+        WritingDirection::VerticalLeft | WritingDirection::VerticalRight => CueBox {
+            x: offset,
+            y: indent,
+            width: extent,
+            height: size,
+        },
+    }
+}
+
+/// Places a box that snaps to lines. Positive line numbers count lines from
+/// the top edge for horizontal cues, from the right edge for vertical cues
+/// growing left, and from the left edge for vertical cues growing right;
+/// negative line numbers count from the opposite edge.
+fn snapped_offset(direction: WritingDirection, line: i64, step: f64, extent: f64) -> f64 {
+    if step <= 0.0 {
+        return 0.0;
+    }
+
+    let line = match direction {
+        WritingDirection::VerticalLeft => -line - 1,
+        _ => line,
+    };
+
+    let mut position = step * line as f64;
+
+    if line < 0 {
+        position += 100.0;
+    }
+
+    // move the box back inside the viewport a whole line at a time
+    if position + extent > 100.0 {
+        position -= ((position + extent - 100.0) / step).ceil() * step;
+    }
+
+    if position < 0.0 {
+        position += (-position / step).ceil() * step;
+    }
+
+    position.clamp(0.0, (100.0 - extent).max(0.0))
+}
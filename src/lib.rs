@@ -1,35 +1,381 @@
 //! This crate implements a bare-bones WebVTT parser. It is missing a few
-//! features, notably support for regions, styles, and most types of settings
-//! that are applicable to cues.
+//! features, notably support for regions and styles.
 
 use std::{iter::Peekable, time::Duration};
 
-use thiserror::Error;
-
+mod align;
+mod anchor;
+mod audit;
+mod bidi;
+mod build;
+mod burnin;
+mod bytes;
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "json")]
+mod chapters;
+mod color;
+mod confidence;
+mod csv;
+mod datetime;
+mod diff;
+mod edit;
+mod emoji;
+mod error;
+mod extensions;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "test-fixtures")]
+pub mod fixtures;
+mod forced;
+mod format;
+mod fragment;
+mod frozen;
+mod gaps;
+pub mod generate;
+mod hash;
+mod header;
+mod html;
+#[cfg(feature = "hyphenation")]
+mod hyphenate;
+mod index;
+mod karaoke;
+pub mod layout;
+mod markdown;
+mod mask;
+mod matching;
+mod merge;
+mod metadata;
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+#[cfg(feature = "napi")]
+pub mod node;
+mod nonspeech;
+mod normalize;
+mod paginate;
+mod patch;
+#[cfg(feature = "pretty-errors")]
+mod pretty;
+mod profile;
+mod prune;
+#[cfg(feature = "python")]
+pub mod python;
+mod quality;
+mod realign;
+mod region;
+mod rollup;
+mod scan;
+mod scheduler;
+mod search;
+mod sentence;
+mod settings;
+mod shift;
+pub mod simulate;
+mod snap;
+mod speed;
+mod split;
+#[cfg(feature = "srv3")]
+mod srv3;
+mod tail;
+mod template;
 #[cfg(test)]
 mod test;
+pub mod text;
+mod timeline;
+mod timestamp;
+mod trace;
+mod track;
+mod validate;
+mod vttcue;
+mod width;
+mod wrap;
+mod write;
+#[cfg(feature = "xliff")]
+mod xliff;
+
+pub use align::Aligner;
+pub use anchor::Marker;
+pub use audit::{Audit, AuditRecord};
+pub use bidi::{base_direction, Direction};
+pub use build::TimedWord;
+pub use burnin::DrawtextOptions;
+pub use bytes::{parse_bytes, Utf8Policy};
+pub use datetime::{format_date_time, ProgramDateTime};
+pub use diff::CueEvent;
+pub use edit::{Change, ChangeKind, EditSession};
+pub use emoji::{EmojiPolicy, EmojiReport};
+pub use error::{ConvertError, Error, ParseError, ValidationError};
+pub use extensions::ExtensionMap;
+pub use format::{HeaderSeparator, LineEnding, SourceFormat, TimestampStyle};
+pub use fragment::DeepLink;
+pub use frozen::{FrozenCue, FrozenFile};
+pub use header::{count_cues, parse_header, Header};
+#[cfg(feature = "hyphenation")]
+pub use hyphenate::Hyphenation;
+pub use index::CueIndex;
+pub use karaoke::KaraokeUnit;
+pub use markdown::MarkdownOptions;
+pub use mask::{MaskStyle, WordList};
+pub use merge::{merge_edits, ConflictKind, MergeConflict};
+pub use metadata::{CuePayload, TrackKind};
+pub use nonspeech::{CueClass, SdhOptions};
+pub use normalize::{NormalizeOptions, QuoteStyle};
+pub use paginate::Page;
+pub use patch::{CuePatch, PatchError, PatchOp};
+pub use profile::{Profile, ProfileRules};
+pub use quality::{CategoryScore, QualityCategory, QualityScore, Rubric};
+pub use scheduler::{Clock, Scheduler, SystemClock};
+pub use search::{Concordance, Match, Query, WordIndexOptions};
+#[cfg(feature = "icu")]
+pub use sentence::IcuSegmenter;
+pub use sentence::{RuleSegmenter, SentenceSegmenter};
+pub use settings::{
+    Align, CueSettings, Line, LineAlign, Percentage, PositionAlign, WritingDirection,
+};
+pub use shift::{Shift, ShiftError};
+pub use snap::SnapPolicy;
+pub use speed::{SpeedFix, TextBudget};
+pub use tail::TailParser;
+pub use template::{CueTemplate, TemplateError};
+pub use timeline::{TimelineEvent, Transition};
+pub use timestamp::{Timestamp, TimestampError};
+pub use trace::{parse_file_traced, LineKind, ParseTrace, TraceLine};
+pub use track::{merge_bilingual, BilingualStyle, TrackSet};
+pub use validate::{Finding, FindingKind};
+pub use vttcue::{NumberOrAuto, VttCueInit};
+pub use width::LengthMetric;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+/// A non-fatal problem found while parsing, reported when requested through
+/// [`ParseOptions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Byte offset in the input of the block the diagnostic applies to.
+    pub offset: usize,
+    pub kind: DiagnosticKind,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    #[error("cue starts at {start:?}, before the previous cue at {previous:?}")]
+    UnsortedCue { start: Duration, previous: Duration },
+
+    #[error("cue ends at {end:?}, not after its start at {start:?}")]
+    EndNotAfterStart { start: Duration, end: Duration },
+
+    #[error("cue identifier contains \"-->\", so the cue has no identifier")]
+    ArrowInId,
+
+    #[error("cue text contains \"-->\", which ends the cue")]
+    ArrowInText,
+
+    #[error("another WEBVTT header starts a new segment")]
+    RepeatedHeader,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ParseOptions {
+    /// Report cues that start before the cue preceding them. The spec permits
+    /// this, but most consumers expect cues to be sorted by start time.
+    pub check_order: bool,
+    /// How to fix cues that do not end after they start, which are always
+    /// reported. They are kept as they are if this is `None`.
+    pub timing_fix: Option<TimingFix>,
+    /// Where to keep the comments of NOTE blocks.
+    pub note_attachment: NoteAttachment,
+    /// How to interpret the payloads of cues. Use [`TrackKind::payload`] to
+    /// choose based on the kind of track being parsed.
+    pub payload: CuePayload,
+    /// What to do with further `WEBVTT` headers in the file, as left by
+    /// concatenating several files. They are always reported.
+    pub repeated_header: RepeatedHeader,
+    /// Keep blocks that cannot be parsed, such as cues with bad timings, as
+    /// [`Block::Raw`] instead of dropping them. `STYLE` and `REGION` blocks
+    /// before the first cue are kept either way.
+    pub lenient: bool,
+}
 
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("missing file magic")]
-    NoMagic,
+/// What the parser does with a `WEBVTT` header after the first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RepeatedHeader {
+    /// Treat the header as the start of a new segment and merge the cues of
+    /// every segment into one file. The header block itself is dropped.
+    #[default]
+    Merge,
+    /// Fail with [`ParseError::RepeatedHeader`].
+    Reject,
+}
 
-    #[error("bad file header")]
-    BadHeader,
+/// Where the parser keeps the comments of NOTE blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NoteAttachment {
+    /// Attach each NOTE block to the cue after it, in [`Cue::notes`], so that
+    /// it follows the cue when cues are reordered or removed. NOTE blocks after
+    /// the last cue are kept as [`Block::Note`]s.
+    #[default]
+    Following,
+    /// Keep every NOTE block as a [`Block::Note`] where it appears.
+    Detached,
+}
 
-    #[error("unexpected end-of-file")]
-    UnexpectedEof,
+/// A way to fix a cue whose end time is not after its start time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimingFix {
+    /// Swap the start and end times. Cues with equal start and end times are
+    /// left as they are.
+    Swap,
+    /// Move the end time so that the cue lasts at least this long.
+    ExtendMinDuration(Duration),
+    /// Leave the cue out of the file.
+    Drop,
 }
 
 #[derive(Debug, Clone)]
 pub struct File {
+    /// The text after `WEBVTT` and its separator on the first line, like the
+    /// `- Episode 1` of `WEBVTT - Episode 1`. A file without one is written
+    /// with `WEBVTT` alone on its first line.
     pub description: Option<String>,
+    /// The lines between the `WEBVTT` line and the first blank line, like
+    /// `Kind: captions` or the `X-TIMESTAMP-MAP` of an HLS segment, which are
+    /// written back as they were read.
+    pub header_lines: Vec<String>,
+    /// The blocks of the file, in document order. The spec does not require
+    /// cues to be sorted by start time; see [`File::is_sorted`] and
+    /// [`File::sort_by_start`].
     pub blocks: Vec<Block>,
+    /// How the file was encoded, which the writer follows.
+    pub format: SourceFormat,
 }
 
+impl File {
+    pub fn cues(&self) -> impl Iterator<Item = &Cue> {
+        self.blocks.iter().filter_map(Block::as_cue)
+    }
+
+    pub fn cues_mut(&mut self) -> impl Iterator<Item = &mut Cue> {
+        self.blocks.iter_mut().filter_map(Block::as_cue_mut)
+    }
+
+    /// Returns whether the cues in this file are in order of start time.
+    pub fn is_sorted(&self) -> bool {
+        self.cues()
+            .zip(self.cues().skip(1))
+            .all(|(a, b)| a.start <= b.start)
+    }
+
+    /// Returns the earliest start and latest end of the cues in this file, or
+    /// `None` if it has no cues. They are computed on each call, since the
+    /// blocks can be changed directly.
+    pub fn span(&self) -> Option<(Duration, Duration)> {
+        let start = self.cues().map(|cue| cue.start).min()?;
+        let end = self.cues().map(|cue| cue.end).max()?;
+
+        Some((start, end))
+    }
+
+    /// Returns how long at least one cue is shown for, counting the time where
+    /// cues overlap once.
+    pub fn total_display_time(&self) -> Duration {
+        let mut intervals: Vec<_> = self
+            .cues()
+            .filter(|cue| cue.end > cue.start)
+            .map(|cue| (cue.start, cue.end))
+            .collect();
+
+        intervals.sort();
+
+        let mut total = Duration::ZERO;
+        let mut current: Option<(Duration, Duration)> = None;
+
+        for (start, end) in intervals {
+            match &mut current {
+                Some((_, current_end)) if start <= *current_end => {
+                    *current_end = (*current_end).max(end);
+                }
+                _ => {
+                    if let Some((current_start, current_end)) = current {
+                        total += current_end - current_start;
+                    }
+
+                    current = Some((start, end));
+                }
+            }
+        }
+
+        if let Some((current_start, current_end)) = current {
+            total += current_end - current_start;
+        }
+
+        total
+    }
+
+    /// Sorts the cues in this file by start time, then by
+    /// [`Cue::source_order`], so cues with equal start times are in the order
+    /// they were parsed in. The sort is stable, so cues that are also equal in
+    /// that keep their current order. Other blocks stay just before the cue
+    /// that followed them.
+    pub fn sort_by_start(&mut self) {
+        let mut next_key = (Duration::MAX, usize::MAX);
+
+        let mut keys: Vec<_> = self
+            .blocks
+            .iter()
+            .rev()
+            .map(|block| {
+                if let Some(cue) = block.as_cue() {
+                    next_key = (cue.start, cue.source_order);
+                }
+
+                next_key
+            })
+            .collect();
+
+        keys.reverse();
+
+        let mut keyed: Vec<_> = keys
+            .into_iter()
+            .zip(std::mem::take(&mut self.blocks))
+            .collect();
+        keyed.sort_by_key(|&(key, _)| key);
+
+        self.blocks = keyed.into_iter().map(|(_, block)| block).collect();
+    }
+}
+
+// Cues are by far the most common blocks, so boxing them to shrink the other
+// variants would cost more than it saves.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum Block {
     Cue(Cue),
+    /// The full text of a NOTE block that is not attached to a cue, starting
+    /// with `NOTE`.
+    Note(String),
+    /// A `STYLE` or `REGION` block, or in lenient mode a block the parser
+    /// could not classify, kept so that it is written back exactly as it was
+    /// read.
+    Raw {
+        lines: Vec<String>,
+    },
+}
+
+impl Block {
+    pub fn as_cue(&self) -> Option<&Cue> {
+        match self {
+            Block::Cue(cue) => Some(cue),
+            _ => None,
+        }
+    }
+
+    pub fn as_cue_mut(&mut self) -> Option<&mut Cue> {
+        match self {
+            Block::Cue(cue) => Some(cue),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone)]
@@ -39,367 +385,708 @@ pub struct Cue {
     pub id: String,
     pub text: String,
     pub settings: CueSettings,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
-pub struct CueSettings {
-    pub region: Option<String>,
-    pub writing_direction: WritingDirection,
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
-pub enum WritingDirection {
-    /// horizontal (a line extends horizontally and is offset vertically from
-    /// the video viewport’s top edge, with consecutive lines displayed below
-    /// each other)
-    #[default]
-    Horizontal,
-    /// vertical growing left (a line extends vertically and is offset
-    /// horizontally from the video viewport’s right edge, with consecutive
-    /// lines displayed to the left of each other)
-    VerticalLeft,
-    /// vertical growing right (a line extends vertically and is offset
-    /// horizontally from the video viewport’s left edge, with consecutive lines
-    /// displayed to the right of each other)
-    VerticalRight,
+    /// The settings as they were written after the timings, including the
+    /// whitespace before them. They are written back as they are while they
+    /// still parse to [`Cue::settings`], so that unknown or oddly formatted
+    /// settings survive changes to the timings.
+    pub raw_settings: Option<String>,
+    /// Byte offset of the start of this cue's block in the input it was parsed
+    /// from.
+    pub offset: usize,
+    /// Position of this cue among the cues of the input it was parsed from.
+    /// Cues that start at the same time are sorted by it, so that they are
+    /// shown in document order as the spec requires even after the blocks
+    /// have been reordered.
+    pub source_order: usize,
+    /// User data carried alongside the cue, written as a NOTE block before it.
+    pub extensions: ExtensionMap,
+    /// The full text of the NOTE blocks attached to this cue, each starting
+    /// with `NOTE`. They are written before the cue.
+    pub notes: Vec<String>,
+    pub payload: CuePayload,
 }
 
 struct FileContext {
     seen_cue: bool,
     in_header: bool,
+    options: ParseOptions,
+    diagnostics: Vec<Diagnostic>,
+    /// Extensions read from a NOTE block, waiting for the cue they belong to.
+    extensions: Option<ExtensionMap>,
+    /// Whether a cue was read with timestamps without hours, and whether one
+    /// in the first hour was read with them. Hours are only left out when
+    /// the file is written if every cue left them out.
+    short_timestamps: bool,
+    full_timestamps: bool,
+    /// The number of cues parsed so far.
+    cue_count: usize,
+    /// What each line was taken to be, kept when the parse is traced.
+    trace: Option<trace::Decisions>,
+}
+
+impl FileContext {
+    fn new(options: ParseOptions) -> Self {
+        FileContext {
+            seen_cue: false,
+            in_header: false,
+            options,
+            diagnostics: vec![],
+            extensions: None,
+            short_timestamps: false,
+            full_timestamps: false,
+            cue_count: 0,
+            trace: None,
+        }
+    }
 }
 
 /// Parses a string as a WebVTT file.
-pub fn parse_file(input: &str) -> Result<File, Error> {
-    use Error::*;
+pub fn parse_file(input: &str) -> Result<File, ParseError> {
+    parse_file_with(input, &ParseOptions::default()).map(|(file, _)| file)
+}
 
-    let mut lines = input.split('\n').enumerate().peekable();
+/// Parses a string as a WebVTT file, returning any diagnostics requested by
+/// `options` alongside it.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bytes = input.len()), err)
+)]
+pub fn parse_file_with(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(File, Vec<Diagnostic>), ParseError> {
+    let mut file_ctx = FileContext::new(options.clone());
+    let file = parse_input(input, &mut file_ctx)?;
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        cues = file.cues().count(),
+        diagnostics = file_ctx.diagnostics.len(),
+        "parsed file"
+    );
+
+    Ok((file, file_ctx.diagnostics))
+}
 
-    let (_, line) = lines.next().ok_or(NoMagic)?;
-    let line = expect_str(line, "WEBVTT", NoMagic)?;
+/// Parses a whole file, keeping diagnostics and the trace in `file_ctx`.
+fn parse_input(input: &str, file_ctx: &mut FileContext) -> Result<File, ParseError> {
+    let mut lines = Lines::new(input, 0).peekable();
+    let first = lines.peek().copied();
+    let description = parse_magic(&mut lines);
 
-    let description = if line.len() > 0 {
-        let line = expect_char(line, &[' ', '\t'], BadHeader)?;
-        Some(line.to_owned())
-    } else {
-        None
-    };
+    if let Some((offset, _)) = first {
+        match description {
+            Ok(_) => file_ctx.trace(offset, LineKind::Header, "WEBVTT file magic"),
+            Err(_) => file_ctx.trace(offset, LineKind::Skip, "bad WEBVTT line, file rejected"),
+        }
+    }
+
+    let description = description?;
+    let header_lines = parse_header_lines(&mut lines, file_ctx);
 
     skip_blank_lines(&mut lines);
 
-    let mut file_ctx = FileContext {
-        in_header: false,
-        seen_cue: false,
+    let mut assembly = Assembly::default();
+
+    parse_blocks(&mut lines, file_ctx, &mut assembly)?;
+
+    let format = SourceFormat {
+        line_ending: LineEnding::detect(input),
+        bom: input.starts_with('\u{feff}'),
+        header_separator: HeaderSeparator::detect(input),
+        ..Default::default()
     };
 
-    let mut blocks = vec![];
+    Ok(assembly.finish(description, header_lines, file_ctx, format))
+}
 
-    while lines.peek().is_some() {
-        if let Some(block) = parse_block(&mut lines, &mut file_ctx) {
-            blocks.push(block);
+/// Reads the lines after the `WEBVTT` line, up to the first blank line or to
+/// a line with an arrow, which starts the first cue.
+fn parse_header_lines<'a, I: Iterator<Item = (usize, &'a str)>>(
+    lines: &mut Peekable<I>,
+    file_ctx: &mut FileContext,
+) -> Vec<String> {
+    let mut header_lines = vec![];
+
+    while let Some(&(offset, line)) = lines.peek() {
+        if line.is_empty() || scan::has_arrow(line) {
+            break;
         }
 
-        skip_blank_lines(&mut lines);
+        file_ctx.trace(offset, LineKind::Header, "header line");
+        header_lines.push(line.to_owned());
+        lines.next();
     }
 
-    Ok(File {
-        description,
-        blocks,
-    })
+    header_lines
 }
 
-struct BlockContext {
-    line_count: usize,
-    seen_eof: bool,
-    seen_arrow: bool,
-    cue: Option<Cue>,
-    buffer: String,
+/// Parses a WebVTT file from its lines, as they come out of a source like
+/// [`BufRead::lines`](std::io::BufRead::lines). Only one block is held in
+/// memory at a time besides the parsed file.
+///
+/// Each line is taken to end with a single `\n`. A line may keep the `\r` of
+/// a CRLF line ending, as the lines of [`str::split`] on `\n` do, and then the
+/// line ending and the byte offsets of cues and diagnostics are those of the
+/// input. [`BufRead::lines`](std::io::BufRead::lines) removes the `\r`, so a
+/// CRLF file read that way is written back with LF line endings, and its
+/// offsets are short by one byte for every line before them.
+pub fn parse_lines<I: IntoIterator<Item = String>>(lines: I) -> Result<File, ParseError> {
+    parse_lines_with(lines, &ParseOptions::default()).map(|(file, _)| file)
 }
 
-fn parse_block<'a, I: Iterator<Item = (usize, &'a str)>>(
+/// Parses a WebVTT file from its lines as in [`parse_lines`], returning any
+/// diagnostics requested by `options` alongside it.
+pub fn parse_lines_with<I: IntoIterator<Item = String>>(
+    lines: I,
+    options: &ParseOptions,
+) -> Result<(File, Vec<Diagnostic>), ParseError> {
+    let mut lines = lines.into_iter().peekable();
+
+    let first = lines.next().unwrap_or_default();
+    let format = SourceFormat {
+        line_ending: if first.ends_with('\r') {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        },
+        bom: first.starts_with('\u{feff}'),
+        header_separator: HeaderSeparator::detect(&first),
+        ..Default::default()
+    };
+    let description = parse_magic(&mut Lines::new(&first, 0))?;
+
+    let mut file_ctx = FileContext::new(options.clone());
+    let mut assembly = Assembly::default();
+
+    // Blocks end at blank lines, so the input is parsed one blank-line
+    // separated chunk at a time, each with its offset in the whole input.
+    let mut chunk = String::new();
+    let mut chunk_offset = 0;
+    let mut offset = first.len() + 1;
+    let mut header_lines = vec![];
+
+    while let Some(line) = lines.next_if(|line| {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        !line.is_empty() && !scan::has_arrow(line)
+    }) {
+        offset += line.len() + 1;
+        header_lines.push(line.strip_suffix('\r').unwrap_or(&line).to_owned());
+    }
+
+    for line in lines.chain([String::new()]) {
+        if line.strip_suffix('\r').unwrap_or(&line).is_empty() {
+            if !chunk.is_empty() {
+                let mut chunk_lines = Lines::new(&chunk, chunk_offset).peekable();
+                parse_blocks(&mut chunk_lines, &mut file_ctx, &mut assembly)?;
+                chunk.clear();
+            }
+        } else {
+            if chunk.is_empty() {
+                chunk_offset = offset;
+            } else {
+                chunk.push('\n');
+            }
+
+            chunk.push_str(&line);
+        }
+
+        offset += line.len() + 1;
+    }
+
+    let file = assembly.finish(description, header_lines, &file_ctx, format);
+    Ok((file, file_ctx.diagnostics))
+}
+
+/// The blocks of a file as they are parsed, with the state needed to attach
+/// notes and check the order of cues across blocks.
+#[derive(Default)]
+struct Assembly {
+    blocks: Vec<Block>,
+    previous_start: Option<Duration>,
+    /// Notes waiting for the cue they are attached to.
+    notes: Vec<String>,
+}
+
+impl Assembly {
+    /// Builds the file, completing `format` with what was learned about it
+    /// while parsing.
+    fn finish(
+        mut self,
+        description: Option<String>,
+        header_lines: Vec<String>,
+        file_ctx: &FileContext,
+        format: SourceFormat,
+    ) -> File {
+        self.blocks.extend(self.notes.into_iter().map(Block::Note));
+
+        File {
+            description,
+            header_lines,
+            blocks: self.blocks,
+            format: SourceFormat {
+                timestamps: if file_ctx.short_timestamps && !file_ctx.full_timestamps {
+                    TimestampStyle::OmitZeroHours
+                } else {
+                    TimestampStyle::Full
+                },
+                ..format
+            },
+        }
+    }
+}
+
+/// Parses every block in `lines`, adding them to `assembly`.
+fn parse_blocks<'a, I: Iterator<Item = (usize, &'a str)>>(
     lines: &mut Peekable<I>,
     file_ctx: &mut FileContext,
-) -> Option<Block> {
-    let mut block_ctx = BlockContext {
-        line_count: 0,
-        seen_arrow: false,
-        seen_eof: false,
-
-        cue: None,
-        buffer: String::new(),
-    };
+    assembly: &mut Assembly,
+) -> Result<(), ParseError> {
+    while let Some(&(offset, line)) = lines.peek() {
+        if is_magic(line) {
+            file_ctx.trace(offset, LineKind::Header, "WEBVTT header of another segment");
+
+            if file_ctx.options.repeated_header == RepeatedHeader::Reject {
+                return Err(ParseError::RepeatedHeader { offset });
+            }
 
-    while let Some((_, line)) = lines.next() {
-        block_ctx.line_count += 1;
-        block_ctx.seen_eof = lines.peek().is_none();
+            file_ctx.diagnostics.push(Diagnostic {
+                offset,
+                kind: DiagnosticKind::RepeatedHeader,
+            });
 
-        if line.contains("-->") {
-            if !file_ctx.in_header {
-                if (block_ctx.line_count == 1)
-                    || (block_ctx.line_count == 2 && !block_ctx.seen_arrow)
-                {
-                    block_ctx.seen_arrow = true;
+            // The header runs up to a blank line, or the timings of the first
+            // cue of the segment if there is no blank line before them.
+            lines.next();
 
-                    if let Some((start, end, settings)) = parse_cue_timings_settings(line) {
-                        let buffer = std::mem::replace(&mut block_ctx.buffer, String::new());
+            while let Some((offset, _)) =
+                lines.next_if(|&(_, line)| !line.is_empty() && !scan::has_arrow(line))
+            {
+                file_ctx.trace(offset, LineKind::Header, "header of another segment");
+            }
 
-                        let cue = Cue {
-                            id: buffer,
-                            start,
-                            end,
-                            settings,
-                            ..Default::default()
-                        };
+            skip_blank_lines(lines);
+            continue;
+        }
 
-                        block_ctx.cue = Some(cue);
-                    }
+        if let Some(mut block) = parse_block(lines, file_ctx) {
+            if let Block::Note(note) = block {
+                match file_ctx.options.note_attachment {
+                    NoteAttachment::Following => assembly.notes.push(note),
+                    NoteAttachment::Detached => assembly.blocks.push(Block::Note(note)),
                 }
+
+                skip_blank_lines(lines);
+                continue;
             }
-        } else if line.is_empty() {
-            break;
-        } else {
-            if !file_ctx.in_header && block_ctx.line_count == 2 {
-                if !file_ctx.seen_cue {
-                    if block_ctx.buffer.starts_with("STYLE") {
-                        unimplemented!("WebVTT styles are unimplemented")
-                    }
 
-                    if block_ctx.buffer.starts_with("REGION") {
-                        unimplemented!("WebVTT regions are unimplemented")
+            if let Some(cue) = block.as_cue_mut() {
+                cue.notes.append(&mut assembly.notes);
+
+                if let Some(previous) = assembly.previous_start {
+                    if file_ctx.options.check_order && cue.start < previous {
+                        file_ctx.diagnostics.push(Diagnostic {
+                            offset: cue.offset,
+                            kind: DiagnosticKind::UnsortedCue {
+                                start: cue.start,
+                                previous,
+                            },
+                        });
                     }
                 }
-            }
 
-            if !block_ctx.buffer.is_empty() {
-                block_ctx.buffer.push('\n');
+                assembly.previous_start = Some(cue.start);
             }
 
-            block_ctx.buffer.push_str(line);
+            assembly.blocks.push(block);
         }
-    }
 
-    if let Some(mut cue) = block_ctx.cue {
-        cue.text = block_ctx.buffer;
-        Some(Block::Cue(cue))
-    } else {
-        None
+        skip_blank_lines(lines);
     }
-}
 
-fn parse_cue_timings_settings(line: &str) -> Option<(Duration, Duration, CueSettings)> {
-    let line = line.trim_start();
-    let (start_time, line) = parse_timestamp(line)?;
+    Ok(())
+}
 
-    let line = line.trim_start();
-    let line = line.strip_prefix("-->")?;
-    let line = line.trim_start();
+/// Lazily parses the cues of a WebVTT file, one block at a time.
+pub struct CueReader<'a> {
+    lines: Peekable<Lines<'a>>,
+    file_ctx: FileContext,
+}
 
-    let (end_time, line) = parse_timestamp(line)?;
-    let settings = parse_settings(line);
+impl<'a> CueReader<'a> {
+    /// Starts reading cues from the beginning of a WebVTT file, checking its
+    /// header first.
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        let mut lines = Lines::new(input, 0).peekable();
+        parse_magic(&mut lines)?;
+
+        Ok(CueReader {
+            lines,
+            file_ctx: FileContext::new(ParseOptions::default()),
+        })
+    }
 
-    Some((start_time, end_time, settings))
+    /// Starts reading cues at the given byte offset into a WebVTT file, which
+    /// should be the start of a block (such as one obtained from a
+    /// [`CueIndex`]). The header is not checked.
+    ///
+    /// Fails with [`ParseError::BadIndex`] if the offset is past the end of
+    /// the file or inside a character, as it can be when the index is stale.
+    pub fn at(input: &'a str, offset: usize) -> Result<Self, ParseError> {
+        let Some(rest) = input.get(offset..) else {
+            return Err(ParseError::BadIndex {
+                reason: "offset outside the file or inside a character",
+            });
+        };
+
+        Ok(CueReader {
+            lines: Lines::new(rest, offset).peekable(),
+            file_ctx: FileContext {
+                seen_cue: true,
+                ..FileContext::new(ParseOptions::default())
+            },
+        })
+    }
 }
 
-fn parse_settings(line: &str) -> CueSettings {
-    let mut settings = CueSettings {
-        region: None,
-        writing_direction: WritingDirection::Horizontal,
-    };
+impl Iterator for CueReader<'_> {
+    type Item = Cue;
 
-    for setting in line.split(' ') {
-        if let Some((key, value)) = setting.split_once(':') {
-            if key == "" || value == "" {
-                continue;
-            }
+    fn next(&mut self) -> Option<Cue> {
+        loop {
+            skip_blank_lines(&mut self.lines);
+            self.lines.peek()?;
 
-            match key {
-                "region" => {
-                    settings.region = Some(value.to_owned());
-                }
-                "vertical" => match value {
-                    "lr" => settings.writing_direction = WritingDirection::VerticalLeft,
-                    "rl" => settings.writing_direction = WritingDirection::VerticalRight,
-                    _ => {}
-                },
-                _ => {}
+            if let Some(Block::Cue(cue)) = parse_block(&mut self.lines, &mut self.file_ctx) {
+                return Some(cue);
             }
         }
     }
+}
+
+/// Checks the `WEBVTT` line at the start of a file, returning the
+/// description after it.
+fn parse_magic<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Option<String>, ParseError> {
+    use ParseError::*;
 
-    // there are no vertical regions
-    if settings.writing_direction != WritingDirection::Horizontal {
-        settings.region = None;
+    let (_, line) = lines.next().ok_or(NoMagic)?;
+    let rest = expect_str(line, "WEBVTT", NoMagic)?;
+
+    if rest.is_empty() {
+        return Ok(None);
     }
 
-    settings
-}
+    let description = rest.strip_prefix([' ', '\t']).ok_or_else(|| BadHeader {
+        line: line.to_owned(),
+    })?;
 
-/// Parses a timestamp from the given string. Returns a Duration that represents
-/// the timestamp's offset from the zero, and the remainder of the string after
-/// skipping the timestamp.
-fn parse_timestamp(line: &str) -> Option<(Duration, &str)> {
-    let mut has_hours = false;
-    let mut buf = String::new();
-    let mut places: Vec<u64> = vec![];
+    Ok(Some(description.to_owned()))
+}
 
-    let (mut last_idx, mut last_char);
-    let mut chars = line.char_indices();
+/// Returns whether the line is a `WEBVTT` header line.
+fn is_magic(line: &str) -> bool {
+    line.strip_prefix("WEBVTT")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
 
-    // parse digits if there are any
-    loop {
-        let (_, char) = chars.next()?;
-        last_char = char;
+/// Splits the input into lines, yielding each one along with its byte offset.
+/// Lines may end with LF or CRLF, and a byte order mark at the start of the
+/// input is skipped.
+struct Lines<'a> {
+    input: &'a str,
+    base: usize,
+    pos: usize,
+}
 
-        if char.is_ascii_digit() {
-            buf.push(char);
+impl<'a> Lines<'a> {
+    fn new(input: &'a str, base: usize) -> Self {
+        let pos = if base == 0 && input.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
         } else {
-            break;
-        }
-    }
+            0
+        };
 
-    // if there were no digits, or we hit something that isn't ':', error
-    if buf.is_empty() || last_char != ':' {
-        return None;
+        Lines { input, base, pos }
     }
+}
 
-    let num = buf.parse().unwrap();
+impl<'a> Iterator for Lines<'a> {
+    type Item = (usize, &'a str);
 
-    // this could either be the hours place or the minutes place
-    places.push(num);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos > self.input.len() {
+            return None;
+        }
+
+        let start = self.pos;
+        let rest = &self.input[start..];
+        let end = scan::find_newline(rest).map_or(self.input.len(), |idx| start + idx);
 
-    if num > 59 || buf.len() != 2 {
-        has_hours = true;
+        self.pos = end + 1;
+
+        let line = &self.input[start..end];
+        Some((self.base + start, line.strip_suffix('\r').unwrap_or(line)))
     }
+}
 
-    buf.clear();
+struct BlockContext {
+    offset: usize,
+    line_count: usize,
+    seen_eof: bool,
+    seen_arrow: bool,
+    cue: Option<Cue>,
+    buffer: String,
+    /// Every line of the block, kept in lenient mode.
+    raw: Vec<String>,
+    /// The offset of each line of the block and whether it is blank, kept
+    /// when the parse is traced.
+    traced: Vec<(usize, bool)>,
+    timing_offset: Option<usize>,
+}
+
+fn parse_block<'a, I: Iterator<Item = (usize, &'a str)>>(
+    lines: &mut Peekable<I>,
+    file_ctx: &mut FileContext,
+) -> Option<Block> {
+    let mut block_ctx = BlockContext {
+        offset: lines.peek().map_or(0, |&(offset, _)| offset),
+        line_count: 0,
+        seen_arrow: false,
+        seen_eof: false,
 
-    // parse out some digits again
-    loop {
-        let (_, char) = chars.next()?;
-        last_char = char;
+        cue: None,
+        buffer: String::new(),
+        raw: vec![],
+        traced: vec![],
+        timing_offset: None,
+    };
 
-        if char.is_ascii_digit() {
-            buf.push(char);
-        } else {
+    while let Some(&(offset, line)) = lines.peek() {
+        let has_arrow = scan::has_arrow(line);
+
+        // Only the first line of a block, or the second if the first has no
+        // arrow, can hold cue timings. Any other line with an arrow starts the
+        // next block, so it is left for the next call.
+        if has_arrow
+            && block_ctx.line_count > 0
+            && (file_ctx.in_header || block_ctx.line_count > 1 || block_ctx.seen_arrow)
+        {
+            report_arrow(line, &block_ctx, file_ctx);
             break;
         }
-    }
 
-    // if we didn't get a 2-digit number, error
-    if buf.len() != 2 {
-        return None;
-    }
-
-    let num = buf.parse().unwrap();
+        lines.next();
+        block_ctx.line_count += 1;
+        block_ctx.seen_eof = lines.peek().is_none();
 
-    if num > 59 {
-        return None;
-    }
+        if file_ctx.options.lenient && !line.is_empty() {
+            block_ctx.raw.push(line.to_owned());
+        }
 
-    places.push(num);
+        if file_ctx.trace.is_some() {
+            block_ctx.traced.push((offset, line.is_empty()));
+        }
 
-    buf.clear();
+        if has_arrow {
+            if !file_ctx.in_header {
+                block_ctx.seen_arrow = true;
 
-    // if we have an hours place, or we hit another colon, parse out another
-    // 2-digit number
-    if has_hours || last_char == ':' {
-        // parse out some digits again
-        loop {
-            let (_, char) = chars.next()?;
-            last_char = char;
+                if let Some((start, end, settings, raw_settings)) = parse_cue_timings_settings(line)
+                {
+                    if !format::has_hours(line) {
+                        file_ctx.short_timestamps = true;
+                    } else if start.as_secs() < 3600 {
+                        file_ctx.full_timestamps = true;
+                    }
 
-            if char.is_ascii_digit() {
-                buf.push(char);
-            } else {
-                break;
+                    let buffer = std::mem::take(&mut block_ctx.buffer);
+
+                    let cue = Cue {
+                        id: buffer,
+                        start,
+                        end,
+                        settings,
+                        raw_settings: Some(raw_settings.to_owned()),
+                        offset: block_ctx.offset,
+                        source_order: file_ctx.cue_count,
+                        extensions: file_ctx.extensions.take().unwrap_or_default(),
+                        payload: file_ctx.options.payload,
+                        ..Default::default()
+                    };
+
+                    block_ctx.cue = Some(cue);
+                    block_ctx.timing_offset = Some(offset);
+                    file_ctx.seen_cue = true;
+                    file_ctx.cue_count += 1;
+                }
+            }
+        } else if line.is_empty() {
+            break;
+        } else {
+            if !block_ctx.buffer.is_empty() {
+                block_ctx.buffer.push('\n');
             }
-        }
 
-        // if we didn't get a 2-digit number, error
-        if buf.len() != 2 {
-            return None;
+            block_ctx.buffer.push_str(line);
         }
+    }
 
-        let num = buf.parse().unwrap();
+    trace::trace_block(&block_ctx, file_ctx);
 
-        if num > 59 {
-            return None;
+    if let Some(mut cue) = block_ctx.cue {
+        cue.text = block_ctx.buffer;
+        check_timings(cue, file_ctx).map(Block::Cue)
+    } else if let Some(extensions) = ExtensionMap::from_note(&block_ctx.buffer) {
+        file_ctx.extensions = Some(extensions);
+        None
+    } else {
+        // extensions only belong to a cue right after them
+        file_ctx.extensions = None;
+
+        if is_note(&block_ctx.buffer) {
+            Some(Block::Note(block_ctx.buffer))
+        } else if file_ctx.options.lenient && !block_ctx.raw.is_empty() {
+            Some(Block::Raw {
+                lines: block_ctx.raw,
+            })
+        } else if is_definition(&block_ctx, file_ctx) {
+            Some(Block::Raw {
+                lines: block_ctx.buffer.split('\n').map(str::to_owned).collect(),
+            })
+        } else {
+            None
         }
+    }
+}
 
-        places.push(num);
+/// Reports the spec violation behind a line with an arrow that ends a block.
+fn report_arrow(line: &str, block_ctx: &BlockContext, file_ctx: &mut FileContext) {
+    let kind = if block_ctx.cue.is_some() {
+        DiagnosticKind::ArrowInText
+    } else if block_ctx.line_count == 1
+        && block_ctx.seen_arrow
+        && parse_cue_timings_settings(line).is_some()
+    {
+        DiagnosticKind::ArrowInId
+    } else {
+        return;
+    };
 
-        buf.clear();
-    }
+    file_ctx.diagnostics.push(Diagnostic {
+        offset: block_ctx.offset,
+        kind,
+    });
+}
 
-    // if we hit a decimal point, we have a fractional number of seconds
-    if last_char != '.' {
-        return None;
-    }
+/// Returns whether a line is the keyword alone, possibly followed by spaces
+/// or tabs.
+fn is_keyword(line: &str, keyword: &str) -> bool {
+    line.strip_prefix(keyword)
+        .is_some_and(|rest| rest.trim_start_matches([' ', '\t']).is_empty())
+}
 
-    // parse out some digits again
-    loop {
-        if let Some((idx, char)) = chars.next() {
-            last_idx = idx;
+/// Returns whether a block is a `STYLE` or `REGION` block, which are only
+/// recognized before the first cue.
+fn is_definition(block_ctx: &BlockContext, file_ctx: &FileContext) -> bool {
+    let first_line = block_ctx.buffer.split('\n').next().unwrap_or_default();
 
-            if char.is_ascii_digit() {
-                buf.push(char);
-            } else {
-                break;
-            }
-        } else {
-            last_idx = line.len();
-            break;
-        }
+    !file_ctx.in_header
+        && !file_ctx.seen_cue
+        && !block_ctx.seen_arrow
+        && (is_keyword(first_line, "STYLE") || is_keyword(first_line, "REGION"))
+}
+
+/// Returns whether a block is a comment: one starting with `NOTE` followed by
+/// whitespace or the end of the block.
+fn is_note(block: &str) -> bool {
+    block
+        .strip_prefix("NOTE")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '\n']))
+}
+
+/// Reports a cue that does not end after it starts, and fixes it as the parse
+/// options require.
+fn check_timings(mut cue: Cue, file_ctx: &mut FileContext) -> Option<Cue> {
+    if cue.end > cue.start {
+        return Some(cue);
     }
 
-    // if we didn't get a 3-digit number, error
-    if buf.len() != 3 {
-        return None;
+    file_ctx.diagnostics.push(Diagnostic {
+        offset: cue.offset,
+        kind: DiagnosticKind::EndNotAfterStart {
+            start: cue.start,
+            end: cue.end,
+        },
+    });
+
+    match file_ctx.options.timing_fix {
+        None => {}
+        Some(TimingFix::Swap) => std::mem::swap(&mut cue.start, &mut cue.end),
+        Some(TimingFix::ExtendMinDuration(duration)) => cue.end = cue.start + duration,
+        Some(TimingFix::Drop) => return None,
     }
 
-    places.push(buf.parse().unwrap());
+    Some(cue)
+}
 
-    Some((
-        match &places[..] {
-            &[hours, minutes, seconds, millis] => {
-                Duration::from_millis(millis + seconds * 1000 + minutes * 60_000 + hours * 3600_000)
-            }
-            &[minutes, seconds, millis] => {
-                Duration::from_millis(millis + seconds * 1000 + minutes * 60_000)
-            }
-            _ => unreachable!(),
-        },
-        &line[last_idx..],
-    ))
+/// Parses the timings and settings of a cue, also returning the settings as
+/// written, including the whitespace before them. The parts of the line may
+/// be separated by any amount of ASCII whitespace, including tabs.
+fn parse_cue_timings_settings(line: &str) -> Option<(Duration, Duration, CueSettings, &str)> {
+    let line = skip_whitespace(line);
+    let (start_time, line) = parse_timestamp(line)?;
+
+    let line = skip_whitespace(line);
+    let line = line.strip_prefix("-->")?;
+    let line = skip_whitespace(line);
+
+    let (end_time, line) = parse_timestamp(line)?;
+    let settings = settings::parse_settings(line);
+
+    Some((start_time, end_time, settings, line))
 }
 
-fn skip_blank_lines<'a, I: Iterator<Item = (usize, &'a str)>>(lines: &mut Peekable<I>) {
-    loop {
-        match lines.peek() {
-            Some((_, line)) => {
-                if !line.is_empty() {
-                    break;
-                }
+/// Skips ASCII whitespace, which is what the spec considers whitespace. Other
+/// Unicode whitespace, like non-breaking spaces, is not skipped.
+fn skip_whitespace(input: &str) -> &str {
+    input.trim_start_matches(|c: char| c.is_ascii_whitespace())
+}
 
-                lines.next();
-            }
-            None => break,
-        }
-    }
+/// Parses a timestamp from the given string. Returns a Duration that represents
+/// the timestamp's offset from the zero, and the remainder of the string after
+/// skipping the timestamp.
+fn parse_timestamp(line: &str) -> Option<(Duration, &str)> {
+    Timestamp::parse(line)
+        .ok()
+        .map(|(timestamp, rest)| (timestamp.as_duration(), rest))
 }
 
-fn expect_str<'a>(input: &'a str, pattern: &str, error: Error) -> Result<&'a str, Error> {
-    input.strip_prefix(pattern).ok_or(error)
+/// Formats a timestamp as `hh:mm:ss.ttt`, using more digits for the hours if
+/// needed.
+pub(crate) fn format_timestamp(time: Duration) -> String {
+    let millis = time.as_millis();
+
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
 }
 
-fn expect_pred<'a, F: FnMut(char) -> bool>(
-    input: &'a str,
-    pattern: F,
-    error: Error,
-) -> Result<&'a str, Error> {
-    input.strip_prefix(pattern).ok_or(error)
+fn skip_blank_lines<'a, I: Iterator<Item = (usize, &'a str)>>(lines: &mut Peekable<I>) {
+    while let Some((_, line)) = lines.peek() {
+        if !line.is_empty() {
+            break;
+        }
+
+        lines.next();
+    }
 }
 
-fn expect_char<'a>(input: &'a str, pattern: &[char], error: Error) -> Result<&'a str, Error> {
+fn expect_str<'a>(input: &'a str, pattern: &str, error: ParseError) -> Result<&'a str, ParseError> {
     input.strip_prefix(pattern).ok_or(error)
 }
@@ -0,0 +1,131 @@
+//! Export of files as readable Markdown transcripts.
+
+use std::{fmt::Write, time::Duration};
+
+use crate::{Cue, File};
+
+#[derive(Clone, Debug)]
+pub struct MarkdownOptions {
+    /// Title of the document. Defaults to the description of the file, and no
+    /// title is written if neither is present.
+    pub title: Option<String>,
+    /// Write each paragraph's timestamp as a heading rather than at the start
+    /// of the paragraph.
+    pub timestamp_headings: bool,
+    /// Link each timestamp to this URL with a `t` query parameter holding the
+    /// offset in seconds, as YouTube deep links do.
+    pub link_base: Option<String>,
+    /// Cues are joined into one paragraph as long as the speaker stays the
+    /// same and the gap between them is at most this long.
+    pub paragraph_gap: Duration,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        MarkdownOptions {
+            title: None,
+            timestamp_headings: false,
+            link_base: None,
+            paragraph_gap: Duration::from_secs(2),
+        }
+    }
+}
+
+impl File {
+    /// Formats the cues of this file as a Markdown transcript, with speakers
+    /// in bold.
+    pub fn to_markdown(&self, options: &MarkdownOptions) -> String {
+        let mut out = String::new();
+
+        if let Some(title) = options.title.as_ref().or(self.description.as_ref()) {
+            let _ = writeln!(out, "# {}\n", escape(title));
+        }
+
+        let mut paragraphs: Vec<(Duration, Option<String>, Vec<&Cue>)> = vec![];
+        let mut last_end = None;
+
+        for cue in self.cues() {
+            let speaker = cue.speaker();
+
+            let continues = match (paragraphs.last(), last_end) {
+                (Some((_, last_speaker, _)), Some(end)) => {
+                    *last_speaker == speaker
+                        && cue.start.saturating_sub(end) <= options.paragraph_gap
+                }
+                _ => false,
+            };
+
+            if continues {
+                paragraphs.last_mut().unwrap().2.push(cue);
+            } else {
+                paragraphs.push((cue.start, speaker, vec![cue]));
+            }
+
+            last_end = Some(cue.end);
+        }
+
+        for (start, speaker, cues) in paragraphs {
+            let timestamp = match &options.link_base {
+                Some(base) => {
+                    let separator = if base.contains('?') { '&' } else { '?' };
+                    format!(
+                        "[{}]({base}{separator}t={})",
+                        short_timestamp(start),
+                        start.as_secs()
+                    )
+                }
+                None => short_timestamp(start),
+            };
+
+            if options.timestamp_headings {
+                let _ = writeln!(out, "## {timestamp}\n");
+            } else {
+                let _ = write!(out, "{timestamp} ");
+            }
+
+            if let Some(speaker) = speaker {
+                let _ = write!(out, "**{}:** ", escape(&speaker));
+            }
+
+            let text: Vec<_> = cues
+                .iter()
+                .map(|cue| {
+                    cue.plain_text()
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .filter(|text| !text.is_empty())
+                .collect();
+
+            let _ = writeln!(out, "{}\n", escape(&text.join(" ")));
+        }
+
+        out
+    }
+}
+
+/// Formats a timestamp as `m:ss`, or `h:mm:ss` if it is over an hour.
+fn short_timestamp(time: Duration) -> String {
+    let secs = time.as_secs();
+
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        if matches!(char, '\\' | '*' | '_' | '[' | ']' | '`' | '#' | '<' | '>') {
+            out.push('\\');
+        }
+
+        out.push(char);
+    }
+
+    out
+}
@@ -0,0 +1,160 @@
+//! Masking words in cue text, like profanity for a kids' variant of a track.
+//! Only text is changed; markup and timings are kept.
+
+use std::collections::HashSet;
+
+use crate::{
+    text::{Node, Span, SpanKind},
+    Cue, File,
+};
+
+/// A set of words to match, ignoring case.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WordList(HashSet<String>);
+
+impl WordList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        self.0.insert(word.to_lowercase());
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.0.contains(&word.to_lowercase())
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for WordList {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut list = WordList::new();
+
+        for word in iter {
+            list.insert(word.as_ref());
+        }
+
+        list
+    }
+}
+
+/// What a masked word is replaced with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MaskStyle {
+    /// An asterisk for each character: `****`.
+    Asterisks,
+    /// The first character, then an asterisk for each other one: `f***`.
+    FirstLetter,
+    /// `<c.beep>[beep]</c>`, which players can style or replace with a sound
+    /// effect.
+    Beep,
+}
+
+impl Cue {
+    /// Masks the words of this cue's text that are in `words`, returning the
+    /// number masked. Words are runs of letters, digits and apostrophes, so a
+    /// word split by a tag is not matched. Metadata cues are left as they
+    /// are.
+    pub fn mask_words(&mut self, words: &WordList, style: MaskStyle) -> usize {
+        if self.is_metadata() {
+            return 0;
+        }
+
+        let mut masked = 0;
+        let nodes = mask_nodes(self.nodes(), words, style, &mut masked);
+
+        if masked > 0 {
+            self.set_nodes(&nodes);
+        }
+
+        masked
+    }
+}
+
+impl File {
+    /// Masks words in every cue, as in [`Cue::mask_words`]. Returns the number
+    /// of words masked.
+    pub fn mask_words(&mut self, words: &WordList, style: MaskStyle) -> usize {
+        self.cues_mut()
+            .map(|cue| cue.mask_words(words, style))
+            .sum()
+    }
+}
+
+fn mask_nodes(
+    nodes: Vec<Node>,
+    words: &WordList,
+    style: MaskStyle,
+    masked: &mut usize,
+) -> Vec<Node> {
+    let mut out = vec![];
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => mask_text(&text, words, style, masked, &mut out),
+            Node::Span(span) => out.push(Node::Span(Span {
+                children: mask_nodes(span.children, words, style, masked),
+                ..span
+            })),
+            node => out.push(node),
+        }
+    }
+
+    out
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\'' || c == '’'
+}
+
+fn mask_text(
+    text: &str,
+    words: &WordList,
+    style: MaskStyle,
+    masked: &mut usize,
+    out: &mut Vec<Node>,
+) {
+    let mut buffer = String::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let start = rest.find(is_word_char).unwrap_or(rest.len());
+        buffer.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        rest = &rest[end..];
+
+        if word.is_empty() || !words.contains(word) {
+            buffer.push_str(word);
+            continue;
+        }
+
+        *masked += 1;
+
+        match style {
+            MaskStyle::Asterisks => buffer.extend(word.chars().map(|_| '*')),
+            MaskStyle::FirstLetter => {
+                let mut chars = word.chars();
+                buffer.extend(chars.next());
+                buffer.extend(chars.map(|_| '*'));
+            }
+            MaskStyle::Beep => {
+                if !buffer.is_empty() {
+                    out.push(Node::Text(std::mem::take(&mut buffer)));
+                }
+
+                out.push(Node::Span(Span {
+                    kind: SpanKind::Class,
+                    classes: vec!["beep".to_owned()],
+                    children: vec![Node::Text("[beep]".to_owned())],
+                }));
+            }
+        }
+    }
+
+    if !buffer.is_empty() {
+        out.push(Node::Text(buffer));
+    }
+}
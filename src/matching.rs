@@ -0,0 +1,232 @@
+//! Matching the cues of two versions of a file, for diffs and merges.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
+
+use crate::Cue;
+
+/// The most cells of the table used to align cues. Past this, cues are only
+/// paired by their timings and text, which is quicker but can miss the
+/// order of the cues.
+const MAX_ALIGNMENT_CELLS: usize = 1 << 22;
+
+/// Returns, for each cue of `new`, the index of the cue of `old` that it is a
+/// version of, if there is one. No cue of `old` is matched twice.
+///
+/// Cues with an id used once in each version are matched by id. The others,
+/// without an id or with an id used more than once, are aligned in order:
+/// first the cues that did not change at all, then between those the cues
+/// with the same timings, then the cues with the same text, and then the
+/// cues that are left if as many are left on each side. Cues are only
+/// matched with cues that have the same id.
+pub(crate) fn match_cues(old: &[&Cue], new: &[&Cue]) -> Vec<Option<usize>> {
+    let old_counts = id_counts(old);
+    let new_counts = id_counts(new);
+    let is_unique = |id: &str| old_counts.get(id) == Some(&1) && new_counts.get(id) == Some(&1);
+
+    let old_by_id: HashMap<&str, usize> = old
+        .iter()
+        .enumerate()
+        .filter(|(_, cue)| is_unique(&cue.id))
+        .map(|(idx, cue)| (cue.id.as_str(), idx))
+        .collect();
+
+    let mut matches = vec![None; new.len()];
+    let mut new_rest = vec![];
+
+    for (idx, cue) in new.iter().enumerate() {
+        match old_by_id.get(cue.id.as_str()) {
+            Some(&old_idx) => matches[idx] = Some(old_idx),
+            None => new_rest.push(idx),
+        }
+    }
+
+    let old_rest: Vec<_> = (0..old.len())
+        .filter(|&idx| !is_unique(&old[idx].id))
+        .collect();
+
+    align(old, new, &old_rest, &new_rest, &mut matches);
+    matches
+}
+
+/// Counts the cues with each id, leaving out cues without one.
+fn id_counts<'a>(cues: &[&'a Cue]) -> HashMap<&'a str, usize> {
+    let mut counts = HashMap::new();
+
+    for cue in cues.iter().filter(|cue| !cue.id.is_empty()) {
+        *counts.entry(cue.id.as_str()).or_default() += 1;
+    }
+
+    counts
+}
+
+/// Returns whether two cues are the same, as far as a diff can tell.
+fn unchanged(a: &Cue, b: &Cue) -> bool {
+    a.id == b.id && a.start == b.start && a.end == b.end && a.text == b.text
+}
+
+/// Matches the cues at the indices `old_rest` with those at `new_rest`.
+fn align(
+    old: &[&Cue],
+    new: &[&Cue],
+    old_rest: &[usize],
+    new_rest: &[usize],
+    matches: &mut [Option<usize>],
+) {
+    let same = |old_idx: usize, new_idx: usize| unchanged(old[old_idx], new[new_idx]);
+
+    // most edits leave the start and the end of a file as they were
+    let prefix = old_rest
+        .iter()
+        .zip(new_rest)
+        .take_while(|&(&old_idx, &new_idx)| same(old_idx, new_idx))
+        .count();
+    let suffix = old_rest[prefix..]
+        .iter()
+        .rev()
+        .zip(new_rest[prefix..].iter().rev())
+        .take_while(|&(&old_idx, &new_idx)| same(old_idx, new_idx))
+        .count();
+
+    for (&old_idx, &new_idx) in old_rest[..prefix].iter().zip(&new_rest[..prefix]) {
+        matches[new_idx] = Some(old_idx);
+    }
+
+    for (&old_idx, &new_idx) in old_rest[old_rest.len() - suffix..]
+        .iter()
+        .zip(&new_rest[new_rest.len() - suffix..])
+    {
+        matches[new_idx] = Some(old_idx);
+    }
+
+    let old_mid = &old_rest[prefix..old_rest.len() - suffix];
+    let new_mid = &new_rest[prefix..new_rest.len() - suffix];
+
+    let mut anchors = if old_mid.len().saturating_mul(new_mid.len()) <= MAX_ALIGNMENT_CELLS {
+        longest_common(old_mid.len(), new_mid.len(), |i, j| {
+            same(old_mid[i], new_mid[j])
+        })
+    } else {
+        vec![]
+    };
+    anchors.push((old_mid.len(), new_mid.len()));
+
+    let mut gap_start = (0, 0);
+
+    for (i, j) in anchors {
+        pair_gap(
+            old,
+            new,
+            &old_mid[gap_start.0..i],
+            &new_mid[gap_start.1..j],
+            matches,
+        );
+
+        if i < old_mid.len() {
+            matches[new_mid[j]] = Some(old_mid[i]);
+        }
+
+        gap_start = (i + 1, j + 1);
+    }
+}
+
+/// Returns the pairs of indices of a longest common subsequence of two
+/// sequences of lengths `n` and `m`.
+fn longest_common(n: usize, m: usize, same: impl Fn(usize, usize) -> bool) -> Vec<(usize, usize)> {
+    let width = m + 1;
+    let mut lengths = vec![0u32; (n + 1) * width];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i * width + j] = if same(i, j) {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < n && j < m {
+        if same(i, j) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    pairs
+}
+
+/// Matches the cues of one gap between unchanged cues: by timings, then by
+/// text, then in order if as many cues are left on each side.
+fn pair_gap(
+    old: &[&Cue],
+    new: &[&Cue],
+    old_gap: &[usize],
+    new_gap: &[usize],
+    matches: &mut [Option<usize>],
+) {
+    let mut old_left = old_gap.to_vec();
+    let mut new_left = new_gap.to_vec();
+
+    pair_by(old, new, &mut old_left, &mut new_left, matches, |cue| {
+        (&cue.id, cue.start, cue.end)
+    });
+    pair_by(old, new, &mut old_left, &mut new_left, matches, |cue| {
+        (&cue.id, &cue.text)
+    });
+
+    if old_left.len() == new_left.len() {
+        for (old_idx, new_idx) in old_left.into_iter().zip(new_left) {
+            if old[old_idx].id == new[new_idx].id {
+                matches[new_idx] = Some(old_idx);
+            }
+        }
+    }
+}
+
+/// Matches the cues left on each side that have the same key, in order,
+/// removing them from what is left.
+fn pair_by<'a, K: Hash + Eq>(
+    old: &[&'a Cue],
+    new: &[&'a Cue],
+    old_left: &mut Vec<usize>,
+    new_left: &mut Vec<usize>,
+    matches: &mut [Option<usize>],
+    key: impl Fn(&'a Cue) -> K,
+) {
+    let mut by_key: HashMap<K, VecDeque<usize>> = HashMap::new();
+
+    for &old_idx in old_left.iter() {
+        by_key
+            .entry(key(old[old_idx]))
+            .or_default()
+            .push_back(old_idx);
+    }
+
+    let mut paired = HashSet::new();
+
+    new_left.retain(|&new_idx| {
+        let Some(old_idx) = by_key
+            .get_mut(&key(new[new_idx]))
+            .and_then(VecDeque::pop_front)
+        else {
+            return true;
+        };
+
+        matches[new_idx] = Some(old_idx);
+        paired.insert(old_idx);
+        false
+    });
+
+    old_left.retain(|old_idx| !paired.contains(old_idx));
+}
@@ -0,0 +1,229 @@
+//! Three-way merging of concurrent edits to a file.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{matching::match_cues, Block, Cue, File};
+
+/// The part of a cue that both sides of a merge changed differently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConflictKind {
+    /// The start or end time.
+    Timing,
+    Text,
+    Settings,
+    /// One side deleted the cue and the other edited it.
+    EditedAndDeleted,
+}
+
+/// A conflict found by [`merge_edits`]. The merged cue has our side of the
+/// conflicting change, or is the edited cue if one side deleted it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// Index of the conflicting cue among the cues of the merged file.
+    pub cue_index: usize,
+    pub kind: ConflictKind,
+}
+
+/// Merges one field three ways, returning the merged value and whether the
+/// two sides conflict.
+fn merge_field<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> (T, bool) {
+    if ours == base || ours == theirs {
+        (theirs.clone(), false)
+    } else if theirs == base {
+        (ours.clone(), false)
+    } else {
+        (ours.clone(), true)
+    }
+}
+
+fn same_cue(a: &Cue, b: &Cue) -> bool {
+    a.start == b.start && a.end == b.end && a.text == b.text && a.settings == b.settings
+}
+
+/// How a cue is recognized across the three versions of a merge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum MergeKey {
+    /// The index of the cue among the cues of the base.
+    Base(usize),
+    /// The index among our cues of a cue we added.
+    Ours(usize),
+    /// The index among their cues of a cue only they added.
+    Theirs(usize),
+}
+
+/// Returns the keys of our cues and of their cues. Cues both sides added the
+/// same way are one cue.
+fn merge_keys(base: &[&Cue], ours: &[&Cue], theirs: &[&Cue]) -> (Vec<MergeKey>, Vec<MergeKey>) {
+    let our_keys: Vec<_> = match_cues(base, ours)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, base_idx)| base_idx.map_or(MergeKey::Ours(idx), MergeKey::Base))
+        .collect();
+
+    let mut our_additions: HashMap<_, VecDeque<usize>> = HashMap::new();
+
+    for (idx, key) in our_keys.iter().enumerate() {
+        if *key == MergeKey::Ours(idx) {
+            let cue = ours[idx];
+            our_additions
+                .entry((&cue.id, cue.start, cue.end, &cue.text))
+                .or_default()
+                .push_back(idx);
+        }
+    }
+
+    let their_keys = match_cues(base, theirs)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, base_idx)| {
+            let cue = theirs[idx];
+
+            base_idx.map(MergeKey::Base).unwrap_or_else(|| {
+                our_additions
+                    .get_mut(&(&cue.id, cue.start, cue.end, &cue.text))
+                    .and_then(VecDeque::pop_front)
+                    .map_or(MergeKey::Theirs(idx), MergeKey::Ours)
+            })
+        })
+        .collect();
+
+    (our_keys, their_keys)
+}
+
+/// Merges two files edited concurrently from a common `base`, cue by cue.
+///
+/// Cues are matched between the versions by id where each version uses it
+/// once, and otherwise by aligning them on their timings and text, so that
+/// adding a cue without an id does not shift the cues after it. The timings,
+/// text and
+/// settings of a cue are merged independently, so one side can retime a cue
+/// while the other corrects its text. Where both sides changed the same thing
+/// differently, our change is kept and a conflict is reported.
+///
+/// The merged file has the blocks of `ours`, followed by the cues that only
+/// `theirs` added. It is sorted by start time if `ours` is.
+pub fn merge_edits(base: &File, ours: &File, theirs: &File) -> (File, Vec<MergeConflict>) {
+    let base_list: Vec<_> = base.cues().collect();
+    let their_list: Vec<_> = theirs.cues().collect();
+    let (our_keys, their_keys) =
+        merge_keys(&base_list, &ours.cues().collect::<Vec<_>>(), &their_list);
+
+    let base_cues: HashMap<_, _> = base_list
+        .iter()
+        .enumerate()
+        .map(|(idx, &cue)| (MergeKey::Base(idx), cue))
+        .collect();
+    let their_cues: HashMap<_, _> = their_keys.iter().copied().zip(their_list).collect();
+    let our_key_set: HashSet<_> = our_keys.iter().copied().collect();
+
+    let mut merged = File {
+        blocks: vec![],
+        ..ours.clone()
+    };
+    let mut conflicts = vec![];
+    let mut cue_index = 0;
+
+    let mut keys = our_keys.iter();
+
+    for block in &ours.blocks {
+        let Block::Cue(our_cue) = block else {
+            merged.blocks.push(block.clone());
+            continue;
+        };
+
+        let key = keys.next().unwrap();
+        let mut cue = our_cue.clone();
+
+        match (base_cues.get(key), their_cues.get(key)) {
+            (Some(base_cue), Some(their_cue)) => {
+                let (timing, timing_conflict) = merge_field(
+                    &(base_cue.start, base_cue.end),
+                    &(our_cue.start, our_cue.end),
+                    &(their_cue.start, their_cue.end),
+                );
+                let (text, text_conflict) =
+                    merge_field(&base_cue.text, &our_cue.text, &their_cue.text);
+                let (settings, settings_conflict) =
+                    merge_field(&base_cue.settings, &our_cue.settings, &their_cue.settings);
+
+                (cue.start, cue.end) = timing;
+                cue.text = text;
+
+                if settings != cue.settings {
+                    cue.settings = settings;
+                    cue.raw_settings = their_cue.raw_settings.clone();
+                }
+
+                for (conflict, kind) in [
+                    (timing_conflict, ConflictKind::Timing),
+                    (text_conflict, ConflictKind::Text),
+                    (settings_conflict, ConflictKind::Settings),
+                ] {
+                    if conflict {
+                        conflicts.push(MergeConflict { cue_index, kind });
+                    }
+                }
+            }
+            (Some(base_cue), None) => {
+                // they deleted it, which wins unless we edited it
+                if same_cue(base_cue, our_cue) {
+                    continue;
+                }
+
+                conflicts.push(MergeConflict {
+                    cue_index,
+                    kind: ConflictKind::EditedAndDeleted,
+                });
+            }
+            // we added it
+            (None, _) => {}
+        }
+
+        merged.blocks.push(Block::Cue(cue));
+        cue_index += 1;
+    }
+
+    for (key, their_cue) in their_keys.iter().zip(theirs.cues()) {
+        if our_key_set.contains(key) {
+            continue;
+        }
+
+        match base_cues.get(key) {
+            // we deleted it, which wins unless they edited it
+            Some(base_cue) if same_cue(base_cue, their_cue) => continue,
+            Some(_) => conflicts.push(MergeConflict {
+                cue_index,
+                kind: ConflictKind::EditedAndDeleted,
+            }),
+            None => {}
+        }
+
+        merged.blocks.push(Block::Cue(their_cue.clone()));
+        cue_index += 1;
+    }
+
+    if ours.is_sorted() && !merged.is_sorted() {
+        // sorting moves cues, so the conflicts have to follow them
+        let mut order: Vec<_> = merged
+            .cues()
+            .enumerate()
+            .map(|(idx, cue)| ((cue.start, cue.source_order), idx))
+            .collect();
+        order.sort_by_key(|&(key, _)| key);
+
+        let mut new_index = vec![0; order.len()];
+
+        for (new, &(_, old)) in order.iter().enumerate() {
+            new_index[old] = new;
+        }
+
+        merged.sort_by_start();
+
+        for conflict in &mut conflicts {
+            conflict.cue_index = new_index[conflict.cue_index];
+        }
+    }
+
+    (merged, conflicts)
+}
@@ -0,0 +1,79 @@
+//! Cues whose payload is metadata rather than text for display.
+//!
+//! The payload of a metadata cue is opaque: tags and character references in
+//! it have no meaning, so it is neither parsed nor escaped, and transforms
+//! that edit cue text leave it alone.
+
+use crate::{Cue, File};
+
+/// How the payload of a cue is interpreted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CuePayload {
+    /// Cue text, with tags and character references.
+    #[default]
+    Text,
+    /// Metadata text, kept exactly as written.
+    Metadata,
+}
+
+/// The kinds of text tracks defined by HTML, which determine how the payloads
+/// of their cues are interpreted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TrackKind {
+    #[default]
+    Subtitles,
+    Captions,
+    Descriptions,
+    Chapters,
+    Metadata,
+}
+
+impl TrackKind {
+    /// Parses the value of a `<track>` element's `kind` attribute, ignoring
+    /// case.
+    pub fn from_attribute(kind: &str) -> Option<TrackKind> {
+        match kind.to_ascii_lowercase().as_str() {
+            "subtitles" => Some(TrackKind::Subtitles),
+            "captions" => Some(TrackKind::Captions),
+            "descriptions" => Some(TrackKind::Descriptions),
+            "chapters" => Some(TrackKind::Chapters),
+            "metadata" => Some(TrackKind::Metadata),
+            _ => None,
+        }
+    }
+
+    /// Returns how the payloads of cues in a track of this kind are
+    /// interpreted.
+    pub fn payload(self) -> CuePayload {
+        match self {
+            TrackKind::Metadata => CuePayload::Metadata,
+            _ => CuePayload::Text,
+        }
+    }
+}
+
+impl Cue {
+    /// Returns whether the payload of this cue is metadata text.
+    pub fn is_metadata(&self) -> bool {
+        self.payload == CuePayload::Metadata
+    }
+}
+
+#[cfg(feature = "json")]
+impl Cue {
+    /// Parses the payload of this cue as JSON, as used by many metadata
+    /// tracks for interactive video events.
+    pub fn json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::from_str(&self.text)
+    }
+}
+
+impl File {
+    /// Sets how the payloads of all cues in this file are interpreted, such as
+    /// after learning the kind of the track the file belongs to.
+    pub fn set_payload(&mut self, payload: CuePayload) {
+        for cue in self.cues_mut() {
+            cue.payload = payload;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Kotlin and Swift bindings through UniFFI, exposing parsing, serialization
+//! and timeline queries to Android and iOS players. Generate the bindings
+//! with `uniffi-bindgen` from a build with the `uniffi` feature. Times are in
+//! milliseconds.
+//!
+//! A panic in the crate is returned as [`MobileError::Internal`] instead of
+//! unwinding into the host.
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{error::catch_panic, parse_file, ConvertError, Cue, File, ParseError};
+
+/// An error from the mobile bindings, carrying only its message across the
+/// boundary.
+#[derive(thiserror::Error, Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+#[non_exhaustive]
+pub enum MobileError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Convert(#[from] ConvertError),
+    /// A bug in the crate.
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+/// Runs `run`, returning a panic as [`MobileError::Internal`].
+fn guard<T>(run: impl FnOnce() -> T) -> Result<T, MobileError> {
+    catch_panic(run).map_err(MobileError::Internal)
+}
+
+/// A copy of a cue.
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Record)]
+pub struct MobileCue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub id: String,
+    pub text: String,
+    pub plain_text: String,
+}
+
+impl From<&Cue> for MobileCue {
+    fn from(cue: &Cue) -> Self {
+        MobileCue {
+            start_ms: cue.start.as_millis() as u64,
+            end_ms: cue.end.as_millis() as u64,
+            id: cue.id.clone(),
+            text: cue.text.clone(),
+            plain_text: cue.plain_text(),
+        }
+    }
+}
+
+/// A parsed WebVTT file. Files are immutable once parsed, so they can be
+/// shared freely between threads.
+#[derive(Debug, uniffi::Object)]
+pub struct MobileFile(File);
+
+#[uniffi::export]
+impl MobileFile {
+    #[uniffi::constructor]
+    pub fn parse(input: String) -> Result<Arc<Self>, MobileError> {
+        Ok(Arc::new(MobileFile(guard(|| parse_file(&input))??)))
+    }
+
+    #[uniffi::constructor]
+    pub fn from_csv(input: String) -> Result<Arc<Self>, MobileError> {
+        Ok(Arc::new(MobileFile(guard(|| File::from_csv(&input))??)))
+    }
+
+    pub fn description(&self) -> Option<String> {
+        self.0.description.clone()
+    }
+
+    pub fn cue_count(&self) -> u64 {
+        self.0.cues().count() as u64
+    }
+
+    pub fn cues(&self) -> Result<Vec<MobileCue>, MobileError> {
+        guard(|| self.0.cues().map(MobileCue::from).collect())
+    }
+
+    /// The cues that are showing at `time_ms`, in the order they should be
+    /// rendered.
+    pub fn cues_at(&self, time_ms: u64) -> Result<Vec<MobileCue>, MobileError> {
+        guard(|| {
+            self.0
+                .cues_at(Duration::from_millis(time_ms))
+                .into_iter()
+                .map(MobileCue::from)
+                .collect()
+        })
+    }
+
+    pub fn serialize(&self) -> Result<String, MobileError> {
+        guard(|| self.0.to_string())
+    }
+
+    pub fn to_csv(&self) -> Result<String, MobileError> {
+        guard(|| self.0.to_csv())
+    }
+}
@@ -0,0 +1,135 @@
+//! Node.js bindings, exposing parsing, serialization and conversion to
+//! JavaScript. Build the addon with `napi build`, enabling the `napi`
+//! feature. Times are in milliseconds, as elsewhere in JavaScript.
+//!
+//! A panic in the crate is thrown as an `Error` instead of aborting Node.
+
+use std::time::Duration;
+
+use napi::{Error, Result};
+use napi_derive::napi;
+
+use crate::{error::catch_panic, parse_file, Block, Cue, File, Shift};
+
+fn millis(value: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(value / 1000.0).map_err(|err| Error::from_reason(err.to_string()))
+}
+
+/// Runs `run`, turning a panic into an error.
+fn guard<T>(run: impl FnOnce() -> T) -> Result<T> {
+    catch_panic(run).map_err(|message| Error::from_reason(format!("internal error: {message}")))
+}
+
+/// A cue, as a plain JavaScript object.
+#[napi(object, js_name = "Cue")]
+pub struct JsCue {
+    pub start: f64,
+    pub end: f64,
+    pub id: String,
+    pub text: String,
+}
+
+impl From<&Cue> for JsCue {
+    fn from(cue: &Cue) -> Self {
+        JsCue {
+            start: cue.start.as_secs_f64() * 1000.0,
+            end: cue.end.as_secs_f64() * 1000.0,
+            id: cue.id.clone(),
+            text: cue.text.clone(),
+        }
+    }
+}
+
+impl JsCue {
+    fn to_cue(&self) -> Result<Cue> {
+        Ok(Cue {
+            start: millis(self.start)?,
+            end: millis(self.end)?,
+            id: self.id.clone(),
+            text: self.text.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+/// A WebVTT file.
+#[napi(js_name = "File")]
+pub struct JsFile(File);
+
+#[napi]
+impl JsFile {
+    #[napi(factory)]
+    pub fn parse(input: String) -> Result<Self> {
+        guard(|| parse_file(&input))?
+            .map(JsFile)
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    #[napi(factory)]
+    pub fn from_csv(input: String) -> Result<Self> {
+        guard(|| File::from_csv(&input))?
+            .map(JsFile)
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    #[napi(factory)]
+    pub fn from_tsv(input: String) -> Result<Self> {
+        guard(|| File::from_tsv(&input))?
+            .map(JsFile)
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    #[napi(getter)]
+    pub fn description(&self) -> Option<String> {
+        self.0.description.clone()
+    }
+
+    /// Copies of the cues of the file. Assign an array of cues to replace
+    /// them, which also drops NOTE blocks that are not attached to a cue.
+    #[napi(getter)]
+    pub fn cues(&self) -> Vec<JsCue> {
+        self.0.cues().map(JsCue::from).collect()
+    }
+
+    #[napi(setter)]
+    pub fn set_cues(&mut self, cues: Vec<JsCue>) -> Result<()> {
+        self.0.blocks = cues
+            .iter()
+            .map(|cue| cue.to_cue().map(Block::Cue))
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// Moves every cue by a number of milliseconds, which is negative to move
+    /// them earlier.
+    #[napi]
+    pub fn shift(&mut self, millis: f64) -> Result<()> {
+        let shift = if millis < 0.0 {
+            Shift::Earlier(self::millis(-millis)?)
+        } else {
+            Shift::Later(self::millis(millis)?)
+        };
+
+        guard(|| self.0.shift(shift))?.map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    #[napi]
+    pub fn sort_by_start(&mut self) -> Result<()> {
+        guard(|| self.0.sort_by_start())
+    }
+
+    #[napi]
+    pub fn to_csv(&self) -> Result<String> {
+        guard(|| self.0.to_csv())
+    }
+
+    #[napi]
+    pub fn to_tsv(&self) -> Result<String> {
+        guard(|| self.0.to_tsv())
+    }
+
+    #[napi(js_name = "toString")]
+    pub fn serialize(&self) -> Result<String> {
+        guard(|| self.0.to_string())
+    }
+}
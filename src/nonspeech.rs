@@ -0,0 +1,408 @@
+//! Recognition of non-speech information in captions, like `[door slams]`,
+//! `(laughs)` and `♪` song lyrics, for building dialogue-only variants and
+//! subtitles from SDH (subtitles for the deaf and hard of hearing).
+
+use std::ops::Range;
+
+use crate::{
+    text::{self, Node, Span, SpanKind},
+    Block, Cue, File,
+};
+
+const MUSIC_NOTES: &[char] = &['♪', '♫', '♬'];
+
+/// The brackets around sound descriptions, like `[door slams]`.
+const BRACKETS: &[(char, char)] = &[('[', ']'), ('(', ')')];
+
+/// What a cue conveys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CueClass {
+    /// Dialogue, possibly alongside sound descriptions.
+    Speech,
+    /// Only descriptions of sounds, like `[door slams]` or `(laughs)`.
+    SoundEffect,
+    /// Only music: lyrics marked with `♪`, or descriptions of music like
+    /// `[upbeat music]`.
+    Music,
+}
+
+/// What [`File::strip_sdh`] removes.
+#[derive(Clone, Debug)]
+pub struct SdhOptions {
+    /// Remove speaker labels in capitals at the start of lines, like
+    /// `BOB: Hi` or `- MAN #2: Hey`. Voice spans are kept, as they are not
+    /// displayed.
+    pub speaker_labels: bool,
+    /// Remove sound descriptions enclosed by these pairs of brackets.
+    pub brackets: Vec<(char, char)>,
+    /// Remove music notes, keeping the lyrics between them.
+    pub music_notes: bool,
+}
+
+impl Default for SdhOptions {
+    fn default() -> Self {
+        SdhOptions {
+            speaker_labels: true,
+            brackets: BRACKETS.to_vec(),
+            music_notes: true,
+        }
+    }
+}
+
+impl Cue {
+    /// Classifies this cue by its lines of text. A cue is speech if any line
+    /// is more than a bracketed description or lyrics; otherwise it is music
+    /// if any line is music, and a sound effect if not.
+    pub fn classify(&self) -> CueClass {
+        let mut class = CueClass::SoundEffect;
+
+        for line in self.plain_text().lines() {
+            match classify_line(line) {
+                Some(CueClass::Speech) => return CueClass::Speech,
+                Some(CueClass::Music) => class = CueClass::Music,
+                _ => {}
+            }
+        }
+
+        class
+    }
+}
+
+impl File {
+    /// Removes bracketed descriptions and lines of lyrics from every cue. Lines
+    /// left without letters or digits, like a lone dialogue dash, are removed,
+    /// as are cues left without lines. Returns the number of cues removed.
+    pub fn strip_non_speech(&mut self) -> usize {
+        let mut removed = 0;
+
+        for block in std::mem::take(&mut self.blocks) {
+            match block {
+                Block::Cue(mut cue) if !cue.is_metadata() => {
+                    let lines: Vec<_> = text::split_lines(cue.nodes())
+                        .into_iter()
+                        .filter(|line| !is_music_line(&text::plain(line)))
+                        .map(|line| {
+                            let mut line = strip_brackets(line, BRACKETS);
+                            trim_line(&mut line);
+                            line
+                        })
+                        .filter(|line| text::plain(line).contains(char::is_alphanumeric))
+                        .map(|line| cue.format_nodes(&line))
+                        .collect();
+
+                    if lines.is_empty() {
+                        removed += 1;
+                        continue;
+                    }
+
+                    cue.text = lines.join("\n");
+                    self.blocks.push(Block::Cue(cue));
+                }
+                block => self.blocks.push(block),
+            }
+        }
+
+        removed
+    }
+
+    /// Turns an SDH track into a subtitles track by removing speaker labels,
+    /// sound descriptions and music notes as configured. Lines left without
+    /// letters or digits are removed, as are cues left without lines. Returns
+    /// the number of cues removed.
+    pub fn strip_sdh(&mut self, options: &SdhOptions) -> usize {
+        let mut removed = 0;
+
+        for block in std::mem::take(&mut self.blocks) {
+            match block {
+                Block::Cue(mut cue) if !cue.is_metadata() => {
+                    let lines: Vec<_> = text::split_lines(cue.nodes())
+                        .into_iter()
+                        .map(|mut line| {
+                            if options.speaker_labels {
+                                strip_speaker_label(&mut line);
+                            }
+
+                            if options.music_notes {
+                                remove_chars(&mut line, MUSIC_NOTES);
+                            }
+
+                            let mut line = strip_brackets(line, &options.brackets);
+                            trim_line(&mut line);
+                            line
+                        })
+                        .filter(|line| text::plain(line).contains(char::is_alphanumeric))
+                        .map(|line| cue.format_nodes(&line))
+                        .collect();
+
+                    if lines.is_empty() {
+                        removed += 1;
+                        continue;
+                    }
+
+                    cue.text = lines.join("\n");
+                    self.blocks.push(Block::Cue(cue));
+                }
+                block => self.blocks.push(block),
+            }
+        }
+
+        removed
+    }
+
+    /// Wraps bracketed descriptions and lines of lyrics in every cue in a
+    /// class span, like `<c.sfx>[door slams]</c>`, so that they can be styled
+    /// apart from dialogue.
+    pub fn style_non_speech(&mut self, class: &str) {
+        for cue in self.cues_mut() {
+            if cue.is_metadata() {
+                continue;
+            }
+
+            let lines: Vec<_> = text::split_lines(cue.nodes())
+                .into_iter()
+                .map(|line| {
+                    let nodes = if is_music_line(&text::plain(&line)) {
+                        vec![class_span(class, line)]
+                    } else {
+                        wrap_brackets(line, class)
+                    };
+
+                    cue.format_nodes(&nodes)
+                })
+                .collect();
+
+            cue.text = lines.join("\n");
+        }
+    }
+}
+
+/// Classifies a line of plain text, or returns `None` if it is blank.
+fn classify_line(line: &str) -> Option<CueClass> {
+    // dialogue dashes introduce each speaker's line
+    let line = line.trim().trim_start_matches('-').trim_start();
+
+    if line.is_empty() {
+        return None;
+    }
+
+    if is_music_line(line) {
+        return Some(CueClass::Music);
+    }
+
+    let ranges = bracket_ranges(line, BRACKETS);
+
+    // anything outside the brackets other than punctuation is speech, like
+    // the line in `[laughs] You're kidding!`
+    if ranges.is_empty() || remove_ranges(line, &ranges).contains(char::is_alphanumeric) {
+        return Some(CueClass::Speech);
+    }
+
+    let mentions_music = ranges
+        .iter()
+        .any(|range| line[range.clone()].to_lowercase().contains("music"));
+
+    Some(if mentions_music {
+        CueClass::Music
+    } else {
+        CueClass::SoundEffect
+    })
+}
+
+/// Returns whether a line of plain text is lyrics, starting or ending with a
+/// music note.
+fn is_music_line(line: &str) -> bool {
+    let line = line.trim().trim_start_matches('-').trim_start();
+    line.starts_with(MUSIC_NOTES) || line.ends_with(MUSIC_NOTES)
+}
+
+/// Returns the byte ranges of the groups enclosed by any of the pairs of
+/// brackets in a string, including the brackets. Brackets do not nest.
+fn bracket_ranges(text: &str, brackets: &[(char, char)]) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut open: Option<(usize, char)> = None;
+
+    for (idx, c) in text.char_indices() {
+        match open {
+            None => {
+                if let Some(&(_, close)) = brackets.iter().find(|&&(open, _)| open == c) {
+                    open = Some((idx, close));
+                }
+            }
+            Some((start, close)) if c == close => {
+                ranges.push(start..idx + 1);
+                open = None;
+            }
+            _ => {}
+        }
+    }
+
+    ranges
+}
+
+fn remove_ranges(text: &str, ranges: &[Range<usize>]) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+
+    for range in ranges {
+        out.push_str(&text[last..range.start]);
+        last = range.end;
+    }
+
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Removes one or more sorted byte ranges from a string, collapsing the
+/// whitespace on both sides of each one into one space.
+fn remove_groups(text: &str, ranges: &[Range<usize>]) -> String {
+    let mut out = text[..ranges[0].start].to_owned();
+
+    for (idx, range) in ranges.iter().enumerate() {
+        let next = ranges.get(idx + 1).map_or(text.len(), |next| next.start);
+        let piece = &text[range.end..next];
+
+        if out.ends_with(char::is_whitespace) || piece.starts_with(char::is_whitespace) {
+            out.truncate(out.trim_end().len());
+            out.push(' ');
+            out.push_str(piece.trim_start());
+        } else {
+            out.push_str(piece);
+        }
+    }
+
+    out
+}
+
+/// Removes bracketed groups from the text nodes of a line. The whitespace on
+/// both sides of a group is collapsed into one space, and the spaces between
+/// nodes are kept, so the line may need trimming afterwards.
+fn strip_brackets(nodes: Vec<Node>, brackets: &[(char, char)]) -> Vec<Node> {
+    nodes
+        .into_iter()
+        .filter_map(|node| match node {
+            Node::Text(text) => {
+                let ranges = bracket_ranges(&text, brackets);
+
+                if ranges.is_empty() {
+                    return Some(Node::Text(text));
+                }
+
+                let stripped = remove_groups(&text, &ranges);
+
+                (!stripped.is_empty()).then_some(Node::Text(stripped))
+            }
+            Node::Span(mut span) => {
+                span.children = strip_brackets(span.children, brackets);
+                (!span.children.is_empty()).then_some(Node::Span(span))
+            }
+            node => Some(node),
+        })
+        .collect()
+}
+
+/// Wraps the bracketed groups in the text nodes of a line in class spans.
+fn wrap_brackets(nodes: Vec<Node>, class: &str) -> Vec<Node> {
+    let mut out = vec![];
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                let mut last = 0;
+
+                for range in bracket_ranges(&text, BRACKETS) {
+                    if range.start > last {
+                        out.push(Node::Text(text[last..range.start].to_string()));
+                    }
+
+                    let group = Node::Text(text[range.clone()].to_string());
+                    out.push(class_span(class, vec![group]));
+                    last = range.end;
+                }
+
+                if last < text.len() {
+                    out.push(Node::Text(text[last..].to_string()));
+                }
+            }
+            Node::Span(mut span) => {
+                span.children = wrap_brackets(span.children, class);
+                out.push(Node::Span(span));
+            }
+            node => out.push(node),
+        }
+    }
+
+    out
+}
+
+/// Removes a speaker label in capitals, like `BOB:`, from the start of the
+/// first text in a line, keeping any dialogue dash before it.
+fn strip_speaker_label(nodes: &mut [Node]) {
+    match nodes.first_mut() {
+        Some(Node::Text(text)) => {
+            let dash = text.len() - text.trim_start_matches(['-', ' ']).len();
+            let rest = &text[dash..];
+
+            let Some(colon) = rest.find(':') else {
+                return;
+            };
+
+            let label = &rest[..colon];
+
+            let is_label = label.contains(char::is_uppercase)
+                && label
+                    .chars()
+                    .all(|c| c.is_uppercase() || c.is_ascii_digit() || " #.'-".contains(c));
+
+            if is_label {
+                let after = rest[colon + 1..].trim_start().to_string();
+                text.truncate(dash);
+                text.push_str(&after);
+            }
+        }
+        Some(Node::Span(span)) => strip_speaker_label(&mut span.children),
+        _ => {}
+    }
+}
+
+fn remove_chars(nodes: &mut [Node], chars: &[char]) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => text.retain(|c| !chars.contains(&c)),
+            Node::Span(span) => remove_chars(&mut span.children, chars),
+            Node::Timestamp(_) => {}
+        }
+    }
+}
+
+/// Removes whitespace from the start and end of a line.
+fn trim_line(nodes: &mut [Node]) {
+    if let Some(Node::Text(text)) = first_text(nodes) {
+        *text = text.trim_start().to_string();
+    }
+
+    if let Some(Node::Text(text)) = last_text(nodes) {
+        *text = text.trim_end().to_string();
+    }
+}
+
+fn first_text(nodes: &mut [Node]) -> Option<&mut Node> {
+    match nodes.first_mut()? {
+        Node::Span(span) => first_text(&mut span.children),
+        node => Some(node),
+    }
+}
+
+fn last_text(nodes: &mut [Node]) -> Option<&mut Node> {
+    match nodes.last_mut()? {
+        Node::Span(span) => last_text(&mut span.children),
+        node => Some(node),
+    }
+}
+
+fn class_span(class: &str, children: Vec<Node>) -> Node {
+    Node::Span(Span {
+        kind: SpanKind::Class,
+        classes: vec![class.to_string()],
+        children,
+    })
+}
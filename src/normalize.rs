@@ -0,0 +1,197 @@
+//! Modernizing the typography of legacy captions: sentence casing, curly
+//! quotes, ellipses and single spaces. Only text is changed; markup is kept.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{text::Node, Cue, File};
+
+#[derive(Clone, Debug)]
+pub struct NormalizeOptions {
+    /// Rewrite cues written entirely in capitals in sentence case, capitalizing
+    /// the start of each sentence and the pronoun "I". Other proper nouns
+    /// cannot be told apart and end up in lowercase.
+    pub sentence_case: bool,
+    /// Rewrite quotes and apostrophes in this style.
+    pub quotes: Option<QuoteStyle>,
+    /// Replace runs of three or more periods with an ellipsis character.
+    pub ellipses: bool,
+    /// Replace runs of spaces with a single space.
+    pub collapse_spaces: bool,
+    /// Compose characters into Unicode Normalization Form C, so that text
+    /// that looks the same is encoded the same, like `é` as one code point
+    /// rather than `e` and a combining accent.
+    pub nfc: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            sentence_case: true,
+            quotes: Some(QuoteStyle::Curly),
+            ellipses: true,
+            collapse_spaces: true,
+            nfc: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QuoteStyle {
+    /// `"` and `'`
+    Straight,
+    /// `“`, `”`, `‘` and `’`, chosen by the character before each quote
+    Curly,
+}
+
+/// What is known about the text of a cue before the current character.
+struct State<'a> {
+    options: &'a NormalizeOptions,
+    sentence_case: bool,
+    previous: Option<char>,
+    capitalize_next: bool,
+}
+
+impl Cue {
+    /// Normalizes the typography of this cue's text. Metadata cues are left
+    /// as they are.
+    pub fn normalize(&mut self, options: &NormalizeOptions) {
+        if self.is_metadata() {
+            return;
+        }
+
+        let plain = self.plain_text();
+
+        let mut state = State {
+            options,
+            sentence_case: options.sentence_case
+                && plain.chars().any(char::is_uppercase)
+                && !plain.chars().any(char::is_lowercase),
+            previous: None,
+            capitalize_next: true,
+        };
+
+        let mut nodes = self.nodes();
+        normalize_nodes(&mut nodes, &mut state);
+        self.set_nodes(&nodes);
+    }
+}
+
+impl File {
+    /// Normalizes the typography of every cue, as in [`Cue::normalize`].
+    pub fn normalize(&mut self, options: &NormalizeOptions) {
+        for cue in self.cues_mut() {
+            cue.normalize(options);
+        }
+    }
+}
+
+fn normalize_nodes(nodes: &mut [Node], state: &mut State) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => *text = normalize_text(text, state),
+            Node::Span(span) => normalize_nodes(&mut span.children, state),
+            Node::Timestamp(_) => {}
+        }
+    }
+}
+
+fn normalize_text(text: &str, state: &mut State) -> String {
+    let text = if state.options.nfc {
+        text.nfc().collect()
+    } else {
+        text.to_string()
+    };
+
+    let text = if state.options.ellipses {
+        replace_ellipses(&text)
+    } else {
+        text
+    };
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+
+    for (idx, &c) in chars.iter().enumerate() {
+        if state.options.collapse_spaces && c == ' ' && state.previous == Some(' ') {
+            continue;
+        }
+
+        let opening = state
+            .previous
+            .is_none_or(|previous| previous.is_whitespace() || "([{-—“‘".contains(previous));
+
+        let c = match (state.options.quotes, c) {
+            (Some(QuoteStyle::Curly), '"') if opening => '“',
+            (Some(QuoteStyle::Curly), '"') => '”',
+            (Some(QuoteStyle::Curly), '\'') if opening => '‘',
+            (Some(QuoteStyle::Curly), '\'') => '’',
+            (Some(QuoteStyle::Straight), '“' | '”') => '"',
+            (Some(QuoteStyle::Straight), '‘' | '’') => '\'',
+            _ => c,
+        };
+
+        if state.sentence_case && c.is_alphabetic() {
+            let word_start = !state.previous.is_some_and(char::is_alphabetic);
+            let pronoun = c == 'I' && word_start && !is_letter_or_contraction(&chars[idx + 1..]);
+
+            if state.capitalize_next || pronoun {
+                out.extend(c.to_uppercase());
+            } else {
+                out.extend(c.to_lowercase());
+            }
+
+            state.capitalize_next = false;
+        } else {
+            out.push(c);
+
+            if matches!(c, '.' | '!' | '?' | '…') {
+                state.capitalize_next = true;
+            }
+        }
+
+        state.previous = Some(c);
+    }
+
+    out
+}
+
+/// Returns whether the text continues a word: with a letter, or with an
+/// apostrophe other than in a contraction of "I" like "I'm" or "I'll".
+fn is_letter_or_contraction(rest: &[char]) -> bool {
+    match rest {
+        [c, ..] if c.is_alphabetic() => true,
+        ['\'' | '’', suffix @ ..] => {
+            let suffix: String = suffix.iter().take_while(|c| c.is_alphabetic()).collect();
+            !matches!(suffix.to_lowercase().as_str(), "m" | "ll" | "ve" | "d")
+        }
+        _ => false,
+    }
+}
+
+/// Replaces runs of three or more periods with `…`.
+fn replace_ellipses(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut periods = 0;
+
+    let flush = |out: &mut String, periods: usize| {
+        if periods >= 3 {
+            out.push('…');
+        } else {
+            out.extend(std::iter::repeat_n('.', periods));
+        }
+    };
+
+    for c in text.chars() {
+        if c == '.' {
+            periods += 1;
+            continue;
+        }
+
+        flush(&mut out, periods);
+        periods = 0;
+        out.push(c);
+    }
+
+    flush(&mut out, periods);
+    out
+}
@@ -0,0 +1,107 @@
+//! Grouping of cues into pages of fixed time windows, for transcript views that
+//! load a time range at a time.
+
+use std::time::Duration;
+
+use crate::File;
+
+/// The cues of one time window of a file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Page {
+    /// Index of the window of the page, counting from the window at time
+    /// zero.
+    pub index: usize,
+    pub start: Duration,
+    pub end: Duration,
+    /// Indices of the cues that start in the window, among the cues of the
+    /// file, in order of start time.
+    pub cues: Vec<usize>,
+    /// Indices of the cues that started on an earlier page and are still
+    /// showing at the start of this one.
+    pub continued: Vec<usize>,
+    /// Whether any cue continues onto the next page.
+    pub continues: bool,
+    /// Whether there are pages after this one.
+    pub has_next: bool,
+}
+
+impl File {
+    /// Splits the file into pages of consecutive `window`-long time ranges
+    /// starting at zero. Only the windows that some cue starts in get a page,
+    /// so the page of a time is the last one that starts at or before it.
+    /// Each cue is listed on the page it starts on, and on later pages it is
+    /// still showing at the start of, so a view of any page can show
+    /// everything active in its range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn paginate(&self, window: Duration) -> Vec<Page> {
+        assert!(!window.is_zero(), "pages need a window longer than zero");
+
+        let mut cues: Vec<_> = self
+            .cues()
+            .enumerate()
+            .map(|(idx, cue)| (idx, cue.start, cue.end.max(cue.start)))
+            .collect();
+        cues.sort_by_key(|&(idx, start, _)| (start, idx));
+
+        let window_nanos = window.as_nanos();
+        let mut pages: Vec<Page> = vec![];
+        // cues of earlier pages that may still be showing, with their ends
+        let mut showing: Vec<(usize, Duration)> = vec![];
+
+        for &(idx, start, end) in &cues {
+            let page_start = start - nanos_to_duration(start.as_nanos() % window_nanos);
+
+            if pages.last().is_none_or(|page| page.start != page_start) {
+                if let Some(page) = pages.last_mut() {
+                    page.has_next = true;
+                }
+
+                showing.retain(|&(_, end)| end > page_start);
+
+                pages.push(Page {
+                    index: usize::try_from(start.as_nanos() / window_nanos).unwrap_or(usize::MAX),
+                    start: page_start,
+                    end: page_start.saturating_add(window),
+                    cues: vec![],
+                    continued: showing.iter().map(|&(idx, _)| idx).collect(),
+                    continues: false,
+                    has_next: false,
+                });
+            }
+
+            if let Some(page) = pages.last_mut() {
+                page.cues.push(idx);
+            }
+
+            showing.push((idx, end));
+        }
+
+        let mut ends = vec![Duration::ZERO; cues.len()];
+
+        for (idx, _, end) in cues {
+            ends[idx] = end;
+        }
+
+        for page in &mut pages {
+            page.continues = page
+                .cues
+                .iter()
+                .chain(&page.continued)
+                .any(|&idx| ends[idx] > page.end);
+        }
+
+        pages
+    }
+}
+
+/// Converts a number of nanoseconds shorter than some duration back into a
+/// duration.
+fn nanos_to_duration(nanos: u128) -> Duration {
+    let secs = u64::try_from(nanos / 1_000_000_000).unwrap_or(u64::MAX);
+    let subsec = u32::try_from(nanos % 1_000_000_000).unwrap_or(0);
+
+    Duration::new(secs, subsec)
+}
@@ -0,0 +1,273 @@
+//! Sparse edits to cues addressed by id, which are much smaller to send than
+//! a whole file.
+//!
+//! A patch is written as a header and blocks separated by blank lines, each
+//! starting with an operation:
+//!
+//! ```text
+//! WEBVTT PATCH
+//!
+//! RETIME intro
+//! 00:00:01.000 --> 00:00:02.500
+//!
+//! RETEXT intro
+//! Hello, <i>world</i>
+//!
+//! DELETE outro
+//!
+//! INSERT AFTER intro
+//! middle
+//! 00:00:03.000 --> 00:00:04.000 line:0
+//! A new cue
+//! ```
+//!
+//! `INSERT FIRST` inserts a cue before all the others.
+
+use std::{fmt, time::Duration};
+
+use thiserror::Error;
+
+use crate::{format_timestamp, parse_cue_timings_settings, parse_file, Block, Cue, File};
+
+const HEADER: &str = "WEBVTT PATCH";
+
+/// A change to the cue with a given id.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum PatchOp {
+    Retime {
+        id: String,
+        start: Duration,
+        end: Duration,
+    },
+    /// Replaces the text of the cue, which is kept as it is, markup and all.
+    Retext {
+        id: String,
+        text: String,
+    },
+    Delete {
+        id: String,
+    },
+    /// Inserts a cue after the cue with the id `after`, or before all cues if
+    /// it is `None`.
+    InsertAfter {
+        after: Option<String>,
+        cue: Cue,
+    },
+}
+
+/// A list of changes to apply to a file in order.
+#[derive(Clone, Debug, Default)]
+pub struct CuePatch {
+    pub ops: Vec<PatchOp>,
+}
+
+/// An error from reading or applying a [`CuePatch`].
+#[derive(Error, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PatchError {
+    #[error("bad patch at line {line}: {reason}")]
+    BadPatch { line: usize, reason: &'static str },
+
+    #[error("no cue with id {id:?}")]
+    UnknownId { id: String },
+}
+
+impl CuePatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, op: PatchOp) {
+        self.ops.push(op);
+    }
+
+    /// Parses a patch written as in the [`Display`](fmt::Display) output.
+    /// Line numbers in errors count from 1.
+    pub fn parse(input: &str) -> Result<CuePatch, PatchError> {
+        let lines: Vec<_> = input
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect();
+
+        if lines.first().map(|line| line.trim_end()) != Some(HEADER) {
+            return Err(PatchError::BadPatch {
+                line: 1,
+                reason: "missing WEBVTT PATCH header",
+            });
+        }
+
+        let mut patch = CuePatch::new();
+        let mut idx = 1;
+
+        while idx < lines.len() {
+            if lines[idx].trim().is_empty() {
+                idx += 1;
+                continue;
+            }
+
+            let start = idx;
+
+            while idx < lines.len() && !lines[idx].trim().is_empty() {
+                idx += 1;
+            }
+
+            patch.ops.push(parse_op(&lines[start..idx], start + 1)?);
+        }
+
+        Ok(patch)
+    }
+}
+
+fn parse_op(lines: &[&str], line: usize) -> Result<PatchOp, PatchError> {
+    let bad = |offset: usize, reason| PatchError::BadPatch {
+        line: line + offset,
+        reason,
+    };
+
+    let id = |op: &str| {
+        lines[0]
+            .strip_prefix(op)
+            .and_then(|rest| rest.strip_prefix(' '))
+            .map(|id| id.trim().to_owned())
+            .filter(|id| !id.is_empty())
+    };
+
+    if let Some(id) = id("RETIME") {
+        let timing = lines.get(1).ok_or(bad(1, "missing timing"))?;
+        let (start, end, ..) = parse_cue_timings_settings(timing).ok_or(bad(1, "bad timing"))?;
+
+        if lines.len() > 2 {
+            return Err(bad(2, "unexpected text after timing"));
+        }
+
+        Ok(PatchOp::Retime { id, start, end })
+    } else if let Some(id) = id("RETEXT") {
+        Ok(PatchOp::Retext {
+            id,
+            text: lines[1..].join("\n"),
+        })
+    } else if let Some(id) = id("DELETE") {
+        if lines.len() > 1 {
+            return Err(bad(1, "unexpected text after DELETE"));
+        }
+
+        Ok(PatchOp::Delete { id })
+    } else if let Some(after) = id("INSERT AFTER")
+        .map(Some)
+        .or_else(|| (lines[0].trim_end() == "INSERT FIRST").then_some(None))
+    {
+        let block = format!("WEBVTT\n\n{}\n", lines[1..].join("\n"));
+        let mut cues = parse_file(&block)
+            .ok()
+            .map(|file| file.cues().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        if cues.len() != 1 {
+            return Err(bad(1, "expected a cue"));
+        }
+
+        Ok(PatchOp::InsertAfter {
+            after,
+            cue: cues.remove(0),
+        })
+    } else {
+        Err(bad(0, "unknown operation"))
+    }
+}
+
+impl fmt::Display for CuePatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+
+        for op in &self.ops {
+            f.write_str("\n")?;
+
+            match op {
+                PatchOp::Retime { id, start, end } => {
+                    writeln!(f, "RETIME {id}")?;
+                    writeln!(
+                        f,
+                        "{} --> {}",
+                        format_timestamp(*start),
+                        format_timestamp(*end)
+                    )?;
+                }
+                PatchOp::Retext { id, text } => {
+                    writeln!(f, "RETEXT {id}")?;
+
+                    if !text.is_empty() {
+                        writeln!(f, "{text}")?;
+                    }
+                }
+                PatchOp::Delete { id } => writeln!(f, "DELETE {id}")?,
+                PatchOp::InsertAfter { after, cue } => {
+                    match after {
+                        Some(id) => writeln!(f, "INSERT AFTER {id}")?,
+                        None => f.write_str("INSERT FIRST\n")?,
+                    }
+
+                    write!(f, "{cue}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl File {
+    /// Applies the operations of a patch in order. Cues are found by id, so
+    /// a patch can insert a cue and then change it. If an operation refers to
+    /// an id no cue has, the file is left unchanged.
+    pub fn apply_patch(&mut self, patch: &CuePatch) -> Result<(), PatchError> {
+        let mut patched = self.clone();
+
+        for op in &patch.ops {
+            patched.apply_patch_op(op)?;
+        }
+
+        *self = patched;
+        Ok(())
+    }
+
+    fn apply_patch_op(&mut self, op: &PatchOp) -> Result<(), PatchError> {
+        let find = |blocks: &[Block], id: &str| {
+            blocks
+                .iter()
+                .position(|block| block.as_cue().is_some_and(|cue| cue.id == id))
+                .ok_or_else(|| PatchError::UnknownId { id: id.to_owned() })
+        };
+
+        match op {
+            PatchOp::Retime { id, start, end } => {
+                let idx = find(&self.blocks, id)?;
+                let cue = self.blocks[idx].as_cue_mut().unwrap();
+                cue.start = *start;
+                cue.end = *end;
+            }
+            PatchOp::Retext { id, text } => {
+                let idx = find(&self.blocks, id)?;
+                self.blocks[idx].as_cue_mut().unwrap().text = text.clone();
+            }
+            PatchOp::Delete { id } => {
+                let idx = find(&self.blocks, id)?;
+                self.blocks.remove(idx);
+            }
+            PatchOp::InsertAfter { after, cue } => {
+                let idx = match after {
+                    Some(id) => find(&self.blocks, id)? + 1,
+                    None => self
+                        .blocks
+                        .iter()
+                        .position(|block| block.as_cue().is_some())
+                        .unwrap_or(self.blocks.len()),
+                };
+
+                self.blocks.insert(idx, Block::Cue(cue.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+//! Rendering of errors, diagnostics and findings with a snippet of the source
+//! they apply to, for command-line tools and CI logs.
+
+use std::fmt::Write;
+
+use crate::{Diagnostic, DiagnosticKind, File, Finding, FindingKind, ParseError};
+
+/// The part of the source a message points at, as a byte range.
+type Label = Option<(usize, usize)>;
+
+impl ParseError {
+    /// Renders the error with the line of `source` it applies to, where `name`
+    /// is the name of the file shown to the user.
+    pub fn render(&self, source: &str, name: &str) -> String {
+        let first_line = source.find('\n').unwrap_or(source.len());
+
+        let (label, note) = match self {
+            ParseError::NoMagic => (
+                Some((0, first_line)),
+                Some("a WebVTT file starts with a line reading WEBVTT"),
+            ),
+            ParseError::BadHeader { .. } => {
+                let start = bom_len(source) + "WEBVTT".len();
+                let length = source
+                    .get(start..)
+                    .and_then(|rest| rest.chars().next())
+                    .map_or(0, char::len_utf8);
+
+                (
+                    Some((start, length)),
+                    Some("WEBVTT is followed by a space or tab before any description"),
+                )
+            }
+            ParseError::RepeatedHeader { offset } => (
+                Some((*offset, "WEBVTT".len())),
+                Some("concatenated files can be merged with RepeatedHeader::Merge"),
+            ),
+            _ => (None, None),
+        };
+
+        render("error", &self.to_string(), source, name, label, note)
+    }
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic with the line of `source` it applies to, where
+    /// `name` is the name of the file shown to the user.
+    pub fn render(&self, source: &str, name: &str) -> String {
+        let timings = timing_line(source, self.offset);
+
+        let (label, note) = match &self.kind {
+            DiagnosticKind::UnsortedCue { .. } => (
+                timings.map(|(start, line)| start_timestamp(start, line)),
+                Some("most players expect cues in order of start time"),
+            ),
+            DiagnosticKind::EndNotAfterStart { .. } => (
+                timings.map(|(start, line)| end_timestamp(start, line)),
+                Some("timings are written as [hh:]mm:ss.ttt --> [hh:]mm:ss.ttt, with the end after the start"),
+            ),
+            DiagnosticKind::ArrowInId => (
+                arrow_after(source, self.offset),
+                Some("cue identifiers cannot contain \"-->\""),
+            ),
+            DiagnosticKind::ArrowInText => (
+                timings.and_then(|(start, line)| arrow_after(source, start + line.len())),
+                Some("cue text cannot contain \"-->\"; write it as \"--&gt;\""),
+            ),
+            DiagnosticKind::RepeatedHeader => (Some((self.offset, "WEBVTT".len())), None),
+        };
+
+        render("warning", &self.kind.to_string(), source, name, label, note)
+    }
+}
+
+impl Finding {
+    /// Renders the finding with the line of `source` it applies to, where
+    /// `name` is the name of the file shown to the user and `file` is the file
+    /// parsed from `source` that the finding was made on.
+    pub fn render(&self, file: &File, source: &str, name: &str) -> String {
+        let timings = file
+            .cues()
+            .nth(self.cue_index)
+            .and_then(|cue| timing_line(source, cue.offset));
+
+        let label = match (&self.kind, timings) {
+            (FindingKind::LineTooLong { line, .. }, Some((start, timing))) => {
+                let mut offset = start + timing.len() + 1;
+
+                for _ in 0..*line {
+                    offset += source[offset..].find('\n').map_or(0, |idx| idx + 1);
+                }
+
+                let length = source[offset..].find('\n').unwrap_or(source.len() - offset);
+                Some((offset, length))
+            }
+            (_, Some((start, timing))) => Some((start, timing.trim_end().len())),
+            (_, None) => None,
+        };
+
+        render("warning", &self.kind.to_string(), source, name, label, None)
+    }
+}
+
+fn render(
+    level: &str,
+    message: &str,
+    source: &str,
+    name: &str,
+    label: Label,
+    note: Option<&str>,
+) -> String {
+    let mut out = format!("{level}: {message}\n");
+
+    if let Some((offset, length)) = label {
+        let offset = char_boundary(source, offset);
+        let end = char_boundary(source, offset.saturating_add(length));
+        let line_start = source[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |idx| offset + idx);
+
+        let line_number = source[..offset].matches('\n').count() + 1;
+        let column = source[line_start..offset].chars().count();
+        let width = source[offset..end.min(line_end)].chars().count().max(1);
+
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        let gutter = " ".repeat(line_number.to_string().len());
+
+        let _ = writeln!(out, "{gutter}--> {name}:{line_number}:{}", column + 1);
+        let _ = writeln!(out, "{gutter} |");
+        let _ = writeln!(out, "{line_number} | {line}");
+        let _ = writeln!(
+            out,
+            "{gutter} | {}{}",
+            " ".repeat(column),
+            "^".repeat(width)
+        );
+
+        if let Some(note) = note {
+            let _ = writeln!(out, "{gutter} = note: {note}");
+        }
+    } else if let Some(note) = note {
+        let _ = writeln!(out, "  = note: {note}");
+    }
+
+    out
+}
+
+fn bom_len(source: &str) -> usize {
+    if source.starts_with('\u{feff}') {
+        '\u{feff}'.len_utf8()
+    } else {
+        0
+    }
+}
+
+/// Finds the timing line of the cue whose block starts at `offset`, which is
+/// its first or second line, returning its offset along with it.
+fn timing_line(source: &str, offset: usize) -> Option<(usize, &str)> {
+    let mut start = offset;
+
+    for _ in 0..2 {
+        let rest = source.get(start..)?;
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+
+        if line.contains("-->") {
+            return Some((start, line));
+        }
+
+        start += line.len() + 1;
+    }
+
+    None
+}
+
+/// Returns the range of the start timestamp in a timing line.
+fn start_timestamp(start: usize, line: &str) -> (usize, usize) {
+    let trimmed = line.trim_start_matches(|char: char| char.is_ascii_whitespace());
+    let skipped = line.len() - trimmed.len();
+    let length = trimmed
+        .find(|char: char| char.is_ascii_whitespace() || char == '-')
+        .unwrap_or(trimmed.len());
+
+    (start + skipped, length)
+}
+
+/// Returns the range of the end timestamp in a timing line.
+fn end_timestamp(start: usize, line: &str) -> (usize, usize) {
+    let arrow = line.find("-->").map_or(0, |idx| idx + "-->".len());
+    start_timestamp(start + arrow, &line[arrow..])
+}
+
+/// Returns the range of the first arrow at or after `offset`.
+fn arrow_after(source: &str, offset: usize) -> Label {
+    let idx = source.get(offset..)?.find("-->")?;
+    Some((offset + idx, "-->".len()))
+}
+
+/// Returns the last char boundary of `source` at or before `idx`, so that
+/// labels can be sliced out of it.
+fn char_boundary(source: &str, idx: usize) -> usize {
+    let mut idx = idx.min(source.len());
+
+    while !source.is_char_boundary(idx) {
+        idx -= 1;
+    }
+
+    idx
+}
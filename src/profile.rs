@@ -0,0 +1,213 @@
+//! Delivery rules of video platforms, and checking and fixing files against
+//! them.
+
+use std::time::Duration;
+
+use crate::{File, Finding, FindingKind, LengthMetric, LineEnding, SpeedFix, TimestampStyle};
+
+/// The caption rules of a platform.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Profile {
+    /// YouTube's recommendations for uploaded captions.
+    Youtube,
+    /// Netflix's timed text style guide for English.
+    Netflix,
+    /// Apple's HLS authoring specification, which needs cues in order for
+    /// segmenting.
+    Hls,
+    Custom(ProfileRules),
+}
+
+/// The rules of a [`Profile`]. A rule that is `None` is not checked, and a
+/// writer option that is `None` keeps the format the file was read in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileRules {
+    /// Most characters on one line of a cue, not counting markup, as
+    /// measured by `length_metric`.
+    pub max_line_length: Option<usize>,
+    pub max_lines: Option<usize>,
+    /// Most characters shown per second, as in [`crate::Cue::chars_per_second`].
+    pub max_cps: Option<f64>,
+    pub min_duration: Option<Duration>,
+    pub max_duration: Option<Duration>,
+    /// Shortest gap between a cue and the next one, if they do not touch.
+    pub min_gap: Option<Duration>,
+    /// Require cues to be sorted by start time.
+    pub sorted: bool,
+    /// How the length of a line is counted.
+    pub length_metric: LengthMetric,
+    /// The line ending [`File::conform`] writes the file with.
+    pub line_ending: Option<LineEnding>,
+    /// Whether [`File::conform`] writes the file with a byte order mark.
+    pub bom: Option<bool>,
+    /// How [`File::conform`] writes the timestamps of cue timings.
+    pub timestamps: Option<TimestampStyle>,
+}
+
+impl Profile {
+    pub fn rules(&self) -> ProfileRules {
+        match self {
+            Profile::Youtube => ProfileRules {
+                max_line_length: Some(42),
+                max_lines: Some(2),
+                ..Default::default()
+            },
+            Profile::Netflix => ProfileRules {
+                max_line_length: Some(42),
+                max_lines: Some(2),
+                max_cps: Some(20.0),
+                min_duration: Some(Duration::from_millis(833)),
+                max_duration: Some(Duration::from_secs(7)),
+                // two frames at 24 fps
+                min_gap: Some(Duration::from_millis(83)),
+                sorted: true,
+                line_ending: Some(LineEnding::Lf),
+                bom: Some(false),
+                timestamps: Some(TimestampStyle::Full),
+                ..Default::default()
+            },
+            Profile::Hls => ProfileRules {
+                sorted: true,
+                bom: Some(false),
+                timestamps: Some(TimestampStyle::Full),
+                ..Default::default()
+            },
+            Profile::Custom(rules) => rules.clone(),
+        }
+    }
+}
+
+impl File {
+    /// Checks this file against the rules of a profile.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn check_profile(&self, profile: &Profile) -> Vec<Finding> {
+        let rules = profile.rules();
+        let mut findings = vec![];
+        let cues: Vec<_> = self.cues().collect();
+
+        for (cue_index, cue) in cues.iter().enumerate() {
+            let mut push = |kind| findings.push(Finding { cue_index, kind });
+            let plain = cue.plain_text();
+            let duration = cue.end.saturating_sub(cue.start);
+
+            if let Some(max) = rules.max_line_length {
+                for (line, text) in plain.split('\n').enumerate() {
+                    let length = rules.length_metric.measure(text);
+
+                    if length > max {
+                        push(FindingKind::LineTooLong { line, length, max });
+                    }
+                }
+            }
+
+            if let Some(max) = rules.max_lines {
+                let lines = plain.split('\n').count();
+
+                if lines > max {
+                    push(FindingKind::TooManyLines { lines, max });
+                }
+            }
+
+            if let Some(max_cps) = rules.max_cps {
+                if cue.chars_per_second() > max_cps {
+                    let chars = plain.chars().filter(|&c| c != '\n').count();
+                    push(FindingKind::TooFast { chars, duration });
+                }
+            }
+
+            if rules.min_duration.is_some_and(|min| duration < min) {
+                push(FindingKind::TooShort { duration });
+            }
+
+            if rules.max_duration.is_some_and(|max| duration > max) {
+                push(FindingKind::TooLong { duration });
+            }
+
+            if let (Some(min_gap), Some(next)) = (rules.min_gap, cues.get(cue_index + 1)) {
+                if next.start > cue.end && next.start - cue.end < min_gap {
+                    push(FindingKind::GapTooShort {
+                        gap: next.start - cue.end,
+                    });
+                }
+            }
+
+            if rules.sorted && cue_index > 0 && cue.start < cues[cue_index - 1].start {
+                push(FindingKind::Unsorted {
+                    previous: cues[cue_index - 1].start,
+                });
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(findings = findings.len(), "checked file");
+
+        findings
+    }
+
+    /// Fixes what it can of the ways this file breaks the rules of a profile,
+    /// returning the problems that are left. Cues are split into cues with
+    /// fewer lines, sorted, extended to be long and slow enough without
+    /// overlapping the next cue, cut short to the longest duration, and
+    /// ended early to leave the shortest gap. Lines that are too long are not
+    /// rewrapped. The writer options of the profile are set on
+    /// [`File::format`].
+    pub fn conform(&mut self, profile: &Profile) -> Vec<Finding> {
+        let rules = profile.rules();
+
+        if let Some(line_ending) = rules.line_ending {
+            self.format.line_ending = line_ending;
+        }
+
+        if let Some(bom) = rules.bom {
+            self.format.bom = bom;
+        }
+
+        if let Some(timestamps) = rules.timestamps {
+            self.format.timestamps = timestamps;
+        }
+
+        if let Some(max_lines) = rules.max_lines {
+            self.split_long_cues(max_lines);
+        }
+
+        if rules.sorted && !self.is_sorted() {
+            self.sort_by_start();
+        }
+
+        if let Some(max_cps) = rules.max_cps {
+            self.fix_reading_speed(max_cps, &[SpeedFix::Extend]);
+        }
+
+        let starts: Vec<_> = self.cues().map(|cue| cue.start).collect();
+
+        for (idx, cue) in self.cues_mut().enumerate() {
+            let next_start = starts.get(idx + 1).copied();
+
+            if let Some(min) = rules.min_duration {
+                let mut end = cue.start + min;
+
+                if let Some(next_start) = next_start {
+                    end = end.min(next_start.max(cue.end));
+                }
+
+                cue.end = cue.end.max(end);
+            }
+
+            if let Some(max) = rules.max_duration {
+                cue.end = cue.end.min(cue.start + max);
+            }
+
+            if let (Some(min_gap), Some(next_start)) = (rules.min_gap, next_start) {
+                if next_start > cue.end && next_start - cue.end < min_gap {
+                    let end = next_start.saturating_sub(min_gap);
+
+                    if end > cue.start {
+                        cue.end = end;
+                    }
+                }
+            }
+        }
+
+        self.check_profile(profile)
+    }
+}
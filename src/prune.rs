@@ -0,0 +1,38 @@
+//! Removal of cues with no visible text.
+
+use crate::{Block, Cue, File};
+
+impl Cue {
+    /// Returns whether this cue has no text other than whitespace once its
+    /// markup is removed.
+    pub fn is_empty(&self) -> bool {
+        self.plain_text().trim().is_empty()
+    }
+}
+
+impl File {
+    /// Returns the indices of the cues that [`File::prune_empty`] would
+    /// remove, without removing them.
+    pub fn empty_cues(&self) -> Vec<usize> {
+        self.cues()
+            .enumerate()
+            .filter(|(_, cue)| cue.is_empty())
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Removes the cues that have no text other than whitespace once their
+    /// markup is removed, returning them.
+    pub fn prune_empty(&mut self) -> Vec<Cue> {
+        let mut removed = Vec::new();
+
+        for block in std::mem::take(&mut self.blocks) {
+            match block {
+                Block::Cue(cue) if cue.is_empty() => removed.push(cue),
+                block => self.blocks.push(block),
+            }
+        }
+
+        removed
+    }
+}
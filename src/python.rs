@@ -0,0 +1,188 @@
+//! Python bindings, exposing files, cues and the common transforms as the
+//! `webvtt` module. Build the extension with maturin, enabling the `python`
+//! feature along with `pyo3/extension-module`.
+//!
+//! A panic in the crate is raised as a `RuntimeError` rather than pyo3's
+//! `PanicException`, which `except Exception` does not catch.
+
+use std::time::Duration;
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+
+use crate::{
+    error::catch_panic, merge_bilingual, parse_file, BilingualStyle, Block, Cue, File, Shift,
+};
+
+fn seconds(value: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(value).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+/// Runs `run`, raising a panic as a `RuntimeError`.
+fn guard<T>(run: impl FnOnce() -> T) -> PyResult<T> {
+    catch_panic(run)
+        .map_err(|message| PyRuntimeError::new_err(format!("internal error: {message}")))
+}
+
+/// A cue, with times in seconds.
+#[pyclass(name = "Cue")]
+#[derive(Clone)]
+pub struct PyCue(pub Cue);
+
+#[pymethods]
+impl PyCue {
+    #[new]
+    #[pyo3(signature = (start, end, text, id = String::new()))]
+    fn new(start: f64, end: f64, text: String, id: String) -> PyResult<Self> {
+        Ok(PyCue(Cue {
+            start: seconds(start)?,
+            end: seconds(end)?,
+            id,
+            text,
+            ..Default::default()
+        }))
+    }
+
+    #[getter]
+    fn start(&self) -> f64 {
+        self.0.start.as_secs_f64()
+    }
+
+    #[setter]
+    fn set_start(&mut self, start: f64) -> PyResult<()> {
+        self.0.start = seconds(start)?;
+        Ok(())
+    }
+
+    #[getter]
+    fn end(&self) -> f64 {
+        self.0.end.as_secs_f64()
+    }
+
+    #[setter]
+    fn set_end(&mut self, end: f64) -> PyResult<()> {
+        self.0.end = seconds(end)?;
+        Ok(())
+    }
+
+    #[getter]
+    fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    #[setter]
+    fn set_id(&mut self, id: String) {
+        self.0.id = id;
+    }
+
+    #[getter]
+    fn text(&self) -> &str {
+        &self.0.text
+    }
+
+    #[setter]
+    fn set_text(&mut self, text: String) {
+        self.0.text = text;
+    }
+
+    /// The text without markup.
+    fn plain_text(&self) -> PyResult<String> {
+        guard(|| self.0.plain_text())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Cue({}, {}, {:?})", self.start(), self.end(), self.0.text)
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        guard(|| self.0.to_string())
+    }
+}
+
+/// A WebVTT file.
+#[pyclass(name = "File")]
+#[derive(Clone)]
+pub struct PyFile(pub File);
+
+#[pymethods]
+impl PyFile {
+    #[staticmethod]
+    fn parse(input: &str) -> PyResult<Self> {
+        guard(|| parse_file(input))?
+            .map(PyFile)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    #[staticmethod]
+    fn from_csv(input: &str) -> PyResult<Self> {
+        guard(|| File::from_csv(input))?
+            .map(PyFile)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    #[getter]
+    fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    /// Copies of the cues of the file. Assign a list of cues to replace them,
+    /// which also drops NOTE blocks that are not attached to a cue.
+    #[getter]
+    fn cues(&self) -> Vec<PyCue> {
+        self.0.cues().cloned().map(PyCue).collect()
+    }
+
+    #[setter]
+    fn set_cues(&mut self, cues: Vec<PyCue>) {
+        self.0.blocks = cues.into_iter().map(|cue| Block::Cue(cue.0)).collect();
+    }
+
+    /// Moves every cue by a number of seconds, which is negative to move them
+    /// earlier.
+    fn shift(&mut self, seconds: f64) -> PyResult<()> {
+        let shift = if seconds < 0.0 {
+            Shift::Earlier(self::seconds(-seconds)?)
+        } else {
+            Shift::Later(self::seconds(seconds)?)
+        };
+
+        guard(|| self.0.shift(shift))?.map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn sort_by_start(&mut self) -> PyResult<()> {
+        guard(|| self.0.sort_by_start())
+    }
+
+    fn to_csv(&self) -> PyResult<String> {
+        guard(|| self.0.to_csv())
+    }
+
+    fn to_tsv(&self) -> PyResult<String> {
+        guard(|| self.0.to_tsv())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.cues().count()
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        guard(|| self.0.to_string())
+    }
+}
+
+/// Merges two tracks into one with the text of both languages in each cue,
+/// using the timings of `primary`.
+#[pyfunction(name = "merge_bilingual")]
+fn py_merge_bilingual(primary: &PyFile, secondary: &PyFile) -> PyResult<PyFile> {
+    guard(|| merge_bilingual(&primary.0, &secondary.0, &BilingualStyle::Stacked)).map(PyFile)
+}
+
+#[pymodule]
+pub fn webvtt(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyCue>()?;
+    module.add_class::<PyFile>()?;
+    module.add_function(wrap_pyfunction!(py_merge_bilingual, module)?)?;
+    Ok(())
+}
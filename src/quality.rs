@@ -0,0 +1,158 @@
+//! Scoring the overall quality of a file, to gate ingestion on it.
+
+use crate::{File, FindingKind, Profile, ProfileRules};
+
+/// An aspect of quality that a [`QualityScore`] breaks down into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum QualityCategory {
+    /// Whether cues are slow enough to read.
+    ReadingSpeed,
+    /// Whether lines are short enough, and cues have few enough of them.
+    LineLength,
+    /// Whether cues are neither too short nor too long.
+    Duration,
+    /// Whether cues are in order, leave enough of a gap before the next cue,
+    /// and do not overlap it.
+    Spacing,
+}
+
+/// How a file is scored: the rules that cues are checked against, and how much
+/// each category counts towards the total.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rubric {
+    pub rules: ProfileRules,
+    pub reading_speed: f64,
+    pub line_length: f64,
+    pub duration: f64,
+    pub spacing: f64,
+}
+
+impl Rubric {
+    /// Scores against the rules of a profile, with every category weighted
+    /// equally.
+    pub fn for_profile(profile: &Profile) -> Self {
+        Rubric {
+            rules: profile.rules(),
+            reading_speed: 1.0,
+            line_length: 1.0,
+            duration: 1.0,
+            spacing: 1.0,
+        }
+    }
+
+    fn weight(&self, category: QualityCategory) -> f64 {
+        match category {
+            QualityCategory::ReadingSpeed => self.reading_speed,
+            QualityCategory::LineLength => self.line_length,
+            QualityCategory::Duration => self.duration,
+            QualityCategory::Spacing => self.spacing,
+        }
+    }
+}
+
+impl Default for Rubric {
+    fn default() -> Self {
+        Self::for_profile(&Profile::Netflix)
+    }
+}
+
+/// The score of one category of a [`QualityScore`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CategoryScore {
+    pub category: QualityCategory,
+    /// From 0 to 100: the percentage of cues with no problem in the category.
+    pub score: f64,
+    pub weight: f64,
+    /// The number of cues with a problem in the category.
+    pub failing_cues: usize,
+}
+
+/// The result of [`File::quality_score`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityScore {
+    /// From 0 to 100: the average of the category scores, weighted by the
+    /// rubric.
+    pub score: f64,
+    pub categories: Vec<CategoryScore>,
+}
+
+/// Every category, in the order of their discriminants.
+const CATEGORIES: [QualityCategory; 4] = [
+    QualityCategory::ReadingSpeed,
+    QualityCategory::LineLength,
+    QualityCategory::Duration,
+    QualityCategory::Spacing,
+];
+
+fn category(kind: &FindingKind) -> Option<QualityCategory> {
+    match kind {
+        FindingKind::TooFast { .. } => Some(QualityCategory::ReadingSpeed),
+        FindingKind::LineTooLong { .. } | FindingKind::TooManyLines { .. } => {
+            Some(QualityCategory::LineLength)
+        }
+        FindingKind::TooShort { .. } | FindingKind::TooLong { .. } => {
+            Some(QualityCategory::Duration)
+        }
+        FindingKind::GapTooShort { .. } | FindingKind::Unsorted { .. } => {
+            Some(QualityCategory::Spacing)
+        }
+        _ => None,
+    }
+}
+
+impl File {
+    /// Scores this file from 0 to 100 against a rubric. Each category scores
+    /// the share of cues that pass the checks of
+    /// [`File::check_profile`](crate::File::check_profile) for it, with cues
+    /// that overlap the next one also failing [`QualityCategory::Spacing`].
+    /// A file with no cues scores 100.
+    pub fn quality_score(&self, rubric: &Rubric) -> QualityScore {
+        let cues: Vec<_> = self.cues().collect();
+        let findings = self.check_profile(&Profile::Custom(rubric.rules.clone()));
+
+        let mut failing = vec![[false; CATEGORIES.len()]; cues.len()];
+
+        for finding in &findings {
+            if let Some(category) = category(&finding.kind) {
+                failing[finding.cue_index][category as usize] = true;
+            }
+        }
+
+        for (idx, pair) in cues.windows(2).enumerate() {
+            if pair[0].end > pair[1].start && pair[0].start <= pair[1].start {
+                failing[idx][QualityCategory::Spacing as usize] = true;
+            }
+        }
+
+        let categories: Vec<_> = CATEGORIES
+            .into_iter()
+            .map(|category| {
+                let failing_cues = failing.iter().filter(|cue| cue[category as usize]).count();
+
+                let score = if cues.is_empty() {
+                    100.0
+                } else {
+                    100.0 * (cues.len() - failing_cues) as f64 / cues.len() as f64
+                };
+
+                CategoryScore {
+                    category,
+                    score,
+                    weight: rubric.weight(category),
+                    failing_cues,
+                }
+            })
+            .collect();
+
+        let total_weight: f64 = categories.iter().map(|c| c.weight).sum();
+
+        let score = if total_weight > 0.0 {
+            categories.iter().map(|c| c.score * c.weight).sum::<f64>() / total_weight
+        } else {
+            100.0
+        };
+
+        QualityScore { score, categories }
+    }
+}
@@ -0,0 +1,128 @@
+//! Replacing the text of cues with a corrected transcript while keeping their
+//! timings.
+
+use crate::{text, File};
+
+/// How the words of two texts line up, from the last step of the alignment.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// Both words, the same or substituted for each other.
+    Pair,
+    /// A word only in the original.
+    Deleted,
+    /// A word only in the corrected transcript.
+    Inserted,
+}
+
+/// The form of a word that is compared: lowercase, without punctuation.
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Aligns two lists of words with the fewest insertions, deletions and
+/// substitutions, returning for each word of `new` the index of the word of
+/// `old` it was aligned with, or `None` if it was inserted.
+fn align(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+    let width = new.len() + 1;
+    let mut steps = vec![Step::Pair; (old.len() + 1) * width];
+    let mut previous: Vec<usize> = (0..width).collect();
+    let mut current = vec![0; width];
+
+    steps[1..width].fill(Step::Inserted);
+
+    for i in 1..=old.len() {
+        current[0] = i;
+        steps[i * width] = Step::Deleted;
+
+        for j in 1..width {
+            let pair = previous[j - 1] + usize::from(old[i - 1] != new[j - 1]);
+            let deleted = previous[j] + 1;
+            let inserted = current[j - 1] + 1;
+
+            (current[j], steps[i * width + j]) = if pair <= deleted && pair <= inserted {
+                (pair, Step::Pair)
+            } else if deleted <= inserted {
+                (deleted, Step::Deleted)
+            } else {
+                (inserted, Step::Inserted)
+            };
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let mut aligned = vec![None; new.len()];
+    let (mut i, mut j) = (old.len(), new.len());
+
+    while i > 0 || j > 0 {
+        match steps[i * width + j] {
+            Step::Pair => {
+                i -= 1;
+                j -= 1;
+                aligned[j] = Some(i);
+            }
+            Step::Deleted => i -= 1,
+            Step::Inserted => j -= 1,
+        }
+    }
+
+    aligned
+}
+
+impl File {
+    /// Replaces the text of the cues with a corrected transcript of the whole
+    /// file, keeping their timings.
+    ///
+    /// The words of the transcript are aligned with the words of the cues by
+    /// edit distance, ignoring case and punctuation, and each goes to the cue
+    /// of the word it lines up with. Words the transcript adds go to the cue of
+    /// the word before them. The new text of each cue is its words on one
+    /// line, without markup; a cue can be left empty if the transcript drops
+    /// all of its words. Returns the number of cues whose text changed.
+    ///
+    /// The alignment takes time and memory proportional to the product of the
+    /// word counts of the file and the transcript.
+    pub fn realign_text(&mut self, corrected_transcript: &str) -> usize {
+        let mut old = vec![];
+        let mut owners = vec![];
+
+        for (cue_index, cue) in self.cues().enumerate() {
+            for word in cue.plain_text().split_whitespace() {
+                old.push(normalize(word));
+                owners.push(cue_index);
+            }
+        }
+
+        let words: Vec<_> = corrected_transcript.split_whitespace().collect();
+        let new: Vec<_> = words.iter().map(|word| normalize(word)).collect();
+
+        let mut texts = vec![vec![]; owners.last().map_or(0, |&last| last + 1)];
+        let mut owner = owners.first().copied();
+
+        for (word, aligned) in words.iter().zip(align(&old, &new)) {
+            if let Some(old_index) = aligned {
+                owner = Some(owners[old_index]);
+            }
+
+            if let Some(owner) = owner {
+                texts[owner].push(*word);
+            }
+        }
+
+        let mut changed = 0;
+
+        for (cue, words) in self.cues_mut().zip(texts) {
+            let text = text::escape(&words.join(" "));
+
+            if cue.text != text {
+                cue.text = text;
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+}
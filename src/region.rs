@@ -0,0 +1,83 @@
+//! Merging duplicate region definitions.
+
+use std::collections::HashMap;
+
+use crate::{Block, File};
+
+/// Returns the id and the other settings, sorted, of a `REGION` block.
+pub(crate) fn region_definition(lines: &[String]) -> Option<(&str, Vec<&str>)> {
+    if lines.first()?.trim_end() != "REGION" {
+        return None;
+    }
+
+    let mut id = None;
+    let mut settings = vec![];
+
+    for setting in lines[1..].iter().flat_map(|line| line.split_whitespace()) {
+        match setting.strip_prefix("id:") {
+            Some(value) => id = Some(value),
+            None => settings.push(setting),
+        }
+    }
+
+    settings.sort_unstable();
+    Some((id?, settings))
+}
+
+impl File {
+    /// Removes `REGION` blocks that define the same region as an earlier
+    /// block under another id, pointing the cues in them at the earlier
+    /// region instead. Settings are compared regardless of their order and
+    /// the lines they are on. Returns the number of regions removed.
+    ///
+    /// Regions are read from the [`Block::Raw`] blocks that the parser keeps
+    /// them in.
+    pub fn dedupe_regions(&mut self) -> usize {
+        let mut kept: HashMap<Vec<String>, String> = HashMap::new();
+        let mut renamed: HashMap<String, String> = HashMap::new();
+        let mut duplicates = vec![];
+
+        for (idx, block) in self.blocks.iter().enumerate() {
+            let Block::Raw { lines } = block else {
+                continue;
+            };
+
+            let Some((id, settings)) = region_definition(lines) else {
+                continue;
+            };
+
+            let settings = settings.into_iter().map(String::from).collect();
+
+            match kept.get(&settings) {
+                Some(kept_id) if kept_id != id => {
+                    renamed.insert(id.to_owned(), kept_id.clone());
+                    duplicates.push(idx);
+                }
+                Some(_) => {}
+                None => {
+                    kept.insert(settings, id.to_owned());
+                }
+            }
+        }
+
+        for &idx in duplicates.iter().rev() {
+            self.blocks.remove(idx);
+        }
+
+        for cue in self.cues_mut() {
+            let Some(kept_id) = cue
+                .settings
+                .region
+                .as_ref()
+                .and_then(|region| renamed.get(region))
+            else {
+                continue;
+            };
+
+            cue.settings.region = Some(kept_id.clone());
+            cue.raw_settings = None;
+        }
+
+        duplicates.len()
+    }
+}
@@ -0,0 +1,101 @@
+//! Conversion of cues into a roll-up presentation, where lines of text scroll
+//! up through a region as in live captions.
+
+use std::{mem, time::Duration};
+
+use crate::{region::region_definition, Block, CueSettings, File};
+
+/// The id of the region that [`File::roll_up`] creates.
+const ROLL_UP_REGION: &str = "rollup";
+
+impl File {
+    /// Converts the cues of the file into a roll-up presentation showing at
+    /// most `lines` lines at once. The cues are sorted, each line of text
+    /// becomes its own cue in a scrolling region, and each line stays on
+    /// screen until `lines` newer lines have pushed it out or there is a gap
+    /// in the captions. Metadata cues are left as they are.
+    ///
+    /// The region, with the id `rollup`, is defined in a [`Block::Raw`] at the
+    /// start of the file, replacing any definition left by an earlier call.
+    pub fn roll_up(&mut self, lines: usize) {
+        assert!(lines > 0, "a roll-up region needs at least one line");
+
+        self.sort_by_start();
+
+        let mut rolled = vec![];
+
+        for block in mem::take(&mut self.blocks) {
+            match block {
+                Block::Cue(cue) if !cue.is_metadata() => {
+                    for mut part in cue.split_lines(1) {
+                        part.settings = CueSettings {
+                            region: Some(ROLL_UP_REGION.to_owned()),
+                            ..Default::default()
+                        };
+                        part.raw_settings = None;
+
+                        rolled.push(self.blocks.len());
+                        self.blocks.push(Block::Cue(part));
+                    }
+                }
+                block => self.blocks.push(block),
+            }
+        }
+
+        let timings: Vec<_> = rolled
+            .iter()
+            .map(|&idx| {
+                let cue = self.blocks[idx].as_cue().unwrap();
+                (cue.start, cue.end)
+            })
+            .collect();
+
+        // the region is cleared at the end of each run of lines without a gap,
+        // so find the run of each line and when each run ends
+        let mut runs = vec![];
+        let mut run_ends = vec![];
+        let mut run_end = Duration::ZERO;
+
+        for (idx, &(start, end)) in timings.iter().enumerate() {
+            if idx > 0 && start > run_end {
+                run_ends.push(run_end);
+            }
+
+            run_end = if idx == 0 || start > run_end {
+                end
+            } else {
+                run_end.max(end)
+            };
+
+            runs.push(run_ends.len());
+        }
+
+        run_ends.push(run_end);
+
+        for (idx, &block_idx) in rolled.iter().enumerate() {
+            let run = runs[idx];
+
+            let pushed_out = (idx + lines < timings.len() && runs[idx + lines] == run)
+                .then(|| timings[idx + lines].0);
+
+            let cue = self.blocks[block_idx].as_cue_mut().unwrap();
+            cue.end = pushed_out.unwrap_or(run_ends[run]).max(cue.start);
+        }
+
+        let region = vec![
+            "REGION".to_owned(),
+            format!("id:{ROLL_UP_REGION}"),
+            "width:80%".to_owned(),
+            format!("lines:{lines}"),
+            "regionanchor:0%,100%".to_owned(),
+            "viewportanchor:10%,90%".to_owned(),
+            "scroll:up".to_owned(),
+        ];
+
+        self.blocks.retain(|block| {
+            !matches!(block, Block::Raw { lines }
+                if region_definition(lines).is_some_and(|(id, _)| id == ROLL_UP_REGION))
+        });
+        self.blocks.insert(0, Block::Raw { lines: region });
+    }
+}
@@ -0,0 +1,39 @@
+//! Byte scanning for the parser's hot paths, using the vectorized searches of
+//! `memchr`. Every byte searched for is ASCII, so the indices returned are
+//! always on character boundaries.
+
+use memchr::memchr;
+
+/// Returns the index of the first `\n` in the input.
+pub(crate) fn find_newline(input: &str) -> Option<usize> {
+    memchr(b'\n', input.as_bytes())
+}
+
+/// Returns the index of the first occurrence of a byte in the input.
+pub(crate) fn find_byte(input: &str, byte: u8) -> Option<usize> {
+    memchr(byte, input.as_bytes())
+}
+
+/// Returns the index of the first `-->` in the input. The search is for its
+/// `>`, which is rarer in cue text than `-`.
+pub(crate) fn find_arrow(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+
+    memchr::memchr_iter(b'>', bytes)
+        .find(|&idx| idx >= 2 && &bytes[idx - 2..idx] == b"--")
+        .map(|idx| idx - 2)
+}
+
+/// Returns whether the input contains `-->`.
+pub(crate) fn has_arrow(input: &str) -> bool {
+    find_arrow(input).is_some()
+}
+
+/// Returns the length of the run of ASCII digits at the start of the input.
+pub(crate) fn digits_len(input: &str) -> usize {
+    input
+        .as_bytes()
+        .iter()
+        .position(|byte| !byte.is_ascii_digit())
+        .unwrap_or(input.len())
+}
@@ -0,0 +1,243 @@
+//! Delivery of cue events at the right moments during playback.
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::{File, TimelineEvent, Transition};
+
+/// A monotonic source of wall-clock time for a [`Scheduler`].
+pub trait Clock {
+    /// Returns the time elapsed since some fixed point.
+    fn now(&self) -> Duration;
+
+    /// Blocks until `duration` has elapsed.
+    fn sleep(&self, duration: Duration);
+}
+
+/// A [`Clock`] backed by the system's monotonic clock.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Tracks the media time of a playing file against a [`Clock`] and delivers
+/// the [`TimelineEvent`]s of its cues as they become due.
+///
+/// As an iterator, the scheduler sleeps until the next event is due and yields
+/// it, ending when playback is paused or there are no more events. Between
+/// events, playback can be paused, sought or sped up.
+pub struct Scheduler<C: Clock> {
+    clock: C,
+    /// The start and end of every cue, by cue index.
+    cues: Vec<(Duration, Duration)>,
+    events: Vec<TimelineEvent>,
+    /// Index of the next event in `events` that has not been delivered.
+    next: usize,
+    active: BTreeSet<usize>,
+    pending: VecDeque<TimelineEvent>,
+    /// The wall-clock and media times at the last change of playback state.
+    anchor_wall: Duration,
+    anchor_media: Duration,
+    rate: f64,
+    playing: bool,
+}
+
+impl<C: Clock> Scheduler<C> {
+    /// Creates a scheduler for the cues of a file, paused at the start of the
+    /// media.
+    pub fn new(file: &File, clock: C) -> Self {
+        Scheduler {
+            anchor_wall: clock.now(),
+            clock,
+            cues: file.cues().map(|cue| (cue.start, cue.end)).collect(),
+            events: file.timeline(),
+            next: 0,
+            active: BTreeSet::new(),
+            pending: VecDeque::new(),
+            anchor_media: Duration::ZERO,
+            rate: 1.0,
+            playing: false,
+        }
+    }
+
+    /// Returns the current media time.
+    pub fn media_time(&self) -> Duration {
+        if !self.playing {
+            return self.anchor_media;
+        }
+
+        let elapsed = self.clock.now().saturating_sub(self.anchor_wall);
+        self.anchor_media + elapsed.mul_f64(self.rate)
+    }
+
+    /// Returns the indices of the cues that are active as far as delivered
+    /// events go.
+    pub fn active(&self) -> impl Iterator<Item = usize> + '_ {
+        self.active.iter().copied()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn play(&mut self) {
+        self.rebase();
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.rebase();
+        self.playing = false;
+    }
+
+    /// Sets the playback rate, where `1.0` is normal speed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is not positive and finite.
+    pub fn set_rate(&mut self, rate: f64) {
+        assert!(
+            rate > 0.0 && rate.is_finite(),
+            "invalid playback rate {rate}"
+        );
+
+        self.rebase();
+        self.rate = rate;
+    }
+
+    /// Jumps to a media time. Cues that stop being active exit, and cues that
+    /// become active enter, with events at the new media time rather than at
+    /// the times of the cues.
+    pub fn seek(&mut self, time: Duration) {
+        self.rebase();
+
+        // deliver whatever was due before the jump, so the events stay in
+        // order and the active set is up to date
+        let due = self.due(self.anchor_media);
+        self.pending.extend(due);
+        self.anchor_media = time;
+
+        let target: BTreeSet<_> = (0..self.cues.len())
+            .filter(|&idx| {
+                let (start, end) = self.cues[idx];
+                start <= time && time < end
+            })
+            .collect();
+
+        for &cue_index in self.active.difference(&target) {
+            self.pending.push_back(TimelineEvent {
+                time,
+                cue_index,
+                transition: Transition::Exit,
+            });
+        }
+
+        for &cue_index in target.difference(&self.active) {
+            self.pending.push_back(TimelineEvent {
+                time,
+                cue_index,
+                transition: Transition::Enter,
+            });
+        }
+
+        self.active = target;
+        self.next = self.events.partition_point(|event| event.time <= time);
+    }
+
+    /// Returns the events that have become due since the last call, without
+    /// blocking.
+    pub fn poll(&mut self) -> Vec<TimelineEvent> {
+        let due = self.due(self.media_time());
+        self.pending.drain(..).chain(due).collect()
+    }
+
+    /// Returns how long to wait in wall-clock time until the next event is
+    /// due, or `None` if playback is paused or there are no more events.
+    pub fn time_until_next(&self) -> Option<Duration> {
+        if !self.pending.is_empty() {
+            return Some(Duration::ZERO);
+        }
+
+        let event = self.events.get(self.next)?;
+
+        if !self.playing {
+            return None;
+        }
+
+        let media = event.time.saturating_sub(self.media_time());
+        Some(media.div_f64(self.rate))
+    }
+
+    /// Takes the undelivered events up to `time` and updates the active set.
+    fn due(&mut self, time: Duration) -> Vec<TimelineEvent> {
+        let end = self.next + self.events[self.next..].partition_point(|event| event.time <= time);
+        let due = self.events[self.next..end].to_vec();
+
+        for event in &due {
+            match event.transition {
+                Transition::Enter => self.active.insert(event.cue_index),
+                Transition::Exit => self.active.remove(&event.cue_index),
+            };
+        }
+
+        self.next = end;
+        due
+    }
+
+    /// Moves the anchors to now, before the playback state changes.
+    fn rebase(&mut self) {
+        self.anchor_media = self.media_time();
+        self.anchor_wall = self.clock.now();
+    }
+}
+
+impl<C: Clock> Iterator for Scheduler<C> {
+    type Item = TimelineEvent;
+
+    fn next(&mut self) -> Option<TimelineEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+
+            let wait = self.time_until_next()?;
+
+            if !wait.is_zero() {
+                self.clock.sleep(wait);
+            }
+
+            let due = self.due(self.media_time());
+            self.pending.extend(due);
+        }
+    }
+}
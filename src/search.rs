@@ -0,0 +1,220 @@
+//! Searching for text in the cues of a file.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+    time::Duration,
+};
+
+use crate::File;
+
+/// What to search for with [`File::search`].
+#[derive(Clone, Debug)]
+pub enum Query {
+    /// Text matched regardless of case.
+    Text(String),
+    /// Text matched exactly.
+    Exact(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl From<&str> for Query {
+    fn from(text: &str) -> Self {
+        Query::Text(text.to_owned())
+    }
+}
+
+impl From<String> for Query {
+    fn from(text: String) -> Self {
+        Query::Text(text)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Regex> for Query {
+    fn from(regex: regex::Regex) -> Self {
+        Query::Regex(regex)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    /// Index of the cue among the cues of the file.
+    pub cue_index: usize,
+    /// Byte range of the match in the cue's [plain text](crate::Cue::plain_text).
+    pub range: Range<usize>,
+    /// Start time of the cue.
+    pub time: Duration,
+}
+
+/// A [`Match`] along with the text surrounding it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Concordance {
+    pub found: Match,
+    pub before: String,
+    pub matched: String,
+    pub after: String,
+}
+
+/// Options for [`File::word_index_with`].
+#[derive(Clone, Debug, Default)]
+pub struct WordIndexOptions {
+    /// Lowercase words to leave out of the index.
+    pub stop_words: HashSet<String>,
+    /// Words with fewer characters than this are left out of the index.
+    pub min_length: usize,
+}
+
+impl WordIndexOptions {
+    /// Options that leave out common English function words.
+    pub fn english() -> Self {
+        const STOP_WORDS: &[&str] = &[
+            "a", "about", "an", "and", "are", "as", "at", "be", "but", "by", "do", "for", "from",
+            "had", "has", "have", "he", "her", "his", "i", "if", "in", "is", "it", "its", "me",
+            "my", "no", "not", "of", "on", "or", "our", "she", "so", "that", "the", "their",
+            "them", "there", "they", "this", "to", "up", "was", "we", "were", "what", "when",
+            "which", "who", "will", "with", "you", "your",
+        ];
+
+        WordIndexOptions {
+            stop_words: STOP_WORDS.iter().map(|word| word.to_string()).collect(),
+            min_length: 0,
+        }
+    }
+}
+
+impl File {
+    /// Finds every occurrence of `query` in the text of the cues, ignoring
+    /// markup. Matches do not span cues.
+    pub fn search(&self, query: impl Into<Query>) -> Vec<Match> {
+        let query = query.into();
+
+        self.cues()
+            .enumerate()
+            .flat_map(|(cue_index, cue)| {
+                find_all(&cue.plain_text(), &query)
+                    .into_iter()
+                    .map(move |range| Match {
+                        cue_index,
+                        range,
+                        time: cue.start,
+                    })
+            })
+            .collect()
+    }
+
+    /// Like [`File::search`], but also returns up to `context` characters of
+    /// the cue's text on either side of each match. Line breaks in the cue are
+    /// replaced with spaces.
+    pub fn search_concordance(&self, query: impl Into<Query>, context: usize) -> Vec<Concordance> {
+        let texts: Vec<_> = self.cues().map(|cue| cue.plain_text()).collect();
+
+        self.search(query)
+            .into_iter()
+            .map(|found| {
+                let text = &texts[found.cue_index];
+
+                let before_start = text[..found.range.start]
+                    .char_indices()
+                    .rev()
+                    .take(context)
+                    .last()
+                    .map_or(found.range.start, |(idx, _)| idx);
+                let after: String = text[found.range.end..].chars().take(context).collect();
+
+                Concordance {
+                    before: text[before_start..found.range.start].replace('\n', " "),
+                    matched: text[found.range.clone()].replace('\n', " "),
+                    after: after.replace('\n', " "),
+                    found,
+                }
+            })
+            .collect()
+    }
+
+    /// Builds an index from each lowercased word in the text of the cues to the
+    /// cues it appears in, as pairs of cue index and start time.
+    pub fn word_index(&self) -> BTreeMap<String, Vec<(usize, Duration)>> {
+        self.word_index_with(&WordIndexOptions::default())
+    }
+
+    /// Like [`File::word_index`], but leaves out words as `options` specifies.
+    pub fn word_index_with(
+        &self,
+        options: &WordIndexOptions,
+    ) -> BTreeMap<String, Vec<(usize, Duration)>> {
+        let mut index: BTreeMap<String, Vec<(usize, Duration)>> = BTreeMap::new();
+
+        for (cue_index, cue) in self.cues().enumerate() {
+            let text = cue.plain_text().to_lowercase();
+
+            let words = text
+                .split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’'))
+                .map(|word| word.trim_matches(['\'', '’']))
+                .filter(|word| !word.is_empty() && word.chars().count() >= options.min_length)
+                .filter(|word| !options.stop_words.contains(*word));
+
+            for word in words {
+                let entries = index.entry(word.to_owned()).or_default();
+
+                if entries.last().map(|&(idx, _)| idx) != Some(cue_index) {
+                    entries.push((cue_index, cue.start));
+                }
+            }
+        }
+
+        index
+    }
+}
+
+fn find_all(text: &str, query: &Query) -> Vec<Range<usize>> {
+    match query {
+        Query::Text(needle) => {
+            let (haystack, ends) = fold_case(text);
+            let (needle, _) = fold_case(needle);
+
+            if needle.is_empty() {
+                return vec![];
+            }
+
+            // map the ranges in the folded text back onto the original text
+            haystack
+                .match_indices(&needle)
+                .map(|(start, found)| {
+                    let original_start = if start == 0 { 0 } else { ends[start - 1] };
+                    original_start..ends[start + found.len() - 1]
+                })
+                .collect()
+        }
+        Query::Exact(needle) if needle.is_empty() => vec![],
+        Query::Exact(needle) => text
+            .match_indices(needle.as_str())
+            .map(|(start, found)| start..start + found.len())
+            .collect(),
+        #[cfg(feature = "regex")]
+        Query::Regex(regex) => regex
+            .find_iter(text)
+            .filter(|found| !found.is_empty())
+            .map(|found| found.range())
+            .collect(),
+    }
+}
+
+/// Lowercases the text, returning for each byte of the result the offset in
+/// the original text of the end of the character it came from.
+fn fold_case(text: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut ends = Vec::with_capacity(text.len());
+
+    for (idx, char) in text.char_indices() {
+        let end = idx + char.len_utf8();
+
+        for lower in char.to_lowercase() {
+            folded.push(lower);
+            ends.resize(folded.len(), end);
+        }
+    }
+
+    (folded, ends)
+}
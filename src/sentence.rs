@@ -0,0 +1,129 @@
+//! Finding sentence boundaries, which make the best places to break cues.
+
+use std::collections::HashSet;
+
+/// Splits text into sentences. The rules differ between languages, so
+/// callers can plug in their own.
+pub trait SentenceSegmenter {
+    /// Returns the byte offsets in `text` at which each sentence after the
+    /// first starts, in order.
+    fn sentence_starts(&self, text: &str) -> Vec<usize>;
+}
+
+/// Abbreviations that [`RuleSegmenter`] does not end sentences at, without
+/// their trailing full stop.
+const ABBREVIATIONS: [&str; 16] = [
+    "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "etc", "e.g", "i.e", "no", "approx",
+    "mt", "ft",
+];
+
+/// A segmenter for languages written with spaces between sentences, like
+/// English, that also breaks after the full-width punctuation of Chinese and
+/// Japanese. A sentence ends at a `.`, `!`, `?` or `…`, and any closing quotes
+/// or brackets after it, if the next word does not start with a lowercase
+/// letter. Full stops after abbreviations and initials do not end sentences.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleSegmenter {
+    abbreviations: HashSet<String>,
+}
+
+impl Default for RuleSegmenter {
+    fn default() -> Self {
+        RuleSegmenter {
+            abbreviations: ABBREVIATIONS.iter().map(|&abbr| abbr.to_owned()).collect(),
+        }
+    }
+}
+
+impl RuleSegmenter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an abbreviation, without its trailing full stop, that sentences do
+    /// not end at. Abbreviations are matched ignoring case.
+    pub fn add_abbreviation(&mut self, abbreviation: &str) {
+        self.abbreviations
+            .insert(abbreviation.trim_end_matches('.').to_lowercase());
+    }
+
+    fn is_abbreviation(&self, word: &str) -> bool {
+        let word = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let mut chars = word.chars();
+
+        // a single letter is an initial, like in `J. R. R. Tolkien`
+        let initial = chars.next().is_some_and(char::is_alphabetic) && chars.next().is_none();
+
+        initial || self.abbreviations.contains(&word.to_lowercase())
+    }
+}
+
+impl SentenceSegmenter for RuleSegmenter {
+    fn sentence_starts(&self, text: &str) -> Vec<usize> {
+        let mut starts = vec![];
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((idx, c)) = chars.next() {
+            let full_width = matches!(c, '。' | '！' | '？');
+
+            if !full_width && !matches!(c, '.' | '!' | '?' | '…') {
+                continue;
+            }
+
+            // runs of punctuation, like `?!` or `...`, and closing quotes and
+            // brackets belong to the sentence they end
+            while let Some(&(_, next)) = chars.peek() {
+                if matches!(next, '.' | '!' | '?' | '…' | '。' | '！' | '？')
+                    || matches!(
+                        next,
+                        '"' | '\'' | '”' | '’' | ')' | ']' | '」' | '』' | '）'
+                    )
+                {
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            let end = chars.peek().map_or(text.len(), |&(idx, _)| idx);
+            let rest = &text[end..];
+            let next = rest.trim_start();
+
+            if next.is_empty() || (!full_width && rest.len() == next.len()) {
+                continue;
+            }
+
+            if next.starts_with(char::is_lowercase) {
+                continue;
+            }
+
+            if c == '.' {
+                let word = text[..idx].rsplit(char::is_whitespace).next().unwrap_or("");
+
+                if self.is_abbreviation(word) {
+                    continue;
+                }
+            }
+
+            starts.push(text.len() - next.len());
+        }
+
+        starts
+    }
+}
+
+/// A segmenter following the Unicode sentence boundary rules, as implemented
+/// by ICU4X.
+#[cfg(feature = "icu")]
+#[derive(Debug, Default)]
+pub struct IcuSegmenter;
+
+#[cfg(feature = "icu")]
+impl SentenceSegmenter for IcuSegmenter {
+    fn sentence_starts(&self, text: &str) -> Vec<usize> {
+        icu_segmenter::SentenceSegmenter::new(Default::default())
+            .segment_str(text)
+            .filter(|&idx| idx > 0 && idx < text.len())
+            .collect()
+    }
+}
@@ -0,0 +1,422 @@
+//! Cue settings, which control where and how a cue is displayed.
+
+use std::fmt;
+
+use crate::{layout::computed_position, Direction};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CueSettings {
+    pub region: Option<String>,
+    pub writing_direction: WritingDirection,
+    /// the offset of the cue box from the video viewport’s edge in the
+    /// direction orthogonal to the writing direction
+    pub line: Line,
+    pub line_align: LineAlign,
+    /// the indent of the cue box in the direction defined by the writing
+    /// direction, or `None` for auto
+    pub position: Option<Percentage>,
+    pub position_align: PositionAlign,
+    /// the size of the cue box in the direction defined by the writing
+    /// direction
+    pub size: Percentage,
+    pub align: Align,
+}
+
+impl Default for CueSettings {
+    fn default() -> Self {
+        CueSettings {
+            region: None,
+            writing_direction: WritingDirection::Horizontal,
+            line: Line::Auto,
+            line_align: LineAlign::Start,
+            position: None,
+            position_align: PositionAlign::Auto,
+            size: Percentage::HUNDRED,
+            align: Align::Center,
+        }
+    }
+}
+
+impl CueSettings {
+    /// Returns whether the cue can be displayed in its region. Cues with
+    /// vertical text, a line or a size cannot be.
+    pub fn region_applies(&self) -> bool {
+        self.writing_direction == WritingDirection::Horizontal
+            && self.line == Line::Auto
+            && self.size == Percentage::HUNDRED
+    }
+
+    /// Returns the alignment of the cue's text with `start` and `end` resolved
+    /// for text with the given base direction, as in the spec's mapping to
+    /// CSS `text-align`. Never returns [`Align::Start`] or [`Align::End`].
+    pub fn effective_alignment(&self, text_direction: Direction) -> Align {
+        match (self.align, text_direction) {
+            (Align::Start, Direction::Ltr) | (Align::End, Direction::Rtl) => Align::Left,
+            (Align::Start, Direction::Rtl) | (Align::End, Direction::Ltr) => Align::Right,
+            (align, _) => align,
+        }
+    }
+
+    /// Returns the settings with values that have no effect reset to their
+    /// defaults: the region of a cue that cannot be displayed in one, and the
+    /// line alignment of a cue without a line.
+    pub fn canonicalize(mut self) -> Self {
+        if !self.region_applies() {
+            self.region = None;
+        }
+
+        if self.line == Line::Auto {
+            self.line_align = LineAlign::Start;
+        }
+
+        self
+    }
+
+    /// Returns the effective settings as `(name, value)` pairs, in a canonical
+    /// order, leaving out those with default values.
+    pub fn items(&self) -> Vec<(&'static str, String)> {
+        let settings = self.clone().canonicalize();
+        let mut items = vec![];
+
+        match settings.writing_direction {
+            WritingDirection::Horizontal => {}
+            WritingDirection::VerticalLeft => items.push(("vertical", "rl".to_owned())),
+            WritingDirection::VerticalRight => items.push(("vertical", "lr".to_owned())),
+        }
+
+        let line = match settings.line {
+            Line::Auto => None,
+            Line::Number(number) => Some(number.to_string()),
+            Line::Percentage(percentage) => Some(percentage.to_string()),
+        };
+
+        if let Some(line) = line {
+            match settings.line_align {
+                LineAlign::Start => items.push(("line", line)),
+                align => items.push(("line", format!("{line},{}", align.as_str()))),
+            }
+        }
+
+        // a position alignment needs a position to be written, so use the
+        // position that auto would resolve to
+        let position = match (settings.position, settings.position_align) {
+            (None, PositionAlign::Auto) => None,
+            (None, _) => Percentage::new(computed_position(&settings)),
+            (position, _) => position,
+        };
+
+        if let Some(position) = position {
+            match settings.position_align {
+                PositionAlign::Auto => items.push(("position", position.to_string())),
+                align => items.push(("position", format!("{position},{}", align.as_str()))),
+            }
+        }
+
+        if settings.size != Percentage::HUNDRED {
+            items.push(("size", settings.size.to_string()));
+        }
+
+        if settings.align != Align::Center {
+            items.push(("align", settings.align.as_str().to_owned()));
+        }
+
+        if let Some(region) = settings.region {
+            items.push(("region", region));
+        }
+
+        items
+    }
+}
+
+impl fmt::Display for CueSettings {
+    /// Formats the effective settings as they would appear after the timings
+    /// of a cue.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (name, value)) in self.items().into_iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" ")?;
+            }
+
+            write!(f, "{name}:{value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A percentage between 0 and 100, inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Default)]
+pub struct Percentage(f64);
+
+impl Percentage {
+    pub const ZERO: Percentage = Percentage(0.0);
+    pub const HUNDRED: Percentage = Percentage(100.0);
+
+    /// Returns `None` if the value is not between 0 and 100.
+    pub fn new(value: f64) -> Option<Self> {
+        (0.0..=100.0).contains(&value).then_some(Percentage(value))
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    /// Parses a WebVTT percentage, like `50%` or `33.3%`.
+    pub(crate) fn parse(input: &str) -> Option<Self> {
+        let number = input.strip_suffix('%')?;
+        let (int, frac) = number.split_once('.').unwrap_or((number, "0"));
+
+        if int.is_empty()
+            || frac.is_empty()
+            || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+
+        Percentage::new(number.parse().ok()?)
+    }
+}
+
+// percentages are never NaN, so they can be compared and hashed by value
+impl Eq for Percentage {}
+
+impl std::hash::Hash for Percentage {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl fmt::Display for Percentage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Line {
+    /// auto (the cue is placed after any other showing cues, at the end of
+    /// the video viewport)
+    #[default]
+    Auto,
+    /// a line number (the cue is snapped to lines, counting from the start of
+    /// the video viewport if positive and from the end if negative)
+    Number(i64),
+    /// a percentage of the video viewport (the cue is not snapped to lines)
+    Percentage(Percentage),
+}
+
+impl Line {
+    /// Returns whether the cue's box is positioned at whole lines.
+    pub fn snap_to_lines(&self) -> bool {
+        !matches!(self, Line::Percentage(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum LineAlign {
+    /// the cue box’s top side (for horizontal cues), left side (for vertical
+    /// growing right), or right side (for vertical growing left) is aligned at
+    /// the line
+    #[default]
+    Start,
+    /// the cue box is centered at the line
+    Center,
+    /// the cue box’s bottom side (for horizontal cues), right side (for
+    /// vertical growing right), or left side (for vertical growing left) is
+    /// aligned at the line
+    End,
+}
+
+impl LineAlign {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            LineAlign::Start => "start",
+            LineAlign::Center => "center",
+            LineAlign::End => "end",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum PositionAlign {
+    /// the alignment follows the cue’s text alignment
+    #[default]
+    Auto,
+    /// the cue box’s left side (for horizontal cues) or top side (otherwise)
+    /// is aligned at the position
+    LineLeft,
+    /// the cue box is centered at the position
+    Center,
+    /// the cue box’s right side (for horizontal cues) or bottom side
+    /// (otherwise) is aligned at the position
+    LineRight,
+}
+
+impl PositionAlign {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            PositionAlign::Auto => "auto",
+            PositionAlign::LineLeft => "line-left",
+            PositionAlign::Center => "center",
+            PositionAlign::LineRight => "line-right",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Align {
+    /// the text of each line is aligned towards the start side of the box,
+    /// depending on its base direction
+    Start,
+    /// the text of each line is centered in the box
+    #[default]
+    Center,
+    /// the text of each line is aligned towards the end side of the box,
+    /// depending on its base direction
+    End,
+    /// the text of each line is aligned to the box’s left side (for
+    /// horizontal cues) or top side (otherwise)
+    Left,
+    /// the text of each line is aligned to the box’s right side (for
+    /// horizontal cues) or bottom side (otherwise)
+    Right,
+}
+
+impl Align {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Align::Start => "start",
+            Align::Center => "center",
+            Align::End => "end",
+            Align::Left => "left",
+            Align::Right => "right",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum WritingDirection {
+    /// horizontal (a line extends horizontally and is offset vertically from
+    /// the video viewport’s top edge, with consecutive lines displayed below
+    /// each other)
+    #[default]
+    Horizontal,
+    /// vertical growing left (a line extends vertically and is offset
+    /// horizontally from the video viewport’s right edge, with consecutive
+    /// lines displayed to the left of each other)
+    VerticalLeft,
+    /// vertical growing right (a line extends vertically and is offset
+    /// horizontally from the video viewport’s left edge, with consecutive lines
+    /// displayed to the right of each other)
+    VerticalRight,
+}
+
+/// Parses the settings that follow the timings of a cue, per the spec's
+/// algorithm: settings are separated by whitespace, invalid settings are
+/// ignored, and when a setting appears more than once the last valid value
+/// wins. The result is canonicalized.
+pub(crate) fn parse_settings(input: &str) -> CueSettings {
+    let mut settings = CueSettings::default();
+
+    for setting in input.split_ascii_whitespace() {
+        let Some((name, value)) = setting.split_once(':') else {
+            continue;
+        };
+
+        if name.is_empty() || value.is_empty() {
+            continue;
+        }
+
+        match name {
+            "region" => settings.region = Some(value.to_owned()),
+            "vertical" => match value {
+                "rl" => settings.writing_direction = WritingDirection::VerticalLeft,
+                "lr" => settings.writing_direction = WritingDirection::VerticalRight,
+                _ => {}
+            },
+            "line" => {
+                if let Some((line, line_align)) = parse_line(value) {
+                    settings.line = line;
+                    settings.line_align = line_align;
+                }
+            }
+            "position" => {
+                if let Some((position, position_align)) = parse_position(value) {
+                    settings.position = Some(position);
+                    settings.position_align = position_align;
+                }
+            }
+            "size" => {
+                if let Some(size) = Percentage::parse(value) {
+                    settings.size = size;
+                }
+            }
+            "align" => match value {
+                "start" => settings.align = Align::Start,
+                "center" => settings.align = Align::Center,
+                "end" => settings.align = Align::End,
+                "left" => settings.align = Align::Left,
+                "right" => settings.align = Align::Right,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    settings.canonicalize()
+}
+
+fn parse_line(value: &str) -> Option<(Line, LineAlign)> {
+    let (line, align) = match value.split_once(',') {
+        Some((line, align)) => (line, Some(align)),
+        None => (value, None),
+    };
+
+    let line_align = match align {
+        None | Some("start") => LineAlign::Start,
+        Some("center") => LineAlign::Center,
+        Some("end") => LineAlign::End,
+        Some(_) => return None,
+    };
+
+    if !line.bytes().any(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    if line.ends_with('%') {
+        return Some((Line::Percentage(Percentage::parse(line)?), line_align));
+    }
+
+    // a line number is an optionally negative decimal number, which is
+    // rounded to a whole line
+    let unsigned = line.strip_prefix('-').unwrap_or(line);
+    let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+
+    if int.is_empty()
+        || frac.is_empty()
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let number: f64 = line.parse().ok()?;
+
+    Some((Line::Number((number + 0.5).floor() as i64), line_align))
+}
+
+fn parse_position(value: &str) -> Option<(Percentage, PositionAlign)> {
+    let (position, align) = match value.split_once(',') {
+        Some((position, align)) => (position, Some(align)),
+        None => (value, None),
+    };
+
+    let position_align = match align {
+        None => PositionAlign::Auto,
+        Some("line-left") => PositionAlign::LineLeft,
+        Some("center") => PositionAlign::Center,
+        Some("line-right") => PositionAlign::LineRight,
+        Some(_) => return None,
+    };
+
+    Some((Percentage::parse(position)?, position_align))
+}
@@ -0,0 +1,158 @@
+//! Moving cues in time.
+
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::{text::Node, Cue, File, Timestamp};
+
+/// A signed amount of time to move timestamps by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shift {
+    Later(Duration),
+    Earlier(Duration),
+}
+
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShiftError {
+    #[error("cue {cue_index} would have a negative time")]
+    Negative { cue_index: usize },
+
+    #[error("cue {cue_index} would have a time too large to represent")]
+    Overflow { cue_index: usize },
+}
+
+impl ShiftError {
+    /// The error of shifting the cue at `cue_index` out of range.
+    fn new(shift: Shift, cue_index: usize) -> Self {
+        match shift {
+            Shift::Later(_) => ShiftError::Overflow { cue_index },
+            Shift::Earlier(_) => ShiftError::Negative { cue_index },
+        }
+    }
+}
+
+impl Timestamp {
+    /// Shifts the timestamp, returning `None` if it would become negative or
+    /// overflow.
+    pub fn checked_shift(self, shift: Shift) -> Option<Timestamp> {
+        match shift {
+            Shift::Later(by) => self.0.checked_add(by).map(Timestamp),
+            Shift::Earlier(by) => self.0.checked_sub(by).map(Timestamp),
+        }
+    }
+
+    /// Shifts the timestamp, clamping it to zero or the largest duration.
+    pub fn saturating_shift(self, shift: Shift) -> Timestamp {
+        match shift {
+            Shift::Later(by) => Timestamp(self.0.saturating_add(by)),
+            Shift::Earlier(by) => Timestamp(self.0.saturating_sub(by)),
+        }
+    }
+}
+
+impl Cue {
+    /// Shifts the timings of this cue, and any timestamps in its text. On
+    /// error, the cue is left unchanged. As the cue does not know where it is
+    /// in a file, the error names it as cue 0.
+    pub fn checked_shift(&mut self, shift: Shift) -> Result<(), ShiftError> {
+        let error = ShiftError::new(shift, 0);
+        let start = Timestamp(self.start).checked_shift(shift).ok_or(error)?;
+        let end = Timestamp(self.end).checked_shift(shift).ok_or(error)?;
+
+        let mut text = None;
+
+        if self.text.contains('<') {
+            let mut nodes = self.nodes();
+            let mut ok = true;
+            let mut changed = false;
+
+            map_timestamps(
+                &mut nodes,
+                &mut |time| match Timestamp(*time).checked_shift(shift) {
+                    Some(shifted) => {
+                        changed |= shifted.0 != *time;
+                        *time = shifted.0;
+                    }
+                    None => ok = false,
+                },
+            );
+
+            if !ok {
+                return Err(error);
+            }
+
+            // the text is only written again when it has to be, as writing
+            // nodes drops unknown tags and decodes character references
+            if changed {
+                text = Some(self.format_nodes(&nodes));
+            }
+        }
+
+        self.start = start.0;
+        self.end = end.0;
+
+        if let Some(text) = text {
+            self.text = text;
+        }
+
+        Ok(())
+    }
+
+    /// Shifts the timings of this cue, and any timestamps in its text,
+    /// clamping them to zero or the largest duration.
+    pub fn saturating_shift(&mut self, shift: Shift) {
+        self.start = Timestamp(self.start).saturating_shift(shift).0;
+        self.end = Timestamp(self.end).saturating_shift(shift).0;
+
+        if self.text.contains('<') {
+            let mut nodes = self.nodes();
+            let mut changed = false;
+
+            map_timestamps(&mut nodes, &mut |time| {
+                let shifted = Timestamp(*time).saturating_shift(shift).0;
+                changed |= shifted != *time;
+                *time = shifted;
+            });
+
+            if changed {
+                self.set_nodes(&nodes);
+            }
+        }
+    }
+}
+
+fn map_timestamps(nodes: &mut [Node], f: &mut impl FnMut(&mut Duration)) {
+    for node in nodes {
+        match node {
+            Node::Timestamp(time) => f(time),
+            Node::Span(span) => map_timestamps(&mut span.children, f),
+            Node::Text(_) => {}
+        }
+    }
+}
+
+impl File {
+    /// Shifts every cue in the file. If any cue would end up with a negative
+    /// time or overflow, returns an error naming the first such cue and leaves
+    /// the file unchanged.
+    pub fn shift(&mut self, shift: Shift) -> Result<(), ShiftError> {
+        let mut shifted = self.clone();
+
+        for (cue_index, cue) in shifted.cues_mut().enumerate() {
+            cue.checked_shift(shift)
+                .map_err(|_| ShiftError::new(shift, cue_index))?;
+        }
+
+        *self = shifted;
+        Ok(())
+    }
+
+    /// Shifts every cue in the file, clamping times to zero or the largest
+    /// duration.
+    pub fn saturating_shift(&mut self, shift: Shift) {
+        for cue in self.cues_mut() {
+            cue.saturating_shift(shift);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Replaying a file as a live caption stream would deliver it, late and
+//! unevenly, to test how a player copes with cues that arrive after their
+//! start.
+
+use std::time::Duration;
+
+use crate::{Cue, File};
+
+/// The state of the jitter generator unless a seed is given.
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// A cue of a [`Live`] stream and the media time it arrives at.
+#[derive(Clone, Debug)]
+pub struct LiveCue<'a> {
+    /// Index of the cue among the cues of the file.
+    pub cue_index: usize,
+    /// The time the cue is delivered, on the same timeline as its timings.
+    /// It is never earlier than the arrival of the cue before it.
+    pub at: Duration,
+    pub cue: &'a Cue,
+}
+
+/// The cues of a file in the order a live encoder would send them, each
+/// delayed by a latency and a random jitter, and held back further when the
+/// encoder's rate limit is used up.
+///
+/// The rate limit is a token bucket: the encoder can send `burst` cues at
+/// once, then one more every `1 / rate` seconds. The jitter comes from a
+/// generator seeded with [`Live::with_seed`], so a stream is the same every
+/// time it is replayed with the same seed.
+#[derive(Clone, Debug)]
+pub struct Live<'a> {
+    cues: Vec<&'a Cue>,
+    next: usize,
+    latency: Duration,
+    jitter: Duration,
+    state: u64,
+    /// The cues sent per second, and the most that can be sent at once.
+    rate: Option<(f64, f64)>,
+    tokens: f64,
+    /// The arrival of the last cue delivered.
+    last: Duration,
+}
+
+/// Returns a stream of the cues of `file` that arrive `latency` after their
+/// start, plus up to `jitter` more, with no rate limit.
+pub fn live(file: &File, latency: Duration, jitter: Duration) -> Live<'_> {
+    Live {
+        cues: file.cues().collect(),
+        next: 0,
+        latency,
+        jitter,
+        state: DEFAULT_SEED,
+        rate: None,
+        tokens: 0.0,
+        last: Duration::ZERO,
+    }
+}
+
+impl Live<'_> {
+    /// Sets the seed of the jitter, so that streams with different seeds
+    /// arrive at different times.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        // xorshift is stuck at zero, so zero is mapped to another state
+        self.state = if seed == 0 { DEFAULT_SEED } else { seed };
+        self
+    }
+
+    /// Limits the stream to `cues_per_second` cues a second, after a burst of
+    /// up to `burst` cues.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cues_per_second` is not above zero or `burst` is zero.
+    pub fn with_rate(mut self, cues_per_second: f64, burst: usize) -> Self {
+        assert!(cues_per_second > 0.0, "a rate limit needs a positive rate");
+        assert!(burst > 0, "a rate limit needs a burst of at least one cue");
+
+        self.rate = Some((cues_per_second, burst as f64));
+        self.tokens = burst as f64;
+        self
+    }
+
+    /// Returns a jitter between zero and `self.jitter`, from a xorshift
+    /// generator.
+    fn next_jitter(&mut self) -> Duration {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        let fraction = (self.state >> 11) as f64 / (1u64 << 53) as f64;
+        self.jitter.mul_f64(fraction)
+    }
+}
+
+impl<'a> Iterator for Live<'a> {
+    type Item = LiveCue<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cue_index = self.next;
+        let cue = *self.cues.get(cue_index)?;
+        self.next += 1;
+
+        let mut at = (cue.start + self.latency + self.next_jitter()).max(self.last);
+
+        if let Some((rate, burst)) = self.rate {
+            let refilled = (at - self.last).as_secs_f64() * rate;
+            self.tokens = (self.tokens + refilled).min(burst);
+
+            if self.tokens < 1.0 {
+                at += Duration::from_secs_f64((1.0 - self.tokens) / rate);
+                self.tokens = 1.0;
+            }
+
+            self.tokens -= 1.0;
+        }
+
+        self.last = at;
+        Some(LiveCue { cue_index, at, cue })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.cues.len() - self.next;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Live<'_> {}
@@ -0,0 +1,112 @@
+//! Aligning cue times to frame boundaries and shot changes.
+
+use std::time::Duration;
+
+use crate::File;
+
+/// How [`File::snap_to_frames`] handles cues that snapping would make overlap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SnapPolicy {
+    /// Round every time to the nearest frame, extending cues that would become
+    /// empty to last one frame, even if that makes them overlap the next cue.
+    #[default]
+    Nearest,
+    /// Like [`SnapPolicy::Nearest`], but cues that did not overlap before
+    /// snapping never overlap after it: a cue is not extended past the start
+    /// of the next cue, even if that leaves it empty.
+    NoOverlap,
+}
+
+/// Rounds `time` to the nearest boundary of a frame at `fps` frames per
+/// second, to millisecond precision.
+fn snap(time: Duration, fps: f64) -> Duration {
+    let frame = (time.as_secs_f64() * fps).round();
+    Duration::from_millis((frame * 1000.0 / fps).round() as u64)
+}
+
+impl File {
+    /// Rounds the start and end times of every cue to the nearest frame
+    /// boundary at `fps` frames per second (e.g. `30000.0 / 1001.0` for NTSC).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fps` is not positive and finite.
+    pub fn snap_to_frames(&mut self, fps: f64, policy: SnapPolicy) {
+        assert!(fps > 0.0 && fps.is_finite(), "invalid frame rate {fps}");
+
+        let frame = Duration::from_secs_f64(1.0 / fps);
+
+        let original: Vec<_> = self.cues().map(|cue| (cue.start, cue.end)).collect();
+        let mut order: Vec<_> = (0..original.len()).collect();
+        order.sort_by_key(|&idx| original[idx].0);
+
+        let mut snapped: Vec<_> = original
+            .iter()
+            .map(|&(start, end)| {
+                let snapped_start = snap(start, fps);
+                let snapped_end = snap(end, fps).max(snapped_start);
+
+                if snapped_end == snapped_start && end > start {
+                    (snapped_start, snap(snapped_start + frame, fps))
+                } else {
+                    (snapped_start, snapped_end)
+                }
+            })
+            .collect();
+
+        if policy == SnapPolicy::NoOverlap {
+            for pair in order.windows(2) {
+                let (current, next) = (pair[0], pair[1]);
+
+                if original[current].1 <= original[next].0 && snapped[current].1 > snapped[next].0 {
+                    snapped[current].1 = snapped[next].0.max(snapped[current].0);
+                }
+            }
+        }
+
+        for (cue, (start, end)) in self.cues_mut().zip(snapped) {
+            cue.start = start;
+            cue.end = end;
+        }
+    }
+
+    /// Moves cue start and end times that are within `window` of a shot
+    /// change onto the nearest one, so that cues appear and disappear with
+    /// cuts. `shot_changes` need not be sorted. A time is left alone if moving
+    /// it would leave its cue empty. Returns the number of times moved.
+    pub fn snap_to_shot_changes(&mut self, shot_changes: &[Duration], window: Duration) -> usize {
+        let mut shot_changes = shot_changes.to_vec();
+        shot_changes.sort();
+
+        let nearest = |time: Duration| {
+            let idx = shot_changes.partition_point(|&change| change < time);
+
+            [idx.checked_sub(1), Some(idx)]
+                .into_iter()
+                .flatten()
+                .filter_map(|idx| shot_changes.get(idx).copied())
+                .filter(|&change| change.abs_diff(time) <= window)
+                .min_by_key(|&change| change.abs_diff(time))
+        };
+
+        let mut moved = 0;
+
+        for cue in self.cues_mut() {
+            if let Some(start) = nearest(cue.start) {
+                if start != cue.start && start < cue.end {
+                    cue.start = start;
+                    moved += 1;
+                }
+            }
+
+            if let Some(end) = nearest(cue.end) {
+                if end != cue.end && end > cue.start {
+                    cue.end = end;
+                    moved += 1;
+                }
+            }
+        }
+
+        moved
+    }
+}
@@ -0,0 +1,276 @@
+//! Fixing cues that are too fast to read.
+
+use std::time::Duration;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    text::{self, Node},
+    Block, Cue, File, LengthMetric,
+};
+
+/// A way for [`File::fix_reading_speed`] to slow a cue down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpeedFix {
+    /// Move the end of the cue later, up to the start of the next cue.
+    Extend,
+    /// Merge the cue with the next one, if the result is slow enough.
+    Merge,
+    /// Cut the text of the cue at a word boundary and end it with `…`.
+    Truncate,
+}
+
+impl Cue {
+    /// Returns the number of characters of text shown per second, not counting
+    /// markup or line breaks.
+    pub fn chars_per_second(&self) -> f64 {
+        let chars = text_length(&self.plain_text());
+        let seconds = self.end.saturating_sub(self.start).as_secs_f64();
+
+        if chars == 0 {
+            0.0
+        } else if seconds == 0.0 {
+            f64::INFINITY
+        } else {
+            chars as f64 / seconds
+        }
+    }
+
+    /// Returns the most characters of text, not counting markup or line
+    /// breaks, that can be read in this cue's duration at `max_cps`
+    /// characters per second.
+    pub fn text_budget(&self, max_cps: f64) -> usize {
+        let seconds = self.end.saturating_sub(self.start).as_secs_f64();
+        (max_cps * seconds).floor() as usize
+    }
+}
+
+/// How the text of a cue compares to what its duration allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextBudget {
+    /// Index of the cue among the cues of the file.
+    pub cue_index: usize,
+    /// The number of characters of text, not counting markup or line breaks.
+    pub length: usize,
+    /// The most characters that fit, as in [`Cue::text_budget`].
+    pub budget: usize,
+}
+
+impl TextBudget {
+    pub fn fits(&self) -> bool {
+        self.length <= self.budget
+    }
+
+    /// The number of characters that can still be added, or if negative, that
+    /// need to be cut.
+    pub fn remaining(&self) -> i64 {
+        self.budget as i64 - self.length as i64
+    }
+}
+
+impl File {
+    /// Brings every cue down to at most `max_cps` characters per second, trying
+    /// the fixes in `fixes` in order until one works. [`SpeedFix::Extend`] is
+    /// applied even if it only helps partly. Returns the indices of the cues
+    /// that are still too fast.
+    ///
+    /// Nothing is fixed if `max_cps` is not above zero or is NaN, so every cue
+    /// with text is returned.
+    pub fn fix_reading_speed(&mut self, max_cps: f64, fixes: &[SpeedFix]) -> Vec<usize> {
+        let fixes = if max_cps > 0.0 { fixes } else { &[] };
+
+        for idx in 0..self.blocks.len() {
+            for &fix in fixes {
+                match self.blocks.get(idx).and_then(Block::as_cue) {
+                    Some(cue) if !cue_fits(cue, max_cps) => self.apply_speed_fix(idx, fix, max_cps),
+                    _ => break,
+                }
+            }
+        }
+
+        self.cues()
+            .enumerate()
+            .filter(|(_, cue)| !cue_fits(cue, max_cps))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Returns the text budget of every cue at `max_cps` characters per
+    /// second, for fitting new text, like a translation, into the existing
+    /// timings. Metadata cues are left out.
+    pub fn text_budgets(&self, max_cps: f64) -> Vec<TextBudget> {
+        self.cues()
+            .enumerate()
+            .filter(|(_, cue)| !cue.is_metadata())
+            .map(|(cue_index, cue)| TextBudget {
+                cue_index,
+                length: text_length(&cue.plain_text()),
+                budget: cue.text_budget(max_cps),
+            })
+            .collect()
+    }
+
+    /// Applies a fix to the cue in the block at `idx`.
+    fn apply_speed_fix(&mut self, idx: usize, fix: SpeedFix, max_cps: f64) {
+        let next = (idx + 1..self.blocks.len()).find(|&next| self.blocks[next].as_cue().is_some());
+        let next_start = next
+            .and_then(|next| self.blocks[next].as_cue())
+            .map(|cue| cue.start);
+
+        let Some(Block::Cue(cue)) = self.blocks.get(idx) else {
+            return;
+        };
+
+        match fix {
+            SpeedFix::Extend => {
+                let needed = text_length(&cue.plain_text()) as f64 / max_cps;
+
+                // a cue that would end past the largest time ends at the next
+                let end = Duration::try_from_secs_f64(needed)
+                    .ok()
+                    .and_then(|needed| cue.start.checked_add(needed));
+
+                let end = match (end, next_start) {
+                    (Some(end), Some(next_start)) => end.min(next_start),
+                    (Some(end), None) => end,
+                    (None, Some(next_start)) => next_start,
+                    (None, None) => return,
+                };
+
+                let end = end.max(cue.end);
+                self.blocks[idx].as_cue_mut().unwrap().end = end;
+            }
+            SpeedFix::Merge => {
+                let Some(next) = next else {
+                    return;
+                };
+
+                let next_cue = self.blocks[next].as_cue().unwrap();
+
+                let merged = Cue {
+                    end: cue.end.max(next_cue.end),
+                    text: format!("{}\n{}", cue.text, next_cue.text),
+                    ..cue.clone()
+                };
+
+                if cue_fits(&merged, max_cps) {
+                    let Block::Cue(next_cue) = self.blocks.remove(next) else {
+                        unreachable!();
+                    };
+
+                    let mut merged = merged;
+                    merged.notes.extend(next_cue.notes);
+                    self.blocks[idx] = Block::Cue(merged);
+                }
+            }
+            SpeedFix::Truncate => {
+                let budget = cue.text_budget(max_cps);
+
+                // leave room for the ellipsis, and keep at least one character
+                if budget < 2 || cue.is_metadata() {
+                    return;
+                }
+
+                let mut nodes = cue.nodes();
+                truncate(&mut nodes, budget - 1);
+                push_ellipsis(&mut nodes);
+
+                self.blocks[idx].as_cue_mut().unwrap().set_nodes(&nodes);
+            }
+        }
+    }
+}
+
+fn cue_fits(cue: &Cue, max_cps: f64) -> bool {
+    cue.chars_per_second() <= max_cps
+}
+
+fn text_length(text: &str) -> usize {
+    text.split('\n')
+        .map(|line| LengthMetric::Graphemes.measure(line))
+        .sum()
+}
+
+/// Cuts a tree of nodes down to at most `budget` characters, not counting line
+/// breaks, ending at a word boundary where there is one.
+fn truncate(nodes: &mut Vec<Node>, budget: usize) {
+    let plain = text::plain(nodes);
+    let graphemes: Vec<&str> = plain.graphemes(true).collect();
+    let is_space = |grapheme: &&str| grapheme.chars().all(char::is_whitespace);
+
+    // the number of characters to keep, counting line breaks
+    let mut keep = 0;
+    let mut counted = 0;
+
+    while keep < graphemes.len() && counted < budget {
+        if graphemes[keep] != "\n" {
+            counted += 1;
+        }
+
+        keep += 1;
+    }
+
+    if keep < graphemes.len() && !is_space(&graphemes[keep]) {
+        if let Some(space) = graphemes[..keep].iter().rposition(is_space) {
+            keep = space;
+        }
+    }
+
+    while keep > 0 && is_space(&graphemes[keep - 1]) {
+        keep -= 1;
+    }
+
+    let mut chars = graphemes[..keep]
+        .iter()
+        .map(|grapheme| grapheme.chars().count())
+        .sum();
+
+    truncate_chars(nodes, &mut chars);
+}
+
+/// Keeps the first `remaining` characters of text in a tree of nodes, removing
+/// everything after them.
+fn truncate_chars(nodes: &mut Vec<Node>, remaining: &mut usize) {
+    let mut idx = 0;
+
+    while idx < nodes.len() {
+        if *remaining == 0 {
+            nodes.truncate(idx);
+            return;
+        }
+
+        match &mut nodes[idx] {
+            Node::Text(text) => {
+                let length = text.chars().count();
+
+                if length <= *remaining {
+                    *remaining -= length;
+                } else {
+                    *text = text.chars().take(*remaining).collect();
+                    *remaining = 0;
+                }
+            }
+            Node::Span(span) => {
+                truncate_chars(&mut span.children, remaining);
+
+                if span.children.is_empty() {
+                    nodes.remove(idx);
+                    continue;
+                }
+            }
+            Node::Timestamp(_) => {}
+        }
+
+        idx += 1;
+    }
+}
+
+/// Appends an ellipsis to the last text in a tree of nodes, inside any spans
+/// around it.
+fn push_ellipsis(nodes: &mut Vec<Node>) {
+    match nodes.last_mut() {
+        Some(Node::Text(text)) => text.push('…'),
+        Some(Node::Span(span)) => push_ellipsis(&mut span.children),
+        _ => nodes.push(Node::Text("…".to_string())),
+    }
+}
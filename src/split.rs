@@ -0,0 +1,104 @@
+//! Splitting cues with too many lines of text.
+
+use std::time::Duration;
+
+use crate::{text, Block, Cue, File, LengthMetric};
+
+impl Cue {
+    /// Splits this cue into consecutive cues of at most `max_lines` lines
+    /// each, dividing its time between them in proportion to the length of
+    /// their text. Markup that crosses a split is repeated in each part. Only
+    /// the first part keeps the cue's id and notes.
+    pub fn split_lines(&self, max_lines: usize) -> Vec<Cue> {
+        let max_lines = max_lines.max(1);
+
+        if self.text.lines().count() <= max_lines {
+            return vec![self.clone()];
+        }
+
+        let lines = text::split_lines(self.nodes());
+        let chunks: Vec<_> = lines.chunks(max_lines).collect();
+
+        let weights: Vec<u128> = chunks
+            .iter()
+            .map(|chunk| {
+                let length: usize = chunk
+                    .iter()
+                    .map(|line| LengthMetric::Graphemes.measure(&text::plain(line)))
+                    .sum();
+                length.max(1) as u128
+            })
+            .collect();
+
+        let total: u128 = weights.iter().sum();
+        let duration = self.end.saturating_sub(self.start).as_millis();
+
+        let mut parts = vec![];
+        let mut elapsed = 0;
+        let mut start = self.start;
+
+        for (idx, (chunk, weight)) in chunks.iter().zip(&weights).enumerate() {
+            elapsed += weight;
+
+            let end = if idx + 1 == chunks.len() {
+                self.end
+            } else {
+                self.start + Duration::from_millis((duration * elapsed / total) as u64)
+            };
+
+            let text = chunk
+                .iter()
+                .map(|line| self.format_nodes(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            parts.push(Cue {
+                start,
+                end,
+                id: if idx == 0 {
+                    self.id.clone()
+                } else {
+                    String::new()
+                },
+                text,
+                settings: self.settings.clone(),
+                raw_settings: self.raw_settings.clone(),
+                offset: self.offset,
+                source_order: self.source_order,
+                extensions: self.extensions.clone(),
+                payload: self.payload,
+                notes: if idx == 0 { self.notes.clone() } else { vec![] },
+            });
+
+            start = end;
+        }
+
+        parts
+    }
+}
+
+impl File {
+    /// Splits every cue with more than `max_lines` lines of text into
+    /// consecutive cues, as with [`Cue::split_lines`]. Returns the number of
+    /// cues that were split.
+    pub fn split_long_cues(&mut self, max_lines: usize) -> usize {
+        let mut split = 0;
+
+        for block in std::mem::take(&mut self.blocks) {
+            match block {
+                Block::Cue(cue) => {
+                    let parts = cue.split_lines(max_lines);
+
+                    if parts.len() > 1 {
+                        split += 1;
+                    }
+
+                    self.blocks.extend(parts.into_iter().map(Block::Cue));
+                }
+                block => self.blocks.push(block),
+            }
+        }
+
+        split
+    }
+}
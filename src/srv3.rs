@@ -0,0 +1,376 @@
+//! Import of YouTube's srv3 caption format, as downloaded by tools like
+//! yt-dlp:
+//!
+//! ```xml
+//! <timedtext format="3">
+//!   <head>
+//!     <pen id="1" b="1" fc="#FFFF00"/>
+//!     <wp id="1" ap="0" ah="10" av="10"/>
+//!   </head>
+//!   <body>
+//!     <p t="1000" d="2000" wp="1"><s>Hello</s><s t="500" p="1"> world</s></p>
+//!   </body>
+//! </timedtext>
+//! ```
+//!
+//! Times are in milliseconds. Each `<p>` becomes a cue, the offset of each
+//! word segment `<s>` becomes an inline timestamp, pens become `<b>`, `<i>`,
+//! `<u>` and color classes, and window positions become cue settings.
+
+use std::{collections::HashMap, time::Duration};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::{
+    color::color_name,
+    text::{Node, Span, SpanKind},
+    Align, Block, ConvertError, Cue, CueSettings, File, Line, LineAlign, Percentage, PositionAlign,
+};
+
+/// The styling of a `<pen>`.
+#[derive(Default)]
+struct Pen {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    /// Color classes, like `yellow` and `bg_black`.
+    classes: Vec<String>,
+}
+
+/// A `<wp>` window position: an anchor point from 0 (top left) to 8 (bottom
+/// right), and the percentages it is placed at.
+struct WindowPosition {
+    anchor: u8,
+    horizontal: f64,
+    vertical: f64,
+}
+
+/// A run of text in a `<p>`, with the attributes of its `<s>` if it is in one.
+struct Segment {
+    offset: Option<String>,
+    pen: Option<String>,
+    text: String,
+}
+
+struct Paragraph {
+    time: Option<String>,
+    duration: Option<String>,
+    pen: Option<String>,
+    position: Option<String>,
+    style: Option<String>,
+    segments: Vec<Segment>,
+}
+
+#[derive(Default)]
+struct Document {
+    pens: HashMap<String, Pen>,
+    positions: HashMap<String, WindowPosition>,
+    /// The text alignment of each `<ws>` window style.
+    styles: HashMap<String, Align>,
+    paragraphs: Vec<Paragraph>,
+}
+
+fn attribute(tag: &BytesStart, name: &str) -> Result<Option<String>, quick_xml::Error> {
+    Ok(match tag.try_get_attribute(name)? {
+        Some(attr) => Some(attr.unescape_value()?.into_owned()),
+        None => None,
+    })
+}
+
+fn number<T: std::str::FromStr>(
+    tag: &BytesStart,
+    name: &str,
+) -> Result<Option<T>, quick_xml::Error> {
+    Ok(attribute(tag, name)?.and_then(|value| value.trim().parse().ok()))
+}
+
+fn read_pen(tag: &BytesStart) -> Result<Pen, quick_xml::Error> {
+    let flag = |name| -> Result<bool, quick_xml::Error> {
+        Ok(attribute(tag, name)?.is_some_and(|value| value == "1"))
+    };
+
+    let mut classes = vec![];
+
+    for (name, prefix) in [("fc", ""), ("bc", "bg_")] {
+        if let Some(color) = attribute(tag, name)?.as_deref().and_then(color_name) {
+            classes.push(format!("{prefix}{color}"));
+        }
+    }
+
+    Ok(Pen {
+        bold: flag("b")?,
+        italic: flag("i")?,
+        underline: flag("u")?,
+        classes,
+    })
+}
+
+fn read_document(xml: &str) -> Result<Document, quick_xml::Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut document = Document::default();
+
+    let mut paragraph: Option<Paragraph> = None;
+    let mut in_segment = false;
+
+    loop {
+        let event = reader.read_event()?;
+        let is_empty = matches!(event, Event::Empty(_));
+
+        match event {
+            Event::Start(tag) | Event::Empty(tag) => match tag.name().as_ref() {
+                b"pen" => {
+                    if let Some(id) = attribute(&tag, "id")? {
+                        document.pens.insert(id, read_pen(&tag)?);
+                    }
+                }
+                b"wp" => {
+                    if let Some(id) = attribute(&tag, "id")? {
+                        let position = WindowPosition {
+                            anchor: number(&tag, "ap")?.filter(|&ap| ap <= 8).unwrap_or(7),
+                            horizontal: number(&tag, "ah")?.unwrap_or(50.0),
+                            vertical: number(&tag, "av")?.unwrap_or(100.0),
+                        };
+                        document.positions.insert(id, position);
+                    }
+                }
+                b"ws" => {
+                    let align = match attribute(&tag, "ju")?.as_deref() {
+                        Some("0") => Align::Left,
+                        Some("1") => Align::Right,
+                        Some("2") => Align::Center,
+                        _ => continue,
+                    };
+
+                    if let Some(id) = attribute(&tag, "id")? {
+                        document.styles.insert(id, align);
+                    }
+                }
+                b"p" => {
+                    let new = Paragraph {
+                        time: attribute(&tag, "t")?,
+                        duration: attribute(&tag, "d")?,
+                        pen: attribute(&tag, "p")?,
+                        position: attribute(&tag, "wp")?,
+                        style: attribute(&tag, "ws")?,
+                        segments: vec![],
+                    };
+
+                    if is_empty {
+                        document.paragraphs.push(new);
+                    } else {
+                        paragraph = Some(new);
+                    }
+                }
+                b"s" => {
+                    if let Some(paragraph) = &mut paragraph {
+                        paragraph.segments.push(Segment {
+                            offset: attribute(&tag, "t")?,
+                            pen: attribute(&tag, "p")?,
+                            text: String::new(),
+                        });
+                        in_segment = !is_empty;
+                    }
+                }
+                b"br" => {
+                    if let Some(paragraph) = &mut paragraph {
+                        push_text(paragraph, in_segment, "\n");
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let Some(paragraph) = &mut paragraph {
+                    push_text(paragraph, in_segment, &text.unescape()?);
+                }
+            }
+            Event::CData(text) => {
+                if let Some(paragraph) = &mut paragraph {
+                    push_text(paragraph, in_segment, &String::from_utf8_lossy(&text));
+                }
+            }
+            Event::End(tag) => match tag.name().as_ref() {
+                b"s" => in_segment = false,
+                b"p" => document.paragraphs.extend(paragraph.take()),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(document)
+}
+
+/// Adds text to the open segment, or to a new segment outside any `<s>`.
+fn push_text(paragraph: &mut Paragraph, in_segment: bool, text: &str) {
+    match paragraph.segments.last_mut() {
+        Some(segment) if in_segment || (segment.offset.is_none() && segment.pen.is_none()) => {
+            segment.text.push_str(text)
+        }
+        _ => paragraph.segments.push(Segment {
+            offset: None,
+            pen: None,
+            text: text.to_owned(),
+        }),
+    }
+}
+
+fn styled(pen: Option<&Pen>, text: &str) -> Node {
+    let mut node = Node::Text(text.to_owned());
+
+    let Some(pen) = pen else {
+        return node;
+    };
+
+    let wrap = |kind, classes, node| {
+        Node::Span(Span {
+            kind,
+            classes,
+            children: vec![node],
+        })
+    };
+
+    for (on, kind) in [
+        (pen.underline, SpanKind::Underline),
+        (pen.italic, SpanKind::Italic),
+        (pen.bold, SpanKind::Bold),
+    ] {
+        if on {
+            node = wrap(kind, vec![], node);
+        }
+    }
+
+    if !pen.classes.is_empty() {
+        node = wrap(SpanKind::Class, pen.classes.clone(), node);
+    }
+
+    node
+}
+
+fn settings(position: &WindowPosition, align: Option<Align>) -> CueSettings {
+    let mut settings = CueSettings {
+        line: Percentage::new(position.vertical).map_or(Line::Auto, Line::Percentage),
+        line_align: [LineAlign::Start, LineAlign::Center, LineAlign::End]
+            [usize::from(position.anchor / 3)],
+        position: Percentage::new(position.horizontal),
+        position_align: [
+            PositionAlign::LineLeft,
+            PositionAlign::Center,
+            PositionAlign::LineRight,
+        ][usize::from(position.anchor % 3)],
+        ..Default::default()
+    };
+
+    if let Some(align) = align {
+        settings.align = align;
+    }
+
+    settings
+}
+
+impl File {
+    /// Imports captions in YouTube's srv3 XML format. Each paragraph becomes a
+    /// cue, with an inline timestamp for each timed word segment, `<b>`, `<i>`
+    /// and `<u>` tags for its pens, and color classes for pen colors that have
+    /// one of the spec's default classes. Paragraphs placed in a window
+    /// position get `line` and `position` settings. Paragraphs without text,
+    /// which auto-generated captions use to move windows, are skipped.
+    pub fn from_srv3(xml: &str) -> Result<File, ConvertError> {
+        let document = read_document(xml).map_err(|err| ConvertError::BadSrv3(err.to_string()))?;
+
+        let mut blocks = vec![];
+
+        for (idx, paragraph) in document.paragraphs.iter().enumerate() {
+            let bad = |what| ConvertError::BadSrv3(format!("paragraph {} has {what}", idx + 1));
+            let millis = |value: &Option<String>, what| match value {
+                Some(value) => value
+                    .trim()
+                    .parse()
+                    .map(Duration::from_millis)
+                    .map_err(|_| bad(what)),
+                None => Ok(Duration::ZERO),
+            };
+
+            if paragraph.time.is_none() {
+                return Err(bad("no start time"));
+            }
+
+            let start = millis(&paragraph.time, "a bad start time")?;
+            let end = start + millis(&paragraph.duration, "a bad duration")?;
+
+            if paragraph
+                .segments
+                .iter()
+                .all(|segment| segment.text.trim().is_empty())
+            {
+                continue;
+            }
+
+            let last = paragraph.segments.len() - 1;
+            let mut nodes = vec![];
+
+            for (segment_idx, segment) in paragraph.segments.iter().enumerate() {
+                let mut text = segment.text.as_str();
+
+                if segment_idx == 0 {
+                    text = text.trim_start();
+                }
+
+                if segment_idx == last {
+                    text = text.trim_end();
+                }
+
+                // whitespace before a word goes before its timestamp
+                let word_start = text.len() - text.trim_start().len();
+
+                if word_start > 0 {
+                    nodes.push(Node::Text(text[..word_start].to_owned()));
+                    text = &text[word_start..];
+                }
+
+                let offset = millis(&segment.offset, "a segment with a bad offset")?;
+
+                if !offset.is_zero() && !text.is_empty() {
+                    nodes.push(Node::Timestamp(start + offset));
+                }
+
+                if !text.is_empty() {
+                    let pen = segment.pen.as_ref().or(paragraph.pen.as_ref());
+                    nodes.push(styled(pen.and_then(|pen| document.pens.get(pen)), text));
+                }
+            }
+
+            let mut cue = Cue {
+                start,
+                end,
+                ..Default::default()
+            };
+
+            if let Some(position) = paragraph
+                .position
+                .as_ref()
+                .and_then(|id| document.positions.get(id))
+            {
+                let align = paragraph
+                    .style
+                    .as_ref()
+                    .and_then(|id| document.styles.get(id))
+                    .copied();
+                cue.settings = settings(position, align);
+            }
+
+            cue.set_nodes(&nodes);
+            blocks.push(Block::Cue(cue));
+        }
+
+        Ok(File {
+            description: None,
+            header_lines: vec![],
+            blocks,
+            format: Default::default(),
+        })
+    }
+}
@@ -0,0 +1,137 @@
+//! Incremental parsing of a file that is still being written, like the output
+//! of live transcription.
+
+use crate::{
+    parse_block, parse_magic, skip_blank_lines, Block, Cue, FileContext, Lines, ParseError,
+    ParseOptions,
+};
+
+/// Parses a WebVTT file as it grows, yielding each cue once its block is
+/// complete.
+///
+/// A block is only known to be complete once the blank line after it has
+/// arrived, so the last block is held back until more input comes or
+/// [`TailParser::finish`] is called.
+pub struct TailParser {
+    /// Input that has not been parsed yet, starting at a block boundary.
+    buffer: String,
+    /// Byte offset of the start of `buffer` in the whole input.
+    base: usize,
+    header_checked: bool,
+    /// How much of the buffer has been searched for the end of the header or
+    /// a block, so that a long line arriving in many pieces is not searched
+    /// again for each of them.
+    searched: usize,
+    file_ctx: FileContext,
+}
+
+impl TailParser {
+    pub fn new() -> Self {
+        Self::with_options(ParseOptions::default())
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        TailParser {
+            buffer: String::new(),
+            base: 0,
+            header_checked: false,
+            searched: 0,
+            file_ctx: FileContext::new(options),
+        }
+    }
+
+    /// Appends newly written input, returning the cues whose blocks it
+    /// completed. Fails if the header turns out to be invalid.
+    pub fn push(&mut self, input: &str) -> Result<Vec<Cue>, ParseError> {
+        self.buffer.push_str(input);
+
+        if !self.header_checked {
+            let Some(newline) = self.find_from_searched(|rest| rest.find('\n')) else {
+                return Ok(vec![]);
+            };
+
+            parse_magic(&mut Lines::new(&self.buffer[..newline], 0))?;
+
+            self.header_checked = true;
+            self.consume(newline + 1);
+            // only the header was searched for
+            self.searched = 0;
+        }
+
+        // a blank line is `\n` or `\r\n` after the newline that ends a line
+        let last_blank = |rest: &str| rest.rfind("\n\n").max(rest.rfind("\n\r\n"));
+
+        match self.find_from_searched(last_blank) {
+            Some(idx) => Ok(self.parse_until(idx + 1)),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Searches the part of the buffer not searched before with `find`,
+    /// including a match that starts at a newline, or a newline and a
+    /// carriage return, just before it, and marks the whole buffer as
+    /// searched.
+    fn find_from_searched(&mut self, find: impl Fn(&str) -> Option<usize>) -> Option<usize> {
+        let mut from = self.searched;
+
+        if self.buffer[..from].ends_with("\n\r") {
+            from -= 2;
+        } else if self.buffer[..from].ends_with('\n') {
+            from -= 1;
+        }
+
+        self.searched = self.buffer.len();
+        find(&self.buffer[from..]).map(|idx| from + idx)
+    }
+
+    /// Parses whatever input is left as if the file had ended, returning the
+    /// cues in it.
+    pub fn finish(&mut self) -> Result<Vec<Cue>, ParseError> {
+        if !self.header_checked {
+            parse_magic(&mut Lines::new(&self.buffer, 0))?;
+            self.header_checked = true;
+
+            let end = self
+                .buffer
+                .find('\n')
+                .map_or(self.buffer.len(), |newline| newline + 1);
+            self.consume(end);
+        }
+
+        Ok(self.parse_until(self.buffer.len()))
+    }
+
+    /// Parses the complete blocks in the first `end` bytes of the buffer.
+    fn parse_until(&mut self, end: usize) -> Vec<Cue> {
+        let mut cues = vec![];
+        let mut lines = Lines::new(&self.buffer[..end], self.base).peekable();
+
+        loop {
+            skip_blank_lines(&mut lines);
+
+            if lines.peek().is_none() {
+                break;
+            }
+
+            if let Some(Block::Cue(cue)) = parse_block(&mut lines, &mut self.file_ctx) {
+                cues.push(cue);
+            }
+        }
+
+        self.consume(end);
+        cues
+    }
+
+    /// Drops the first `len` bytes of the buffer.
+    fn consume(&mut self, len: usize) {
+        self.buffer.drain(..len);
+        self.base += len;
+        self.searched = self.searched.saturating_sub(len);
+    }
+}
+
+impl Default for TailParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
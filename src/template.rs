@@ -0,0 +1,117 @@
+//! Templates for the text of generated cues.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{text, CuePayload};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Part {
+    Literal(String),
+    Variable(String),
+}
+
+/// Cue text with `{name}` placeholders, like `<b>{home}</b> {score} {away}`.
+/// `{{` and `}}` stand for literal braces.
+///
+/// The text around the placeholders is cue text, so it can contain markup,
+/// while the values are escaped. For templates of metadata cues, whose
+/// payloads are not cue text, set `payload` to [`CuePayload::Metadata`] and
+/// the values are used as they are.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CueTemplate {
+    parts: Vec<Part>,
+    pub payload: CuePayload,
+}
+
+/// An error from parsing or rendering a [`CueTemplate`].
+#[derive(Error, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TemplateError {
+    #[error("unclosed placeholder at byte {offset}")]
+    Unclosed { offset: usize },
+
+    #[error("unmatched '}}' at byte {offset}")]
+    UnmatchedBrace { offset: usize },
+
+    #[error("placeholder without a name at byte {offset}")]
+    EmptyName { offset: usize },
+
+    #[error("no value for {name:?}")]
+    MissingValue { name: String },
+}
+
+impl CueTemplate {
+    pub fn parse(template: &str) -> Result<CueTemplate, TemplateError> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = template.char_indices().peekable();
+
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => literal.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => literal.push('}'),
+                '}' => return Err(TemplateError::UnmatchedBrace { offset }),
+                '{' => {
+                    let rest = &template[offset + 1..];
+                    let end = rest.find('}').ok_or(TemplateError::Unclosed { offset })?;
+                    let name = rest[..end].trim();
+
+                    if name.is_empty() {
+                        return Err(TemplateError::EmptyName { offset });
+                    }
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(Part::Variable(name.to_owned()));
+
+                    while chars.next_if(|&(idx, _)| idx <= offset + 1 + end).is_some() {}
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(CueTemplate {
+            parts,
+            payload: CuePayload::Text,
+        })
+    }
+
+    /// Returns the names of the placeholders, in order, with repeats.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Variable(name) => Some(name.as_str()),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Fills in the placeholders with `values`, escaping them for cue text.
+    pub fn render(&self, values: &HashMap<String, String>) -> Result<String, TemplateError> {
+        let mut out = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => out.push_str(literal),
+                Part::Variable(name) => {
+                    let value = values
+                        .get(name)
+                        .ok_or_else(|| TemplateError::MissingValue { name: name.clone() })?;
+
+                    match self.payload {
+                        CuePayload::Text => out.push_str(&text::escape(value)),
+                        CuePayload::Metadata => out.push_str(value),
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
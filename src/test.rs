@@ -1,8 +1,24 @@
-use std::time::Duration;
+use std::{io::BufRead, time::Duration};
 
-use crate::{parse_timestamp, parse_file};
+use crate::{
+    base_direction, count_cues, format_date_time, merge_bilingual, merge_edits, parse_bytes,
+    parse_file, parse_file_traced, parse_file_with, parse_header, parse_lines, parse_lines_with,
+    parse_timestamp, Align, Aligner, Audit, AuditRecord, BilingualStyle, Block, ChangeKind, Clock,
+    ConflictKind, ConvertError, Cue, CueClass, CueEvent, CueIndex, CuePatch, CuePayload, CueReader,
+    CueSettings, CueTemplate, DeepLink, DiagnosticKind, Direction, DrawtextOptions, EditSession,
+    EmojiPolicy, EmojiReport, Error, File, Finding, FindingKind, HeaderSeparator, KaraokeUnit,
+    LengthMetric, Line, LineAlign, LineEnding, LineKind, MarkdownOptions, Marker, MaskStyle,
+    MergeConflict, NormalizeOptions, NoteAttachment, NumberOrAuto, ParseError, ParseOptions,
+    PatchError, PatchOp, Percentage, PositionAlign, Profile, ProfileRules, ProgramDateTime,
+    QualityCategory, Query, QuoteStyle, RepeatedHeader, Rubric, RuleSegmenter, Scheduler,
+    SdhOptions, SentenceSegmenter, Shift, ShiftError, SnapPolicy, SourceFormat, SpeedFix,
+    TailParser, TemplateError, TimedWord, Timestamp, TimestampStyle, TimingFix, TrackKind,
+    TrackSet, Transition, Utf8Policy, VttCueInit, WordIndexOptions, WordList, WritingDirection,
+};
 
 #[test]
+#[rustfmt::skip]
+#[allow(clippy::identity_op, clippy::inconsistent_digit_grouping)]
 fn timestamp() {
     let line = "00:31.500";
     let result = parse_timestamp(line);
@@ -74,6 +90,33 @@ fn timestamp() {
 }
 
 #[test]
+fn timestamp_errors() {
+    use crate::TimestampError::*;
+
+    let parse = |input| Timestamp::parse(input).map(|(t, rest)| (t.as_duration(), rest));
+
+    assert_eq!(
+        parse("01:02.003x"),
+        Ok((Duration::from_millis(62_003), "x"))
+    );
+    assert_eq!(parse(""), Err(ExpectedDigits));
+    assert_eq!(parse("01.000"), Err(ExpectedColon));
+    assert_eq!(parse("2:31.500"), Err(BadMinutes));
+    assert_eq!(parse("60:00.000"), Err(MinutesOver59));
+    assert_eq!(parse("1:60:00.000"), Err(MinutesOver59));
+    assert_eq!(parse("00:5.000"), Err(BadSeconds));
+    assert_eq!(parse("00:60.000"), Err(SecondsOver59));
+    assert_eq!(parse("00:05"), Err(ExpectedDecimalPoint));
+    assert_eq!(parse("00:05.5"), Err(BadMillis));
+    assert_eq!(parse("00:05."), Err(BadMillis));
+    assert_eq!(parse("99999999999999999999:00:00.000"), Err(BadHours));
+
+    let (timestamp, _) = Timestamp::parse("1:02:03.004").unwrap();
+    assert_eq!(timestamp.to_string(), "01:02:03.004");
+}
+
+#[test]
+#[rustfmt::skip]
 fn sample1() {
   let sample = include_str!("../test/sample1.vtt");
   let file = parse_file(sample).unwrap();
@@ -81,8 +124,3842 @@ fn sample1() {
 }
 
 #[test]
+#[rustfmt::skip]
 fn sample2() {
   let sample = include_str!("../test/sample2.vtt");
   let file = parse_file(sample).unwrap();
   println!("{file:#?}");
 }
+
+#[test]
+fn cue_index() {
+    let sample = include_str!("../test/sample1.vtt");
+    let file = parse_file(sample).unwrap();
+    let index = file.build_index();
+    assert_eq!(index.len(), 13);
+
+    let index = CueIndex::from_bytes(&index.to_bytes()).unwrap();
+    assert_eq!(index, file.build_index());
+
+    // 00:32.000 --> 00:35.500 is still active at 00:33.000
+    let offset = index.seek(Duration::from_millis(33_000)).unwrap();
+    let cue = CueReader::at(sample, offset).unwrap().next().unwrap();
+    assert_eq!(cue.start, Duration::from_millis(32_000));
+    assert_eq!(
+        cue.text,
+        "<v Roger Bingham>No! No no no no; 'cos 'cos obviously 'cos"
+    );
+
+    assert_eq!(index.seek(Duration::from_secs(60)), None);
+    assert!(CueIndex::from_bytes(b"VTTI").is_err());
+
+    assert!(CueReader::at(sample, sample.len()).is_ok());
+    assert!(matches!(
+        CueReader::at(sample, sample.len() + 1),
+        Err(ParseError::BadIndex { .. })
+    ));
+    assert!(CueReader::at("WEBVTT é", 8).is_err());
+
+    let err = crate::Error::from(CueIndex::from_bytes(b"").unwrap_err());
+    assert_eq!(err.to_string(), "bad cue index: missing magic");
+}
+
+#[test]
+fn cue_reader() {
+    let sample = include_str!("../test/sample2.vtt");
+    let cues: Vec<_> = CueReader::new(sample).unwrap().collect();
+    assert_eq!(cues.len(), 3);
+    assert_eq!(
+        cues[1].text,
+        "— It will perforate your stomach.\n— You could die."
+    );
+    assert_eq!(&sample[cues[1].offset..][..9], "00:05.000");
+}
+
+#[test]
+fn ordering() {
+    let sample = "WEBVTT\n\n00:05.000 --> 00:06.000\nsecond\n\n00:01.000 --> 00:02.000\nfirst\n";
+
+    let options = ParseOptions {
+        check_order: true,
+        ..Default::default()
+    };
+    let (mut file, diagnostics) = parse_file_with(sample, &options).unwrap();
+    assert!(!file.is_sorted());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].kind,
+        DiagnosticKind::UnsortedCue {
+            start: Duration::from_secs(1),
+            previous: Duration::from_secs(5),
+        }
+    );
+
+    file.sort_by_start();
+    assert!(file.is_sorted());
+    assert_eq!(file.cues().next().unwrap().text, "first");
+
+    let (_, diagnostics) = parse_file_with(sample, &ParseOptions::default()).unwrap();
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn track_set() {
+    let en = parse_file(
+        "WEBVTT\n\nintro\n00:01.000 --> 00:03.000\nHello\n\n00:04.000 --> 00:06.000\nGoodbye\n",
+    )
+    .unwrap();
+    let fr = parse_file(
+        "WEBVTT\n\nintro\n00:01.200 --> 00:03.100\nBonjour\n\n00:04.500 --> 00:06.500\nAu revoir\n",
+    )
+    .unwrap();
+    let de = parse_file("WEBVTT\n\n00:01.000 --> 00:03.000\nHallo\n").unwrap();
+
+    let mut tracks = TrackSet::new();
+    tracks.insert("en", en);
+    tracks.insert("fr", fr);
+    tracks.insert("de", de);
+    assert_eq!(tracks.languages().collect::<Vec<_>>(), ["de", "en", "fr"]);
+
+    let missing = tracks.missing_cues("en");
+    assert_eq!(missing["de"].len(), 1);
+    assert_eq!(missing["de"][0].text, "Goodbye");
+    assert!(missing["fr"].is_empty());
+
+    tracks.align_to("en");
+    let fr: Vec<_> = tracks.get("fr").unwrap().cues().collect();
+    assert_eq!(fr[0].start, Duration::from_secs(1));
+    assert_eq!(fr[0].end, Duration::from_secs(3));
+    assert_eq!(fr[1].start, Duration::from_secs(4));
+    assert_eq!(fr[1].end, Duration::from_secs(6));
+}
+
+#[test]
+fn bilingual() {
+    let en =
+        parse_file("WEBVTT\n\n00:01.000 --> 00:03.000\nHello\n\n00:04.000 --> 00:06.000\nBye\n")
+            .unwrap();
+    let fr = parse_file("WEBVTT\n\n00:01.200 --> 00:03.100\nBonjour\n").unwrap();
+
+    let merged = merge_bilingual(&en, &fr, &BilingualStyle::Stacked);
+    let cues: Vec<_> = merged.cues().collect();
+    assert_eq!(cues[0].text, "Hello\nBonjour");
+    assert_eq!(cues[0].start, Duration::from_secs(1));
+    assert_eq!(cues[1].text, "Bye");
+
+    let style = BilingualStyle::Classed {
+        primary: "en".into(),
+        secondary: "fr".into(),
+    };
+    let merged = merge_bilingual(&en, &fr, &style);
+    let cues: Vec<_> = merged.cues().collect();
+    assert_eq!(cues[0].text, "<c.en>Hello</c>\n<c.fr>Bonjour</c>");
+    assert_eq!(cues[1].text, "<c.en>Bye</c>");
+}
+
+#[cfg(feature = "xliff")]
+#[test]
+fn xliff() {
+    let en = parse_file(
+        "WEBVTT\n\nintro\n00:01.000 --> 00:03.000\nHello & <i>welcome</i>\n\n00:04.000 --> 00:06.000\nBye\n",
+    )
+    .unwrap();
+
+    let xliff = en.to_xliff("en", "fr");
+    assert!(xliff.contains("<trans-unit id=\"intro\">"));
+    assert!(xliff.contains("<source>Hello &amp; &lt;i&gt;welcome&lt;/i&gt;</source>"));
+    assert!(xliff.contains("<trans-unit id=\"00:00:04.000-00:00:06.000\">"));
+
+    let translated = xliff.replace(
+        "<source>Hello &amp; &lt;i&gt;welcome&lt;/i&gt;</source>",
+        "<source/><target>Bonjour &amp; &lt;i&gt;bienvenue&lt;/i&gt;</target>",
+    );
+    let fr = en.apply_xliff(&translated).unwrap();
+    let cues: Vec<_> = fr.cues().collect();
+    assert_eq!(cues[0].text, "Bonjour & <i>bienvenue</i>");
+    assert_eq!(cues[0].start, Duration::from_secs(1));
+    assert_eq!(cues[1].text, "Bye");
+
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nOne\n\n00:01.000 --> 00:02.000\nTwo\n\na\n00:03.000 --> 00:04.000\nThree\n\na\n00:05.000 --> 00:06.000\nFour\n\na#2\n00:07.000 --> 00:08.000\nFive\n",
+    )
+    .unwrap();
+
+    let xliff = file.to_xliff("en", "fr");
+    let ids: Vec<_> = xliff
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("<trans-unit id=\""))
+        .collect();
+    assert_eq!(
+        ids,
+        [
+            "00:00:01.000-00:00:02.000#0\">",
+            "00:00:01.000-00:00:02.000#1\">",
+            "a#2_\">",
+            "a#3\">",
+            "a#2\">"
+        ]
+    );
+
+    let mut translated = xliff;
+    for word in ["One", "Two", "Three", "Four", "Five"] {
+        translated = translated.replace(
+            &format!("<source>{word}</source>"),
+            &format!("<target>{word}!</target>"),
+        );
+    }
+    let texts: Vec<_> = file
+        .apply_xliff(&translated)
+        .unwrap()
+        .cues()
+        .map(|cue| cue.text.clone())
+        .collect();
+    assert_eq!(texts, ["One!", "Two!", "Three!", "Four!", "Five!"]);
+}
+
+#[test]
+fn cue_text() {
+    use crate::text::{self, Node, Span, SpanKind};
+
+    let nodes = text::parse(
+        "<v.loud Roger  Bingham>Hi &amp; <i>bye<00:01.000></i></v> <foo>x</foo> &bogus;",
+    );
+    assert_eq!(
+        nodes,
+        [
+            Node::Span(Span {
+                kind: SpanKind::Voice {
+                    name: "Roger Bingham".into()
+                },
+                classes: vec!["loud".into()],
+                children: vec![
+                    Node::Text("Hi & ".into()),
+                    Node::Span(Span {
+                        kind: SpanKind::Italic,
+                        classes: vec![],
+                        children: vec![
+                            Node::Text("bye".into()),
+                            Node::Timestamp(Duration::from_secs(1)),
+                        ],
+                    }),
+                ],
+            }),
+            Node::Text(" ".into()),
+            Node::Text("x".into()),
+            Node::Text(" &bogus;".into()),
+        ]
+    );
+    assert_eq!(text::plain(&nodes), "Hi & bye x &bogus;");
+    assert_eq!(
+        text::write(&nodes),
+        "<v.loud Roger Bingham>Hi &amp; <i>bye<00:00:01.000></i></v> x &amp;bogus;"
+    );
+
+    let nodes = text::parse("<ruby>漢<rt>かん</ruby>字<rt>x</rt>");
+    assert_eq!(text::write(&nodes), "<ruby>漢<rt>かん</rt></ruby>字x");
+}
+
+#[test]
+fn csv() {
+    let sample = include_str!("../test/sample1.vtt");
+    let file = parse_file(sample).unwrap();
+
+    let csv = file.to_csv();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("id,start,end,speaker,text,settings"));
+    assert_eq!(
+        lines.next(),
+        Some(",00:00:11.000,00:00:13.000,Roger Bingham,We are in New York City,")
+    );
+
+    let imported = File::from_csv(&csv).unwrap();
+    for (a, b) in file.cues().zip(imported.cues()) {
+        assert_eq!((a.start, a.end), (b.start, b.end));
+        assert_eq!(a.speaker(), b.speaker());
+        assert_eq!(a.plain_text(), b.plain_text());
+    }
+
+    let tsv = "text\tend\tstart\n\"two\nlines, \"\"quoted\"\"\"\t00:02.000\t00:01.000\n";
+    let imported = File::from_tsv(tsv).unwrap();
+    let cue = imported.cues().next().unwrap();
+    assert_eq!(cue.text, "two\nlines, \"quoted\"");
+    assert_eq!(cue.end, Duration::from_secs(2));
+
+    assert!(matches!(
+        File::from_csv("start,end\n00:01.000,soon\n"),
+        Err(ConvertError::BadCsv {
+            row: 2,
+            column: "end"
+        })
+    ));
+}
+
+#[test]
+fn markdown() {
+    let sample = include_str!("../test/sample1.vtt");
+    let file = parse_file(sample).unwrap();
+
+    let markdown = file.to_markdown(&MarkdownOptions {
+        title: Some("Interview".into()),
+        link_base: Some("https://youtu.be/abc".into()),
+        ..Default::default()
+    });
+    let mut lines = markdown.lines();
+    assert_eq!(lines.next(), Some("# Interview"));
+    assert_eq!(lines.next(), Some(""));
+    let first = lines.next().unwrap();
+    assert!(first.starts_with(
+        "[0:11](https://youtu.be/abc?t=11) **Roger Bingham:** We are in New York City We’re actually"
+    ));
+    assert!(markdown.contains("**Neil deGrasse Tyson:** Didn’t we talk"));
+
+    let markdown = file.to_markdown(&MarkdownOptions {
+        timestamp_headings: true,
+        ..Default::default()
+    });
+    assert!(markdown.starts_with("## 0:11\n\n**Roger Bingham:**"));
+}
+
+#[test]
+fn search() {
+    let sample = include_str!("../test/sample1.vtt");
+    let file = parse_file(sample).unwrap();
+
+    let matches = file.search("NO");
+    // "enough", five times in one cue, and "know"
+    assert_eq!(matches.len(), 7);
+    assert_eq!(matches[1].cue_index, 10);
+    assert_eq!(matches[1].range, 0..2);
+    assert_eq!(matches[1].time, Duration::from_secs(32));
+
+    assert_eq!(file.search(Query::Exact("No".into())).len(), 2);
+    assert!(file.search("").is_empty());
+
+    let found = file.search_concordance("hayden", 10);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].before, "or of the ");
+    assert_eq!(found[0].matched, "Hayden");
+    assert_eq!(found[0].after, " Planetari");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn search_regex() {
+    let sample = include_str!("../test/sample1.vtt");
+    let file = parse_file(sample).unwrap();
+
+    let regex = regex::Regex::new(r"\bno\b").unwrap();
+    assert_eq!(file.search(regex).len(), 3);
+}
+
+#[test]
+fn word_index() {
+    let sample = include_str!("../test/sample1.vtt");
+    let file = parse_file(sample).unwrap();
+
+    let index = file.word_index();
+    assert_eq!(index["no"], [(10, Duration::from_secs(32))]);
+    assert_eq!(index["didn’t"], [(9, Duration::from_millis(30_500))]);
+    assert_eq!(index["the"].len(), 5);
+
+    let index = file.word_index_with(&WordIndexOptions {
+        min_length: 4,
+        ..WordIndexOptions::english()
+    });
+    assert!(!index.contains_key("the"));
+    assert!(!index.contains_key("cos"));
+    assert_eq!(index["glasses"], [(12, Duration::from_millis(35_500))]);
+}
+
+#[test]
+fn descriptions() {
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\nA door opens.\n\n00:05.000 --> 00:09.000\nShe leaves.\n",
+    )
+    .unwrap();
+
+    let secs = Duration::from_secs;
+    let dialogue = [(secs(8), secs(10)), (secs(0), secs(1)), (secs(3), secs(5))];
+
+    assert_eq!(
+        file.validate_descriptions(&dialogue),
+        [Finding {
+            cue_index: 1,
+            kind: FindingKind::DialogueCollision {
+                start: secs(8),
+                end: secs(10)
+            },
+        }]
+    );
+}
+
+#[test]
+fn write() {
+    let sample = include_str!("../test/sample1.vtt");
+    let file = parse_file(sample).unwrap();
+
+    let written = file.to_string();
+    assert!(written.starts_with("WEBVTT\n\n00:11.000 --> 00:13.000\n"));
+
+    let reparsed = parse_file(&written).unwrap();
+    assert_eq!(reparsed.cues().count(), file.cues().count());
+    for (a, b) in file.cues().zip(reparsed.cues()) {
+        assert_eq!((a.start, a.end, &a.text), (b.start, b.end, &b.text));
+    }
+}
+
+#[test]
+fn source_format() {
+    let input = "\u{feff}WEBVTT\r\n\r\n1\r\n00:01.000 --> 00:02.000 align:start\r\nA\r\nB\r\n\r\n01:00:00.000 --> 01:00:01.500\r\nC\r\n";
+
+    let file = parse_file(input).unwrap();
+    assert_eq!(
+        file.format,
+        SourceFormat {
+            line_ending: LineEnding::CrLf,
+            bom: true,
+            timestamps: TimestampStyle::OmitZeroHours,
+            header_separator: HeaderSeparator::Space,
+        }
+    );
+    assert_eq!(file.cues().next().unwrap().text, "A\nB");
+    assert_eq!(file.to_string(), input);
+
+    let mut file = parse_file("WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nA\n").unwrap();
+    assert_eq!(file.format, SourceFormat::default());
+
+    file.format.timestamps = TimestampStyle::OmitZeroHours;
+    assert_eq!(file.to_string(), "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n");
+
+    let input = "WEBVTT\nKind: captions\nLanguage: en\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n00:01.000 --> 00:02.000\nHello\n";
+    let file = parse_file(input).unwrap();
+    assert_eq!(
+        file.header_lines,
+        [
+            "Kind: captions",
+            "Language: en",
+            "X-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000"
+        ]
+    );
+    assert_eq!(file.cues().count(), 1);
+    assert_eq!(file.to_string(), input);
+
+    let streamed = parse_lines(input.lines().map(str::to_owned)).unwrap();
+    assert_eq!(streamed.to_string(), input);
+
+    let mixed = "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\n00:00:03.000 --> 00:00:04.000\nB\n";
+    let file = parse_file(mixed).unwrap();
+    assert_eq!(file.format.timestamps, TimestampStyle::Full);
+    assert!(file.to_string().contains("00:00:03.000 --> 00:00:04.000"));
+}
+
+#[test]
+fn forced() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\n<c.forced>Hola</c>\n\n00:04.000 --> 00:06.000\nHello\n",
+    )
+    .unwrap();
+
+    let forced = file.extract_forced();
+    assert_eq!(forced.cues().count(), 1);
+    assert!(forced
+        .to_string()
+        .ends_with("00:01.000 --> 00:03.000\n<c.forced>Hola</c>\n"));
+
+    let mut cues: Vec<_> = file.cues_mut().collect();
+    cues[0].set_forced(false);
+    assert_eq!(cues[0].text, "Hola");
+    cues[1].set_forced(true);
+    assert!(cues[1].is_forced());
+    assert_eq!(cues[1].text, "<c.forced>Hello</c>");
+}
+
+#[test]
+fn languages() {
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\n<lang en-GB>Colour</lang> <lang fr>couleur <lang en-GB>!</lang></lang>\n\n00:04.000 --> 00:06.000\n<lang>Hello</lang>\n",
+    )
+    .unwrap();
+
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues[0].languages(), ["en-GB", "fr"]);
+    assert!(cues[1].languages().is_empty());
+}
+
+#[cfg(feature = "bcp47")]
+#[test]
+fn language_tag_validation() {
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\n<lang en-GB>Colour</lang> <lang english>colour</lang>\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        file.validate_language_tags(),
+        [Finding {
+            cue_index: 0,
+            kind: FindingKind::InvalidLanguageTag {
+                tag: "english".into()
+            },
+        }]
+    );
+}
+
+#[test]
+fn ruby() {
+    use crate::text::RubyPart;
+
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\n<v 先生><ruby>漢<rt>かん</rt>字<rt>じ</ruby>を<i>読む</i>\n",
+    )
+    .unwrap();
+
+    let cue = file.cues().next().unwrap();
+    assert_eq!(
+        cue.render_html(),
+        "<span title=\"先生\"><ruby>漢<rt>かん</rt>字<rt>じ</rt></ruby>を<i>読む</i></span>"
+    );
+
+    let mut annotated = file.clone();
+    annotated.strip_ruby(RubyPart::Annotation);
+    assert_eq!(
+        annotated.cues().next().unwrap().text,
+        "<v 先生>かんじを<i>読む</i></v>"
+    );
+
+    file.strip_ruby(RubyPart::Base);
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "<v 先生>漢字を<i>読む</i></v>"
+    );
+
+    let html = crate::text::render_html(&crate::text::parse(
+        "<c.a.b>1 &lt; 2</c>\n<lang en>x</lang>",
+    ));
+    assert_eq!(
+        html,
+        "<span class=\"a b\">1 &lt; 2</span><br><span lang=\"en\">x</span>"
+    );
+}
+
+#[test]
+fn bidi() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\n- שלום, עולם!\n<i>Hello</i>\n\n00:04.000 --> 00:06.000\nHello, world!\n",
+    )
+    .unwrap();
+
+    let cues: Vec<_> = file.cues().collect();
+    assert!(cues[0].is_rtl());
+    assert!(!cues[1].is_rtl());
+    assert_eq!(cues[0].line_directions(), [Direction::Rtl, Direction::Ltr]);
+    assert_eq!(base_direction("123 ..."), None);
+
+    file.add_direction_marks();
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues[0].text, "&rlm;- שלום, עולם!&rlm;\n<i>Hello</i>");
+    assert_eq!(cues[1].text, "Hello, world!&lrm;");
+
+    let mut cue = cues[0].clone();
+    cue.text.push_str("\u{200f}&#X200F;");
+    cue.strip_direction_marks();
+    assert_eq!(cue.text, "- שלום, עולם!\n<i>Hello</i>");
+
+    cue.text = "a&&lrm;b &amp; &LRM;&#x200Fc&#8206;".to_owned();
+    cue.strip_direction_marks();
+    assert_eq!(cue.text, "a&b &amp; &LRM;&#x200Fc");
+}
+
+#[test]
+fn settings() {
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000 vertical:rl line:-2 position:10%,line-left size:35.5% align:start\nA\n\n00:01.000 --> 00:03.000 line:10%,center position:x size:101% align:middle region:r\nB\n",
+    )
+    .unwrap();
+
+    let cues: Vec<_> = file.cues().collect();
+    let settings = &cues[0].settings;
+    assert_eq!(settings.writing_direction, WritingDirection::VerticalLeft);
+    assert_eq!(settings.line, Line::Number(-2));
+    assert_eq!(settings.position, Percentage::new(10.0));
+    assert_eq!(settings.position_align, PositionAlign::LineLeft);
+    assert_eq!(settings.size, Percentage::new(35.5).unwrap());
+    assert_eq!(settings.align, Align::Start);
+    assert_eq!(
+        settings.to_string(),
+        "vertical:rl line:-2 position:10%,line-left size:35.5% align:start"
+    );
+
+    let settings = &cues[1].settings;
+    assert_eq!(
+        settings.line,
+        Line::Percentage(Percentage::new(10.0).unwrap())
+    );
+    assert_eq!(settings.line_align, LineAlign::Center);
+    assert_eq!(settings.position, None);
+    assert_eq!(settings.size, Percentage::HUNDRED);
+    assert_eq!(settings.align, Align::Center);
+    assert_eq!(settings.region, None);
+    assert_eq!(settings.to_string(), "line:10%,center");
+}
+
+#[test]
+fn resolved_position() {
+    use crate::layout::ResolvedPosition;
+
+    let settings = |line: &str| {
+        let file = parse_file(&format!("WEBVTT\n\n00:01.000 --> 00:03.000 {line}\nA\n")).unwrap();
+        let cue = file.cues().next().unwrap();
+        cue.settings.clone()
+    };
+
+    assert_eq!(
+        settings("").resolved_position(16.0 / 9.0),
+        ResolvedPosition {
+            x: 0.0,
+            y: 95.0,
+            size: 100.0
+        }
+    );
+    assert_eq!(
+        settings("position:10% align:left size:40% line:0").resolved_position(16.0 / 9.0),
+        ResolvedPosition {
+            x: 10.0,
+            y: 0.0,
+            size: 40.0
+        }
+    );
+
+    // a line of vertical text is narrower in a wider video
+    let rl = settings("vertical:rl line:0").resolved_position(2.0);
+    assert_eq!((rl.x, rl.y, rl.size), (97.5, 0.0, 100.0));
+}
+
+#[test]
+fn effective_alignment() {
+    let mut settings = CueSettings::default();
+    assert_eq!(settings.effective_alignment(Direction::Rtl), Align::Center);
+
+    settings.align = Align::Start;
+    assert_eq!(settings.effective_alignment(Direction::Ltr), Align::Left);
+    assert_eq!(settings.effective_alignment(Direction::Rtl), Align::Right);
+
+    settings.align = Align::End;
+    assert_eq!(settings.effective_alignment(Direction::Rtl), Align::Left);
+
+    settings.align = Align::Right;
+    assert_eq!(settings.effective_alignment(Direction::Rtl), Align::Right);
+}
+
+#[test]
+fn raw_settings() {
+    let input = "WEBVTT\n\n00:01.000 --> 00:02.000 \t align:start  x-vendor:1 line:10%\nA\n";
+    let mut file = parse_file(input).unwrap();
+
+    file.shift(Shift::Later(Duration::from_secs(1))).unwrap();
+    assert_eq!(
+        file.to_string(),
+        "WEBVTT\n\n00:02.000 --> 00:03.000 \t align:start  x-vendor:1 line:10%\nA\n"
+    );
+
+    let cue = file.cues_mut().next().unwrap();
+    cue.settings.align = Align::End;
+    assert_eq!(
+        file.to_string(),
+        "WEBVTT\n\n00:02.000 --> 00:03.000 line:10% align:end\nA\n"
+    );
+}
+
+#[test]
+fn settings_conflicts() {
+    let settings = |line: &str| {
+        let file = parse_file(&format!("WEBVTT\n\n00:01.000 --> 00:03.000 {line}\nA\n")).unwrap();
+        let cue = file.cues().next().unwrap();
+        cue.settings.clone()
+    };
+
+    // the last valid value wins
+    let s = settings("align:start align:bogus align:end\tline:1 line:junk size:50% size:150%");
+    assert_eq!(s.align, Align::End);
+    assert_eq!(s.line, Line::Number(1));
+    assert_eq!(s.size, Percentage::new(50.0).unwrap());
+
+    assert_eq!(settings("line:+1").line, Line::Auto);
+    assert_eq!(settings("line:1.").line, Line::Auto);
+    assert_eq!(settings("line:-").line, Line::Auto);
+    assert_eq!(settings("line:1.5").line, Line::Number(2));
+    assert_eq!(settings("line:-1.5").line, Line::Number(-1));
+    assert_eq!(settings("position:auto,center").position, None);
+    assert_eq!(settings("position:50%,middle").position, None);
+    assert_eq!(settings(":x region: region:a").region.as_deref(), Some("a"));
+
+    // regions only apply to horizontal cues without a line or size
+    assert_eq!(settings("region:a size:50%").region, None);
+    assert_eq!(
+        settings("position:auto,center line:auto,end vertical:lr").to_string(),
+        "vertical:lr"
+    );
+
+    let s = CueSettings {
+        position_align: PositionAlign::LineLeft,
+        align: Align::Right,
+        ..Default::default()
+    };
+    assert_eq!(s.to_string(), "position:100%,line-left align:right");
+}
+
+#[test]
+fn layout() {
+    use crate::layout::{cue_box, CueBox};
+
+    let settings = |line: &str| {
+        let file = parse_file(&format!("WEBVTT\n\n00:01.000 --> 00:03.000 {line}\nA\n")).unwrap();
+        let cue = file.cues().next().unwrap();
+        cue.settings.clone()
+    };
+
+    // by default, cues are centered at the bottom of the viewport
+    assert_eq!(
+        cue_box(&settings(""), Direction::Ltr, 5.0, 2),
+        CueBox {
+            x: 0.0,
+            y: 90.0,
+            width: 100.0,
+            height: 10.0
+        }
+    );
+
+    // vertical cues growing left start at the left edge, since the last line
+    // is on the left, and count positive lines from the right edge
+    let rl = cue_box(&settings("vertical:rl size:50%"), Direction::Ltr, 5.0, 1);
+    assert_eq!(
+        rl,
+        CueBox {
+            x: 0.0,
+            y: 25.0,
+            width: 5.0,
+            height: 50.0
+        }
+    );
+    let rl = cue_box(&settings("vertical:rl line:0"), Direction::Ltr, 5.0, 1);
+    assert_eq!(rl.x, 95.0);
+
+    // vertical cues growing right are mirrored
+    let lr = cue_box(&settings("vertical:lr"), Direction::Ltr, 5.0, 1);
+    assert_eq!(lr.x, 95.0);
+    let lr = cue_box(&settings("vertical:lr line:1"), Direction::Ltr, 5.0, 1);
+    assert_eq!(lr.x, 5.0);
+
+    // for vertical cues the line percentage is the horizontal offset
+    let lr = cue_box(
+        &settings("vertical:lr line:20%,end position:20% align:start"),
+        Direction::Ltr,
+        5.0,
+        2,
+    );
+    assert_eq!(
+        lr,
+        CueBox {
+            x: 10.0,
+            y: 20.0,
+            width: 10.0,
+            height: 80.0
+        }
+    );
+
+    // start alignment depends on the base direction
+    let rtl = cue_box(
+        &settings("position:70% align:start"),
+        Direction::Rtl,
+        5.0,
+        1,
+    );
+    assert_eq!((rtl.x, rtl.width), (0.0, 70.0));
+}
+
+#[test]
+fn timing_whitespace() {
+    let file = parse_file(
+        "WEBVTT\n\n\t00:00:01.000\t-->\t00:00:02.000\tline:0\t\talign:start\nA\n\n00:03.000   -->  00:04.000  \t size:50%\nB\n\n00:05.000\u{a0}--> 00:06.000\nC\n",
+    )
+    .unwrap();
+
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].start, Duration::from_secs(1));
+    assert_eq!(cues[0].settings.line, Line::Number(0));
+    assert_eq!(cues[0].settings.align, Align::Start);
+    assert_eq!(cues[1].end, Duration::from_secs(4));
+    assert_eq!(cues[1].settings.size, Percentage::new(50.0).unwrap());
+}
+
+#[test]
+fn shift() {
+    let secs = Duration::from_secs;
+
+    let timestamp = Timestamp(secs(2));
+    assert_eq!(timestamp.checked_shift(Shift::Earlier(secs(3))), None);
+    assert_eq!(
+        timestamp.saturating_shift(Shift::Earlier(secs(3))),
+        Timestamp(secs(0))
+    );
+    assert_eq!(
+        timestamp.checked_shift(Shift::Later(secs(3))),
+        Some(Timestamp(secs(5)))
+    );
+    assert_eq!(
+        Timestamp(Duration::MAX).checked_shift(Shift::Later(secs(1))),
+        None
+    );
+
+    let input = "WEBVTT\n\n00:05.000 --> 00:07.000\nA <00:06.000>B\n\n00:01.000 --> 00:02.000\nC\n";
+    let mut file = parse_file(input).unwrap();
+
+    file.shift(Shift::Later(secs(1))).unwrap();
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!((cues[0].start, cues[0].end), (secs(6), secs(8)));
+    assert_eq!(cues[0].text, "A <00:00:07.000>B");
+    assert_eq!(cues[1].start, secs(2));
+
+    assert_eq!(
+        file.shift(Shift::Earlier(secs(4))),
+        Err(ShiftError::Negative { cue_index: 1 })
+    );
+    assert_eq!(file.cues().next().unwrap().start, secs(6));
+
+    let mut cue = file.cues().next().unwrap().clone();
+    assert_eq!(
+        cue.checked_shift(Shift::Later(Duration::MAX)),
+        Err(ShiftError::Overflow { cue_index: 0 })
+    );
+    assert_eq!(cue.start, secs(6));
+    assert_eq!(cue.checked_shift(Shift::Earlier(secs(6))), Ok(()));
+    assert_eq!(cue.text, "A <00:00:01.000>B");
+
+    let markup = "<font>Hi</font> &#8212; <i>x</i>";
+    let mut cue = Cue {
+        start: secs(1),
+        end: secs(2),
+        text: markup.to_owned(),
+        ..Default::default()
+    };
+    cue.checked_shift(Shift::Later(secs(1))).unwrap();
+    assert_eq!((cue.start, cue.text.as_str()), (secs(2), markup));
+    cue.saturating_shift(Shift::Earlier(secs(5)));
+    assert_eq!((cue.start, cue.text.as_str()), (secs(0), markup));
+
+    file.saturating_shift(Shift::Earlier(secs(4)));
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues[0].text, "A <00:00:03.000>B");
+    assert_eq!((cues[1].start, cues[1].end), (secs(0), secs(0)));
+}
+
+#[test]
+fn timing_fixes() {
+    let input = "WEBVTT\n\n00:05.000 --> 00:03.000\nbackwards\n\n00:06.000 --> 00:06.000\nempty\n\n00:07.000 --> 00:08.000\nfine\n";
+    let secs = Duration::from_secs;
+
+    let parse = |timing_fix| {
+        let options = ParseOptions {
+            timing_fix,
+            ..Default::default()
+        };
+        let (file, diagnostics) = parse_file_with(input, &options).unwrap();
+        let timings: Vec<_> = file.cues().map(|cue| (cue.start, cue.end)).collect();
+        (timings, diagnostics)
+    };
+
+    let (timings, diagnostics) = parse(None);
+    assert_eq!(timings[0], (secs(5), secs(3)));
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+        diagnostics[1].kind,
+        DiagnosticKind::EndNotAfterStart {
+            start: secs(6),
+            end: secs(6)
+        }
+    );
+    assert_eq!(&input[diagnostics[1].offset..][..9], "00:06.000");
+
+    let (timings, _) = parse(Some(TimingFix::Swap));
+    assert_eq!(timings[..2], [(secs(3), secs(5)), (secs(6), secs(6))]);
+
+    let (timings, _) = parse(Some(TimingFix::ExtendMinDuration(secs(1))));
+    assert_eq!(timings[..2], [(secs(5), secs(6)), (secs(6), secs(7))]);
+
+    let (timings, _) = parse(Some(TimingFix::Drop));
+    assert_eq!(timings, [(secs(7), secs(8))]);
+}
+
+#[test]
+fn repeated_header() {
+    let input = "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\nWEBVTT - part two\nKind: captions\n\n00:00.500 --> 00:01.000\nB\n\nWEBVTT\n00:03.000 --> 00:04.000\nC\n";
+
+    let (file, diagnostics) = parse_file_with(input, &ParseOptions::default()).unwrap();
+    let texts: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(texts, ["A", "B", "C"]);
+    assert_eq!(file.cues().nth(2).unwrap().id, "");
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::RepeatedHeader);
+    assert_eq!(&input[diagnostics[0].offset..][..10], "WEBVTT - p");
+
+    let options = ParseOptions {
+        repeated_header: RepeatedHeader::Reject,
+        ..Default::default()
+    };
+    let result = parse_file_with(input, &options);
+    assert!(matches!(
+        result,
+        Err(ParseError::RepeatedHeader { offset: 35 })
+    ));
+}
+
+#[cfg(feature = "pretty-errors")]
+#[test]
+fn pretty_errors() {
+    let input = "WEBVTT\n\n1\n00:05.000 --> 00:03.000\nbackwards\n";
+    let (file, diagnostics) = parse_file_with(input, &ParseOptions::default()).unwrap();
+
+    assert_eq!(
+        diagnostics[0].render(input, "in.vtt"),
+        "warning: cue ends at 3s, not after its start at 5s
+ --> in.vtt:4:15
+  |
+4 | 00:05.000 --> 00:03.000
+  |               ^^^^^^^^^
+  = note: timings are written as [hh:]mm:ss.ttt --> [hh:]mm:ss.ttt, with the end after the start
+"
+    );
+
+    let findings = file.check_profile(&Profile::Custom(ProfileRules {
+        max_line_length: Some(5),
+        ..Default::default()
+    }));
+    assert_eq!(
+        findings[0].render(&file, input, "in.vtt"),
+        "warning: line 0 has 9 characters, more than 5
+ --> in.vtt:5:1
+  |
+5 | backwards
+  | ^^^^^^^^^
+"
+    );
+
+    let input = "WEBVTTX\n";
+    assert_eq!(
+        parse_file(input).unwrap_err().render(input, "in.vtt"),
+        "error: bad file header \"WEBVTTX\": expected whitespace after WEBVTT
+ --> in.vtt:1:7
+  |
+1 | WEBVTTX
+  |       ^
+  = note: WEBVTT is followed by a space or tab before any description
+"
+    );
+
+    let input = "WEBVTTé\n";
+    assert_eq!(
+        parse_file(input).unwrap_err().render(input, "in.vtt"),
+        "error: bad file header \"WEBVTTé\": expected whitespace after WEBVTT
+ --> in.vtt:1:7
+  |
+1 | WEBVTTé
+  |       ^
+  = note: WEBVTT is followed by a space or tab before any description
+"
+    );
+
+    let error = ParseError::RepeatedHeader { offset: 1 };
+    assert!(error.render("éWEBVTT", "in.vtt").contains("1 | éWEBVTT"));
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn cache_bytes() {
+    let input = "\u{feff}WEBVTT desc\r\nKind: captions\r\n\r\nSTYLE\r\n::cue { color: red }\r\n\r\nNOTE about A\r\n\r\nNOTE extensions\r\nscore=1\r\n\r\na\r\n00:01.000 --> 00:02.500 line:-2 position:10%,line-left size:50%  x:y\r\n<i>A</i>\r\n\r\n00:02.000 --> 00:03.000 line:20%,end\r\nB\r\n\r\nNOTE trailing\r\n";
+    let options = ParseOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let (file, _) = parse_file_with(input, &options).unwrap();
+
+    let bytes = file.to_cache_bytes();
+    let cached = File::from_cache_bytes(&bytes).unwrap();
+    assert_eq!(cached.to_string(), input);
+    assert_eq!(cached.format, file.format);
+    assert_eq!(cached.header_lines, ["Kind: captions"]);
+
+    for (a, b) in file.cues().zip(cached.cues()) {
+        assert_eq!(a.settings, b.settings);
+        assert_eq!(a.extensions, b.extensions);
+        assert_eq!((a.offset, a.source_order), (b.offset, b.source_order));
+    }
+
+    assert!(matches!(
+        File::from_cache_bytes(&bytes[..bytes.len() - 1]),
+        Err(ParseError::BadCache {
+            reason: "truncated"
+        })
+    ));
+    assert!(File::from_cache_bytes(b"VTTI").is_err());
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi() {
+    use std::ffi::CStr;
+
+    use crate::ffi::*;
+
+    let input = "WEBVTT\n\nintro\n00:01.000 --> 00:02.500\n<i>Hello</i>\n";
+
+    unsafe {
+        let file = vtt_parse(input.as_ptr().cast(), input.len());
+        assert!(!file.is_null());
+        assert_eq!(vtt_cue_count(file), 1);
+        assert_eq!(
+            (vtt_cue_start_ms(file, 0), vtt_cue_end_ms(file, 0)),
+            (1000, 2500)
+        );
+        assert_eq!(CStr::from_ptr(vtt_cue_id(file, 0)).to_str(), Ok("intro"));
+        assert_eq!(
+            CStr::from_ptr(vtt_cue_text(file, 0)).to_str(),
+            Ok("<i>Hello</i>")
+        );
+        assert!(vtt_cue_text(file, 1).is_null());
+
+        let written = vtt_write(file);
+        assert_eq!(CStr::from_ptr(written).to_str(), Ok(input));
+        vtt_string_free(written);
+        vtt_file_free(file);
+
+        assert!(vtt_parse(b"WEBVTX".as_ptr().cast(), 6).is_null());
+        assert_eq!(vtt_last_error(), VTT_ERROR_PARSE);
+        assert!(vtt_parse(b"WEBVTT\xff".as_ptr().cast(), 7).is_null());
+        assert_eq!(vtt_last_error(), VTT_ERROR_UTF8);
+
+        let input = "WEBVTT\n\nSTYLE\n::cue { color: red }\n";
+        let file = vtt_parse(input.as_ptr().cast(), input.len());
+        assert!(!file.is_null());
+        assert_eq!(vtt_last_error(), VTT_OK);
+        vtt_file_free(file);
+    }
+}
+
+#[cfg(feature = "python")]
+#[test]
+fn python() {
+    use pyo3::{prelude::*, types::PyDict};
+
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let module = PyModule::new(py, "webvtt").unwrap();
+        crate::python::webvtt(&module).unwrap();
+
+        let locals = PyDict::new(py);
+        locals.set_item("webvtt", module).unwrap();
+
+        py.run(
+            c"
+file = webvtt.File.parse('WEBVTT\\n\\n00:01.000 --> 00:02.000\\n<i>Hola</i>\\n')
+assert len(file) == 1
+file.shift(-0.5)
+cue = file.cues[0]
+assert (cue.start, cue.end, cue.plain_text()) == (0.5, 1.5, 'Hola')
+
+english = webvtt.File.parse('WEBVTT\\n\\n00:01.000 --> 00:02.000\\nHello\\n')
+english.cues = [webvtt.Cue(0.5, 1.5, 'Hello', id='1')]
+merged = webvtt.merge_bilingual(file, english)
+assert merged.cues[0].text == '<i>Hola</i>\\nHello', merged.cues[0].text
+
+try:
+    file.shift(-1)
+    assert False
+except ValueError:
+    pass
+
+styled = webvtt.File.parse('WEBVTT\\n\\nSTYLE\\n::cue { color: red }\\n\\n00:01.000 --> 00:02.000\\nHi\\n')
+assert len(styled) == 1
+",
+            None,
+            Some(&locals),
+        )
+        .unwrap();
+    });
+}
+
+#[cfg(feature = "uniffi")]
+#[test]
+fn mobile() {
+    use crate::mobile::{MobileCue, MobileFile};
+
+    let file = MobileFile::parse(
+        "WEBVTT\n\n1\n00:01.000 --> 00:03.000\n<b>One</b>\n\n00:02.000 --> 00:04.000\nTwo\n"
+            .to_owned(),
+    )
+    .unwrap();
+
+    assert_eq!(file.cue_count(), 2);
+    assert_eq!(
+        file.cues_at(1500).unwrap(),
+        vec![MobileCue {
+            start_ms: 1000,
+            end_ms: 3000,
+            id: "1".to_owned(),
+            text: "<b>One</b>".to_owned(),
+            plain_text: "One".to_owned(),
+        }]
+    );
+    assert_eq!(file.cues_at(2500).unwrap().len(), 2);
+    assert!(file
+        .serialize()
+        .unwrap()
+        .starts_with("WEBVTT\n\n1\n00:01.000 --> 00:03.000"));
+
+    let err = MobileFile::parse("WEBVT".to_owned()).unwrap_err();
+    assert_eq!(err.to_string(), ParseError::NoMagic.to_string());
+
+    let file = MobileFile::parse("WEBVTT\n\nSTYLE\n::cue { color: red }\n".to_owned()).unwrap();
+    assert_eq!(file.cue_count(), 0);
+}
+
+#[cfg(feature = "napi")]
+#[test]
+fn node() {
+    use crate::node::{JsCue, JsFile};
+
+    let mut file =
+        JsFile::parse("WEBVTT\n\n00:01.000 --> 00:02.000\n<i>Hola</i>\n".to_owned()).unwrap();
+    file.shift(-500.0).unwrap();
+
+    let cues = file.cues();
+    assert_eq!((cues[0].start, cues[0].end), (500.0, 1500.0));
+    assert_eq!(cues[0].text, "<i>Hola</i>");
+
+    file.set_cues(vec![JsCue {
+        start: 250.0,
+        end: 1000.0,
+        id: "1".to_owned(),
+        text: "Hi".to_owned(),
+    }])
+    .unwrap();
+    assert_eq!(
+        file.serialize().unwrap(),
+        "WEBVTT\n\n1\n00:00.250 --> 00:01.000\nHi\n"
+    );
+    assert_eq!(
+        file.to_csv().unwrap(),
+        "id,start,end,speaker,text,settings\n1,00:00:00.250,00:00:01.000,,Hi,\n"
+    );
+
+    assert!(file.shift(-500.0).is_err());
+    assert!(JsFile::parse("WEBVTT".to_owned()).is_ok());
+    assert!(JsFile::parse("WEBVTT\n\nREGION\nid:fred\n".to_owned()).is_ok());
+}
+
+#[test]
+fn prune_empty() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\n<i> </i>&nbsp;\n\n00:03.000 --> 00:04.000\nA\n\n00:05.000 --> 00:06.000\n\n00:07.000 --> 00:08.000\n<v Bob></v>\n",
+    )
+    .unwrap();
+
+    assert_eq!(file.empty_cues(), [0, 2, 3]);
+    assert_eq!(file.cues().count(), 4);
+
+    let removed = file.prune_empty();
+    assert_eq!(removed.len(), 3);
+    assert_eq!(removed[2].text, "<v Bob></v>");
+    assert_eq!(
+        file.cues().map(|cue| cue.text.as_str()).collect::<Vec<_>>(),
+        ["A"]
+    );
+}
+
+#[test]
+fn split_long_cues() {
+    let mut file = parse_file(
+        "WEBVTT\n\nintro\n00:00.000 --> 00:03.000 line:0\n<i>One\nTwo\nThree\nFour</i> five six\n\n00:04.000 --> 00:05.000\nShort\nEnough\n",
+    )
+    .unwrap();
+
+    assert_eq!(file.split_long_cues(2), 1);
+
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues.len(), 3);
+
+    assert_eq!(cues[0].id, "intro");
+    assert_eq!(cues[0].text, "<i>One</i>\n<i>Two</i>");
+    assert_eq!(cues[0].start, Duration::ZERO);
+    assert_eq!(cues[0].end, Duration::from_millis(750));
+    assert_eq!(cues[0].settings.to_string(), "line:0");
+
+    assert_eq!(cues[1].id, "");
+    assert_eq!(cues[1].text, "<i>Three</i>\n<i>Four</i> five six");
+    assert_eq!(cues[1].start, Duration::from_millis(750));
+    assert_eq!(cues[1].end, Duration::from_secs(3));
+
+    assert_eq!(cues[2].text, "Short\nEnough");
+}
+
+#[test]
+fn snap_to_frames() {
+    let input = "WEBVTT\n\n00:00.010 --> 00:00.930\nA\n\n00:01.000 --> 00:01.010\nB\n\n00:01.012 --> 00:02.000\nC\n";
+
+    let times = |file: &File| {
+        file.cues()
+            .map(|cue| (cue.start.as_millis(), cue.end.as_millis()))
+            .collect::<Vec<_>>()
+    };
+
+    let mut file = parse_file(input).unwrap();
+    file.snap_to_frames(25.0, SnapPolicy::Nearest);
+    assert_eq!(times(&file), [(0, 920), (1000, 1040), (1000, 2000)]);
+
+    let mut file = parse_file(input).unwrap();
+    file.snap_to_frames(25.0, SnapPolicy::NoOverlap);
+    assert_eq!(times(&file), [(0, 920), (1000, 1000), (1000, 2000)]);
+
+    let mut file = parse_file(input).unwrap();
+    file.snap_to_frames(30000.0 / 1001.0, SnapPolicy::Nearest);
+    assert_eq!(times(&file), [(0, 934), (1001, 1034), (1001, 2002)]);
+}
+
+#[test]
+fn snap_to_shot_changes() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.100 --> 00:02.900\nA\n\n00:04.000 --> 00:04.200\nB\n\n00:10.000 --> 00:11.000\nC\n",
+    )
+    .unwrap();
+
+    let shot_changes = [
+        Duration::from_secs(3),
+        Duration::from_secs(1),
+        Duration::from_millis(4100),
+    ];
+
+    assert_eq!(
+        file.snap_to_shot_changes(&shot_changes, Duration::from_millis(200)),
+        3
+    );
+
+    let times: Vec<_> = file
+        .cues()
+        .map(|cue| (cue.start.as_millis(), cue.end.as_millis()))
+        .collect();
+
+    // B's end would move to 4.1 as well, but its start already did
+    assert_eq!(times, [(1000, 3000), (4100, 4200), (10000, 11000)]);
+}
+
+#[test]
+fn burnin() {
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.500\n<v Bob>It's 5:00, <i>{late}</i></v>\nC:\\temp\n\n01:00:00.000 --> 01:00:01.234 line:0 align:left\nTop\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        file.to_ffmpeg_drawtext(&DrawtextOptions::default()),
+        "drawtext=text=It\\\\\\'s 5\\\\:00\\, {late}\nC\\\\:\\\\\\\\temp:expansion=none:fontsize=48:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=8:x=(w-text_w)/2:y=h-text_h-h/12:enable=between(t\\\\\\,1.000\\\\\\,2.500),\n\
+         drawtext=text=Top:expansion=none:fontsize=48:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=8:x=(w-text_w)/2:y=h-text_h-h/12:enable=between(t\\\\\\,3600.000\\\\\\,3601.234)\n"
+    );
+
+    let ass = file.to_ass_for_burnin();
+    assert!(ass.starts_with("[Script Info]\nScriptType: v4.00+\nPlayResX: 1920\nPlayResY: 1080\n"));
+    assert!(ass.contains("\nStyle: Default,Arial,54,"));
+
+    let events: Vec<_> = ass
+        .lines()
+        .filter(|line| line.starts_with("Dialogue:"))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            "Dialogue: 0,0:00:01.00,0:00:02.50,Default,Bob,0,0,0,,It's 5:00, {\\i1}\\{late\\}{\\i0}\\NC:\\\u{2060}temp",
+            "Dialogue: 0,1:00:00.00,1:00:01.23,Default,,0,0,0,,{\\an7\\pos(0,0)}Top",
+        ]
+    );
+}
+
+#[test]
+fn extensions() {
+    let mut file =
+        parse_file("WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n\n00:03.000 --> 00:04.000\nWorld\n")
+            .unwrap();
+
+    let cue = file.blocks[0].as_cue_mut().unwrap();
+    cue.extensions.insert("confidence", 0.93);
+    cue.extensions.insert("odd=key", "a\\b\nc --> d");
+    assert_eq!(cue.extensions.get_parsed::<f64>("confidence"), Some(0.93));
+    assert_eq!(cue.extensions.get_parsed::<u32>("confidence"), None);
+
+    file.shift(Shift::Later(Duration::from_secs(1))).unwrap();
+
+    let written = file.to_string();
+    assert_eq!(
+        written,
+        "WEBVTT\n\nNOTE extensions\nconfidence=0.93\nodd\\=key=a\\\\b\\nc --\\> d\n\n00:02.000 --> 00:03.000\nHello\n\n00:04.000 --> 00:05.000\nWorld\n"
+    );
+
+    let parsed = parse_file(&written).unwrap();
+    let cues: Vec<_> = parsed.cues().collect();
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].extensions, file.cues().next().unwrap().extensions);
+    assert_eq!(cues[0].extensions.get("odd=key"), Some("a\\b\nc --> d"));
+    assert!(cues[1].extensions.is_empty());
+
+    let parsed =
+        parse_file("WEBVTT\n\nNOTE other\nconfidence=1\n\n00:01.000 --> 00:02.000\nHello\n")
+            .unwrap();
+    assert!(parsed.cues().next().unwrap().extensions.is_empty());
+
+    for between in ["NOTE other", "STYLE\n::cue { color: red }", "garbage"] {
+        let input = format!(
+            "WEBVTT\n\nNOTE extensions\nconfidence=1\n\n{between}\n\n00:01.000 --> 00:02.000\nHello\n"
+        );
+        let parsed = parse_file(&input).unwrap();
+        assert!(parsed.cues().next().unwrap().extensions.is_empty());
+    }
+}
+
+#[test]
+fn block_classification() {
+    let input = "WEBVTT\n\na-->b\n00:01.000 --> 00:02.000\nA\n\n2\n00:03.000 --> 00:04.000\nB\nC --> D\nE\n\nx\ny\n00:05.000 --> 00:06.000\nF\n";
+
+    let (file, diagnostics) = parse_file_with(input, &ParseOptions::default()).unwrap();
+    let cues: Vec<_> = file
+        .cues()
+        .map(|cue| (cue.id.as_str(), cue.text.as_str()))
+        .collect();
+    assert_eq!(cues, [("", "A"), ("2", "B"), ("", "F")]);
+    assert_eq!(count_cues(input).unwrap(), 3);
+
+    let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        [DiagnosticKind::ArrowInId, DiagnosticKind::ArrowInText]
+    );
+    assert_eq!(&input[diagnostics[0].offset..][..5], "a-->b");
+    assert_eq!(&input[diagnostics[1].offset..][..2], "2\n");
+
+    let options = ParseOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let (file, _) = parse_file_with(input, &options).unwrap();
+    assert!(matches!(&file.blocks[3], Block::Raw { lines } if lines == &["C --> D", "E"]));
+}
+
+#[test]
+fn classes() {
+    use crate::text::ClassUsage;
+
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\n00:03.000 --> 00:04.000\n<c.yellow.bg_black>B</c> <v.loud Ann>C <c.yellow>D</c></v>\n",
+    )
+    .unwrap();
+
+    let usage = |name: &str, count| ClassUsage {
+        name: name.to_owned(),
+        count,
+        first_cue: 1,
+        first_time: Duration::from_secs(3),
+    };
+    assert_eq!(
+        file.classes(),
+        [usage("yellow", 2), usage("bg_black", 1), usage("loud", 1)]
+    );
+}
+
+#[test]
+fn color_styles() {
+    let input = "WEBVTT\n\n00:01.000 --> 00:02.000\n<c.yellow.bg_black>A</c> <c.loud>B</c>\n";
+
+    let mut file = parse_file(input).unwrap();
+    assert_eq!(file.add_color_styles(), 2);
+    assert!(matches!(
+        &file.blocks[0],
+        Block::Raw { lines } if lines == &[
+            "STYLE",
+            "::cue(.yellow) { color: yellow; }",
+            "::cue(.bg_black) { background-color: black; }",
+        ]
+    ));
+    assert_eq!(file.add_color_styles(), 0);
+
+    let written = file.to_string();
+    let mut parsed = parse_file(&written).unwrap();
+    assert_eq!(parsed.to_string(), written);
+
+    parsed.cues_mut().next().unwrap().text = "<c.red>A</c> <c.yellow>B</c>".into();
+    assert_eq!(parsed.add_color_styles(), 1);
+    assert_eq!(parsed.blocks.len(), 2);
+    assert!(matches!(
+        &parsed.blocks[0],
+        Block::Raw { lines } if lines.len() == 4 && lines[3] == "::cue(.red) { color: red; }"
+    ));
+
+    let input = "WEBVTT\n\nSTYLE\n::cue(.speaker2) { color: #FF0; background-color: black }\n::cue(.loud) { font-weight: bold }\n\n00:01.000 --> 00:02.000\n<c.speaker2>A</c> <c.loud>B</c>\n";
+    let options = ParseOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let (mut file, _) = parse_file_with(input, &options).unwrap();
+    assert_eq!(file.apply_color_styles(), 1);
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "<c.yellow.bg_black>A</c> <c.loud>B</c>"
+    );
+}
+
+#[test]
+fn roll_up() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:03.000 --> 00:04.000\nC\n\n00:01.000 --> 00:03.000 align:start\nA\nB\n\n00:10.000 --> 00:11.000\nD\n",
+    )
+    .unwrap();
+    let secs = Duration::from_secs;
+
+    file.roll_up(2);
+
+    let cues: Vec<_> = file
+        .cues()
+        .map(|cue| (cue.text.as_str(), cue.start, cue.end))
+        .collect();
+    assert_eq!(
+        cues,
+        [
+            ("A", secs(1), secs(3)),
+            ("B", secs(2), secs(4)),
+            ("C", secs(3), secs(4)),
+            ("D", secs(10), secs(11)),
+        ]
+    );
+    assert!(file
+        .cues()
+        .all(|cue| cue.settings.region.as_deref() == Some("rollup")));
+
+    let written = file.to_string();
+    assert!(written.starts_with("WEBVTT\n\nREGION\nid:rollup\nwidth:80%\nlines:2\n"));
+    assert!(written.contains("00:01.000 --> 00:03.000 region:rollup\nA\n"));
+
+    let mut parsed = parse_file(&written).unwrap();
+    assert_eq!(parsed.cues().count(), 4);
+    assert_eq!(parsed.to_string(), written);
+
+    parsed.roll_up(3);
+    assert_eq!(
+        parsed
+            .blocks
+            .iter()
+            .filter(|block| matches!(block, Block::Raw { .. }))
+            .count(),
+        1
+    );
+    assert!(parsed.to_string().contains("lines:3\n"));
+}
+
+#[test]
+fn style_and_region_blocks() {
+    let input = "WEBVTT\n\nSTYLE\n::cue { color: red }\n\nREGION\nid:fred\nwidth:40%\n\n00:01.000 --> 00:02.000 region:fred\nA\n";
+
+    let file = parse_file(input).unwrap();
+    assert_eq!(file.blocks.len(), 3);
+    assert!(
+        matches!(&file.blocks[0], Block::Raw { lines } if lines == &["STYLE", "::cue { color: red }"])
+    );
+    assert!(
+        matches!(&file.blocks[1], Block::Raw { lines } if lines == &["REGION", "id:fred", "width:40%"])
+    );
+    assert_eq!(file.to_string(), input);
+
+    // style blocks after the first cue are not styles, and are dropped
+    let file = parse_file("WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\nSTYLE\n::cue { color: red }\n")
+        .unwrap();
+    assert_eq!(file.blocks.len(), 1);
+
+    let file = parse_file("WEBVTT\n\nREGION\n").unwrap();
+    assert!(matches!(&file.blocks[0], Block::Raw { lines } if lines == &["REGION"]));
+}
+
+#[test]
+fn raw_blocks() {
+    let input = "WEBVTT\n\nSTYLE\n::cue { color: red }\n\nX-VENDOR-THING\nwhatever\n\n00:01.000 --> 00:02.000\nA\n\n2\n00:02.000 --> bad\nB\n";
+
+    let options = ParseOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let (file, _) = parse_file_with(input, &options).unwrap();
+    assert_eq!(file.blocks.len(), 4);
+    assert!(
+        matches!(&file.blocks[0], Block::Raw { lines } if lines == &["STYLE", "::cue { color: red }"])
+    );
+    assert!(
+        matches!(&file.blocks[3], Block::Raw { lines } if lines == &["2", "00:02.000 --> bad", "B"])
+    );
+    assert_eq!(file.to_string(), input);
+
+    let file = parse_file("WEBVTT\n\n00:02.000 --> bad\nB\n").unwrap();
+    assert!(file.blocks.is_empty());
+}
+
+#[test]
+fn source_order() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\nA\n\n00:01.000 --> 00:02.000\nB\n\n00:00.000 --> 00:02.000\nC\n",
+    )
+    .unwrap();
+
+    let orders: Vec<_> = file.cues().map(|cue| cue.source_order).collect();
+    assert_eq!(orders, [0, 1, 2]);
+
+    file.blocks.reverse();
+    file.sort_by_start();
+    let texts: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(texts, ["C", "A", "B"]);
+
+    file.blocks.reverse();
+    let active: Vec<_> = file
+        .cues_at(Duration::from_millis(1500))
+        .into_iter()
+        .map(|cue| cue.text.as_str())
+        .collect();
+    assert_eq!(active, ["A", "B", "C"]);
+    assert_eq!(file.cues_at(Duration::from_secs(2)).len(), 1);
+}
+
+#[test]
+fn notes() {
+    let input = "WEBVTT\n\nNOTE about B\n\n00:05.000 --> 00:06.000\nB\n\nNOTE\nabout A\nspanning lines\n\n00:01.000 --> 00:02.000\nA\n\nNOTE trailing\n";
+
+    let mut file = parse_file(input).unwrap();
+    assert_eq!(file.to_string(), input);
+
+    file.sort_by_start();
+    assert_eq!(
+        file.to_string(),
+        "WEBVTT\n\nNOTE\nabout A\nspanning lines\n\n00:01.000 --> 00:02.000\nA\n\nNOTE about B\n\n00:05.000 --> 00:06.000\nB\n\nNOTE trailing\n"
+    );
+
+    file.blocks
+        .retain(|block| block.as_cue().is_none_or(|cue| cue.text != "A"));
+    assert_eq!(
+        file.to_string(),
+        "WEBVTT\n\nNOTE about B\n\n00:05.000 --> 00:06.000\nB\n\nNOTE trailing\n"
+    );
+
+    let options = ParseOptions {
+        note_attachment: NoteAttachment::Detached,
+        ..Default::default()
+    };
+
+    let (mut file, _) = parse_file_with(input, &options).unwrap();
+    assert!(file.cues().all(|cue| cue.notes.is_empty()));
+    assert!(matches!(&file.blocks[0], Block::Note(note) if note == "NOTE about B"));
+
+    file.sort_by_start();
+    assert_eq!(
+        file.blocks
+            .iter()
+            .map(|block| match block {
+                Block::Cue(cue) => cue.text.clone(),
+                Block::Note(note) => note.clone(),
+                Block::Raw { .. } => unreachable!(),
+            })
+            .collect::<Vec<_>>(),
+        [
+            "NOTE\nabout A\nspanning lines",
+            "A",
+            "NOTE about B",
+            "B",
+            "NOTE trailing"
+        ]
+    );
+
+    // NOTE must be followed by whitespace to start a comment
+    let file = parse_file("WEBVTT\n\nNOTES\n\n00:01.000 --> 00:02.000\nA\n").unwrap();
+    assert_eq!(file.blocks.len(), 1);
+    assert!(file.cues().next().unwrap().notes.is_empty());
+}
+
+#[test]
+fn metadata_payload() {
+    let input = "WEBVTT\n\n00:01.000 --> 00:02.000\n{\"html\": \"<b>&amp;</b>\"}\n";
+
+    let file = parse_file(input).unwrap();
+    assert_eq!(
+        file.cues().next().unwrap().plain_text(),
+        "{\"html\": \"&\"}"
+    );
+
+    let options = ParseOptions {
+        payload: TrackKind::from_attribute("Metadata").unwrap().payload(),
+        ..Default::default()
+    };
+
+    let (mut file, _) = parse_file_with(input, &options).unwrap();
+    let cue = file.cues().next().unwrap();
+    assert!(cue.is_metadata());
+    assert_eq!(cue.plain_text(), "{\"html\": \"<b>&amp;</b>\"}");
+    assert_eq!(
+        cue.render_html(),
+        "{&quot;html&quot;: &quot;&lt;b&gt;&amp;amp;&lt;/b&gt;&quot;}"
+    );
+
+    file.shift(Shift::Later(Duration::from_secs(1))).unwrap();
+    file.add_direction_marks();
+    file.strip_ruby(crate::text::RubyPart::Base);
+    file.blocks[0].as_cue_mut().unwrap().set_forced(true);
+    assert_eq!(
+        file.to_string(),
+        "WEBVTT\n\n00:02.000 --> 00:03.000\n{\"html\": \"<b>&amp;</b>\"}\n"
+    );
+
+    file.set_payload(CuePayload::Text);
+    assert!(!file.cues().next().unwrap().is_metadata());
+    assert_eq!(
+        TrackKind::from_attribute("chapters").unwrap().payload(),
+        CuePayload::Text
+    );
+}
+
+#[cfg(feature = "json-schema")]
+#[test]
+fn metadata_schema() {
+    let options = ParseOptions {
+        payload: CuePayload::Metadata,
+        ..Default::default()
+    };
+
+    let (file, _) = parse_file_with(
+        "WEBVTT\n\nquiz-1\n00:01.000 --> 00:02.000\n{\"type\": \"quiz\", \"question\": \"Why?\"}\n\nquiz-2\n00:03.000 --> 00:04.000\n{\"type\": \"quiz\"}\n\npoll\n00:05.000 --> 00:06.000\n{\"type\": \"poll\",\n\"question\": 5}\n\n00:07.000 --> 00:08.000\nnot json\n",
+        &options,
+    )
+    .unwrap();
+
+    let schema = serde_json::json!({
+        "type": "object",
+        "required": ["type", "question"],
+        "properties": {
+            "type": { "enum": ["quiz", "poll"] },
+            "question": { "type": "string" },
+        },
+    });
+
+    let failing = |file: &File| -> Vec<(String, u64, String)> {
+        file.validate_metadata_schema(&schema)
+            .unwrap()
+            .into_iter()
+            .map(|finding| match finding.kind {
+                FindingKind::SchemaMismatch {
+                    id, start, path, ..
+                } => (id, start.as_secs(), path),
+                FindingKind::InvalidJson { id, start, .. } => {
+                    (id, start.as_secs(), "(not json)".into())
+                }
+                kind => panic!("unexpected finding {kind:?}"),
+            })
+            .collect()
+    };
+
+    let expected = [
+        ("quiz-2".to_owned(), 3, String::new()),
+        ("poll".to_owned(), 5, "/question".to_owned()),
+        (String::new(), 7, "(not json)".to_owned()),
+    ];
+    assert_eq!(failing(&file), expected);
+
+    // cues parsed as captions are checked if their text is JSON
+    let mut captions = file.clone();
+    captions.set_payload(CuePayload::Text);
+    assert_eq!(failing(&captions), expected[..2]);
+
+    assert!(matches!(
+        file.validate_metadata_schema(&serde_json::json!({ "type": 5 })),
+        Err(crate::ValidationError::BadSchema(_))
+    ));
+}
+
+#[test]
+fn timeline() {
+    let file = parse_file(
+        "WEBVTT\n\n00:03.000 --> 00:05.000\nB\n\n00:01.000 --> 00:03.000\nA\n\n00:02.000 --> 00:02.000\nempty\n\n00:01.000 --> 00:04.000\nC\n",
+    )
+    .unwrap();
+
+    let events: Vec<_> = file
+        .timeline()
+        .into_iter()
+        .map(|event| (event.time.as_secs(), event.cue_index, event.transition))
+        .collect();
+
+    assert_eq!(
+        events,
+        [
+            (1, 1, Transition::Enter),
+            (1, 3, Transition::Enter),
+            (3, 1, Transition::Exit),
+            (3, 0, Transition::Enter),
+            (4, 3, Transition::Exit),
+            (5, 0, Transition::Exit),
+        ]
+    );
+}
+
+#[test]
+fn scheduler() {
+    use std::cell::Cell;
+
+    struct ManualClock(Cell<Duration>);
+
+    impl Clock for &ManualClock {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+    }
+
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\nA\n\n00:02.000 --> 00:06.000\nB\n\n00:08.000 --> 00:09.000\nC\n",
+    )
+    .unwrap();
+
+    let clock = ManualClock(Cell::new(Duration::from_secs(100)));
+    let mut scheduler = Scheduler::new(&file, &clock);
+
+    let next = |scheduler: &mut Scheduler<&ManualClock>| {
+        scheduler.next().map(|event| {
+            (
+                clock.0.get().as_millis() - 100_000,
+                event.cue_index,
+                event.transition,
+            )
+        })
+    };
+
+    // paused
+    assert_eq!(next(&mut scheduler), None);
+
+    scheduler.play();
+    assert_eq!(next(&mut scheduler), Some((1000, 0, Transition::Enter)));
+    assert_eq!(next(&mut scheduler), Some((2000, 1, Transition::Enter)));
+
+    scheduler.set_rate(2.0);
+    assert_eq!(next(&mut scheduler), Some((2500, 0, Transition::Exit)));
+    assert_eq!(scheduler.media_time(), Duration::from_secs(3));
+    assert_eq!(scheduler.active().collect::<Vec<_>>(), [1]);
+
+    scheduler.seek(Duration::from_millis(8500));
+    assert_eq!(next(&mut scheduler), Some((2500, 1, Transition::Exit)));
+    assert_eq!(next(&mut scheduler), Some((2500, 2, Transition::Enter)));
+    assert_eq!(
+        scheduler.time_until_next(),
+        Some(Duration::from_millis(250))
+    );
+
+    clock.0.set(clock.0.get() + Duration::from_secs(1));
+    let events = scheduler.poll();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].cue_index, 2);
+    assert_eq!(events[0].transition, Transition::Exit);
+
+    assert_eq!(next(&mut scheduler), None);
+    assert_eq!(scheduler.time_until_next(), None);
+}
+
+#[test]
+fn fill_gaps() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\n00:10.000 --> 00:11.000\nC\n\n00:01.500 --> 00:05.000\nB\n\n00:05.500 --> 00:06.000\nshort gap\n",
+    )
+    .unwrap();
+
+    assert_eq!(file.fill_gaps("[no dialogue]", Duration::from_secs(1)), 1);
+
+    let cues: Vec<_> = file
+        .cues()
+        .map(|cue| {
+            (
+                cue.start.as_millis(),
+                cue.end.as_millis(),
+                cue.text.as_str(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        cues,
+        [
+            (1000, 2000, "A"),
+            (6000, 10000, "[no dialogue]"),
+            (10000, 11000, "C"),
+            (1500, 5000, "B"),
+            (5500, 6000, "short gap"),
+        ]
+    );
+
+    let mut file =
+        parse_file("WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\n00:10.000 --> 00:11.000\nB\n").unwrap();
+    file.fill_gaps("<music & silence>", Duration::from_secs(1));
+    let placeholder = file.cues().nth(1).unwrap();
+    assert_eq!(placeholder.text, "&lt;music &amp; silence&gt;");
+    assert_eq!(placeholder.plain_text(), "<music & silence>");
+}
+
+#[test]
+fn non_speech() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\n[door slams]\n\n00:02.000 --> 00:03.000\n- (laughs)\n- <i>[upbeat music]</i>\n\n00:03.000 --> 00:04.000\n♪ Never gonna give you up ♪\n\n00:04.000 --> 00:05.000\n<v Ann>[sighs] <i>Fine.</i></v>\n- [beep]\n",
+    )
+    .unwrap();
+
+    let classes: Vec<_> = file.cues().map(Cue::classify).collect();
+    assert_eq!(
+        classes,
+        [
+            CueClass::SoundEffect,
+            CueClass::Music,
+            CueClass::Music,
+            CueClass::Speech
+        ]
+    );
+
+    let mut styled = file.clone();
+    styled.style_non_speech("sfx");
+    assert_eq!(
+        styled
+            .cues()
+            .map(|cue| cue.text.as_str())
+            .collect::<Vec<_>>(),
+        [
+            "<c.sfx>[door slams]</c>",
+            "- <c.sfx>(laughs)</c>\n- <i><c.sfx>[upbeat music]</c></i>",
+            "<c.sfx>♪ Never gonna give you up ♪</c>",
+            "<v Ann><c.sfx>[sighs]</c> <i>Fine.</i></v>\n- <c.sfx>[beep]</c>",
+        ]
+    );
+
+    assert_eq!(file.strip_non_speech(), 3);
+    assert_eq!(
+        file.cues().map(|cue| cue.text.as_str()).collect::<Vec<_>>(),
+        ["<v Ann><i>Fine.</i></v>"]
+    );
+
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\n[laughs] Hello <i>there</i>\nWell [sighs]  <b>fine</b> (beat) then\n",
+    )
+    .unwrap();
+    assert_eq!(file.strip_non_speech(), 0);
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "Hello <i>there</i>\nWell <b>fine</b> then"
+    );
+}
+
+#[test]
+fn normalize() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\n<v BOB>I'M NOT SURE.... WHAT DID  <i>I</i> SAY?</v>\n\"IT'S FINE,\" SAID I.\n\n00:02.000 --> 00:03.000\nKeep NASA's   \"case\" ... as is.\n",
+    )
+    .unwrap();
+
+    file.normalize(&NormalizeOptions::default());
+
+    assert_eq!(
+        file.cues().map(|cue| cue.text.as_str()).collect::<Vec<_>>(),
+        [
+            "<v BOB>I’m not sure… What did <i>I</i> say?</v>\n“It’s fine,” said I.",
+            "Keep NASA’s “case” … as is.",
+        ]
+    );
+
+    file.normalize(&NormalizeOptions {
+        sentence_case: false,
+        quotes: Some(QuoteStyle::Straight),
+        ellipses: false,
+        collapse_spaces: false,
+        nfc: false,
+    });
+
+    assert_eq!(
+        file.cues().nth(1).unwrap().text,
+        "Keep NASA's \"case\" … as is."
+    );
+}
+
+#[test]
+fn strip_sdh() {
+    let input = "WEBVTT\n\n00:01.000 --> 00:02.000\n- BOB: [panting] Run!\n- MAN #2: <i>Where?</i>\n\n00:02.000 --> 00:03.000\n[door slams]\n\n00:03.000 --> 00:04.000\n<i>♪ Never gonna give you up ♪</i>\n\n00:04.000 --> 00:05.000\nNote: {whispers} keep Mr. Smith's time: 5:00\n";
+
+    let mut file = parse_file(input).unwrap();
+    assert_eq!(file.strip_sdh(&SdhOptions::default()), 1);
+    assert_eq!(
+        file.cues().map(|cue| cue.text.as_str()).collect::<Vec<_>>(),
+        [
+            "- Run!\n- <i>Where?</i>",
+            "<i>Never gonna give you up</i>",
+            "Note: {whispers} keep Mr. Smith's time: 5:00",
+        ]
+    );
+
+    let mut file = parse_file(input).unwrap();
+    let options = SdhOptions {
+        speaker_labels: false,
+        brackets: vec![('{', '}')],
+        music_notes: false,
+    };
+
+    assert_eq!(file.strip_sdh(&options), 0);
+    assert_eq!(
+        file.cues().nth(3).unwrap().text,
+        "Note: keep Mr. Smith's time: 5:00"
+    );
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "- BOB: [panting] Run!\n- MAN #2: <i>Where?</i>"
+    );
+
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nANN: [gasps] Look <i>out</i>!\n<b>Now</b> [thud] <i>go</i>\n",
+    )
+    .unwrap();
+    assert_eq!(file.strip_sdh(&SdhOptions::default()), 0);
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "Look <i>out</i>!\n<b>Now</b> <i>go</i>"
+    );
+}
+
+#[test]
+fn reading_speed() {
+    let input = "WEBVTT\n\n00:00.000 --> 00:01.000\nThis line is far too long to read\n\n00:03.000 --> 00:04.000\nThis one is too fast as well!!\n\n00:04.000 --> 00:06.000\nOk.\n\n00:10.000 --> 00:11.000\n<i>Another line that is much too long</i> for its time\n\n00:12.000 --> 00:12.100\nHi!\n";
+
+    let mut file = parse_file(input).unwrap();
+    assert_eq!(file.fix_reading_speed(20.0, &[]), [0, 1, 3, 4]);
+
+    let fixes = [SpeedFix::Extend, SpeedFix::Merge, SpeedFix::Truncate];
+
+    let mut unchanged = file.clone();
+    for max_cps in [0.0, -1.0, f64::NAN] {
+        assert_eq!(unchanged.fix_reading_speed(max_cps, &fixes).len(), 5);
+    }
+    assert_eq!(unchanged.to_string(), input);
+
+    let mut slowest = parse_file("WEBVTT\n\n00:00.000 --> 00:01.000\nSlow\n").unwrap();
+    assert_eq!(slowest.fix_reading_speed(1e-300, &[SpeedFix::Extend]), [0]);
+
+    assert_eq!(file.fix_reading_speed(20.0, &fixes), [] as [usize; 0]);
+
+    let cues: Vec<_> = file
+        .cues()
+        .map(|cue| {
+            (
+                cue.start.as_millis(),
+                cue.end.as_millis(),
+                cue.text.as_str(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        cues,
+        [
+            (0, 1650, "This line is far too long to read"),
+            (3000, 6000, "This one is too fast as well!!\nOk."),
+            (
+                10000,
+                12000,
+                "<i>Another line that is much too long</i> for…"
+            ),
+            (12000, 12150, "Hi!"),
+        ]
+    );
+
+    assert!(cues
+        .iter()
+        .zip(file.cues())
+        .all(|(_, cue)| cue.chars_per_second() <= 20.0));
+}
+
+#[test]
+fn profiles() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:05.000 --> 00:05.500\nShort\n\n00:00.000 --> 00:09.000\nThis first line is definitely longer than forty-two\nTwo\nThree\n\n00:05.550 --> 00:06.000\nGap\n",
+    )
+    .unwrap();
+
+    assert_eq!(file.check_profile(&Profile::Hls).len(), 1);
+
+    let kinds: Vec<_> = file
+        .check_profile(&Profile::Netflix)
+        .into_iter()
+        .map(|finding| (finding.cue_index, finding.kind))
+        .collect();
+
+    assert_eq!(
+        kinds,
+        [
+            (
+                0,
+                FindingKind::TooShort {
+                    duration: Duration::from_millis(500)
+                }
+            ),
+            (
+                1,
+                FindingKind::LineTooLong {
+                    line: 0,
+                    length: 51,
+                    max: 42
+                }
+            ),
+            (1, FindingKind::TooManyLines { lines: 3, max: 2 }),
+            (
+                1,
+                FindingKind::TooLong {
+                    duration: Duration::from_secs(9)
+                }
+            ),
+            (
+                1,
+                FindingKind::Unsorted {
+                    previous: Duration::from_secs(5)
+                }
+            ),
+            (
+                2,
+                FindingKind::TooShort {
+                    duration: Duration::from_millis(450)
+                }
+            ),
+        ]
+    );
+
+    let remaining: Vec<_> = file
+        .conform(&Profile::Netflix)
+        .into_iter()
+        .map(|finding| (finding.cue_index, finding.kind))
+        .collect();
+
+    assert_eq!(
+        remaining,
+        [
+            (
+                0,
+                FindingKind::LineTooLong {
+                    line: 0,
+                    length: 51,
+                    max: 42
+                }
+            ),
+            (
+                1,
+                FindingKind::TooShort {
+                    duration: Duration::from_millis(550)
+                }
+            ),
+        ]
+    );
+
+    let times: Vec<_> = file
+        .cues()
+        .map(|cue| (cue.start.as_millis(), cue.end.as_millis()))
+        .collect();
+
+    assert_eq!(times, [(0, 7000), (5000, 5550), (5550, 6383), (8237, 9070)]);
+    assert_eq!(file.format.timestamps, TimestampStyle::Full);
+    assert!(file
+        .to_string()
+        .contains("\n00:00:00.000 --> 00:00:07.000\n"));
+
+    let custom = Profile::Custom(ProfileRules {
+        max_lines: Some(1),
+        ..Default::default()
+    });
+
+    assert_eq!(file.check_profile(&custom).len(), 1);
+}
+
+#[test]
+fn content_hash() {
+    let file = parse_file(
+        "WEBVTT Original\n\nintro\n00:01.000 --> 00:02.000 align:start line:0\n<i>Hello</i>  &amp; welcome\n",
+    )
+    .unwrap();
+
+    let reformatted = parse_file(
+        "WEBVTT\n\nNOTE edited\n\nintro\n00:00:01.000\t-->\t00:00:02.000 line:0 align:start\n<i >Hello</i> &#38;\twelcome\n",
+    )
+    .unwrap();
+
+    let edited = parse_file(
+        "WEBVTT\n\nintro\n00:01.000 --> 00:02.000 align:start line:0\n<i>Hello</i> & welcome!\n",
+    )
+    .unwrap();
+
+    assert_eq!(file.content_hash(), reformatted.content_hash());
+    assert_ne!(file.content_hash(), edited.content_hash());
+    assert_eq!(
+        parse_file("WEBVTT\n").unwrap().content_hash(),
+        0xcbf2_9ce4_8422_2325
+    );
+}
+
+#[test]
+fn header_and_count() {
+    let input = "WEBVTT - Episode 1\nKind: captions\nLanguage: en\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\nNOTE 00:01.000 --> 00:02.000 is not a cue\n\n1\n00:01.000 --> 00:02.000\nA\n\n00:02.000 --> bad\nB\n\n00:03.000 --> 00:04.000\nC\n";
+
+    let header = parse_header(input).unwrap();
+    assert_eq!(header.description.as_deref(), Some("- Episode 1"));
+    assert_eq!(header.get("kind"), Some("captions"));
+    assert_eq!(header.get("Language"), Some("en"));
+    assert_eq!(
+        header.get("X-TIMESTAMP-MAP"),
+        Some("MPEGTS:900000,LOCAL:00:00:00.000")
+    );
+    assert_eq!(header.get("Region"), None);
+
+    assert_eq!(count_cues(input).unwrap(), 2);
+    assert_eq!(
+        count_cues(input).unwrap(),
+        parse_file(input).unwrap().cues().count()
+    );
+
+    for sample in [
+        include_str!("../test/sample1.vtt"),
+        include_str!("../test/sample2.vtt"),
+    ] {
+        assert_eq!(
+            count_cues(sample).unwrap(),
+            parse_file(sample).unwrap().cues().count()
+        );
+    }
+
+    assert!(matches!(
+        parse_header("WEBVTTX"),
+        Err(ParseError::BadHeader { line }) if line == "WEBVTTX"
+    ));
+    assert!(matches!(count_cues(""), Err(ParseError::NoMagic)));
+}
+
+#[test]
+fn span_and_display_time() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:05.000 --> 00:07.000\nB\n\n00:01.000 --> 00:03.000\nA\n\n00:02.000 --> 00:04.000\nA2\n\n00:06.000 --> 00:06.500\ninside B\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        file.span(),
+        Some((Duration::from_secs(1), Duration::from_secs(7)))
+    );
+    assert_eq!(file.total_display_time(), Duration::from_secs(5));
+
+    file.blocks.remove(0);
+    assert_eq!(
+        file.span(),
+        Some((Duration::from_secs(1), Duration::from_millis(6500)))
+    );
+    assert_eq!(file.total_display_time(), Duration::from_millis(3500));
+
+    file.blocks.clear();
+    assert_eq!(file.span(), None);
+    assert_eq!(file.total_display_time(), Duration::ZERO);
+}
+
+#[test]
+fn edit_session() {
+    use std::sync::{Arc, Mutex};
+
+    let file = parse_file("WEBVTT\n\n00:01.000 --> 00:02.000\nOne\n").unwrap();
+    let mut session = EditSession::new(file);
+
+    let changes = Arc::new(Mutex::new(vec![]));
+    let log = changes.clone();
+    session.subscribe(move |change| log.lock().unwrap().push(change.clone()));
+
+    let text = |session: &EditSession| session.file().cues().next().unwrap().text.clone();
+
+    session.edit("retype", |file| {
+        file.cues_mut().next().unwrap().text = "Uno".into()
+    });
+
+    session.begin_group("shift and retype");
+    session
+        .edit("shift", |file| {
+            file.shift(Shift::Later(Duration::from_secs(1)))
+        })
+        .unwrap();
+    session.edit("retype", |file| {
+        file.cues_mut().next().unwrap().text = "Eins".into()
+    });
+    session.end_group();
+
+    session.begin_group("nothing");
+    session.end_group();
+
+    assert_eq!(text(&session), "Eins");
+    assert_eq!(session.undo_label(), Some("shift and retype"));
+
+    assert!(session.undo());
+    assert_eq!(text(&session), "Uno");
+    assert_eq!(
+        session.file().cues().next().unwrap().start,
+        Duration::from_secs(1)
+    );
+
+    assert!(session.undo());
+    assert_eq!(text(&session), "One");
+    assert!(!session.undo());
+
+    assert!(session.redo());
+    assert_eq!(text(&session), "Uno");
+    assert_eq!(session.redo_label(), Some("shift and retype"));
+
+    session.edit("retype", |file| {
+        file.cues_mut().next().unwrap().text = "Ein".into()
+    });
+    assert!(!session.can_redo());
+    assert_eq!(session.into_file().cues().next().unwrap().text, "Ein");
+
+    let kinds: Vec<_> = changes
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|change| change.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ChangeKind::Edit,
+            ChangeKind::Edit,
+            ChangeKind::Edit,
+            ChangeKind::Undo,
+            ChangeKind::Undo,
+            ChangeKind::Redo,
+            ChangeKind::Edit,
+        ]
+    );
+}
+
+#[test]
+fn merge_concurrent_edits() {
+    let base = parse_file(
+        "WEBVTT\n\na\n00:01.000 --> 00:02.000\nOne\n\nb\n00:03.000 --> 00:04.000\nTwo\n\nc\n00:05.000 --> 00:06.000\nThree\n\nd\n00:07.000 --> 00:08.000\nFour\n",
+    )
+    .unwrap();
+
+    // we retime a, fix the text of b, delete c and retype d
+    let ours = parse_file(
+        "WEBVTT\n\na\n00:01.500 --> 00:02.000\nOne\n\nb\n00:03.000 --> 00:04.000\nTwo!\n\nd\n00:07.000 --> 00:08.000\nFour?\n",
+    )
+    .unwrap();
+
+    // they fix the text of a, retype b, edit c, retype d, and add e early on
+    let theirs = parse_file(
+        "WEBVTT\n\ne\n00:00.000 --> 00:00.500\nZero\n\na\n00:01.000 --> 00:02.000\nUno\n\nb\n00:03.000 --> 00:04.000\nDos\n\nc\n00:05.000 --> 00:06.000 align:start\nThree\n\nd\n00:07.000 --> 00:08.000\nFour?\n",
+    )
+    .unwrap();
+
+    let (merged, conflicts) = merge_edits(&base, &ours, &theirs);
+
+    let cues: Vec<_> = merged
+        .cues()
+        .map(|cue| (cue.id.as_str(), cue.start.as_millis(), cue.text.as_str()))
+        .collect();
+    assert_eq!(
+        cues,
+        [
+            ("e", 0, "Zero"),
+            ("a", 1500, "Uno"),
+            ("b", 3000, "Two!"),
+            ("c", 5000, "Three"),
+            ("d", 7000, "Four?"),
+        ]
+    );
+    assert_eq!(
+        conflicts,
+        [
+            MergeConflict {
+                cue_index: 2,
+                kind: ConflictKind::Text,
+            },
+            MergeConflict {
+                cue_index: 3,
+                kind: ConflictKind::EditedAndDeleted,
+            },
+        ]
+    );
+}
+
+#[test]
+fn merge_without_unique_ids() {
+    let texts = |file: &File| -> Vec<(u128, String)> {
+        file.cues()
+            .map(|cue| (cue.start.as_millis(), cue.text.clone()))
+            .collect()
+    };
+
+    let base = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\n00:03.000 --> 00:04.000\nB\n\n00:05.000 --> 00:06.000\nC\n",
+    )
+    .unwrap();
+    // we add a cue at the start and fix the text of C, they retime B
+    let ours = parse_file(
+        "WEBVTT\n\n00:00.000 --> 00:00.500\nX\n\n00:01.000 --> 00:02.000\nA\n\n00:03.000 --> 00:04.000\nB\n\n00:05.000 --> 00:06.000\nC!\n",
+    )
+    .unwrap();
+    let theirs = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\n00:03.500 --> 00:04.000\nB\n\n00:05.000 --> 00:06.000\nC\n",
+    )
+    .unwrap();
+
+    let (merged, conflicts) = merge_edits(&base, &ours, &theirs);
+    assert_eq!(
+        texts(&merged),
+        [
+            (0, "X".into()),
+            (1000, "A".into()),
+            (3500, "B".into()),
+            (5000, "C!".into())
+        ]
+    );
+    assert!(conflicts.is_empty());
+
+    // both add the same cue, which is only kept once
+    let (merged, conflicts) = merge_edits(&base, &ours, &ours);
+    assert_eq!(merged.cues().count(), 4);
+    assert!(conflicts.is_empty());
+
+    let base = parse_file(
+        "WEBVTT\n\ndup\n00:01.000 --> 00:02.000\nA\n\ndup\n00:03.000 --> 00:04.000\nB\n",
+    )
+    .unwrap();
+    let ours = parse_file(
+        "WEBVTT\n\ndup\n00:01.000 --> 00:02.000\nA\n\ndup\n00:03.000 --> 00:04.000\nB!\n",
+    )
+    .unwrap();
+    let theirs = parse_file(
+        "WEBVTT\n\ndup\n00:01.000 --> 00:02.000\nA!\n\ndup\n00:03.000 --> 00:04.000\nB\n",
+    )
+    .unwrap();
+
+    let (merged, conflicts) = merge_edits(&base, &ours, &theirs);
+    assert_eq!(texts(&merged), [(1000, "A!".into()), (3000, "B!".into())]);
+    assert!(conflicts.is_empty());
+}
+
+#[test]
+fn rebase_on_markers() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nIntro\n\nNOTE extensions\nanchor=credits\n\n00:09.000 --> 00:10.000\nRolled early\n\n00:11.000 --> 00:12.000\nChapter two\n\n00:21.000 --> 00:22.000\nCut chapter\n",
+    )
+    .unwrap();
+    assert_eq!(file.cues().nth(1).unwrap().anchor(), Some("credits"));
+
+    let old = [
+        Marker::new("intro", Duration::ZERO),
+        Marker::new("two", Duration::from_secs(10)),
+        Marker::new("credits", Duration::from_secs(10)),
+        Marker::new("three", Duration::from_secs(20)),
+    ];
+    let new = [
+        Marker::new("intro", Duration::ZERO),
+        Marker::new("two", Duration::from_secs(15)),
+        Marker::new("credits", Duration::from_secs(30)),
+    ];
+
+    assert_eq!(file.rebase_on_markers(&old, &new), 2);
+
+    let starts: Vec<_> = file.cues().map(|cue| cue.start.as_secs()).collect();
+    assert_eq!(starts, [1, 29, 16, 21]);
+    assert_eq!(file.cues().nth(1).unwrap().end, Duration::from_secs(30));
+
+    let cue = file.cues_mut().next().unwrap();
+    cue.set_anchor(Some("intro"));
+    assert!(file
+        .to_string()
+        .contains("NOTE extensions\nanchor=intro\n\n00:01.000"));
+}
+
+#[test]
+fn align_with() {
+    /// Finds each word of the cue in a fixed transcript of word timings.
+    struct Transcript(Vec<(&'static str, u64, u64)>, Vec<(Duration, Duration)>);
+
+    impl Aligner for Transcript {
+        fn align(
+            &mut self,
+            text: &str,
+            window: (Duration, Duration),
+        ) -> Option<(Duration, Duration)> {
+            self.1.push(window);
+
+            let words: Vec<_> = self
+                .0
+                .iter()
+                .filter(|(word, _, _)| text.contains(word))
+                .collect();
+
+            let start = words.first()?.1;
+            let end = words.last()?.2;
+            Some((Duration::from_millis(start), Duration::from_millis(end)))
+        }
+    }
+
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\n<b>Hello</b> there\n\n00:03.000 --> 00:06.000\nGeneral Kenobi\n\n00:07.000 --> 00:08.000\n[music]\n",
+    )
+    .unwrap();
+
+    let mut aligner = Transcript(
+        vec![
+            ("Hello", 1200, 1500),
+            ("there", 1550, 1900),
+            ("General", 3400, 3900),
+            ("Kenobi", 4000, 7500),
+        ],
+        vec![],
+    );
+
+    assert_eq!(file.align_with(&mut aligner), 2);
+
+    let spans: Vec<_> = file
+        .cues()
+        .map(|cue| (cue.start.as_millis(), cue.end.as_millis()))
+        .collect();
+    assert_eq!(spans, [(1200, 1900), (3400, 7000), (7000, 8000)]);
+    assert_eq!(
+        aligner.1[1],
+        (Duration::from_secs(3), Duration::from_secs(7))
+    );
+}
+
+#[test]
+fn quality_score() {
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:03.000\nFine\n\n00:03.500 --> 00:03.600\nWay too much text to read in a tenth of a second\n\n00:04.000 --> 00:06.000\nOverlapping\n\n00:05.000 --> 00:07.000\nFine too\n",
+    )
+    .unwrap();
+
+    let rubric = Rubric {
+        line_length: 0.0,
+        ..Rubric::default()
+    };
+    let quality = file.quality_score(&rubric);
+
+    let categories: Vec<_> = quality
+        .categories
+        .iter()
+        .map(|c| (c.category, c.failing_cues, c.score))
+        .collect();
+    assert_eq!(
+        categories,
+        [
+            (QualityCategory::ReadingSpeed, 1, 75.0),
+            (QualityCategory::LineLength, 1, 75.0),
+            (QualityCategory::Duration, 1, 75.0),
+            (QualityCategory::Spacing, 1, 75.0),
+        ]
+    );
+    assert_eq!(quality.score, 75.0);
+
+    let empty = parse_file("WEBVTT\n").unwrap();
+    assert_eq!(empty.quality_score(&rubric).score, 100.0);
+}
+
+#[test]
+fn mask_words() {
+    let input = "WEBVTT\n\n00:01.000 --> 00:03.000\n<v Bob>Darn it, <i>DARN</i> heck!</v>\n\n00:03.000 --> 00:04.000\nDarnation\n";
+    let words: WordList = ["darn", "heck"].into_iter().collect();
+
+    let mut file = parse_file(input).unwrap();
+    assert_eq!(file.mask_words(&words, MaskStyle::Asterisks), 3);
+
+    let texts: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        ["<v Bob>**** it, <i>****</i> ****!</v>", "Darnation"]
+    );
+    assert_eq!(file.cues().next().unwrap().end, Duration::from_secs(3));
+
+    let mut file = parse_file(input).unwrap();
+    file.mask_words(&words, MaskStyle::FirstLetter);
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "<v Bob>D*** it, <i>D***</i> h***!</v>"
+    );
+
+    let mut file = parse_file(input).unwrap();
+    file.mask_words(&words, MaskStyle::Beep);
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "<v Bob><c.beep>[beep]</c> it, <i><c.beep>[beep]</c></i> <c.beep>[beep]</c>!</v>"
+    );
+}
+
+#[test]
+fn confidence() {
+    let input = "WEBVTT\n\nNOTE extensions\nconfidence=0.9\nword_confidence=0.98 0.2 0.95\n\n00:01.000 --> 00:02.000\nTurn <b>left here</b>\n\nNOTE extensions\nconfidence=0.4\n\n00:02.000 --> 00:03.000\nMumble\n\n00:03.000 --> 00:04.000\nUnscored\n";
+
+    let mut file = parse_file(input).unwrap();
+    assert_eq!(file.cues().next().unwrap().confidence(), Some(0.9));
+    assert_eq!(file.mark_low_confidence(0.5, "unsure"), 2);
+
+    let texts: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "Turn <b><c.unsure>left</c> here</b>",
+            "<c.unsure>Mumble</c>",
+            "Unscored",
+        ]
+    );
+
+    let mut file = parse_file(input).unwrap();
+    assert_eq!(file.filter_by_confidence(0.5), 1);
+
+    let texts: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(texts, ["Turn <b>left here</b>", "Unscored"]);
+}
+
+#[test]
+fn realign_text() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nthe quick brown\n\n00:02.000 --> 00:03.000\n<i>focks jumped</i>\n\n00:03.000 --> 00:04.000\nover the\nlazy dog\n",
+    )
+    .unwrap();
+
+    let changed = file.realign_text("So, the quick brown fox jumps over the very lazy dog.");
+    assert_eq!(changed, 3);
+
+    let texts: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "So, the quick brown",
+            "fox jumps",
+            "over the very lazy dog."
+        ]
+    );
+    assert_eq!(file.cues().nth(1).unwrap().start, Duration::from_secs(2));
+
+    assert_eq!(
+        file.realign_text("So, the quick brown fox jumps over the very lazy dog."),
+        0
+    );
+}
+
+#[test]
+fn header_separator() {
+    for input in [
+        "WEBVTT\n",
+        "WEBVTT \n",
+        "WEBVTT - Episode 1\n",
+        "WEBVTT\tEpisode 1\n",
+    ] {
+        assert_eq!(parse_file(input).unwrap().to_string(), input);
+    }
+
+    let mut file = parse_file("WEBVTT\tEpisode 1\n").unwrap();
+    assert_eq!(file.format.header_separator, HeaderSeparator::Tab);
+    assert_eq!(file.description.as_deref(), Some("Episode 1"));
+
+    file.format.header_separator = HeaderSeparator::Space;
+    assert_eq!(file.to_string(), "WEBVTT Episode 1\n");
+
+    file.description = None;
+    assert_eq!(file.to_string(), "WEBVTT\n");
+}
+
+#[test]
+fn from_html_track_list() {
+    let html = r#"<html><body><article class="transcript">
+<h1>Evening news</h1>
+<p data-begin="12.5" data-end='15'>Good <b>evening</b>.<br>I'm Ann &amp; Bob.</p>
+<div data-start="00:00:15.000"><p>Our top story<span> tonight&#8230;</span></p></div>
+<P DATA-BEGIN="20s" data-end="21.25">x < y</P>
+<p data-begin=22 data-end=23>  </p>
+</article></body></html>"#;
+
+    let file = File::from_html_track_list(html).unwrap();
+
+    let cues: Vec<_> = file
+        .cues()
+        .map(|cue| {
+            (
+                cue.start.as_millis(),
+                cue.end.as_millis(),
+                cue.text.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        cues,
+        [
+            (12500, 15000, "Good evening.\nI'm Ann &amp; Bob."),
+            (15000, 20000, "Our top story tonight…"),
+            (20000, 21250, "x &lt; y"),
+        ]
+    );
+
+    assert!(matches!(
+        File::from_html_track_list(r#"<p data-begin="1">A</p>"#),
+        Err(ConvertError::BadHtml {
+            element: 1,
+            reason: "missing end time"
+        })
+    ));
+    assert!(matches!(
+        File::from_html_track_list(r#"<p data-begin="soon">A</p>"#),
+        Err(ConvertError::BadHtml { element: 1, .. })
+    ));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn podcast_chapters() {
+    let json = r#"{
+        "version": "1.2.0",
+        "title": "Episode 12",
+        "chapters": [
+            { "startTime": 95.5, "title": "Q&A", "url": "https://example.com/qa", "toc": false },
+            { "startTime": 0, "title": "Intro", "img": "https://example.com/intro.png" },
+            { "startTime": 30, "endTime": 90, "title": "Interview" }
+        ]
+    }"#;
+
+    let file = File::from_podcast_chapters(json, Some(Duration::from_secs(120))).unwrap();
+    assert_eq!(file.description.as_deref(), Some("Episode 12"));
+
+    let cues: Vec<_> = file
+        .cues()
+        .map(|cue| {
+            (
+                cue.start.as_millis(),
+                cue.end.as_millis(),
+                cue.text.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        cues,
+        [
+            (0, 30000, "Intro"),
+            (30000, 90000, "Interview"),
+            (95500, 120000, "Q&amp;A"),
+        ]
+    );
+
+    let file = parse_file(&file.to_string()).unwrap();
+    let exported: serde_json::Value = serde_json::from_str(&file.to_podcast_chapters()).unwrap();
+    assert_eq!(
+        exported,
+        serde_json::json!({
+            "version": "1.2.0",
+            "title": "Episode 12",
+            "chapters": [
+                { "startTime": 0.0, "endTime": 30.0, "title": "Intro", "img": "https://example.com/intro.png" },
+                { "startTime": 30.0, "endTime": 90.0, "title": "Interview" },
+                { "startTime": 95.5, "endTime": 120.0, "title": "Q&A", "url": "https://example.com/qa", "toc": false },
+            ]
+        })
+    );
+
+    assert!(matches!(
+        File::from_podcast_chapters(r#"{"chapters": [{"startTime": 1}]}"#, None),
+        Err(ConvertError::BadChapters(_))
+    ));
+}
+
+#[cfg(feature = "srv3")]
+#[test]
+fn from_srv3() {
+    let file = File::from_srv3(
+        r##"<?xml version="1.0" encoding="utf-8" ?>
+<timedtext format="3">
+<head>
+<pen id="1" b="1" fc="#FFFF00"/>
+<pen id="2" i="1"/>
+<wp id="1" ap="0" ah="10" av="20"/>
+<ws id="1" ju="0"/>
+</head>
+<body>
+<p t="1000" d="2000" wp="1" ws="1"><s>Hello</s><s t="500" p="1"> world</s></p>
+<p t="3000" d="0" w="1" a="1">
+</p>
+<p t="3500" d="1000" p="2">Plain &amp; simple</p>
+</body>
+</timedtext>"##,
+    )
+    .unwrap();
+
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].start, Duration::from_secs(1));
+    assert_eq!(cues[0].end, Duration::from_secs(3));
+    assert_eq!(
+        cues[0].text,
+        "Hello <00:00:01.500><c.yellow><b>world</b></c>"
+    );
+    assert_eq!(
+        cues[0].settings.line,
+        Line::Percentage(Percentage::new(20.0).unwrap())
+    );
+    assert_eq!(cues[0].settings.position, Percentage::new(10.0));
+    assert_eq!(cues[0].settings.position_align, PositionAlign::LineLeft);
+    assert_eq!(cues[0].settings.align, Align::Left);
+    assert_eq!(cues[1].text, "<i>Plain &amp; simple</i>");
+    assert_eq!(cues[1].settings, CueSettings::default());
+
+    assert!(File::from_srv3("<timedtext><body><p d=\"10\">Hi</p></body></timedtext>").is_err());
+}
+
+#[test]
+fn karaoke() {
+    let file = parse_file(
+        "WEBVTT\n\nintro\n00:01.000 --> 00:04.000\n<i>Hello,</i> <00:02.000>big\n<00:03.000>world &amp; more\n\n00:05.000 --> 00:06.000\nab abc\n",
+    )
+    .unwrap();
+
+    let parts = |unit| -> Vec<(u64, u64, String)> {
+        file.to_karaoke(unit)
+            .cues()
+            .map(|cue| {
+                (
+                    cue.start.as_millis() as u64,
+                    cue.end.as_millis() as u64,
+                    cue.text.clone(),
+                )
+            })
+            .collect()
+    };
+
+    let words = parts(KaraokeUnit::Word);
+    assert_eq!(words.len(), 7);
+    assert_eq!(words[0], (1000, 2000, "Hello,".to_owned()));
+    assert_eq!(words[1], (2000, 3000, "big".to_owned()));
+    assert_eq!(words[3], (3500, 3600, "&amp;".to_owned()));
+    assert_eq!(words[5], (5000, 5400, "ab".to_owned()));
+    assert_eq!(words[6], (5400, 6000, "abc".to_owned()));
+
+    let phrases = parts(KaraokeUnit::Phrase);
+    assert_eq!(phrases[0], (1000, 2000, "Hello,".to_owned()));
+    assert_eq!(phrases[1], (2000, 3000, "big".to_owned()));
+    assert_eq!(phrases[2], (3000, 4000, "world &amp; more".to_owned()));
+
+    let lines = parts(KaraokeUnit::Line);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], (1000, 3000, "Hello, big".to_owned()));
+    assert_eq!(
+        file.to_karaoke(KaraokeUnit::Line).cues().next().unwrap().id,
+        "intro"
+    );
+}
+
+#[test]
+fn paginate() {
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nA\n\n00:08.000 --> 00:25.000\nB\n\n00:09.000 --> 00:10.000\nC\n\n00:12.000 --> 00:13.000\nD\n\n00:40.000 --> 00:40.000\nE\n",
+    )
+    .unwrap();
+
+    let pages = file.paginate(Duration::from_secs(10));
+    assert_eq!(pages.len(), 3);
+    assert_eq!(pages[0].cues, [0, 1, 2]);
+    assert!(pages[0].continues);
+    assert_eq!(
+        (pages[1].index, pages[1].start),
+        (1, Duration::from_secs(10))
+    );
+    assert_eq!(pages[1].cues, [3]);
+    assert_eq!(pages[1].continued, [1]);
+    assert!(pages[1].continues);
+    assert_eq!(pages[2].index, 4);
+    assert_eq!(pages[2].start, Duration::from_secs(40));
+    assert_eq!(pages[2].cues, [4]);
+    assert!(pages[2].continued.is_empty() && !pages[2].continues);
+    assert!(!pages[2].has_next && pages[1].has_next);
+
+    let pages = file.paginate(Duration::from_nanos(1));
+    assert_eq!(pages.len(), 5);
+    assert_eq!(pages[4].index, 40_000_000_000);
+    assert_eq!(pages[4].end, Duration::new(40, 1));
+
+    assert!(parse_file("WEBVTT\n")
+        .unwrap()
+        .paginate(Duration::from_secs(1))
+        .is_empty());
+}
+
+#[test]
+fn text_budget() {
+    let file = parse_file(
+        "WEBVTT\n\n00:00.000 --> 00:02.500\n<i>Hello</i>\nthere\n\n00:03.000 --> 00:03.500\nToo long for this\n",
+    )
+    .unwrap();
+
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues[0].text_budget(17.0), 42);
+    assert_eq!(cues[1].text_budget(17.0), 8);
+
+    let budgets = file.text_budgets(17.0);
+    assert_eq!(budgets[0].length, 10);
+    assert!(budgets[0].fits());
+    assert_eq!(budgets[0].remaining(), 32);
+    assert!(!budgets[1].fits());
+    assert_eq!(budgets[1].remaining(), -9);
+}
+
+#[test]
+fn sentence_segmentation() {
+    let segmenter = RuleSegmenter::new();
+    let text = "Mr. Smith met J. Doe. He slept! ok? \"Yes.\" Fine 你好。再见";
+    let starts = segmenter.sentence_starts(text);
+    let sentences: Vec<_> = starts.iter().map(|&idx| &text[idx..]).collect();
+    assert_eq!(
+        sentences,
+        [
+            "He slept! ok? \"Yes.\" Fine 你好。再见",
+            "\"Yes.\" Fine 你好。再见",
+            "Fine 你好。再见",
+            "再见"
+        ]
+    );
+
+    let ms = Duration::from_millis;
+    let words: Vec<_> = ["Hello", "there.", "How", "are", "you", "doing", "today?"]
+        .iter()
+        .enumerate()
+        .map(|(idx, word)| TimedWord::new(*word, ms(idx as u64 * 500), ms(idx as u64 * 500 + 400)))
+        .collect();
+
+    let file = File::from_words(&words, 12, &segmenter);
+    let cues: Vec<_> = file
+        .cues()
+        .map(|cue| (cue.start, cue.end, cue.text.as_str()))
+        .collect();
+    assert_eq!(
+        cues,
+        [
+            (ms(0), ms(900), "Hello there."),
+            (ms(1000), ms(2400), "How are you"),
+            (ms(2500), ms(3400), "doing today?"),
+        ]
+    );
+
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nI think\n\n00:02.100 --> 00:03.000\nwe should go.\n\n00:03.000 --> 00:04.000\nNext one\n\n00:09.000 --> 00:10.000\nlater\n",
+    )
+    .unwrap();
+
+    assert_eq!(file.merge_adjacent(ms(500), 42, &segmenter), 1);
+    let cues: Vec<_> = file.cues().collect();
+    assert_eq!(cues[0].text, "I think\nwe should go.");
+    assert_eq!(cues[0].end, ms(3000));
+    assert_eq!(cues[1].text, "Next one");
+}
+
+#[cfg(feature = "icu")]
+#[test]
+fn icu_segmenter() {
+    use crate::IcuSegmenter;
+
+    assert_eq!(
+        IcuSegmenter.sentence_starts("Hello there. How are you?"),
+        [13]
+    );
+}
+
+#[test]
+fn rewrap() {
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nThe <i>quick brown</i>\nfox jumps over\n\n00:02.000 --> 00:03.000\n今日はとても\nいい天気ですね。散歩に行きましょう。\n\n00:03.000 --> 00:04.000\n我喜欢Rust语言，你呢？\n",
+    )
+    .unwrap();
+
+    assert_eq!(file.rewrap(10), 3);
+    let cues: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(cues[0], "The <i>quick\nbrown</i> fox\njumps over");
+    assert_eq!(
+        cues[1],
+        "今日はとてもいい天気\nですね。散歩に行きま\nしょう。"
+    );
+    assert_eq!(cues[2], "我喜欢Rust语言，\n你呢？");
+
+    assert_eq!(file.rewrap(10), 0);
+}
+
+#[test]
+fn length_metrics() {
+    let text = "ne\u{301}e 👍🏽 日本";
+    assert_eq!(LengthMetric::Chars.measure(text), 10);
+    assert_eq!(LengthMetric::Graphemes.measure(text), 8);
+    assert_eq!(LengthMetric::Width.measure(text), 11);
+
+    let mut file = parse_file("WEBVTT\n\n00:00.000 --> 00:01.000\n👍🏽👍🏽👍🏽 ne\u{301}e\n").unwrap();
+    let findings = file.check_profile(&Profile::Custom(ProfileRules {
+        max_line_length: Some(7),
+        ..Default::default()
+    }));
+    assert!(findings.is_empty());
+    assert_eq!(file.cues().next().unwrap().chars_per_second(), 7.0);
+
+    assert_eq!(file.rewrap_with(6, LengthMetric::Width), 1);
+    assert_eq!(file.cues().next().unwrap().text, "👍🏽👍🏽👍🏽\nne\u{301}e");
+
+    file.normalize(&NormalizeOptions {
+        sentence_case: false,
+        ..Default::default()
+    });
+    assert_eq!(file.cues().next().unwrap().text, "👍🏽👍🏽👍🏽\nnée");
+}
+
+#[test]
+fn program_date_time() {
+    use std::time::UNIX_EPOCH;
+
+    let anchor = ProgramDateTime::parse(
+        "#EXT-X-PROGRAM-DATE-TIME:2024-03-01T14:00:00.250+02:00",
+        Duration::from_secs(10),
+    )
+    .unwrap();
+    assert_eq!(
+        anchor.wall_clock,
+        UNIX_EPOCH + Duration::from_millis(1_709_294_400_250)
+    );
+    assert_eq!(
+        anchor.to_string(),
+        "#EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00.250Z"
+    );
+
+    let file = parse_file("WEBVTT\n\n00:05.000 --> 01:00:12.000\nHi\n").unwrap();
+    let (start, end) = file.cues().next().unwrap().absolute_time(&anchor);
+    assert_eq!(format_date_time(start), "2024-03-01T11:59:55.250Z");
+    assert_eq!(format_date_time(end), "2024-03-01T13:00:02.250Z");
+    assert_eq!(anchor.media_time_at(start), Some(Duration::from_secs(5)));
+    assert_eq!(anchor.media_time_at(UNIX_EPOCH), None);
+
+    assert_eq!(
+        format_date_time(UNIX_EPOCH - Duration::from_millis(1)),
+        "1969-12-31T23:59:59.999Z"
+    );
+    assert_eq!(
+        ProgramDateTime::parse("1969-12-31T23:59:59.999Z", Duration::ZERO)
+            .unwrap()
+            .wall_clock,
+        UNIX_EPOCH - Duration::from_millis(1)
+    );
+    assert!(ProgramDateTime::parse("2024-13-01T00:00:00Z", Duration::ZERO).is_none());
+    assert!(ProgramDateTime::parse("2024-03-01T00:00:00", Duration::ZERO).is_none());
+}
+
+#[test]
+fn apply_patch() {
+    let mut file = parse_file(
+        "WEBVTT\n\nintro\n00:01.000 --> 00:02.000\nHi\n\nouter\n00:05.000 --> 00:06.000\nBye\n",
+    )
+    .unwrap();
+
+    let patch = CuePatch::parse(
+        "WEBVTT PATCH\n\nRETIME intro\n00:00:01.500 --> 00:00:02.500\n\nRETEXT intro\nHello, <i>world</i>\n\nDELETE outer\n\nINSERT AFTER intro\nmiddle\n00:03.000 --> 00:04.000 line:0\nA new cue\n\nINSERT FIRST\n00:00.000 --> 00:01.000\nFirst\n",
+    )
+    .unwrap();
+    assert_eq!(patch.ops.len(), 5);
+
+    let reparsed = CuePatch::parse(&patch.to_string()).unwrap();
+    assert_eq!(reparsed.to_string(), patch.to_string());
+
+    file.apply_patch(&patch).unwrap();
+    assert_eq!(
+        file.to_string(),
+        "WEBVTT\n\n00:00.000 --> 00:01.000\nFirst\n\nintro\n00:01.500 --> 00:02.500\nHello, <i>world</i>\n\nmiddle\n00:03.000 --> 00:04.000 line:0\nA new cue\n"
+    );
+
+    let before = file.to_string();
+    let mut bad = CuePatch::new();
+    bad.push(PatchOp::Delete { id: "intro".into() });
+    bad.push(PatchOp::Delete {
+        id: "missing".into(),
+    });
+    assert_eq!(
+        file.apply_patch(&bad),
+        Err(PatchError::UnknownId {
+            id: "missing".into()
+        })
+    );
+    assert_eq!(file.to_string(), before);
+
+    assert_eq!(
+        CuePatch::parse("WEBVTT PATCH\n\nRENAME intro\n").unwrap_err(),
+        PatchError::BadPatch {
+            line: 3,
+            reason: "unknown operation"
+        }
+    );
+
+    let err = crate::Error::from(CuePatch::parse("").unwrap_err());
+    assert!(matches!(
+        err,
+        Error::Patch(PatchError::BadPatch { line: 1, .. })
+    ));
+}
+
+#[test]
+fn dedupe_regions() {
+    let region = |id: &str, settings: &[&str]| Block::Raw {
+        lines: ["REGION".to_owned(), format!("id:{id}")]
+            .into_iter()
+            .chain(settings.iter().map(|&setting| setting.to_owned()))
+            .collect(),
+    };
+
+    let mut file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000 region:a\nA\n\n00:02.000 --> 00:03.000 region:b\nB\n\n00:03.000 --> 00:04.000 region:c\nC\n",
+    )
+    .unwrap();
+
+    file.blocks.insert(0, region("c", &["width:50%"]));
+    file.blocks
+        .insert(0, region("b", &["lines:3", "width:40%"]));
+    file.blocks.insert(0, region("a", &["width:40% lines:3"]));
+
+    assert_eq!(file.dedupe_regions(), 1);
+    assert_eq!(file.blocks.len(), 5);
+
+    let regions: Vec<_> = file
+        .cues()
+        .map(|cue| cue.settings.region.as_deref().unwrap())
+        .collect();
+    assert_eq!(regions, ["a", "a", "c"]);
+    assert!(file
+        .to_string()
+        .contains("00:02.000 --> 00:03.000 region:a\n"));
+    assert_eq!(file.dedupe_regions(), 0);
+}
+
+#[test]
+fn cue_text_limits() {
+    use crate::text::{self, Node, ParseLimits};
+
+    fn depth(nodes: &[Node]) -> usize {
+        nodes
+            .iter()
+            .map(|node| match node {
+                Node::Span(span) => 1 + depth(&span.children),
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    let deep = format!("{}x{}", "<b>".repeat(100_000), "</b>".repeat(100_000));
+    let nodes = text::parse(&deep);
+    assert_eq!(depth(&nodes), 64);
+    assert_eq!(text::plain(&nodes), "x");
+
+    let limits = ParseLimits {
+        max_depth: 1,
+        max_nodes: 5,
+    };
+    let nodes = text::parse_with_limits("<i>a<b>b</b>c</i>d<00:01.000>e<u>f</u>g&amp;h", &limits);
+    assert_eq!(text::write(&nodes), "<i>abc</i>defg&amp;h");
+    assert_eq!(text::plain(&nodes), "abcdefg&h");
+}
+
+#[test]
+fn parse_invalid_utf8() {
+    let input = b"WEBVTT\r\n\r\n00:01.000 --> 00:02.000\r\nGood\r\n\r\n\r\n00:02.000 --> 00:03.000\r\nBad \xff\r\n\r\n00:03.000 --> 00:04.000\r\nAlso good\r\n";
+
+    assert_eq!(
+        parse_bytes(input, Utf8Policy::Strict).unwrap_err(),
+        ParseError::InvalidUtf8 { offset: 74 }
+    );
+
+    let lossy = parse_bytes(input, Utf8Policy::Lossy).unwrap();
+    let texts: Vec<_> = lossy.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(texts, ["Good", "Bad \u{fffd}", "Also good"]);
+
+    let skipped = parse_bytes(input, Utf8Policy::SkipInvalidCues).unwrap();
+    let texts: Vec<_> = skipped.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(texts, ["Good", "Also good"]);
+
+    assert_eq!(
+        parse_bytes(
+            b"WEBVTT \xff\n\n00:01.000 --> 00:02.000\nA\n",
+            Utf8Policy::SkipInvalidCues
+        )
+        .unwrap_err(),
+        ParseError::InvalidUtf8 { offset: 7 }
+    );
+
+    let legacy = b"WEBVTT\r\n\r\n00:01.000 --> 00:02.000\r\n\x93Caf\xe9\x94 \x96 5\x80\x85\r\n";
+    let decoded = parse_bytes(legacy, Utf8Policy::Windows1252).unwrap();
+    assert_eq!(
+        decoded.cues().next().unwrap().text,
+        "\u{201c}Café\u{201d} \u{2013} 5€…"
+    );
+
+    let utf8 = parse_bytes(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nCafé €\n".as_bytes(),
+        Utf8Policy::Windows1252,
+    )
+    .unwrap();
+    assert_eq!(utf8.cues().next().unwrap().text, "Café €");
+}
+
+#[test]
+fn generate_from_template() {
+    use crate::generate::Event;
+
+    let template = CueTemplate::parse("<b>{home}</b> {score} {away} {{live}}").unwrap();
+    assert_eq!(
+        template.variables().collect::<Vec<_>>(),
+        ["home", "score", "away"]
+    );
+
+    let values = |score: &str| {
+        [("home", "Ajax"), ("away", "PSV & Co"), ("score", score)]
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect()
+    };
+
+    let file = File::generate(
+        &template,
+        [
+            Event {
+                start: Duration::from_secs(0),
+                end: Duration::from_secs(60),
+                id: "kickoff".into(),
+                values: values("0-0"),
+            },
+            Event {
+                start: Duration::from_secs(60),
+                end: Duration::from_secs(120),
+                values: values("1<0"),
+                ..Default::default()
+            },
+        ],
+    )
+    .unwrap();
+
+    let texts: Vec<_> = file.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "<b>Ajax</b> 0-0 PSV &amp; Co {live}",
+            "<b>Ajax</b> 1&lt;0 PSV &amp; Co {live}"
+        ]
+    );
+    assert_eq!(file.cues().next().unwrap().id, "kickoff");
+
+    assert_eq!(
+        CueTemplate::parse("a { }"),
+        Err(TemplateError::EmptyName { offset: 2 })
+    );
+    assert_eq!(
+        CueTemplate::parse("score}"),
+        Err(TemplateError::UnmatchedBrace { offset: 5 })
+    );
+    assert_eq!(
+        CueTemplate::parse("{score"),
+        Err(TemplateError::Unclosed { offset: 0 })
+    );
+
+    let mut json = CueTemplate::parse("{{\"score\": \"{score}\"}}").unwrap();
+    json.payload = CuePayload::Metadata;
+    assert_eq!(json.render(&values("1<0")).unwrap(), "{\"score\": \"1<0\"}");
+    assert_eq!(
+        json.render(&Default::default()),
+        Err(TemplateError::MissingValue {
+            name: "score".into()
+        })
+    );
+
+    let err = crate::Error::from(CueTemplate::parse("{").unwrap_err());
+    assert_eq!(err.to_string(), "unclosed placeholder at byte 0");
+}
+
+#[test]
+fn generate_intervals() {
+    use crate::generate;
+
+    let file = generate::intervals(
+        Duration::from_millis(2500),
+        Duration::from_secs(1),
+        |interval| {
+            format!(
+                "{}: {}s left",
+                interval.index,
+                interval.remaining.as_secs_f64()
+            )
+        },
+    );
+
+    assert_eq!(
+        file.to_string(),
+        "WEBVTT\n\n00:00:00.000 --> 00:00:01.000\n0: 2.5s left\n\n00:00:01.000 --> 00:00:02.000\n1: 1.5s left\n\n00:00:02.000 --> 00:00:02.500\n2: 0.5s left\n"
+    );
+
+    let dense = generate::intervals(Duration::from_secs(60), Duration::from_millis(10), |_| {
+        "<>".into()
+    });
+    assert_eq!(dense.cues().count(), 6000);
+    assert_eq!(dense.cues().last().unwrap().text, "&lt;&gt;");
+}
+
+#[cfg(feature = "test-fixtures")]
+#[test]
+fn golden_fixtures() {
+    use crate::fixtures::{Expected, FIXTURES};
+
+    for fixture in FIXTURES {
+        assert_eq!(fixture.check(), Ok(()));
+    }
+
+    assert!(FIXTURES
+        .iter()
+        .any(|fixture| matches!(fixture.expected, Expected::Invalid { .. })));
+}
+
+#[test]
+fn parse_trace() {
+    let input = "WEBVTT\n\nNOTE hello\n\nintro\n00:01.000 --> 00:02.000\nHi\nthere\n\n00:03.000 -> 00:04.000\nLost\n\nWEBVTT\n00:05.000 --> 00:06.000\nLater\n";
+    let (result, trace) = parse_file_traced(input, &Default::default());
+
+    assert_eq!(result.unwrap().0.cues().count(), 2);
+
+    let kinds: Vec<_> = trace.lines.iter().map(|line| line.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            LineKind::Header,
+            LineKind::Blank,
+            LineKind::Note,
+            LineKind::Blank,
+            LineKind::Id,
+            LineKind::Timing,
+            LineKind::Text,
+            LineKind::Text,
+            LineKind::Blank,
+            LineKind::Skip,
+            LineKind::Skip,
+            LineKind::Blank,
+            LineKind::Header,
+            LineKind::Timing,
+            LineKind::Text,
+        ]
+    );
+
+    let line = trace.line(10).unwrap();
+    assert_eq!(line.text, "00:03.000 -> 00:04.000");
+    assert_eq!(line.reason, "block without cue timings, dropped");
+    assert!(trace
+        .to_string()
+        .contains("  10 skip   \"00:03.000 -> 00:04.000\" (block without cue timings, dropped)\n"));
+
+    let (result, trace) = parse_file_traced(
+        "WEBVTTX\n\n00:01.000 --> 00:02.000\nHi",
+        &Default::default(),
+    );
+    assert!(result.is_err());
+    assert_eq!(trace.lines[0].kind, LineKind::Skip);
+    assert_eq!(
+        trace.lines[2].reason,
+        "not reached, parsing stopped at an error"
+    );
+}
+
+#[test]
+fn long_lines() {
+    use crate::text;
+
+    let long_reference = format!("&#{};", "0".repeat(40));
+    assert_eq!(text::decode_entities(&long_reference), long_reference);
+    assert_eq!(text::decode_entities("&#x0000000000041;&&amp;"), "A&&");
+
+    let text = "word &amp; ".repeat(100_000);
+    let input =
+        format!("WEBVTT\n\n00:01.000 --> 00:02.000\n{text}\n\n00:03.000 --> 00:04.000\nNext\n");
+
+    let mut parser = TailParser::new();
+    let mut cues = vec![];
+
+    for piece in input.as_bytes().chunks(1000) {
+        cues.extend(parser.push(std::str::from_utf8(piece).unwrap()).unwrap());
+    }
+
+    assert_eq!(cues.len(), 1);
+    assert_eq!(cues[0].text, text);
+
+    cues.extend(parser.finish().unwrap());
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[1].text, "Next");
+}
+
+#[test]
+fn scan_arrow_and_digits() {
+    use crate::scan;
+
+    assert_eq!(scan::find_arrow("-->"), Some(0));
+    assert_eq!(scan::find_arrow("00:01.000 --> 00:02.000"), Some(10));
+    assert_eq!(scan::find_arrow("a ---> b"), Some(3));
+    assert_eq!(scan::find_arrow("-> >- -> ->"), None);
+    assert_eq!(scan::find_arrow(">"), None);
+    assert!(scan::has_arrow("é-->"));
+
+    assert_eq!(scan::digits_len("0123:"), 4);
+    assert_eq!(scan::digits_len("١٢"), 0);
+    assert_eq!(scan::digits_len("99"), 2);
+}
+
+#[cfg(feature = "hyphenation")]
+#[test]
+fn rewrap_hyphenated() {
+    use crate::Hyphenation;
+
+    let mut hyphenation = Hyphenation::new();
+    assert!(hyphenation.add_language("en"));
+    assert!(hyphenation.add_language("de"));
+    assert!(!hyphenation.add_language("tlh"));
+    hyphenation.set_default_language("en-GB");
+
+    let mut cue = Cue {
+        text: "Such incomprehensibilities, <lang de>Donaudampfschifffahrt</lang> <lang ja>ありがとう</lang>".into(),
+        ..Default::default()
+    };
+
+    assert!(cue.rewrap_hyphenated(10, LengthMetric::Graphemes, &hyphenation));
+    assert_eq!(
+        cue.text,
+        "Such\nincompre-\nhensibili-\nties,\n<lang de>Donau-\ndampf-\nschiff-\nfahrt</lang> <lang ja>ありがと\nう</lang>"
+    );
+
+    // text in languages that were not added is only rewrapped
+    let mut file = File {
+        description: None,
+        header_lines: vec![],
+        blocks: vec![Block::Cue(Cue {
+            text: "Such incomprehensibilities".into(),
+            ..Default::default()
+        })],
+        format: Default::default(),
+    };
+
+    let mut french = Hyphenation::new();
+    french.add_language("fr");
+
+    assert_eq!(
+        file.rewrap_hyphenated(10, LengthMetric::Graphemes, &french),
+        1
+    );
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "Such\nincomprehensibilities"
+    );
+}
+
+#[test]
+fn emoji_policy() {
+    let input = "WEBVTT\n\n00:01.000 --> 00:02.000\nGreat 👍 job <b>🇫🇷 team</b> 👨‍👩‍👧\n\n00:03.000 --> 00:04.000\n© 2024 and 1️⃣\n\n00:05.000 --> 00:06.000\nNo emoji ☺\n";
+
+    let mut file = parse_file(input).unwrap();
+    let report = file.apply_emoji_policy(&EmojiPolicy::Keep);
+
+    assert_eq!(
+        report,
+        [
+            EmojiReport {
+                cue_index: 0,
+                emoji: vec!["👍".into(), "🇫🇷".into(), "👨‍👩‍👧".into()],
+            },
+            EmojiReport {
+                cue_index: 1,
+                emoji: vec!["1️⃣".into()],
+            },
+            EmojiReport {
+                cue_index: 2,
+                emoji: vec!["☺".into()],
+            },
+        ]
+    );
+    assert_eq!(file.to_string(), input);
+
+    let mut stripped = file.clone();
+    assert_eq!(stripped.apply_emoji_policy(&EmojiPolicy::Strip).len(), 3);
+
+    let texts: Vec<_> = stripped.cues().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(texts, ["Great job <b>team</b>", "© 2024 and", "No emoji"]);
+
+    file.apply_emoji_policy(&EmojiPolicy::Replace("<emoji>".into()));
+    assert_eq!(
+        file.cues().next().unwrap().text,
+        "Great &lt;emoji&gt; job <b>&lt;emoji&gt; team</b> &lt;emoji&gt;"
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn timeline_json() {
+    let file = parse_file(
+        "WEBVTT\n\nintro\n00:01.000 --> 00:04.000\n<v Roger>Good\nevening.</v>\n\n00:02.000 --> 00:06.000\nRain is on the way, and it is going to last for the whole weekend\n\n00:04.000 --> 00:05.000\nBack\n\n00:07.000 --> 00:07.000\nEmpty\n",
+    )
+    .unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&file.to_timeline_json()).unwrap();
+
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "duration": 7000,
+            "tracks": [
+                {
+                    "bars": [
+                        { "cue": 0, "id": "intro", "start": 1000, "end": 4000, "label": "Good evening.", "speaker": "Roger" },
+                        { "cue": 2, "start": 4000, "end": 5000, "label": "Back" },
+                    ]
+                },
+                {
+                    "bars": [
+                        { "cue": 1, "start": 2000, "end": 6000, "label": "Rain is on the way, and it is going to…" },
+                    ]
+                },
+            ]
+        })
+    );
+}
+
+#[test]
+fn audit_trail() {
+    let mut file =
+        parse_file("WEBVTT\n\n00:01.000 --> 00:02.000\nOne\n\n00:03.000 --> 00:04.000\nTwo\n")
+            .unwrap();
+
+    file.shift_audited(Shift::Later(Duration::from_millis(1500)), &Audit::default())
+        .unwrap();
+
+    assert!(file.to_string().ends_with(&format!(
+        "\n\nNOTE audit\ncues.after=2\ncues.before=2\ncues.changed=2\nparam.offset=+00:00:01.500\ntool=webvtt\ntransform=shift\nversion={}\n",
+        env!("CARGO_PKG_VERSION")
+    )));
+
+    let mut audit = Audit::new("fixer", "1.0");
+    audit.date = Some(std::time::UNIX_EPOCH + Duration::from_secs(86_400));
+
+    assert_eq!(
+        file.fix_reading_speed_audited(2.0, &[SpeedFix::Extend, SpeedFix::Merge], &audit),
+        Vec::<usize>::new()
+    );
+    assert!(file
+        .shift_audited(Shift::Earlier(Duration::from_secs(60)), &audit)
+        .is_err());
+
+    let trail = parse_file(&file.to_string()).unwrap().audit_trail();
+    assert_eq!(trail.len(), 2);
+    assert_eq!(trail[0].transform, "shift");
+    assert_eq!(
+        trail[1],
+        AuditRecord {
+            transform: "fix_reading_speed".into(),
+            tool: "fixer".into(),
+            version: "1.0".into(),
+            date: Some("1970-01-02T00:00:00.000Z".into()),
+            parameters: [("fixes", "extend,merge"), ("max_cps", "2")]
+                .into_iter()
+                .collect(),
+            cues_before: 2,
+            cues_after: 2,
+            cues_changed: 2,
+        }
+    );
+}
+
+#[test]
+fn vttcue_init() {
+    let file = parse_file(
+        "WEBVTT\n\nintro\n00:01.500 --> 00:04.000 vertical:rl line:10%,end position:20%,line-right size:50% align:start\n<b>Hi</b>\n\n00:05.000 --> 00:06.000 line:-2\nThere\n",
+    )
+    .unwrap();
+    let cues: Vec<_> = file.cues().map(Cue::to_vttcue_init).collect();
+
+    assert_eq!(
+        cues[0],
+        VttCueInit {
+            id: "intro".into(),
+            start_time: 1.5,
+            end_time: 4.0,
+            text: "<b>Hi</b>".into(),
+            vertical: "rl",
+            snap_to_lines: false,
+            line: NumberOrAuto::Number(10.0),
+            line_align: "end",
+            position: NumberOrAuto::Number(20.0),
+            position_align: "line-right",
+            size: 50.0,
+            align: "start",
+        }
+    );
+
+    assert_eq!(cues[1].line, NumberOrAuto::Number(-2.0));
+    assert!(cues[1].snap_to_lines);
+    assert_eq!(cues[1].position, NumberOrAuto::Auto);
+    assert_eq!(cues[1].position_align, "auto");
+
+    #[cfg(all(feature = "serde", feature = "json"))]
+    assert_eq!(
+        serde_json::to_value(&cues[1]).unwrap(),
+        serde_json::json!({
+            "id": "",
+            "startTime": 5.0,
+            "endTime": 6.0,
+            "text": "There",
+            "vertical": "",
+            "snapToLines": true,
+            "line": -2.0,
+            "lineAlign": "start",
+            "position": "auto",
+            "positionAlign": "auto",
+            "size": 100.0,
+            "align": "center",
+        })
+    );
+}
+
+#[test]
+fn deep_links() {
+    let file = parse_file(
+        "WEBVTT\n\n00:00:12.500 --> 00:00:15.000\n<v Ann>Hello</v>\n\n01:00:00.000 --> 01:00:00.045\nThere\n",
+    )
+    .unwrap();
+
+    let cue = file.cues().next().unwrap();
+    assert_eq!(cue.media_fragment(), "#t=12.5,15");
+
+    assert_eq!(
+        file.deep_links("https://example.com/video.mp4#t=5"),
+        [
+            DeepLink {
+                cue_index: 0,
+                url: "https://example.com/video.mp4#t=12.5,15".into(),
+                text: "Hello".into(),
+            },
+            DeepLink {
+                cue_index: 1,
+                url: "https://example.com/video.mp4#t=3600,3600.045".into(),
+                text: "There".into(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn cue_events() {
+    use std::sync::{Arc, Mutex};
+
+    let old = parse_file(
+        "WEBVTT\n\na\n00:01.000 --> 00:02.000\nOne\n\nb\n00:03.000 --> 00:04.000\nTwo\n\n00:05.000 --> 00:06.000\nThree\n",
+    )
+    .unwrap();
+    let new = parse_file(
+        "WEBVTT\n\nb\n00:03.500 --> 00:04.000\nTwo!\n\n00:05.000 --> 00:06.000\nThree\n\nc\n00:07.000 --> 00:08.000\nFour\n",
+    )
+    .unwrap();
+
+    let events = old.diff(&new);
+    assert_eq!(
+        events,
+        [
+            CueEvent::Removed {
+                cue_index: 0,
+                id: "a".into()
+            },
+            CueEvent::Retimed {
+                cue_index: 0,
+                id: "b".into(),
+                old_start_ms: 3000,
+                old_end_ms: 4000,
+                start_ms: 3500,
+                end_ms: 4000,
+            },
+            CueEvent::TextChanged {
+                cue_index: 0,
+                id: "b".into(),
+                old_text: "Two".into(),
+                text: "Two!".into(),
+            },
+            CueEvent::Added {
+                cue_index: 2,
+                id: "c".into(),
+                start_ms: 7000,
+                end_ms: 8000,
+                text: "Four".into(),
+            },
+        ]
+    );
+    assert!(new.diff(&new).is_empty());
+
+    // a cue without an id added to a file without ids is the only change
+    let cues = |texts: &[&str]| {
+        let mut input = String::from("WEBVTT\n");
+
+        for (idx, text) in texts.iter().enumerate() {
+            input += &format!("\n00:0{idx}.000 --> 00:0{idx}.500\n{text}\n");
+        }
+
+        parse_file(&input).unwrap()
+    };
+    let mut inserted = cues(&["One", "Two", "Three"]);
+    let cue = Cue {
+        start: Duration::from_millis(250),
+        end: Duration::from_millis(750),
+        text: "New".into(),
+        ..Default::default()
+    };
+    inserted.blocks.insert(1, Block::Cue(cue));
+    assert_eq!(
+        cues(&["One", "Two", "Three"]).diff(&inserted),
+        [CueEvent::Added {
+            cue_index: 1,
+            id: String::new(),
+            start_ms: 250,
+            end_ms: 750,
+            text: "New".into(),
+        }]
+    );
+    assert_eq!(
+        cues(&["One", "Two", "Three"]).diff(&cues(&["One", "2", "Three"])),
+        [CueEvent::TextChanged {
+            cue_index: 1,
+            id: String::new(),
+            old_text: "Two".into(),
+            text: "2".into(),
+        }]
+    );
+
+    // cues with the same id are not merged into one
+    let twice =
+        parse_file("WEBVTT\n\nx\n00:01.000 --> 00:02.000\nA\n\nx\n00:03.000 --> 00:04.000\nB\n")
+            .unwrap();
+    let once = parse_file("WEBVTT\n\nx\n00:03.000 --> 00:04.000\nB\n").unwrap();
+    assert_eq!(
+        twice.diff(&once),
+        [CueEvent::Removed {
+            cue_index: 0,
+            id: "x".into()
+        }]
+    );
+    assert_eq!(once.diff(&twice).len(), 1);
+
+    #[cfg(all(feature = "serde", feature = "json"))]
+    assert_eq!(
+        serde_json::to_value(&events[0]).unwrap(),
+        serde_json::json!({"type": "removed", "cue_index": 0, "id": "a"})
+    );
+
+    let mut session = EditSession::new(old);
+    let changes = Arc::new(Mutex::new(vec![]));
+    let log = changes.clone();
+    session.subscribe(move |change| log.lock().unwrap().push(change.events.clone()));
+
+    session.edit("retype", |file| {
+        file.cues_mut().nth(1).unwrap().text = "Zwei".into()
+    });
+    session.undo();
+
+    let text_changed = |old_text: &str, text: &str| CueEvent::TextChanged {
+        cue_index: 1,
+        id: "b".into(),
+        old_text: old_text.into(),
+        text: text.into(),
+    };
+    assert_eq!(
+        *changes.lock().unwrap(),
+        [
+            vec![text_changed("Two", "Zwei")],
+            vec![text_changed("Zwei", "Two")]
+        ]
+    );
+}
+
+#[test]
+fn simulate_live() {
+    use crate::simulate;
+
+    let file = parse_file(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nOne\n\n00:02.000 --> 00:03.000\nTwo\n\n00:02.100 --> 00:03.000\nThree\n\n00:02.200 --> 00:03.000\nFour\n",
+    )
+    .unwrap();
+    let arrivals = |live: simulate::Live| -> Vec<u64> {
+        live.map(|cue| (cue.at.as_secs_f64() * 1000.0).round() as u64)
+            .collect()
+    };
+
+    let steady = simulate::live(&file, Duration::from_secs(2), Duration::ZERO);
+    assert_eq!(steady.len(), 4);
+    assert_eq!(arrivals(steady), [3000, 4000, 4100, 4200]);
+
+    let jittery = |seed| {
+        simulate::live(&file, Duration::from_secs(2), Duration::from_millis(500)).with_seed(seed)
+    };
+    let times = arrivals(jittery(7));
+    assert_eq!(times, arrivals(jittery(7)));
+    assert_ne!(times, arrivals(jittery(8)));
+    assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(file
+        .cues()
+        .zip(&times)
+        .all(|(cue, &at)| at >= cue.start.as_millis() as u64 + 2000));
+
+    // one cue a second after a burst of two holds back the last cue
+    let limited = simulate::live(&file, Duration::from_secs(2), Duration::ZERO).with_rate(1.0, 2);
+    assert_eq!(arrivals(limited), [3000, 4000, 4100, 5000]);
+
+    let cue = simulate::live(&file, Duration::ZERO, Duration::ZERO)
+        .nth(1)
+        .unwrap();
+    assert_eq!(cue.cue_index, 1);
+    assert_eq!(cue.cue.text, "Two");
+}
+
+#[test]
+fn frozen_file() {
+    let file = parse_file(
+        "WEBVTT\n\nlate\n00:05.000 --> 00:06.000\nLate\n\nlong\n00:01.000 --> 00:10.000\nLong\n\n00:02.000 --> 00:03.000\nShort\n",
+    )
+    .unwrap();
+
+    let frozen = file.freeze();
+    let shared = frozen.clone();
+
+    let texts = std::thread::spawn(move || {
+        shared
+            .cues_at(Duration::from_millis(5500))
+            .iter()
+            .map(|cue| cue.text.to_string())
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(texts, ["Late", "Long"]);
+    assert_eq!(
+        frozen.cues().iter().map(|cue| &*cue.id).collect::<Vec<_>>(),
+        ["long", "", "late"]
+    );
+    assert_eq!(frozen.get("late").unwrap().start, Duration::from_secs(5));
+    assert!(frozen.get("missing").is_none());
+
+    for millis in (0..11_000).step_by(250) {
+        let time = Duration::from_millis(millis);
+        let expected: Vec<_> = file
+            .cues_at(time)
+            .iter()
+            .map(|cue| cue.text.as_str())
+            .collect();
+        let actual: Vec<_> = frozen.cues_at(time).iter().map(|cue| &*cue.text).collect();
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn parse_from_lines() {
+    let input = "WEBVTT - lines\r\n\r\nNOTE kept\r\n\r\n1\r\n00:01.000 --> 00:02.000\r\nFirst\r\n\r\n\r\n00:02.000 --> 00:03.000\r\nSecond\r\n00:03.000 --> 00:04.000\r\nThird\r\n";
+
+    let file = parse_lines(input.split('\n').map(str::to_owned)).unwrap();
+    let expected = parse_file(input).unwrap();
+
+    assert_eq!(file.description, expected.description);
+    assert_eq!(
+        file.cues().map(|cue| cue.offset).collect::<Vec<_>>(),
+        expected.cues().map(|cue| cue.offset).collect::<Vec<_>>()
+    );
+    assert_eq!(file.format, expected.format);
+    assert_eq!(file.to_string(), expected.to_string());
+    assert_eq!(
+        file.cues().nth(2).unwrap().offset,
+        input.rfind("00:03").unwrap()
+    );
+
+    assert!(matches!(parse_lines(vec![]), Err(ParseError::NoMagic)));
+
+    // lines without their \r are read as if the file used LF line endings
+    let stripped = parse_lines(input.as_bytes().lines().map(Result::unwrap)).unwrap();
+    let lf = input.replace("\r\n", "\n");
+    assert_eq!(stripped.format.line_ending, LineEnding::Lf);
+    assert_eq!(stripped.to_string(), parse_file(&lf).unwrap().to_string());
+    assert_eq!(
+        stripped.cues().nth(2).unwrap().offset,
+        lf.rfind("00:03").unwrap()
+    );
+
+    let options = ParseOptions {
+        check_order: true,
+        timing_fix: Some(TimingFix::Drop),
+        ..Default::default()
+    };
+    let input = "WEBVTT\r\n\r\n00:05.000 --> 00:06.000\r\nA\r\n\r\n00:02.000 --> 00:01.000\r\nB\r\n\r\n00:03.000 --> 00:04.000\r\nC\r\n";
+    let (file, diagnostics) =
+        parse_lines_with(input.split('\n').map(str::to_owned), &options).unwrap();
+    let (expected, expected_diagnostics) = parse_file_with(input, &options).unwrap();
+    assert_eq!(file.cues().count(), 2);
+    assert_eq!(file.to_string(), expected.to_string());
+    assert_eq!(diagnostics, expected_diagnostics);
+    assert_eq!(diagnostics.len(), 2);
+}
+
+#[test]
+fn tail_parser() {
+    let input = "WEBVTT\n\n1\n00:01.000 --> 00:02.000\nFirst\n\nNOTE skipped\n\n2\n00:02.000 --> 00:03.000\nSecond\nline\n\n3\n00:03.000 --> 00:04.000\nThird\n";
+
+    let mut parser = TailParser::new();
+    let mut batches = vec![];
+
+    for chunk in [
+        &input[..3],
+        &input[3..40],
+        &input[40..55],
+        &input[55..90],
+        &input[90..],
+    ] {
+        let cues = parser.push(chunk).unwrap();
+        batches.push(cues.iter().map(|cue| cue.id.clone()).collect::<Vec<_>>());
+    }
+
+    let last = parser.finish().unwrap();
+    assert_eq!(last.len(), 1);
+    assert_eq!(last[0].text, "Third");
+    assert_eq!(last[0].offset, input.find("3\n").unwrap());
+
+    assert_eq!(batches, [vec![], vec![], vec!["1"], vec![], vec!["2"]]);
+
+    let all = parse_file(input).unwrap();
+    assert_eq!(
+        all.cues().nth(1).unwrap().offset,
+        input.find("2\n").unwrap()
+    );
+
+    assert!(matches!(
+        TailParser::new().push("WEBVTX\n"),
+        Err(ParseError::NoMagic)
+    ));
+    assert!(TailParser::new().push("WEBVT").unwrap().is_empty());
+
+    let crlf = input.replace('\n', "\r\n");
+    let mut parser = TailParser::new();
+    let mut ids = vec![];
+
+    for char in crlf.chars() {
+        let cues = parser.push(&char.to_string()).unwrap();
+        ids.extend(cues.into_iter().map(|cue| cue.id));
+    }
+
+    assert_eq!(ids, ["1", "2"]);
+    assert_eq!(parser.finish().unwrap()[0].text, "Third");
+}
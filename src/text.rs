@@ -0,0 +1,750 @@
+//! Parsing and serialization of cue text, following the WebVTT cue text
+//! parsing rules.
+
+use std::time::Duration;
+
+use crate::{format_timestamp, parse_timestamp, scan, Cue, CuePayload, File};
+
+/// A node in the tree of a cue's text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    Text(String),
+    Timestamp(Duration),
+    Span(Span),
+}
+
+/// A node created by a tag, like `<c.yellow>` or `<v Roger>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub kind: SpanKind,
+    pub classes: Vec<String>,
+    pub children: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpanKind {
+    /// `<c>`
+    Class,
+    /// `<i>`
+    Italic,
+    /// `<b>`
+    Bold,
+    /// `<u>`
+    Underline,
+    /// `<ruby>`
+    Ruby,
+    /// `<rt>`, only valid directly inside a `<ruby>`
+    RubyText,
+    /// `<v name>`
+    Voice { name: String },
+    /// `<lang tag>`
+    Language { tag: String },
+}
+
+impl SpanKind {
+    fn tag_name(&self) -> &'static str {
+        match self {
+            SpanKind::Class => "c",
+            SpanKind::Italic => "i",
+            SpanKind::Bold => "b",
+            SpanKind::Underline => "u",
+            SpanKind::Ruby => "ruby",
+            SpanKind::RubyText => "rt",
+            SpanKind::Voice { .. } => "v",
+            SpanKind::Language { .. } => "lang",
+        }
+    }
+
+    fn annotation(&self) -> Option<&str> {
+        match self {
+            SpanKind::Voice { name } => Some(name),
+            SpanKind::Language { tag } => Some(tag),
+            _ => None,
+        }
+    }
+}
+
+enum Token {
+    Text(String),
+    StartTag {
+        name: String,
+        classes: Vec<String>,
+        annotation: String,
+    },
+    EndTag(String),
+    Timestamp(String),
+}
+
+/// Bounds on the tree that [`parse_with_limits`] builds from cue text, so that
+/// hostile input cannot exhaust the stack of code that walks the tree, or
+/// memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ParseLimits {
+    /// Most spans that can be open at once. Deeper tags are dropped, keeping
+    /// their text in the enclosing span.
+    pub max_depth: usize,
+    /// Most nodes in the tree. Once it is reached, tags and timestamps are
+    /// dropped and the rest of the text is kept as one text node.
+    pub max_nodes: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_depth: 64,
+            max_nodes: 10_000,
+        }
+    }
+}
+
+/// Parses cue text into a tree of nodes. Unknown tags and unmatched end tags
+/// are dropped, as the spec requires; the parse never fails. The tree is
+/// bounded by the default [`ParseLimits`].
+pub fn parse(input: &str) -> Vec<Node> {
+    parse_with_limits(input, &ParseLimits::default())
+}
+
+/// Parses cue text into a tree of nodes, as in [`parse`], within the given
+/// limits.
+pub fn parse_with_limits(input: &str, limits: &ParseLimits) -> Vec<Node> {
+    let mut root = vec![];
+    let mut stack: Vec<Span> = vec![];
+    let mut rest = input;
+
+    let mut nodes = 0;
+    // the names of start tags dropped for being too deep, so that their end
+    // tags do not close an enclosing span
+    let mut dropped: Vec<String> = vec![];
+    let mut overflow: Option<String> = None;
+
+    while let Some((token, remainder)) = next_token(rest) {
+        rest = remainder;
+
+        if let Some(overflow) = &mut overflow {
+            if let Token::Text(text) = token {
+                overflow.push_str(&text);
+            }
+
+            continue;
+        }
+
+        if nodes >= limits.max_nodes {
+            overflow = Some(match token {
+                Token::Text(text) => text,
+                _ => String::new(),
+            });
+            continue;
+        }
+
+        match token {
+            Token::Text(text) => {
+                nodes += 1;
+                push_node(&mut root, &mut stack, Node::Text(text));
+            }
+            Token::StartTag {
+                name,
+                classes,
+                annotation,
+            } => {
+                let kind = match name.as_str() {
+                    "c" => SpanKind::Class,
+                    "i" => SpanKind::Italic,
+                    "b" => SpanKind::Bold,
+                    "u" => SpanKind::Underline,
+                    "ruby" => SpanKind::Ruby,
+                    "rt" if matches!(
+                        stack.last(),
+                        Some(Span {
+                            kind: SpanKind::Ruby,
+                            ..
+                        })
+                    ) =>
+                    {
+                        SpanKind::RubyText
+                    }
+                    "v" => SpanKind::Voice { name: annotation },
+                    "lang" => SpanKind::Language { tag: annotation },
+                    _ => continue,
+                };
+
+                if stack.len() >= limits.max_depth {
+                    dropped.push(name);
+                    continue;
+                }
+
+                nodes += 1;
+                stack.push(Span {
+                    kind,
+                    classes,
+                    children: vec![],
+                });
+            }
+            Token::EndTag(name) if dropped.last() == Some(&name) => {
+                dropped.pop();
+            }
+            Token::EndTag(name) => match stack.last() {
+                Some(span) if span.kind.tag_name() == name => {
+                    close_span(&mut root, &mut stack);
+                }
+                Some(Span {
+                    kind: SpanKind::RubyText,
+                    ..
+                }) if name == "ruby" => {
+                    close_span(&mut root, &mut stack);
+                    close_span(&mut root, &mut stack);
+                }
+                _ => {}
+            },
+            Token::Timestamp(value) => {
+                if let Some((time, "")) = parse_timestamp(&value) {
+                    nodes += 1;
+                    push_node(&mut root, &mut stack, Node::Timestamp(time));
+                }
+            }
+        }
+    }
+
+    if let Some(text) = overflow.filter(|text| !text.is_empty()) {
+        push_node(&mut root, &mut stack, Node::Text(text));
+    }
+
+    while !stack.is_empty() {
+        close_span(&mut root, &mut stack);
+    }
+
+    root
+}
+
+fn push_node(root: &mut Vec<Node>, stack: &mut [Span], node: Node) {
+    match stack.last_mut() {
+        Some(span) => span.children.push(node),
+        None => root.push(node),
+    }
+}
+
+fn close_span(root: &mut Vec<Node>, stack: &mut Vec<Span>) {
+    if let Some(span) = stack.pop() {
+        push_node(root, stack, Node::Span(span));
+    }
+}
+
+/// Reads the next token from the input, per the WebVTT cue text tokenizer.
+fn next_token(input: &str) -> Option<(Token, &str)> {
+    if input.is_empty() {
+        return None;
+    }
+
+    if let Some(tag) = input.strip_prefix('<') {
+        return Some(read_tag(tag));
+    }
+
+    let end = scan::find_byte(input, b'<').unwrap_or(input.len());
+    Some((Token::Text(decode_entities(&input[..end])), &input[end..]))
+}
+
+/// Reads a tag, given the input just after the opening `<`.
+fn read_tag(input: &str) -> (Token, &str) {
+    let (content, rest) = match scan::find_byte(input, b'>') {
+        Some(idx) => (&input[..idx], &input[idx + 1..]),
+        None => (input, ""),
+    };
+
+    if let Some(name) = content.strip_prefix('/') {
+        return (Token::EndTag(name.to_owned()), rest);
+    }
+
+    if content.starts_with(|c: char| c.is_ascii_digit()) {
+        return (Token::Timestamp(content.to_owned()), rest);
+    }
+
+    let (head, annotation) = match content.find([' ', '\t', '\n', '\x0c', '\r']) {
+        Some(idx) => (&content[..idx], &content[idx + 1..]),
+        None => (content, ""),
+    };
+
+    let mut parts = head.split('.');
+    let name = parts.next().unwrap_or_default().to_owned();
+    let classes = parts
+        .filter(|class| !class.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    let annotation = decode_entities(annotation)
+        .split_ascii_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (
+        Token::StartTag {
+            name,
+            classes,
+            annotation,
+        },
+        rest,
+    )
+}
+
+/// The longest character reference looked for, from `&` to `;`. Without a
+/// bound, text with many `&`s and no `;` would be searched to its end for
+/// each of them.
+const MAX_REFERENCE_LENGTH: usize = 32;
+
+/// Replaces character references like `&amp;` and `&#x2014;` with the
+/// characters they refer to. Unrecognized references, and references longer
+/// than 32 bytes, are kept as they are.
+pub fn decode_entities(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(idx) = scan::find_byte(rest, b'&') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        let window = &rest.as_bytes()[..rest.len().min(MAX_REFERENCE_LENGTH)];
+
+        let decoded = window
+            .iter()
+            .position(|&byte| byte == b';')
+            .and_then(|end| {
+                let name = &rest[1..end];
+
+                let char = match name {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some('\u{a0}'),
+                    "lrm" => Some('\u{200e}'),
+                    "rlm" => Some('\u{200f}'),
+                    _ => {
+                        let code = if let Some(hex) =
+                            name.strip_prefix("#x").or_else(|| name.strip_prefix("#X"))
+                        {
+                            u32::from_str_radix(hex, 16).ok()
+                        } else if let Some(dec) = name.strip_prefix('#') {
+                            dec.parse().ok()
+                        } else {
+                            None
+                        };
+
+                        code.and_then(char::from_u32)
+                    }
+                };
+
+                char.map(|char| (char, end))
+            });
+
+        match decoded {
+            Some((char, end)) => {
+                out.push(char);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Returns whether a language tag is well-formed and valid per BCP 47.
+#[cfg(feature = "bcp47")]
+pub fn is_valid_language_tag(tag: &str) -> bool {
+    language_tags::LanguageTag::parse(tag).is_ok_and(|tag| tag.is_valid())
+}
+
+/// Escapes text so that it can be used as cue text.
+pub fn escape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for char in input.chars() {
+        match char {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            '\u{200e}' => out.push_str("&lrm;"),
+            '\u{200f}' => out.push_str("&rlm;"),
+            _ => out.push(char),
+        }
+    }
+
+    out
+}
+
+/// Serializes a tree of nodes back into cue text.
+pub fn write(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    write_into(&mut out, nodes);
+    out
+}
+
+fn write_into(out: &mut String, nodes: &[Node]) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(&escape(text)),
+            Node::Timestamp(time) => {
+                out.push('<');
+                out.push_str(&format_timestamp(*time));
+                out.push('>');
+            }
+            Node::Span(span) => {
+                let name = span.kind.tag_name();
+
+                out.push('<');
+                out.push_str(name);
+
+                for class in &span.classes {
+                    out.push('.');
+                    out.push_str(class);
+                }
+
+                if let Some(annotation) = span.kind.annotation() {
+                    out.push(' ');
+                    out.push_str(&escape(annotation));
+                }
+
+                out.push('>');
+                write_into(out, &span.children);
+                out.push_str("</");
+                out.push_str(name);
+                out.push('>');
+            }
+        }
+    }
+}
+
+/// Renders a tree of nodes as an HTML fragment, following the spec's
+/// conversion of cue text to DOM nodes. Line breaks become `<br>` elements.
+pub fn render_html(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    render_html_into(&mut out, nodes);
+    out
+}
+
+fn render_html_into(out: &mut String, nodes: &[Node]) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                for (idx, line) in text.split('\n').enumerate() {
+                    if idx > 0 {
+                        out.push_str("<br>");
+                    }
+
+                    out.push_str(&escape_html(line));
+                }
+            }
+            Node::Timestamp(time) => {
+                out.push_str("<?timestamp ");
+                out.push_str(&format_timestamp(*time));
+                out.push_str("?>");
+            }
+            Node::Span(span) => {
+                let element = match span.kind {
+                    SpanKind::Italic => "i",
+                    SpanKind::Bold => "b",
+                    SpanKind::Underline => "u",
+                    SpanKind::Ruby => "ruby",
+                    SpanKind::RubyText => "rt",
+                    SpanKind::Class | SpanKind::Voice { .. } | SpanKind::Language { .. } => "span",
+                };
+
+                out.push('<');
+                out.push_str(element);
+
+                if !span.classes.is_empty() {
+                    out.push_str(" class=\"");
+                    out.push_str(&escape_html(&span.classes.join(" ")));
+                    out.push('"');
+                }
+
+                match &span.kind {
+                    SpanKind::Voice { name } => {
+                        out.push_str(" title=\"");
+                        out.push_str(&escape_html(name));
+                        out.push('"');
+                    }
+                    SpanKind::Language { tag } => {
+                        out.push_str(" lang=\"");
+                        out.push_str(&escape_html(tag));
+                        out.push('"');
+                    }
+                    _ => {}
+                }
+
+                out.push('>');
+                render_html_into(out, &span.children);
+                out.push_str("</");
+                out.push_str(element);
+                out.push('>');
+            }
+        }
+    }
+}
+
+fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for char in input.chars() {
+        match char {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(char),
+        }
+    }
+
+    out
+}
+
+/// Which part of ruby text to keep when removing ruby markup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RubyPart {
+    /// The base text, like the kanji in Japanese furigana.
+    Base,
+    /// The annotations in `<rt>` spans, like the kana readings in furigana.
+    Annotation,
+}
+
+/// Replaces every `<ruby>` span in the tree with just one part of its text.
+pub fn strip_ruby(nodes: Vec<Node>, keep: RubyPart) -> Vec<Node> {
+    let mut out = vec![];
+
+    for node in nodes {
+        match node {
+            Node::Span(span) if span.kind == SpanKind::Ruby => {
+                for child in strip_ruby(span.children, keep) {
+                    match (child, keep) {
+                        (Node::Span(rt), RubyPart::Annotation) if rt.kind == SpanKind::RubyText => {
+                            out.extend(rt.children)
+                        }
+                        (Node::Span(rt), RubyPart::Base) if rt.kind == SpanKind::RubyText => {}
+                        (child, RubyPart::Base) => out.push(child),
+                        (_, RubyPart::Annotation) => {}
+                    }
+                }
+            }
+            Node::Span(mut span) => {
+                span.children = strip_ruby(span.children, keep);
+                out.push(Node::Span(span));
+            }
+            node => out.push(node),
+        }
+    }
+
+    out
+}
+
+/// Returns the text content of a tree of nodes, without any markup.
+pub fn plain(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    plain_into(&mut out, nodes);
+    out
+}
+
+fn plain_into(out: &mut String, nodes: &[Node]) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Timestamp(_) => {}
+            Node::Span(span) => plain_into(out, &span.children),
+        }
+    }
+}
+
+/// Calls `f` on every span in the tree, parents before their children.
+pub fn walk_spans<'a>(nodes: &'a [Node], f: &mut impl FnMut(&'a Span)) {
+    for node in nodes {
+        if let Node::Span(span) = node {
+            f(span);
+            walk_spans(&span.children, f);
+        }
+    }
+}
+
+/// Replaces every span matching `pred` with its children.
+pub fn unwrap_spans(nodes: Vec<Node>, pred: &mut impl FnMut(&Span) -> bool) -> Vec<Node> {
+    let mut out = vec![];
+
+    for node in nodes {
+        match node {
+            Node::Span(mut span) => {
+                let children = unwrap_spans(std::mem::take(&mut span.children), pred);
+
+                if pred(&span) {
+                    out.extend(children);
+                } else {
+                    span.children = children;
+                    out.push(Node::Span(span));
+                }
+            }
+            node => out.push(node),
+        }
+    }
+
+    out
+}
+
+/// Splits a tree of nodes at its line breaks, returning the nodes of each
+/// line. Spans that cross a line break are repeated on every line they cover.
+pub fn split_lines(nodes: Vec<Node>) -> Vec<Vec<Node>> {
+    let mut lines = vec![vec![]];
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                for (idx, part) in text.split('\n').enumerate() {
+                    if idx > 0 {
+                        lines.push(vec![]);
+                    }
+
+                    if !part.is_empty() {
+                        lines.last_mut().unwrap().push(Node::Text(part.to_string()));
+                    }
+                }
+            }
+            Node::Span(span) => {
+                for (idx, children) in split_lines(span.children).into_iter().enumerate() {
+                    if idx > 0 {
+                        lines.push(vec![]);
+                    }
+
+                    if !children.is_empty() {
+                        lines.last_mut().unwrap().push(Node::Span(Span {
+                            kind: span.kind.clone(),
+                            classes: span.classes.clone(),
+                            children,
+                        }));
+                    }
+                }
+            }
+            node => lines.last_mut().unwrap().push(node),
+        }
+    }
+
+    lines
+}
+
+impl Cue {
+    /// Parses the text of this cue into a tree of nodes. The text of a
+    /// metadata cue is a single text node.
+    pub fn nodes(&self) -> Vec<Node> {
+        match self.payload {
+            CuePayload::Text => parse(&self.text),
+            CuePayload::Metadata => vec![Node::Text(self.text.clone())],
+        }
+    }
+
+    /// Replaces the text of this cue with a tree of nodes.
+    pub fn set_nodes(&mut self, nodes: &[Node]) {
+        self.text = self.format_nodes(nodes);
+    }
+
+    /// Serializes a tree of nodes as text for this cue. The text of metadata
+    /// cues is not escaped.
+    pub(crate) fn format_nodes(&self, nodes: &[Node]) -> String {
+        match self.payload {
+            CuePayload::Text => write(nodes),
+            CuePayload::Metadata => plain(nodes),
+        }
+    }
+
+    /// Renders the text of this cue as an HTML fragment.
+    pub fn render_html(&self) -> String {
+        render_html(&self.nodes())
+    }
+
+    /// Removes ruby markup from this cue, keeping only one part of the text.
+    pub fn strip_ruby(&mut self, keep: RubyPart) {
+        self.set_nodes(&strip_ruby(self.nodes(), keep));
+    }
+
+    /// Returns the text of this cue without any markup.
+    pub fn plain_text(&self) -> String {
+        plain(&self.nodes())
+    }
+
+    /// Returns the distinct language tags of the `<lang>` spans in this cue, in
+    /// order of first appearance. A cue with more than one language tag mixes
+    /// languages.
+    pub fn languages(&self) -> Vec<String> {
+        let nodes = self.nodes();
+        let mut languages: Vec<String> = vec![];
+
+        walk_spans(&nodes, &mut |span| {
+            if let SpanKind::Language { tag } = &span.kind {
+                if !tag.is_empty() && !languages.contains(tag) {
+                    languages.push(tag.clone());
+                }
+            }
+        });
+
+        languages
+    }
+
+    /// Returns the name of the first voice (`<v name>`) in this cue, if any.
+    pub fn speaker(&self) -> Option<String> {
+        let nodes = self.nodes();
+        let mut speaker = None;
+
+        walk_spans(&nodes, &mut |span| {
+            if let (None, SpanKind::Voice { name }) = (&speaker, &span.kind) {
+                speaker = Some(name.clone());
+            }
+        });
+
+        speaker
+    }
+}
+
+/// How a CSS class is used in the cues of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassUsage {
+    pub name: String,
+    /// The number of spans with the class.
+    pub count: usize,
+    /// Index of the first cue using the class among the cues of the file.
+    pub first_cue: usize,
+    /// Start time of the first cue using the class.
+    pub first_time: Duration,
+}
+
+impl File {
+    /// Removes ruby markup from every cue, keeping only one part of the text.
+    pub fn strip_ruby(&mut self, keep: RubyPart) {
+        for cue in self.cues_mut() {
+            cue.strip_ruby(keep);
+        }
+    }
+
+    /// Returns every class used by spans in the cues of this file, like
+    /// `yellow` and `bg_black` in `<c.yellow.bg_black>`, in order of first use.
+    pub fn classes(&self) -> Vec<ClassUsage> {
+        let mut classes: Vec<ClassUsage> = vec![];
+
+        for (cue_index, cue) in self.cues().enumerate() {
+            let nodes = cue.nodes();
+
+            walk_spans(&nodes, &mut |span| {
+                for class in &span.classes {
+                    match classes.iter_mut().find(|usage| &usage.name == class) {
+                        Some(usage) => usage.count += 1,
+                        None => classes.push(ClassUsage {
+                            name: class.clone(),
+                            count: 1,
+                            first_cue: cue_index,
+                            first_time: cue.start,
+                        }),
+                    }
+                }
+            });
+        }
+
+        classes
+    }
+}
@@ -0,0 +1,175 @@
+//! Flattening of cues into the events a player needs to show and hide them.
+
+use std::time::Duration;
+
+#[cfg(feature = "json")]
+use serde_json::{json, Map, Value};
+#[cfg(feature = "json")]
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Cue, File};
+
+/// The most grapheme clusters in the label of a bar in
+/// [`File::to_timeline_json`].
+#[cfg(feature = "json")]
+const LABEL_LENGTH: usize = 40;
+
+/// Returns the text of a cue on one line, shortened to [`LABEL_LENGTH`].
+#[cfg(feature = "json")]
+fn label(cue: &Cue) -> String {
+    let text = cue
+        .plain_text()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut graphemes = text.graphemes(true);
+    let mut label: String = graphemes.by_ref().take(LABEL_LENGTH - 1).collect();
+
+    match (graphemes.next(), graphemes.next()) {
+        (Some(last), None) => label.push_str(last),
+        (Some(_), Some(_)) => {
+            label.truncate(label.trim_end().len());
+            label.push('…');
+        }
+        _ => {}
+    }
+
+    label
+}
+
+/// Whether a cue becomes active or inactive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transition {
+    Enter,
+    Exit,
+}
+
+/// The moment a cue becomes active or inactive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimelineEvent {
+    pub time: Duration,
+    /// Index of the cue among the cues of the file.
+    pub cue_index: usize,
+    pub transition: Transition,
+}
+
+impl File {
+    /// Returns the times at which each cue becomes active and inactive, sorted
+    /// by time. A cue is active from its start time up to, but not including,
+    /// its end time, so at equal times exits come before entries; cues that do
+    /// not end after they start are never active and have no events. Events of
+    /// the same kind at the same time are in cue order.
+    pub fn timeline(&self) -> Vec<TimelineEvent> {
+        let mut events = vec![];
+
+        for (cue_index, cue) in self.cues().enumerate() {
+            if cue.end <= cue.start {
+                continue;
+            }
+
+            events.push(TimelineEvent {
+                time: cue.start,
+                cue_index,
+                transition: Transition::Enter,
+            });
+
+            events.push(TimelineEvent {
+                time: cue.end,
+                cue_index,
+                transition: Transition::Exit,
+            });
+        }
+
+        events.sort_by_key(|event| {
+            let rank = match event.transition {
+                Transition::Exit => 0,
+                Transition::Enter => 1,
+            };
+
+            (event.time, rank, event.cue_index)
+        });
+
+        events
+    }
+
+    /// Returns the cues active at the given time, in the order they should be
+    /// shown: by [`Cue::source_order`], then by their order in the file.
+    pub fn cues_at(&self, time: Duration) -> Vec<&Cue> {
+        let mut cues: Vec<_> = self
+            .cues()
+            .filter(|cue| cue.start <= time && time < cue.end)
+            .collect();
+
+        cues.sort_by_key(|cue| cue.source_order);
+        cues
+    }
+
+    /// Exports the cues as JSON for timeline and waveform views of editors:
+    ///
+    /// ```json
+    /// {
+    ///   "duration": 6000,
+    ///   "tracks": [
+    ///     { "bars": [{ "cue": 0, "id": "intro", "start": 1000, "end": 4000, "label": "Good evening.", "speaker": "Roger" }] },
+    ///     { "bars": [{ "cue": 1, "start": 2000, "end": 6000, "label": "Rain is on the way." }] }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Times are in milliseconds. Each cue is a bar on the first track that
+    /// is free from its start to its end, so that bars on a track never
+    /// overlap and overlapping cues are stacked. `cue` is the index of the
+    /// cue among the cues of the file, and the label is its text on one line,
+    /// shortened to 40 characters. `id` and `speaker` are left out when the
+    /// cue has none. Cues that do not end after they start are left out, and
+    /// the duration is the latest end time.
+    #[cfg(feature = "json")]
+    pub fn to_timeline_json(&self) -> String {
+        let mut cues: Vec<_> = self
+            .cues()
+            .enumerate()
+            .filter(|(_, cue)| cue.end > cue.start)
+            .collect();
+        cues.sort_by_key(|&(idx, cue)| (cue.start, idx));
+
+        // the end of the last bar on each track, and its bars
+        let mut tracks: Vec<(Duration, Vec<Value>)> = vec![];
+
+        for (idx, cue) in cues {
+            let mut bar = Map::new();
+            bar.insert("cue".into(), idx.into());
+
+            if !cue.id.is_empty() {
+                bar.insert("id".into(), cue.id.as_str().into());
+            }
+
+            bar.insert("start".into(), json!(cue.start.as_millis() as u64));
+            bar.insert("end".into(), json!(cue.end.as_millis() as u64));
+            bar.insert("label".into(), label(cue).into());
+
+            if let Some(speaker) = cue.speaker() {
+                bar.insert("speaker".into(), speaker.into());
+            }
+
+            match tracks.iter_mut().find(|(end, _)| *end <= cue.start) {
+                Some((end, bars)) => {
+                    *end = cue.end;
+                    bars.push(Value::Object(bar));
+                }
+                None => tracks.push((cue.end, vec![Value::Object(bar)])),
+            }
+        }
+
+        let duration = self.cues().map(|cue| cue.end).max().unwrap_or_default();
+        let tracks: Vec<_> = tracks
+            .into_iter()
+            .map(|(_, bars)| json!({ "bars": bars }))
+            .collect();
+
+        json!({
+            "duration": duration.as_millis() as u64,
+            "tracks": tracks,
+        })
+        .to_string()
+    }
+}
@@ -0,0 +1,141 @@
+//! Parsing of WebVTT timestamps, with detailed errors.
+
+use std::{fmt, time::Duration};
+
+use thiserror::Error;
+
+use crate::format_timestamp;
+
+/// A WebVTT timestamp, like `01:02:03.456` or `02:03.456`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Timestamp(pub Duration);
+
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimestampError {
+    #[error("expected digits")]
+    ExpectedDigits,
+
+    #[error("expected ':'")]
+    ExpectedColon,
+
+    #[error("expected '.'")]
+    ExpectedDecimalPoint,
+
+    #[error("hours out of range")]
+    BadHours,
+
+    #[error("minutes must be two digits")]
+    BadMinutes,
+
+    #[error("minutes over 59")]
+    MinutesOver59,
+
+    #[error("seconds must be two digits")]
+    BadSeconds,
+
+    #[error("seconds over 59")]
+    SecondsOver59,
+
+    #[error("milliseconds must be three digits")]
+    BadMillis,
+}
+
+impl Timestamp {
+    /// Parses a timestamp at the start of the input, returning it along with
+    /// the rest of the input.
+    ///
+    /// The hours are optional, and may have any number of digits. Without
+    /// hours, the minutes must be two digits; a first component that is not
+    /// two digits or is over 59 is taken to be hours.
+    pub fn parse(input: &str) -> Result<(Timestamp, &str), TimestampError> {
+        use TimestampError::*;
+
+        let (first, rest) = digits(input)?;
+        let rest = rest.strip_prefix(':').ok_or(ExpectedColon)?;
+        let (second, rest) = digits(rest)?;
+
+        let (hours, minutes, seconds, rest) = match rest.strip_prefix(':') {
+            Some(rest) => {
+                let (third, rest) = digits(rest)?;
+                (Some(first), second, third, rest)
+            }
+            None => (None, first, second, rest),
+        };
+
+        let hours = match hours {
+            Some(hours) => hours.parse::<u64>().map_err(|_| BadHours)?,
+            None => 0,
+        };
+
+        let minutes = two_digits(minutes, BadMinutes, MinutesOver59)?;
+        let seconds = two_digits(seconds, BadSeconds, SecondsOver59)?;
+
+        let rest = rest.strip_prefix('.').ok_or(ExpectedDecimalPoint)?;
+        let (millis, rest) = digits(rest).map_err(|_| BadMillis)?;
+
+        if millis.len() != 3 {
+            return Err(BadMillis);
+        }
+
+        let millis: u64 = millis.parse().unwrap();
+
+        let total = hours
+            .checked_mul(3_600_000)
+            .and_then(|ms| ms.checked_add(minutes * 60_000 + seconds * 1000 + millis))
+            .ok_or(BadHours)?;
+
+        Ok((Timestamp(Duration::from_millis(total)), rest))
+    }
+
+    pub fn as_duration(self) -> Duration {
+        self.0
+    }
+}
+
+/// Splits a run of ASCII digits off the start of the input.
+fn digits(input: &str) -> Result<(&str, &str), TimestampError> {
+    let end = crate::scan::digits_len(input);
+
+    if end == 0 {
+        return Err(TimestampError::ExpectedDigits);
+    }
+
+    Ok(input.split_at(end))
+}
+
+fn two_digits(
+    digits: &str,
+    bad_length: TimestampError,
+    over_59: TimestampError,
+) -> Result<u64, TimestampError> {
+    if digits.len() != 2 {
+        return Err(bad_length);
+    }
+
+    let value = digits.parse().unwrap();
+
+    if value > 59 {
+        return Err(over_59);
+    }
+
+    Ok(value)
+}
+
+impl From<Duration> for Timestamp {
+    fn from(duration: Duration) -> Self {
+        Timestamp(duration)
+    }
+}
+
+impl From<Timestamp> for Duration {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// Formats the timestamp as `hh:mm:ss.ttt`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_timestamp(self.0))
+    }
+}
@@ -0,0 +1,189 @@
+//! A record of what the parser made of each line of a file, for finding out
+//! why a file parses differently than expected.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    is_definition, is_note, parse_input, BlockContext, Diagnostic, ExtensionMap, File, FileContext,
+    Lines, ParseError, ParseOptions, TimingFix,
+};
+
+/// What the parser took a line to be.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LineKind {
+    /// The `WEBVTT` line, or the header of a later segment.
+    Header,
+    Blank,
+    /// The identifier of a cue.
+    Id,
+    /// The timings and settings of a cue.
+    Timing,
+    /// A line of a cue's payload.
+    Text,
+    /// A line of a NOTE block.
+    Note,
+    /// A line that is not part of any cue, note or header.
+    Skip,
+}
+
+impl fmt::Display for LineKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            LineKind::Header => "header",
+            LineKind::Blank => "blank",
+            LineKind::Id => "id",
+            LineKind::Timing => "timing",
+            LineKind::Text => "text",
+            LineKind::Note => "note",
+            LineKind::Skip => "skip",
+        })
+    }
+}
+
+/// What the parser did with one line.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TraceLine {
+    /// The number of the line, starting at 1.
+    pub line_number: usize,
+    /// The byte offset of the line in the input.
+    pub offset: usize,
+    /// The line, without its line terminator.
+    pub text: String,
+    pub kind: LineKind,
+    /// Why the line was taken to be of its kind.
+    pub reason: &'static str,
+}
+
+/// The decisions the parser made about every line of a file, from
+/// [`parse_file_traced`].
+///
+/// Its [`Display`](fmt::Display) implementation prints one line of the file
+/// per line, with its number, kind and the reason for it, to paste into bug
+/// reports.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ParseTrace {
+    pub lines: Vec<TraceLine>,
+}
+
+impl ParseTrace {
+    /// Returns the trace of a line by its number, starting at 1.
+    pub fn line(&self, line_number: usize) -> Option<&TraceLine> {
+        self.lines.get(line_number.checked_sub(1)?)
+    }
+}
+
+impl fmt::Display for ParseTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(
+                f,
+                "{:>4} {:<6} {:?} ({})",
+                line.line_number, line.kind, line.text, line.reason
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The kind of each line the parser has classified, by offset.
+pub(crate) type Decisions = HashMap<usize, (LineKind, &'static str)>;
+
+impl FileContext {
+    /// Records what a line was taken to be, if the parse is traced.
+    pub(crate) fn trace(&mut self, offset: usize, kind: LineKind, reason: &'static str) {
+        if let Some(decisions) = &mut self.trace {
+            decisions.insert(offset, (kind, reason));
+        }
+    }
+}
+
+/// Records what each line of a block was taken to be, once the whole block
+/// has been read.
+pub(crate) fn trace_block(block_ctx: &BlockContext, file_ctx: &mut FileContext) {
+    if file_ctx.trace.is_none() {
+        return;
+    }
+
+    let (kind, reason) = if let Some(cue) = &block_ctx.cue {
+        if cue.end <= cue.start && file_ctx.options.timing_fix == Some(TimingFix::Drop) {
+            (
+                LineKind::Skip,
+                "cue does not end after it starts, so it is dropped",
+            )
+        } else {
+            (LineKind::Text, "cue payload")
+        }
+    } else if ExtensionMap::from_note(&block_ctx.buffer).is_some() {
+        (
+            LineKind::Note,
+            "NOTE block with extensions for the next cue",
+        )
+    } else if is_note(&block_ctx.buffer) {
+        (LineKind::Note, "NOTE block")
+    } else if !file_ctx.options.lenient && is_definition(block_ctx, file_ctx) {
+        (LineKind::Skip, "STYLE or REGION block, kept raw")
+    } else {
+        match (block_ctx.seen_arrow, file_ctx.options.lenient) {
+            (true, false) => (LineKind::Skip, "block with bad cue timings, dropped"),
+            (true, true) => (LineKind::Skip, "block with bad cue timings, kept raw"),
+            (false, false) => (LineKind::Skip, "block without cue timings, dropped"),
+            (false, true) => (LineKind::Skip, "block without cue timings, kept raw"),
+        }
+    };
+
+    for &(offset, blank) in &block_ctx.traced {
+        let decision = match block_ctx.timing_offset {
+            _ if blank => (LineKind::Blank, "ends the block"),
+            _ if kind == LineKind::Skip => (kind, reason),
+            Some(timing) if offset < timing => (LineKind::Id, "line before the cue timings"),
+            Some(timing) if offset == timing => (LineKind::Timing, "cue timings and settings"),
+            _ => (kind, reason),
+        };
+
+        file_ctx.trace(offset, decision.0, decision.1);
+    }
+}
+
+/// Parses a string as a WebVTT file like [`parse_file_with`](crate::parse_file_with),
+/// also recording what the parser took each line to be and why. The trace
+/// covers the whole input even if parsing fails.
+pub fn parse_file_traced(
+    input: &str,
+    options: &ParseOptions,
+) -> (Result<(File, Vec<Diagnostic>), ParseError>, ParseTrace) {
+    let mut file_ctx = FileContext {
+        trace: Some(Decisions::new()),
+        ..FileContext::new(options.clone())
+    };
+
+    let result = parse_input(input, &mut file_ctx);
+    let mut decisions = file_ctx.trace.take().unwrap_or_default();
+
+    // a line terminator at the end of the input does not start another line
+    let lines = Lines::new(input, 0)
+        .filter(|&(offset, text)| offset < input.len() || !text.is_empty() || offset == 0)
+        .enumerate()
+        .map(|(idx, (offset, text))| {
+            let (kind, reason) = decisions.remove(&offset).unwrap_or(if text.is_empty() {
+                (LineKind::Blank, "separates blocks")
+            } else {
+                (LineKind::Skip, "not reached, parsing stopped at an error")
+            });
+
+            TraceLine {
+                line_number: idx + 1,
+                offset,
+                text: text.to_owned(),
+                kind,
+                reason,
+            }
+        })
+        .collect();
+
+    (
+        result.map(|file| (file, file_ctx.diagnostics)),
+        ParseTrace { lines },
+    )
+}
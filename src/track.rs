@@ -0,0 +1,174 @@
+//! Collections of parallel tracks, such as the translations of one set of
+//! captions into several languages.
+
+use std::{cmp::Reverse, collections::BTreeMap, time::Duration};
+
+use crate::{Cue, File};
+
+/// A set of [`File`]s keyed by language tag.
+#[derive(Default, Debug, Clone)]
+pub struct TrackSet {
+    tracks: BTreeMap<String, File>,
+}
+
+impl TrackSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a track, returning the track previously stored for `language`, if
+    /// any.
+    pub fn insert(&mut self, language: impl Into<String>, file: File) -> Option<File> {
+        self.tracks.insert(language.into(), file)
+    }
+
+    pub fn remove(&mut self, language: &str) -> Option<File> {
+        self.tracks.remove(language)
+    }
+
+    pub fn get(&self, language: &str) -> Option<&File> {
+        self.tracks.get(language)
+    }
+
+    pub fn get_mut(&mut self, language: &str) -> Option<&mut File> {
+        self.tracks.get_mut(language)
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.tracks.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &File)> {
+        self.tracks.iter().map(|(lang, file)| (lang.as_str(), file))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Copies the timings of each cue in the `reference` track onto its
+    /// counterpart in every other track (see [`TrackSet::missing_cues`] for how
+    /// counterparts are found). Does nothing if there is no `reference` track.
+    pub fn align_to(&mut self, reference: &str) {
+        let Some(reference_file) = self.tracks.get(reference) else {
+            return;
+        };
+
+        let timings: Vec<_> = reference_file
+            .cues()
+            .map(|cue| (cue.id.clone(), cue.start, cue.end))
+            .collect();
+
+        for (lang, file) in &mut self.tracks {
+            if lang == reference {
+                continue;
+            }
+
+            // look up every counterpart before changing any timings, since
+            // counterparts are partly found by timing
+            let cues: Vec<&Cue> = file.cues().collect();
+            let counterparts: Vec<_> = timings
+                .iter()
+                .map(|(id, start, end)| find_counterpart(id, *start, *end, &cues))
+                .collect();
+
+            let mut cues: Vec<&mut Cue> = file.cues_mut().collect();
+
+            for ((_, start, end), counterpart) in timings.iter().zip(counterparts) {
+                if let Some(idx) = counterpart {
+                    cues[idx].start = *start;
+                    cues[idx].end = *end;
+                }
+            }
+        }
+    }
+
+    /// Finds the cues of the `reference` track that have no counterpart in
+    /// each of the other tracks, such as segments that have not been
+    /// translated yet.
+    ///
+    /// A cue's counterpart is the cue with the same identifier if it has one,
+    /// and otherwise the cue that overlaps it in time the most. Counterparts
+    /// with empty text are treated as missing.
+    pub fn missing_cues(&self, reference: &str) -> BTreeMap<&str, Vec<&Cue>> {
+        let Some(reference_file) = self.tracks.get(reference) else {
+            return BTreeMap::new();
+        };
+
+        self.tracks
+            .iter()
+            .filter(|(lang, _)| *lang != reference)
+            .map(|(lang, file)| {
+                let cues: Vec<&Cue> = file.cues().collect();
+
+                let missing = reference_file
+                    .cues()
+                    .filter(|cue| {
+                        find_counterpart(&cue.id, cue.start, cue.end, &cues)
+                            .is_none_or(|idx| cues[idx].text.trim().is_empty())
+                    })
+                    .collect();
+
+                (lang.as_str(), missing)
+            })
+            .collect()
+    }
+}
+
+/// How [`merge_bilingual`] lays out the two languages in each cue.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum BilingualStyle {
+    /// The secondary text is placed on the lines below the primary text.
+    #[default]
+    Stacked,
+    /// Like [`BilingualStyle::Stacked`], but each language is wrapped in a
+    /// class span (`<c.primary>…</c>`) so it can be styled separately.
+    Classed { primary: String, secondary: String },
+}
+
+/// Produces a track with the text of both languages in each cue, using the
+/// timings and settings of `primary`. Each primary cue is paired with its
+/// counterpart in `secondary`, found as in [`TrackSet::missing_cues`]; primary
+/// cues without a counterpart keep only their own text.
+pub fn merge_bilingual(primary: &File, secondary: &File, style: &BilingualStyle) -> File {
+    let secondary_cues: Vec<&Cue> = secondary.cues().collect();
+    let mut merged = primary.clone();
+
+    for cue in merged.cues_mut() {
+        let counterpart = find_counterpart(&cue.id, cue.start, cue.end, &secondary_cues)
+            .map(|idx| secondary_cues[idx].text.as_str())
+            .filter(|text| !text.trim().is_empty());
+
+        cue.text = match style {
+            BilingualStyle::Stacked => match counterpart {
+                Some(text) => format!("{}\n{}", cue.text, text),
+                None => cue.text.clone(),
+            },
+            BilingualStyle::Classed { primary, secondary } => match counterpart {
+                Some(text) => format!("<c.{primary}>{}</c>\n<c.{secondary}>{text}</c>", cue.text),
+                None => format!("<c.{primary}>{}</c>", cue.text),
+            },
+        };
+    }
+
+    merged
+}
+
+fn find_counterpart(id: &str, start: Duration, end: Duration, cues: &[&Cue]) -> Option<usize> {
+    if !id.is_empty() {
+        if let Some(idx) = cues.iter().position(|c| c.id == id) {
+            return Some(idx);
+        }
+    }
+
+    cues.iter()
+        .enumerate()
+        .map(|(idx, c)| (idx, end.min(c.end).saturating_sub(start.max(c.start))))
+        .filter(|(_, overlap)| !overlap.is_zero())
+        .max_by_key(|&(idx, overlap)| (overlap, Reverse(idx)))
+        .map(|(idx, _)| idx)
+}
@@ -0,0 +1,189 @@
+//! Checks of files against rules that the parser does not enforce.
+
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::File;
+
+/// A problem found in a cue by one of the validators.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// Index of the cue among the cues of the file.
+    pub cue_index: usize,
+    pub kind: FindingKind,
+}
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FindingKind {
+    #[error("cue collides with dialogue from {start:?} to {end:?}")]
+    DialogueCollision { start: Duration, end: Duration },
+
+    #[error("invalid language tag {tag:?}")]
+    InvalidLanguageTag { tag: String },
+
+    #[error("line {line} has {length} characters, more than {max}")]
+    LineTooLong {
+        line: usize,
+        length: usize,
+        max: usize,
+    },
+
+    #[error("cue has {lines} lines, more than {max}")]
+    TooManyLines { lines: usize, max: usize },
+
+    #[error("cue shows {chars} characters in {duration:?}, which is too fast to read")]
+    TooFast { chars: usize, duration: Duration },
+
+    #[error("cue lasts {duration:?}, which is too short")]
+    TooShort { duration: Duration },
+
+    #[error("cue lasts {duration:?}, which is too long")]
+    TooLong { duration: Duration },
+
+    #[error("gap of {gap:?} before the next cue is too short")]
+    GapTooShort { gap: Duration },
+
+    #[error("cue starts before the previous cue at {previous:?}")]
+    Unsorted { previous: Duration },
+
+    /// The id and start of the cue are included so that the finding can be
+    /// reported without the file.
+    #[cfg(feature = "json")]
+    #[error("metadata of cue {id:?} at {start:?} is not valid JSON: {message}")]
+    InvalidJson {
+        id: String,
+        start: Duration,
+        message: String,
+    },
+
+    #[cfg(feature = "json-schema")]
+    #[error(
+        "metadata of cue {id:?} at {start:?} does not match the schema at {path:?}: {message}"
+    )]
+    SchemaMismatch {
+        id: String,
+        start: Duration,
+        path: String,
+        message: String,
+    },
+}
+
+impl File {
+    /// Checks a `descriptions` track against the intervals of the media that
+    /// contain dialogue, which need not be sorted. Every cue must fit within a
+    /// gap in the dialogue, so each overlap of a cue with an interval is
+    /// reported.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn validate_descriptions(&self, dialogue: &[(Duration, Duration)]) -> Vec<Finding> {
+        let mut dialogue = dialogue.to_vec();
+        dialogue.sort();
+
+        let mut findings = vec![];
+
+        for (cue_index, cue) in self.cues().enumerate() {
+            let collisions = dialogue
+                .iter()
+                .take_while(|&&(start, _)| start < cue.end)
+                .filter(|&&(_, end)| end > cue.start);
+
+            for &(start, end) in collisions {
+                findings.push(Finding {
+                    cue_index,
+                    kind: FindingKind::DialogueCollision { start, end },
+                });
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(findings = findings.len(), "checked file");
+
+        findings
+    }
+}
+
+#[cfg(feature = "bcp47")]
+impl File {
+    /// Checks that the tags of all `<lang>` spans are valid per BCP 47.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn validate_language_tags(&self) -> Vec<Finding> {
+        let mut findings = vec![];
+
+        for (cue_index, cue) in self.cues().enumerate() {
+            for tag in cue.languages() {
+                if !crate::text::is_valid_language_tag(&tag) {
+                    findings.push(Finding {
+                        cue_index,
+                        kind: FindingKind::InvalidLanguageTag { tag },
+                    });
+                }
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(findings = findings.len(), "checked file");
+
+        findings
+    }
+}
+
+#[cfg(feature = "json-schema")]
+impl File {
+    /// Checks the JSON payload of every cue that has one against a JSON
+    /// Schema: every metadata cue, and every other cue whose text is a JSON
+    /// object or array, as in a metadata track parsed as captions. Reports
+    /// metadata cues that are not JSON and every way in which the others do
+    /// not match. Fails if the schema itself is invalid.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn validate_metadata_schema(
+        &self,
+        schema: &serde_json::Value,
+    ) -> Result<Vec<Finding>, crate::ValidationError> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|err| crate::ValidationError::BadSchema(err.to_string()))?;
+
+        let mut findings = vec![];
+
+        for (cue_index, cue) in self.cues().enumerate() {
+            let value = match cue.json() {
+                Ok(value) if cue.is_metadata() || value.is_object() || value.is_array() => value,
+                Ok(_) => continue,
+                Err(err) => {
+                    if cue.is_metadata() {
+                        findings.push(Finding {
+                            cue_index,
+                            kind: FindingKind::InvalidJson {
+                                id: cue.id.clone(),
+                                start: cue.start,
+                                message: err.to_string(),
+                            },
+                        });
+                    }
+
+                    continue;
+                }
+            };
+
+            for err in validator.iter_errors(&value) {
+                findings.push(Finding {
+                    cue_index,
+                    kind: FindingKind::SchemaMismatch {
+                        id: cue.id.clone(),
+                        start: cue.start,
+                        path: err.instance_path().to_string(),
+                        message: err.to_string(),
+                    },
+                });
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(findings = findings.len(), "checked file");
+
+        Ok(findings)
+    }
+}
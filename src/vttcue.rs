@@ -0,0 +1,87 @@
+//! The fields of the browser's `VTTCue` interface, for players that build
+//! cues from data rendered on a server:
+//!
+//! ```js
+//! const cue = Object.assign(new VTTCue(init.startTime, init.endTime, init.text), init);
+//! ```
+
+use crate::{Cue, Line, WritingDirection};
+
+/// A `VTTCue` attribute that is either a number or `"auto"`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberOrAuto {
+    Number(f64),
+    Auto,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for NumberOrAuto {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            NumberOrAuto::Number(number) => serializer.serialize_f64(*number),
+            NumberOrAuto::Auto => serializer.serialize_str("auto"),
+        }
+    }
+}
+
+/// The attributes of a `VTTCue`, named and valued as in the browser's
+/// interface. Times are in seconds.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct VttCueInit {
+    pub id: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// The cue text, with its markup, as `VTTCue` takes it.
+    pub text: String,
+    /// `""`, `"rl"` or `"lr"`.
+    pub vertical: &'static str,
+    /// Whether `line` is a line number rather than a percentage.
+    pub snap_to_lines: bool,
+    pub line: NumberOrAuto,
+    /// `"start"`, `"center"` or `"end"`.
+    pub line_align: &'static str,
+    pub position: NumberOrAuto,
+    /// `"line-left"`, `"center"`, `"line-right"` or `"auto"`.
+    pub position_align: &'static str,
+    pub size: f64,
+    /// `"start"`, `"center"`, `"end"`, `"left"` or `"right"`.
+    pub align: &'static str,
+}
+
+impl Cue {
+    /// Returns the attributes a browser's `VTTCue` would have for this cue,
+    /// as set by the spec's parser: a line percentage sets `snapToLines` to
+    /// `false`, and settings that were not given have their defaults.
+    pub fn to_vttcue_init(&self) -> VttCueInit {
+        let settings = &self.settings;
+
+        let (line, snap_to_lines) = match settings.line {
+            Line::Auto => (NumberOrAuto::Auto, true),
+            Line::Number(number) => (NumberOrAuto::Number(number as f64), true),
+            Line::Percentage(percentage) => (NumberOrAuto::Number(percentage.get()), false),
+        };
+
+        VttCueInit {
+            id: self.id.clone(),
+            start_time: self.start.as_secs_f64(),
+            end_time: self.end.as_secs_f64(),
+            text: self.text.clone(),
+            vertical: match settings.writing_direction {
+                WritingDirection::Horizontal => "",
+                WritingDirection::VerticalLeft => "rl",
+                WritingDirection::VerticalRight => "lr",
+            },
+            snap_to_lines,
+            line,
+            line_align: settings.line_align.as_str(),
+            position: settings.position.map_or(NumberOrAuto::Auto, |position| {
+                NumberOrAuto::Number(position.get())
+            }),
+            position_align: settings.position_align.as_str(),
+            size: settings.size.get(),
+            align: settings.align.as_str(),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Measuring the length of text as viewers see it, rather than in bytes or
+//! code points.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How the length of a line of text is counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LengthMetric {
+    /// Unicode code points, so an emoji with a skin tone counts as two and a
+    /// letter with a combining accent counts as two.
+    Chars,
+    /// Grapheme clusters, the characters a reader sees, so every emoji and
+    /// accented letter counts as one. Subtitle style guides count characters
+    /// this way.
+    #[default]
+    Graphemes,
+    /// Columns of a fixed-width display, where wide characters like CJK
+    /// ideographs and most emoji count as two.
+    Width,
+}
+
+impl LengthMetric {
+    /// Returns the length of `text`, which should not contain line breaks.
+    pub fn measure(self, text: &str) -> usize {
+        match self {
+            LengthMetric::Chars => text.chars().count(),
+            LengthMetric::Graphemes => text.graphemes(true).count(),
+            LengthMetric::Width => text.width(),
+        }
+    }
+}
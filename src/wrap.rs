@@ -0,0 +1,371 @@
+//! Rewrapping cue text to a line length, for text with spaces between words
+//! and for Chinese and Japanese text, which has none.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{text::Node, Cue, File, LengthMetric};
+
+/// What happens to a character of a cue's text when it is rewrapped.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Keep,
+    /// A line break that is joined into a line with a space.
+    Space,
+    /// A line break between two CJK characters, which is joined without one.
+    Remove,
+    /// A space that becomes a line break.
+    Break,
+    /// A character that starts a new line.
+    BreakBefore,
+    /// A character that starts a new line, after a hyphen ending the line
+    /// before it.
+    HyphenBefore,
+}
+
+/// Returns whether a character is written without spaces around it, as in
+/// Chinese and Japanese.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1100}'..='\u{11FF}'
+            | '\u{2E80}'..='\u{2FDF}'
+            | '\u{3000}'..='\u{30FF}'
+            | '\u{3100}'..='\u{312F}'
+            | '\u{31A0}'..='\u{31FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FE30}'..='\u{FE4F}'
+            | '\u{FF00}'..='\u{FFEF}'
+            | '\u{20000}'..='\u{3134F}'
+    )
+}
+
+/// Characters that cannot start a line: closing punctuation, and the small
+/// kana and marks of Japanese.
+fn no_break_before(c: char) -> bool {
+    matches!(
+        c,
+        '、' | '。'
+            | '，'
+            | '．'
+            | '・'
+            | '：'
+            | '；'
+            | '！'
+            | '？'
+            | '）'
+            | '」'
+            | '』'
+            | '】'
+            | '〕'
+            | '〉'
+            | '》'
+            | '”'
+            | '’'
+            | 'ー'
+            | '々'
+            | '〜'
+            | '…'
+            | 'ぁ'
+            | 'ぃ'
+            | 'ぅ'
+            | 'ぇ'
+            | 'ぉ'
+            | 'っ'
+            | 'ゃ'
+            | 'ゅ'
+            | 'ょ'
+            | 'ゎ'
+            | 'ァ'
+            | 'ィ'
+            | 'ゥ'
+            | 'ェ'
+            | 'ォ'
+            | 'ッ'
+            | 'ャ'
+            | 'ュ'
+            | 'ョ'
+            | 'ヮ'
+            | 'ヵ'
+            | 'ヶ'
+    ) || matches!(c, ',' | '.' | ':' | ';' | '!' | '?' | ')' | ']')
+}
+
+/// Characters that cannot end a line: opening punctuation.
+fn no_break_after(c: char) -> bool {
+    matches!(
+        c,
+        '（' | '「' | '『' | '【' | '〔' | '〈' | '《' | '“' | '‘' | '(' | '['
+    )
+}
+
+/// Returns whether a line can break between two characters with no space
+/// between them, which it can next to a CJK character.
+fn can_break_between(before: char, after: char) -> bool {
+    (is_cjk(before) || is_cjk(after)) && !no_break_before(after) && !no_break_after(before)
+}
+
+/// A run of characters that is never broken, as indices into the text.
+struct Word {
+    start: usize,
+    end: usize,
+    /// The index of the space before the word, if there is one.
+    space: Option<usize>,
+}
+
+/// Finds where a word can be hyphenated, given its text and the index of its
+/// first character in the plain text of the cue. Returns the offsets of the
+/// characters a line can start at, in order.
+pub(crate) type WordBreaks<'a> = &'a dyn Fn(&str, usize) -> Vec<usize>;
+
+/// Decides what to do with each character of some plain text to wrap it to
+/// lines of at most `max_length`, where it can be. Words that do not fit on
+/// a line of their own are hyphenated where `hyphenate` allows.
+fn plan(
+    plain: &str,
+    max_length: usize,
+    metric: LengthMetric,
+    hyphenate: WordBreaks,
+) -> Vec<Action> {
+    let chars: Vec<char> = plain.chars().collect();
+    let mut actions = vec![Action::Keep; chars.len()];
+
+    // lines only break between grapheme clusters, so an accent or emoji
+    // modifier stays with its base character
+    let mut cluster_start = vec![false; chars.len()];
+    let mut idx = 0;
+
+    for grapheme in plain.graphemes(true) {
+        cluster_start[idx] = true;
+        idx += grapheme.chars().count();
+    }
+
+    // join the existing lines, without a space between CJK characters
+    for idx in 0..chars.len() {
+        if chars[idx] != '\n' {
+            continue;
+        }
+
+        let before = chars[..idx].iter().rev().find(|c| !c.is_whitespace());
+        let after = chars[idx + 1..].iter().find(|c| !c.is_whitespace());
+
+        actions[idx] = match (before, after) {
+            (Some(&before), Some(&after)) if is_cjk(before) && is_cjk(after) => Action::Remove,
+            _ => Action::Space,
+        };
+    }
+
+    let mut words: Vec<Word> = vec![];
+    let mut space = None;
+    let mut previous: Option<char> = None;
+
+    for (idx, &c) in chars.iter().enumerate() {
+        if actions[idx] == Action::Remove {
+            continue;
+        }
+
+        if c.is_whitespace() {
+            space = space.or(Some(idx));
+            previous = None;
+            continue;
+        }
+
+        match (words.last_mut(), previous) {
+            (Some(word), Some(previous))
+                if !cluster_start[idx] || !can_break_between(previous, c) =>
+            {
+                word.end = idx + 1
+            }
+            _ => words.push(Word {
+                start: idx,
+                end: idx + 1,
+                space: space.take(),
+            }),
+        }
+
+        previous = Some(c);
+    }
+
+    let mut length = 0;
+
+    for word in &words {
+        let text: String = chars[word.start..word.end].iter().collect();
+        let word_length = metric.measure(&text);
+        let space_length = usize::from(word.space.is_some());
+
+        if length > 0 && length + space_length + word_length > max_length {
+            match word.space {
+                Some(space) => actions[space] = Action::Break,
+                None => actions[word.start] = Action::BreakBefore,
+            }
+
+            length = word_length;
+        } else {
+            length += space_length + word_length;
+        }
+
+        if word_length > max_length {
+            let breaks = hyphenate(&text, word.start);
+            length = hyphenate_word(&chars, word, breaks, max_length, metric, &mut actions);
+        }
+    }
+
+    actions
+}
+
+/// Breaks a word that is too long for a line at as few of the given offsets
+/// as keep each line within `max_length`, counting the hyphens. Returns the
+/// length of the last line.
+fn hyphenate_word(
+    chars: &[char],
+    word: &Word,
+    breaks: Vec<usize>,
+    max_length: usize,
+    metric: LengthMetric,
+    actions: &mut [Action],
+) -> usize {
+    let measure =
+        |from: usize, to: usize| metric.measure(&chars[from..to].iter().collect::<String>());
+    let fits = |from, to| measure(from, to) < max_length;
+
+    let mut line_start = word.start;
+    let mut fitting = None;
+
+    let hyphenate_at = |at: usize, actions: &mut [Action]| {
+        actions[at] = if chars[at - 1] == '-' {
+            Action::BreakBefore
+        } else {
+            Action::HyphenBefore
+        };
+    };
+
+    for at in breaks.into_iter().map(|offset| word.start + offset) {
+        if at <= line_start || at >= word.end {
+            continue;
+        }
+
+        if fits(line_start, at) {
+            fitting = Some(at);
+            continue;
+        }
+
+        // break at the last offset that fit, or here if none did
+        let line_end = fitting.take().unwrap_or(at);
+        hyphenate_at(line_end, actions);
+        line_start = line_end;
+
+        if line_end != at && fits(line_start, at) {
+            fitting = Some(at);
+        }
+    }
+
+    if measure(line_start, word.end) > max_length {
+        if let Some(line_end) = fitting {
+            hyphenate_at(line_end, actions);
+            line_start = line_end;
+        }
+    }
+
+    measure(line_start, word.end)
+}
+
+fn apply(nodes: &mut [Node], chars: &mut impl Iterator<Item = Action>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                let mut out = String::with_capacity(text.len());
+
+                for c in text.chars() {
+                    match chars.next().unwrap_or(Action::Keep) {
+                        Action::Keep => out.push(c),
+                        Action::Space => out.push(' '),
+                        Action::Remove => {}
+                        Action::Break => out.push('\n'),
+                        Action::BreakBefore => {
+                            out.push('\n');
+                            out.push(c);
+                        }
+                        Action::HyphenBefore => {
+                            out.push_str("-\n");
+                            out.push(c);
+                        }
+                    }
+                }
+
+                *text = out;
+            }
+            Node::Span(span) => apply(&mut span.children, chars),
+            Node::Timestamp(_) => {}
+        }
+    }
+}
+
+impl Cue {
+    /// Rewraps the text of this cue into lines of at most `max_length`
+    /// characters, counted as grapheme clusters, keeping its markup. Lines
+    /// break at spaces, and between the characters of Chinese and Japanese
+    /// text and where it meets other scripts, but not before closing
+    /// punctuation or after opening punctuation. Existing line breaks are
+    /// joined first, without a space between CJK characters. A word longer
+    /// than `max_length` gets a line of its own. Returns whether the text
+    /// changed; metadata cues are left as they are.
+    pub fn rewrap(&mut self, max_length: usize) -> bool {
+        self.rewrap_with(max_length, LengthMetric::Graphemes)
+    }
+
+    /// Rewraps the text of this cue as in [`Cue::rewrap`], measuring lines
+    /// with `metric`.
+    pub fn rewrap_with(&mut self, max_length: usize, metric: LengthMetric) -> bool {
+        self.rewrap_words(max_length, metric, &|_, _| vec![])
+    }
+
+    /// Rewraps the text of this cue, hyphenating words that do not fit on a
+    /// line where `hyphenate` allows.
+    pub(crate) fn rewrap_words(
+        &mut self,
+        max_length: usize,
+        metric: LengthMetric,
+        hyphenate: WordBreaks,
+    ) -> bool {
+        if self.is_metadata() {
+            return false;
+        }
+
+        let mut nodes = self.nodes();
+        let actions = plan(
+            &crate::text::plain(&nodes),
+            max_length.max(1),
+            metric,
+            hyphenate,
+        );
+
+        apply(&mut nodes, &mut actions.into_iter());
+
+        let text = self.format_nodes(&nodes);
+
+        if text == self.text {
+            return false;
+        }
+
+        self.text = text;
+        true
+    }
+}
+
+impl File {
+    /// Rewraps every cue to lines of at most `max_length` characters, as in
+    /// [`Cue::rewrap`]. Returns the number of cues changed.
+    pub fn rewrap(&mut self, max_length: usize) -> usize {
+        self.rewrap_with(max_length, LengthMetric::Graphemes)
+    }
+
+    /// Rewraps every cue as in [`Cue::rewrap_with`]. Returns the number of
+    /// cues changed.
+    pub fn rewrap_with(&mut self, max_length: usize, metric: LengthMetric) -> usize {
+        self.cues_mut()
+            .map(|cue| cue.rewrap_with(max_length, metric))
+            .filter(|&changed| changed)
+            .count()
+    }
+}
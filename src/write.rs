@@ -0,0 +1,106 @@
+//! Serialization of files back into WebVTT.
+
+use std::fmt::{self, Write};
+
+use crate::{settings::parse_settings, Block, Cue, File, LineEnding, TimestampStyle};
+
+impl fmt::Display for File {
+    /// Writes the file in the format it was read in, as recorded in
+    /// [`File::format`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.format.bom {
+            f.write_char('\u{feff}')?;
+        }
+
+        match self.format.line_ending {
+            LineEnding::Lf => self.write(f),
+            LineEnding::CrLf => {
+                let mut out = String::new();
+                self.write(&mut out)?;
+                f.write_str(&out.replace('\n', "\r\n"))
+            }
+        }
+    }
+}
+
+impl File {
+    fn write(&self, f: &mut impl Write) -> fmt::Result {
+        f.write_str("WEBVTT")?;
+
+        if let Some(description) = &self.description {
+            write!(f, "{}{description}", self.format.header_separator.as_char())?;
+        }
+
+        f.write_str("\n")?;
+
+        for line in &self.header_lines {
+            writeln!(f, "{line}")?;
+        }
+
+        for block in &self.blocks {
+            f.write_str("\n")?;
+
+            match block {
+                Block::Cue(cue) => {
+                    for note in &cue.notes {
+                        writeln!(f, "{note}\n")?;
+                    }
+
+                    if !cue.extensions.is_empty() {
+                        writeln!(f, "{}\n", cue.extensions.to_note())?;
+                    }
+
+                    cue.write(f, self.format.timestamps)?
+                }
+                Block::Note(note) => writeln!(f, "{note}")?,
+                Block::Raw { lines } => {
+                    for line in lines {
+                        writeln!(f, "{line}")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Cue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, TimestampStyle::Full)
+    }
+}
+
+impl Cue {
+    fn write(&self, f: &mut impl Write, timestamps: TimestampStyle) -> fmt::Result {
+        if !self.id.is_empty() {
+            writeln!(f, "{}", self.id)?;
+        }
+
+        write!(
+            f,
+            "{} --> {}",
+            timestamps.format(self.start),
+            timestamps.format(self.end)
+        )?;
+
+        match &self.raw_settings {
+            Some(raw) if parse_settings(raw) == self.settings => f.write_str(raw)?,
+            _ => {
+                let settings = self.settings.to_string();
+
+                if !settings.is_empty() {
+                    write!(f, " {settings}")?;
+                }
+            }
+        }
+
+        f.write_str("\n")?;
+
+        if !self.text.is_empty() {
+            writeln!(f, "{}", self.text)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Export of cue text to XLIFF 1.2 for translation, and import of the
+//! translated units back onto the original timings.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use quick_xml::{events::Event, Reader};
+
+use crate::{format_timestamp, ConvertError, Cue, File};
+
+impl File {
+    /// Exports the text of each cue as an XLIFF translation unit. Units are
+    /// keyed by cue identifier, or by timing for cues without one. When cues
+    /// share a key, the index of the cue among the cues of the file is added
+    /// to it, so every unit has its own id.
+    pub fn to_xliff(&self, source_language: &str, target_language: &str) -> String {
+        let mut out = String::new();
+
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n");
+        let _ = writeln!(
+            out,
+            "  <file original=\"webvtt\" datatype=\"plaintext\" source-language=\"{}\" target-language=\"{}\">",
+            escape(source_language),
+            escape(target_language)
+        );
+        out.push_str("    <body>\n");
+
+        for (cue, id) in self.cues().zip(unit_ids(self)) {
+            let _ = writeln!(out, "      <trans-unit id=\"{}\">", escape(&id));
+            let _ = writeln!(out, "        <source>{}</source>", escape(&cue.text));
+            out.push_str("      </trans-unit>\n");
+        }
+
+        out.push_str("    </body>\n");
+        out.push_str("  </file>\n");
+        out.push_str("</xliff>\n");
+
+        out
+    }
+
+    /// Returns a copy of this file with the text of each cue replaced by the
+    /// `<target>` of the matching unit in `xliff`. Timings and settings are
+    /// kept; cues without a translated unit keep their original text.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = xliff.len()), err)
+    )]
+    pub fn apply_xliff(&self, xliff: &str) -> Result<File, ConvertError> {
+        let targets = read_targets(xliff).map_err(|err| ConvertError::BadXliff(err.to_string()))?;
+        let mut file = self.clone();
+
+        for (cue, id) in file.cues_mut().zip(unit_ids(self)) {
+            if let Some(target) = targets.get(&id) {
+                cue.text = target.clone();
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(units = targets.len(), "applied translations");
+
+        Ok(file)
+    }
+}
+
+/// Returns the id of the translation unit of each cue of a file.
+fn unit_ids(file: &File) -> Vec<String> {
+    let keys: Vec<_> = file.cues().map(unit_key).collect();
+    let mut counts = HashMap::new();
+
+    for key in &keys {
+        *counts.entry(key.as_str()).or_insert(0) += 1;
+    }
+
+    let mut taken: HashSet<_> = keys.iter().cloned().collect();
+
+    keys.iter()
+        .enumerate()
+        .map(|(idx, key)| {
+            if counts[key.as_str()] == 1 {
+                return key.clone();
+            }
+
+            // another cue can have an id that looks like a suffixed key
+            let mut id = format!("{key}#{idx}");
+
+            while !taken.insert(id.clone()) {
+                id.push('_');
+            }
+
+            id
+        })
+        .collect()
+}
+
+/// Returns the key of a cue before it is made unique: its identifier, or its
+/// timings if it has none.
+fn unit_key(cue: &Cue) -> String {
+    if cue.id.is_empty() {
+        format!(
+            "{}-{}",
+            format_timestamp(cue.start),
+            format_timestamp(cue.end)
+        )
+    } else {
+        cue.id.clone()
+    }
+}
+
+fn read_targets(xliff: &str) -> Result<HashMap<String, String>, quick_xml::Error> {
+    let mut reader = Reader::from_str(xliff);
+    let mut targets = HashMap::new();
+
+    let mut unit_id = None;
+    let mut target: Option<String> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) => match tag.name().as_ref() {
+                b"trans-unit" => {
+                    unit_id = match tag.try_get_attribute("id")? {
+                        Some(attr) => Some(attr.unescape_value()?.into_owned()),
+                        None => None,
+                    };
+                }
+                b"target" => target = Some(String::new()),
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let Some(target) = &mut target {
+                    target.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(text) => {
+                if let Some(target) = &mut target {
+                    target.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Event::End(tag) => match tag.name().as_ref() {
+                b"trans-unit" => unit_id = None,
+                b"target" => {
+                    if let (Some(id), Some(target)) = (&unit_id, target.take()) {
+                        targets.insert(id.clone(), target);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(targets)
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        match char {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(char),
+        }
+    }
+
+    out
+}